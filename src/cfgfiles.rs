@@ -4,16 +4,49 @@
 //! HanishKVC, 2022
 //!
 
+use std::cell::RefCell;
 use std::collections::VecDeque;
-use std::fs::File;
+use std::fmt;
+use std::fs::{self, File};
 use std::io::{BufReader, BufRead};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
 
 use datautilsk::hex;
 use loggerk::{log_d, log_w, log_o};
 
+use crate::error::FuzzerKError;
+
 
 const LIST_MAXVALUES: usize = 1024;
 
+thread_local! {
+    /// The cfg file whose cfggroup is being handled currently, if any
+    static CFGFILE: RefCell<String> = RefCell::new(String::new());
+}
+
+///
+/// Resolve a path specified in a cfggroup (like $file:, $dir:, dictfile, grammarfile),
+/// relative to the dir of the cfg file containing it, unless it is a absolute path.
+/// If no cfg file is being parsed (ie the cfggroup is handled directly), its used as is.
+///
+pub(crate) fn cfg_path(spath: &str) -> PathBuf {
+    CFGFILE.with(|cfgfile| {
+        let cfgfile = cfgfile.borrow();
+        if cfgfile.len() == 0 {
+            return PathBuf::from(spath);
+        }
+        include_path(&cfgfile, spath)
+    })
+}
+
+///
+/// Set the cfg file whose cfggroup is being handled, empty if none.
+///
+fn cfgfile_set(sfile: &str) {
+    CFGFILE.with(|cfgfile| *cfgfile.borrow_mut() = sfile.to_string());
+}
+
 
 pub trait FromVecStrings {
 
@@ -81,6 +114,8 @@ pub trait FromVecStrings {
     ///   * if one wants the resultant string to contain double quotes at either end, put a 2nd double quote, where required.
     ///   * if there is double quotes only at one end of the string, it wont be removed.
     /// * interpret the given string has a hex string, if it starts with $0x
+    /// * use the contents of the specified file, if it starts with $file:
+    ///   * a relative path is wrt the dir of the cfg file
     fn strval_process(ins: &str) -> Result<Vec<u8>, String> {
         log_d(&format!("DBUG:FromVS:StrValProcess:{}:{}", Self::get_name(), ins));
         let mut outs = ins.trim();
@@ -89,6 +124,10 @@ pub trait FromVecStrings {
                 let vdata = hex::vu8_from_hex(&outs[3..]);
                 return vdata;
             }
+            if outs.starts_with("$file:") {
+                let path = cfg_path(&outs[6..]);
+                return fs::read(&path).map_err(|e| format!("ERRR:FromVS:StrValProcess:$file:{}:{}", path.display(), e));
+            }
             let mut outschars = outs.chars();
             let startchar = outschars.nth(0).unwrap();
             let endchar = outschars.last().unwrap();
@@ -105,12 +144,48 @@ pub trait FromVecStrings {
         Ok(Vec::from(outs))
     }
 
+    ///
+    /// Get the contents of each of the files (sorted by name) in the specified directory.
+    /// A relative path is wrt the dir of the cfg file.
+    ///
+    fn strval_dir(spath: &str) -> Result<Vec<Vec<u8>>, String> {
+        let dir = cfg_path(spath);
+        let path = dir.display();
+        let entries = fs::read_dir(&dir).map_err(|e| format!("ERRR:FromVS:StrValDir:{}:{}", path, e))?;
+        let mut files = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("ERRR:FromVS:StrValDir:{}:{}", path, e))?.path();
+            if entry.is_file() {
+                files.push(entry);
+            }
+        }
+        if files.len() == 0 {
+            return Err(format!("ERRR:FromVS:StrValDir:No files in {}", path));
+        }
+        files.sort();
+        let mut vdata = Vec::new();
+        for file in files {
+            vdata.push(fs::read(&file).map_err(|e| format!("ERRR:FromVS:StrValDir:{}:{}", file.display(), e))?);
+        }
+        log_o(&format!("INFO:FromVS:StrValDir:{}:Got {} files from {}", Self::get_name(), vdata.len(), path));
+        Ok(vdata)
+    }
+
     ///
     /// Get the value (single) associated with the specified key. Empty value is Ok
     ///
     /// * The key-value pair should be specified as key:value | key: value | key: " value " | key: $0xvalue...
     /// * The key-value pair should be indented with whitespaces chars to match the specified spacesprefix.
     fn get_value_emptyok(vs: &mut VecDeque<String>, key: &str, spacesprefix: usize) -> Result<Vec<u8>, String> {
+        let svalue = Self::get_rawvalue_emptyok(vs, key, spacesprefix)?;
+        return Self::strval_process(&svalue);
+    }
+
+    ///
+    /// Get the value text (trimmed, but otherwise unprocessed) associated with the specified key.
+    /// Empty value is Ok. Refer get_value_emptyok wrt the key-value pair.
+    ///
+    fn get_rawvalue_emptyok(vs: &mut VecDeque<String>, key: &str, spacesprefix: usize) -> Result<String, String> {
         let cursp = Self::get_spacesprefix(vs);
         if cursp != spacesprefix {
             return Err(format!("ERRR:FromVS:GetValueEmptyOk:{}:Prefix whitespaces mismatch:{} != {}", key, spacesprefix, cursp));
@@ -130,7 +205,7 @@ pub trait FromVecStrings {
             return Err(format!("ERRR:FromVS:GetValueEmptyOk:{}:Expected key {}, got key {}", key, key, lt.0));
         }
         log_d(&format!("DBUG:FromVS:GetValueEmptyOk:{}-{}:[{:?}]", Self::get_name(), key, lt));
-        return Self::strval_process(lt.1);
+        return Ok(lt.1.trim().to_string());
     }
 
     ///
@@ -158,7 +233,7 @@ pub trait FromVecStrings {
         if svalue.is_err() {
             return Err(svalue.unwrap_err());
         }
-        let svalue = String::from_utf8(svalue.unwrap()).unwrap();
+        let svalue = String::from_utf8_lossy(&svalue.unwrap()).to_string();
         let ivalue = isize::from_str_radix(svalue.trim(), 10);
         if ivalue.is_err() {
             return Err(format!("ERRR:FromVS:GetIValue:{}:Conversion of {} to Int err", key, svalue))
@@ -166,6 +241,62 @@ pub trait FromVecStrings {
         Ok(ivalue.unwrap())
     }
 
+    ///
+    /// Get the unsigned int value associated with the specified key. Empty value is not Ok with this.
+    ///
+    fn get_uvalue(vs: &mut VecDeque<String>, key: &str, spacesprefix: usize) -> Result<usize, String> {
+        let svalue = Self::get_value(vs, key, spacesprefix);
+        if svalue.is_err() {
+            return Err(svalue.unwrap_err());
+        }
+        let svalue = String::from_utf8_lossy(&svalue.unwrap()).to_string();
+        let uvalue = usize::from_str_radix(svalue.trim(), 10);
+        if uvalue.is_err() {
+            return Err(format!("ERRR:FromVS:GetUValue:{}:Conversion of {} to UInt err", key, svalue))
+        }
+        Ok(uvalue.unwrap())
+    }
+
+    ///
+    /// Check if the next key-value pair is wrt the specified key, so that optional keys
+    /// can be supported. Optional keys still need to be specified in the expected order.
+    ///
+    fn has_key(vs: &VecDeque<String>, key: &str, spacesprefix: usize) -> bool {
+        if Self::get_spacesprefix(vs) != spacesprefix {
+            return false;
+        }
+        let l = vs.front();
+        if l.is_none() {
+            return false;
+        }
+        let lt = l.unwrap().trim().split_once(':');
+        if lt.is_none() {
+            return false;
+        }
+        lt.unwrap().0 == key
+    }
+
+    ///
+    /// Get the string value associated with the specified optional key, else the default.
+    ///
+    fn get_svalue_or(vs: &mut VecDeque<String>, key: &str, spacesprefix: usize, default: &str) -> Result<String, String> {
+        if !Self::has_key(vs, key, spacesprefix) {
+            return Ok(default.to_string());
+        }
+        let svalue = Self::get_value(vs, key, spacesprefix)?;
+        Ok(String::from_utf8_lossy(&svalue).trim().to_string())
+    }
+
+    ///
+    /// Get the unsigned int value associated with the specified optional key, else the default.
+    ///
+    fn get_uvalue_or(vs: &mut VecDeque<String>, key: &str, spacesprefix: usize, default: usize) -> Result<usize, String> {
+        if !Self::has_key(vs, key, spacesprefix) {
+            return Ok(default);
+        }
+        Self::get_uvalue(vs, key, spacesprefix)
+    }
+
     ///
     /// Retrieve the list of values associated with the specified key
     /// * key needs to be in its own line with
@@ -179,13 +310,13 @@ pub trait FromVecStrings {
     ///   * \[WHITESPACE*\]WHITESPACE*The Value
     ///   * the WHITESPACES at begin and end of the Value string will be trimmed.
     ///   * even if the optional ',' termination char is used, any spaces at end of the value string before ',' will be trimmed.
+    /// * a value of the form $dir:path/to/dir is replaced by the contents of each of the files in that directory.
     ///
     fn get_values(vs: &mut VecDeque<String>, key: &str, spacesprefix: usize) -> Result<Vec<Vec<u8>>, String> {
-        let sheadval = Self::get_value_emptyok(vs, key, spacesprefix);
-        if sheadval.is_err() {
-            return Err(sheadval.unwrap_err());
+        let sheadval = Self::get_rawvalue_emptyok(vs, key, spacesprefix)?;
+        if sheadval.starts_with("$dir:") {
+            return Self::strval_dir(sheadval[5..].trim());
         }
-        let sheadval = String::from_utf8(sheadval.unwrap()).unwrap();
         let numvalues;
         let mut vdata = Vec::new();
         if sheadval.len() != 0 {
@@ -229,6 +360,10 @@ pub trait FromVecStrings {
             if curline.chars().last().unwrap() == ',' {
                 curline = curline.strip_suffix(",").unwrap();
             }
+            if curline.starts_with("$dir:") {
+                vdata.extend(Self::strval_dir(curline[5..].trim())?);
+                continue;
+            }
             let curline = Self::strval_process(curline);
             if curline.is_err() {
                 return Err(curline.unwrap_err());
@@ -247,8 +382,9 @@ pub trait FromVecStrings {
 
     ///
     /// Implementer should parse the passed Vector of Strings and Generator a instance of itself.
+    /// If the cfggroup is not valid, a message explaining the issue should be returned.
     ///
-    fn from_vs(vs: &mut VecDeque<String>) -> Self;
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<Self, String> where Self: Sized;
 
 }
 
@@ -264,57 +400,636 @@ pub trait FromVecStrings {
 ///     this logic doesnt bother about same. It is for any handler of the
 ///     CfgGroup to enforce such requirement.
 /// * A empty line, after a bunch of non empty lines, terminates a CfgGroup.
-fn get_cfggroup(fbr: &mut BufReader<File>) -> VecDeque<String> {
+///
+/// lineno keeps track of the lines read from the file till now, and the line numbers
+/// (1 based) of each line in the returned CfgGroup are returned along with it.
+fn get_cfggroup(fbr: &mut BufReader<File>, lineno: &mut usize) -> Result<(Vec<usize>, VecDeque<String>), String> {
     let mut vdata = VecDeque::new();
+    let mut linenos = Vec::new();
     loop {
         let mut sbuf = String::new();
         let gotr = fbr.read_line(&mut sbuf);
         if gotr.is_err() {
-            panic!("ERRR:CfgFiles:GetCfgGroup:read failed {}", gotr.unwrap_err());
+            return Err(format!("ERRR:CfgFiles:GetCfgGroup:read failed {}", gotr.unwrap_err()));
         }
         let gotr = gotr.unwrap();
         if gotr == 0 {
             break;
         }
+        *lineno += 1;
         if sbuf.trim().len() == 0 {
             if vdata.len() > 0 {
                 break;
             }
             continue;
         }
-        let c = sbuf.chars().nth(0).expect("ERRR:CfgFiles:GetCfgGroup:While checking for CfgGroup start or Comment");
+        let c = sbuf.chars().nth(0).unwrap();
         if c == '#' { // Skip comments
             continue;
         }
         if vdata.len() == 0 {
             if !c.is_alphanumeric() {
-                panic!("ERRR:CfgFiles:GetCfgGroup:Didnt get the expected start of a new CfgGroup:{}", sbuf);
+                return Err(format!("ERRR:CfgFiles:GetCfgGroup:Didnt get the expected start of a new CfgGroup:{}", sbuf.trim_end()));
             }
         }
+        linenos.push(*lineno);
         vdata.push_back(sbuf)
     }
-    vdata
+    Ok((linenos, vdata))
+}
+
+///
+/// Skip the remaining lines of a CfgGroup, which has issues, ie till a empty line.
+///
+fn skip_cfggroup(fbr: &mut BufReader<File>, lineno: &mut usize) {
+    loop {
+        let mut sbuf = String::new();
+        let gotr = fbr.read_line(&mut sbuf);
+        if gotr.is_err() || (gotr.unwrap() == 0) {
+            break;
+        }
+        *lineno += 1;
+        if sbuf.trim().len() == 0 {
+            break;
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    /// Things which are not wrong as such, but could be a oversight, like unused fuzzers
+    Warning,
+}
+
+///
+/// A issue found, along with where it was found
+/// * line, col: 1 based, 0 if not known
+///
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub file: String,
+    pub line: usize,
+    pub col: usize,
+    pub severity: Severity,
+    pub msg: String,
+}
+
+impl Diagnostic {
+
+    pub fn new(file: &str, line: usize, col: usize, severity: Severity, msg: &str) -> Diagnostic {
+        Diagnostic {
+            file: file.to_string(),
+            line,
+            col,
+            severity,
+            msg: msg.to_string(),
+        }
+    }
+
+    pub fn error(file: &str, line: usize, col: usize, msg: &str) -> Diagnostic {
+        Self::new(file, line, col, Severity::Error, msg)
+    }
+
+    pub fn warning(file: &str, line: usize, col: usize, msg: &str) -> Diagnostic {
+        Self::new(file, line, col, Severity::Warning, msg)
+    }
+
+}
+
+impl fmt::Display for Diagnostic {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}:{}", self.file, self.line, self.col, self.msg)
+    }
+
+}
+
+
+///
+/// Get the column (1 based) at which the given word occurs (as a whole word) in the line
+///
+pub fn find_word(line: &str, word: &str) -> Option<usize> {
+    let mut from = 0;
+    while word.len() > 0 {
+        let start = from + line[from..].find(word)?;
+        let end = start + word.len();
+        let isboundary = |c: Option<char>| c.map_or(true, |c| !(c.is_alphanumeric() || c == '_'));
+        if isboundary(line[..start].chars().last()) && isboundary(line[end..].chars().next()) {
+            return Some(line[..start].chars().count() + 1);
+        }
+        from = end;
+    }
+    None
+}
+
+///
+/// Get the column (1 based) at which the given word occurs in the line, else the column
+/// of the first non whitespace char in the line.
+///
+pub fn col_of(line: &str, word: &str) -> usize {
+    find_word(line, word).unwrap_or(line.chars().take_while(|c| c.is_whitespace()).count() + 1)
+}
+
+
+///
+/// A CfgGroup along with where it was found, ie the file and the line numbers (1 based)
+/// of each of its lines.
+///
+#[derive(Debug, Clone)]
+pub struct CfgGroupLoc {
+    pub file: String,
+    pub linenos: Vec<usize>,
+    pub lines: Vec<String>,
+}
+
+impl CfgGroupLoc {
+
+    fn new(file: &str, linenos: &Vec<usize>, cg: &VecDeque<String>) -> CfgGroupLoc {
+        CfgGroupLoc {
+            file: file.to_string(),
+            linenos: linenos.clone(),
+            lines: cg.iter().map(|l| l.trim_end().to_string()).collect(),
+        }
+    }
+
+    ///
+    /// Identify the line and column (both 1 based) wrt the issue with this CfgGroup, given
+    /// the number of its lines left unhandled by the handler, when the issue was found.
+    /// * a prefix whitespaces mismatch is wrt the next line, which was not handled.
+    /// * a issue wrt the instance name or fuzzer type is wrt the first line.
+    /// * a reference to a unknown fuzzer is wrt the line which refers to it.
+    /// * a minlen > maxlen issue is wrt the maxlen line.
+    /// * a unsupported escape char is wrt its position in the line being handled.
+    /// * any other issue is wrt the last line handled, ie the one being handled.
+    ///
+    pub fn locate(&self, leftlines: usize, msg: &str) -> (usize, usize) {
+        let handled = self.lines.len() - leftlines.min(self.lines.len());
+        let mut i = handled.max(1) - 1;
+        let mut word = String::new();
+        if msg.contains("Prefix whitespaces mismatch") {
+            i = handled.min(self.lines.len() - 1);
+        } else if msg.contains(":Duplicate name ") || msg.contains(":UnknownFuzzer:") {
+            i = 0;
+            let la: Vec<&str> = self.lines[0].split(':').collect();
+            if la.len() == 3 {
+                word = if msg.contains(":UnknownFuzzer:") { la[1] } else { la[2].trim() }.to_string();
+            }
+        } else if let Some((_, sname)) = msg.split_once("Reference to unknown fuzzer ") {
+            word = sname.split_whitespace().next().unwrap_or("").to_string();
+            if let Some(j) = (1..self.lines.len()).find(|&j| find_word(&self.lines[j], &word).is_some()) {
+                i = j;
+            }
+        } else if msg.contains("> maxlen") {
+            if let Some(j) = self.lines.iter().position(|l| l.trim_start().starts_with("maxlen:")) {
+                i = j;
+                word = "maxlen".to_string();
+            }
+        } else if let Some((_, sc)) = msg.split_once("Unsupported escape char ") {
+            let escape = format!("\\{}", sc.chars().next().unwrap_or(' '));
+            if let Some(pos) = self.lines[i].find(&escape) {
+                return (self.linenos[i], self.lines[i][..pos].chars().count() + 1);
+            }
+        }
+        (self.linenos[i], col_of(&self.lines[i], &word))
+    }
+
 }
 
 
 pub trait HandleCfgGroup {
-    fn handle_cfggroup(&mut self, vs: &mut VecDeque<String>);
+    ///
+    /// Called before handle_cfggroup, with the file and the line at which the CfgGroup starts,
+    /// so that issues found later (in handle_cfgend) can be reported wrt it.
+    ///
+    fn handle_cfgloc(&mut self, _file: &str, _line: usize) {
+    }
+
+    ///
+    /// Handle the passed CfgGroup. If there is any issue with its contents,
+    /// a message explaining the issue should be returned.
+    ///
+    fn handle_cfggroup(&mut self, vs: &mut VecDeque<String>) -> Result<(), String>;
+
+    ///
+    /// Called once all the CfgGroups in the cfg file have been handled, so that any
+    /// forward references can be resolved. If there is any issue, it should be returned
+    /// as a CfgFile error, wrt the file and line of the CfgGroup it relates to.
+    ///
+    fn handle_cfgend(&mut self) -> Result<(), FuzzerKError> {
+        Ok(())
+    }
+}
+
+
+/// The cfggroup, which includes another cfg file
+const INCLUDE_MARKER: &str = "Include";
+/// The min interval between checks for edits to the watched cfg files
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+
+///
+/// Resolve the path of a included cfg file (or any other path specified in it), relative
+/// to the dir of the including file, unless it is a absolute path.
+///
+fn include_path(sfile: &str, sinclude: &str) -> PathBuf {
+    let include = Path::new(sinclude);
+    if include.is_absolute() {
+        return include.to_path_buf();
+    }
+    match Path::new(sfile).parent() {
+        Some(dir) => dir.join(include),
+        None => include.to_path_buf(),
+    }
+}
+
+///
+/// Check if the passed cfggroup is a Include: path, if so return the path(s).
+/// Multiple files can be included, by using a Include: path line wrt each of them.
+///
+fn include_get(cg: &VecDeque<String>) -> Option<Result<Vec<String>, String>> {
+    let (key, _path) = cg.front().unwrap().split_once(':')?;
+    if key != INCLUDE_MARKER {
+        return None;
+    }
+    let mut paths = Vec::new();
+    for l in cg {
+        let path = l.strip_prefix(INCLUDE_MARKER).and_then(|l| l.strip_prefix(':'));
+        if path.is_none() {
+            return Some(Err(format!("ERRR:CfgFiles:Include:Expected Include: path, got {}", l.trim())));
+        }
+        let path = path.unwrap().trim();
+        if path.len() == 0 {
+            return Some(Err("ERRR:CfgFiles:Include:No path given".to_string()));
+        }
+        paths.push(path.to_string());
+    }
+    Some(Ok(paths))
+}
+
+///
+/// Collects the issues found and the CfgGroups seen, when checking cfg files.
+///
+#[derive(Debug, Default)]
+pub struct CfgCheck {
+    pub groups: Vec<CfgGroupLoc>,
+    pub diags: Vec<Diagnostic>,
 }
 
+///
+/// Collect the issue into check if specified, else return it.
+///
+fn issue_handle(check: Option<&mut CfgCheck>, sfile: &str, line: usize, col: usize, msg: String) -> Result<(), FuzzerKError> {
+    match check {
+        Some(check) => {
+            check.diags.push(Diagnostic::error(sfile, line, col, &msg));
+            Ok(())
+        }
+        None => Err(FuzzerKError::CfgFile { file: sfile.to_string(), line, msg }),
+    }
+}
 
-pub fn parse_file(sfile: &str, handler: &mut dyn HandleCfgGroup) {
+///
+/// Parse the cfggroups in the specified file and any files included by it.
+/// * including stack: the files currently being parsed, to catch include cycles
+/// * files: the files parsed till now, a file parsed again is skipped.
+/// * check: if specified, issues are collected into it and the parsing continues,
+///   else the first issue is returned.
+///
+fn parse_file_groups(sfile: &str, handler: &mut dyn HandleCfgGroup, including: &mut Vec<PathBuf>, files: &mut Vec<PathBuf>, mut check: Option<&mut CfgCheck>) -> Result<(), FuzzerKError> {
     let f = File::open(sfile);
     if f.is_err() {
-        panic!("ERRR:CfgFiles:ParseFile:{}:{}", sfile, f.unwrap_err());
+        return Err(FuzzerKError::File(sfile.to_string(), f.unwrap_err().to_string()));
     }
     let f = f.unwrap();
+    let path = fs::canonicalize(sfile).unwrap_or(PathBuf::from(sfile));
+    if files.contains(&path) {
+        log_o(&format!("INFO:CfgFiles:ParseFile:Skipping already parsed {}", sfile));
+        return Ok(());
+    }
+    including.push(path.clone());
+    files.push(path);
     let mut fbr = BufReader::new(f);
+    let mut lineno = 0;
     loop {
-        let mut cgdata = get_cfggroup(&mut fbr);
+        let cgdata = get_cfggroup(&mut fbr, &mut lineno);
+        if cgdata.is_err() {
+            issue_handle(check.as_deref_mut(), sfile, lineno, 1, cgdata.unwrap_err())?;
+            skip_cfggroup(&mut fbr, &mut lineno);
+            continue;
+        }
+        let (linenos, mut cgdata) = cgdata.unwrap();
         if cgdata.len() == 0 {
             break;
         }
         log_d(&format!("CfgFiles:CfgGroup:{:#?}", cgdata));
-        handler.handle_cfggroup(&mut cgdata);
+        let group = CfgGroupLoc::new(sfile, &linenos, &cgdata);
+        if let Some(includes) = include_get(&cgdata) {
+            if includes.is_err() {
+                issue_handle(check.as_deref_mut(), sfile, linenos[0], 1, includes.unwrap_err())?;
+                continue;
+            }
+            for (i, include) in includes.unwrap().iter().enumerate() {
+                let ipath = include_path(sfile, include);
+                let icanon = fs::canonicalize(&ipath).unwrap_or(ipath.clone());
+                let mut issue = None;
+                if including.contains(&icanon) {
+                    issue = Some(format!("ERRR:CfgFiles:Include:Cycle wrt {}", include));
+                } else if files.contains(&icanon) {
+                    log_o(&format!("INFO:CfgFiles:Include:{}:{}:Skipping already included {}", sfile, linenos[i], include));
+                    continue;
+                } else if !ipath.is_file() {
+                    issue = Some(format!("ERRR:CfgFiles:Include:Cant find file {}", include));
+                }
+                if let Some(issue) = issue {
+                    issue_handle(check.as_deref_mut(), sfile, linenos[i], col_of(&group.lines[i], include), issue)?;
+                    continue;
+                }
+                parse_file_groups(&ipath.to_string_lossy(), handler, including, files, check.as_deref_mut())?;
+            }
+            continue;
+        }
+        handler.handle_cfgloc(sfile, linenos[0]);
+        cfgfile_set(sfile);
+        let gotr = handler.handle_cfggroup(&mut cgdata);
+        cfgfile_set("");
+        if gotr.is_err() {
+            let msg = gotr.unwrap_err();
+            let (line, col) = group.locate(cgdata.len(), &msg);
+            issue_handle(check.as_deref_mut(), sfile, line, col, msg)?;
+        }
+        if let Some(check) = check.as_deref_mut() {
+            check.groups.push(group);
+        }
     }
+    including.pop();
+    Ok(())
+}
+
+///
+/// Parse the specified cfg file and pass each of the CfgGroups in it to the given handler.
+/// The first issue encountered, will stop the parsing and be returned along with the file
+/// name and the line (within the problematic CfgGroup) with the issue.
+///
+/// A cfggroup of the form Include: path, parses the specified cfg file at that point.
+/// A relative path is wrt the dir of the including file. Including a file already
+/// included is skipped, while a file including itself (directly or indirectly) is a issue.
+///
+/// files: the files parsed till now, wrt the set of cfg files being loaded together, so that
+/// a library included by more than one of them is parsed only once. The files parsed (ie the
+/// specified file and the files included) are added to it.
+///
+pub fn parse_file_deps(sfile: &str, handler: &mut dyn HandleCfgGroup, files: &mut Vec<PathBuf>) -> Result<(), FuzzerKError> {
+    parse_file_groups(sfile, handler, &mut Vec::new(), files, None)?;
+    handler.handle_cfgend()
+}
+
+///
+/// Parse the specified cfg file (along with the files included by it), refer to parse_file_deps.
+///
+pub fn parse_file(sfile: &str, handler: &mut dyn HandleCfgGroup) -> Result<(), FuzzerKError> {
+    parse_file_deps(sfile, handler, &mut Vec::new())
+}
+
+///
+/// Check the specified cfg file (along with the files included by it), by passing each of
+/// its CfgGroups to the given handler, continuing past any issues. The issues found and the
+/// CfgGroups seen are collected into check, so that further checks can be done on them.
+///
+/// files: the files checked till now, refer to parse_file_deps.
+///
+/// NOTE: handle_cfgend is not called, so that the caller can check the issue if any wrt it.
+///
+pub fn check_file(sfile: &str, handler: &mut dyn HandleCfgGroup, files: &mut Vec<PathBuf>, check: &mut CfgCheck) {
+    let gotr = parse_file_groups(sfile, handler, &mut Vec::new(), files, Some(check));
+    if let Err(err) = gotr {
+        match err {
+            FuzzerKError::File(file, msg) => check.diags.push(Diagnostic::error(&file, 0, 0, &format!("ERRR:CfgFiles:{}", msg))),
+            err => check.diags.push(Diagnostic::error(sfile, 0, 0, &err.to_string())),
+        }
+    }
+}
+
+
+///
+/// Keep track of the modification times of a set of cfg files, so that any edits to them
+/// can be detected. The files are checked atmost once every WATCH_INTERVAL.
+///
+pub struct CfgWatch {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+    lastcheck: Instant,
+}
+
+impl CfgWatch {
+
+    pub fn new(files: Vec<PathBuf>) -> CfgWatch {
+        let mut watch = CfgWatch {
+            files: Vec::new(),
+            lastcheck: Instant::now(),
+        };
+        watch.set_files(files);
+        watch
+    }
+
+    fn mtime(path: &Path) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|md| md.modified()).ok()
+    }
+
+    ///
+    /// Start watching the given files, wrt their current modification times.
+    ///
+    pub fn set_files(&mut self, files: Vec<PathBuf>) {
+        self.files = files.into_iter().map(|path| { let mtime = Self::mtime(&path); (path, mtime) }).collect();
+    }
+
+    ///
+    /// Check if any of the files have been modified (or removed) since the last check.
+    ///
+    pub fn changed(&mut self) -> bool {
+        if self.lastcheck.elapsed() < WATCH_INTERVAL {
+            return false;
+        }
+        self.lastcheck = Instant::now();
+        let mut bchanged = false;
+        for (path, mtime) in self.files.iter_mut() {
+            let curmtime = Self::mtime(path);
+            if curmtime != *mtime {
+                log_o(&format!("INFO:CfgFiles:CfgWatch:{} modified", path.display()));
+                *mtime = curmtime;
+                bchanged = true;
+            }
+        }
+        bchanged
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::cfggroup;
+    use crate::rtm::RunTimeManager;
+    use crate::fixed::RandomFixedStringsFuzzer;
+    use crate::FuzzChain;
+    use std::{rc::Rc, cell::RefCell, collections::VecDeque};
+
+    /// Helps test the getters of FromVecStrings
+    struct Values(Vec<Vec<u8>>);
+
+    impl FromVecStrings for Values {
+
+        fn get_name() -> String {
+            return "Values".to_string();
+        }
+
+        fn from_vs(vs: &mut VecDeque<String>) -> Result<Values, String> {
+            Ok(Values(Self::get_values(vs, "list", 2)?))
+        }
+
+    }
+
+    fn values(lines: &[&str]) -> Result<Vec<Vec<u8>>, String> {
+        Values::from_vs(&mut lines.iter().map(|l| l.to_string()).collect()).map(|v| v.0)
+    }
+
+    #[test]
+    fn get_values_head() {
+        // a single value in the head form is processed only once
+        assert_eq!(values(&["  list: $0xDEADBEEF"]).unwrap(), vec![vec![0xDE, 0xAD, 0xBE, 0xEF]]);
+        assert_eq!(values(&["  list: \"a\\\\b\""]).unwrap(), vec![Vec::from("a\\b")]);
+        assert_eq!(values(&["  list: 2", "    $0xFF00", "    \" x \","]).unwrap(), vec![vec![0xFF, 0x00], Vec::from(" x ")]);
+        assert_eq!(values(&["  list:", "    x"]).unwrap(), vec![Vec::from("x")]);
+    }
+
+    /// Collects the values of the list key in each cfggroup
+    struct ValuesHandler(Vec<Vec<Vec<u8>>>);
+
+    impl HandleCfgGroup for ValuesHandler {
+
+        fn handle_cfggroup(&mut self, vs: &mut VecDeque<String>) -> Result<(), String> {
+            vs.pop_front();
+            self.0.push(Values::from_vs(vs)?.0);
+            Ok(())
+        }
+
+    }
+
+    #[test]
+    fn cfgfile_relative_paths() {
+        // the cfg file is in a dir other than the cwd, and the seeds are binary
+        let dir = std::env::temp_dir().join(format!("fuzzerk-cfgpaths-{}", std::process::id()));
+        fs::create_dir_all(dir.join("corpus")).unwrap();
+        let seed = vec![0xFF, 0x00, b'"', b' ', b'\\', 0xC3];
+        fs::write(dir.join("seed.bin"), &seed).unwrap();
+        fs::write(dir.join("corpus/a.bin"), vec![0x80, 0x0A]).unwrap();
+        fs::write(dir.join("corpus/b.bin"), vec![0xFE]).unwrap();
+        fs::write(dir.join("t.fc"), "G1\n  list: $file:seed.bin\n\nG2\n  list:\n    $file:seed.bin\n    $dir:corpus\n\nG3\n  list: $dir:corpus\n").unwrap();
+        let mut handler = ValuesHandler(Vec::new());
+        parse_file(dir.join("t.fc").to_str().unwrap(), &mut handler).unwrap();
+        assert_eq!(handler.0, vec![
+            vec![seed.clone()],
+            vec![seed.clone(), vec![0x80, 0x0A], vec![0xFE]],
+            vec![vec![0x80, 0x0A], vec![0xFE]],
+        ]);
+        // outside of parsing a cfg file, relative paths are wrt the cwd
+        assert!(values(&["  list: $file:seed.bin"]).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn cfgfile_include() {
+        use crate::error::FuzzerKError;
+        let dir = std::env::temp_dir().join(format!("fuzzerk-include-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("lib")).unwrap();
+        std::fs::write(dir.join("lib/common.fc"), "FuzzerType:LoopFixedStringsFuzzer:HELLO\n  list:\n    Hello\n\nInclude: space.fc\n").unwrap();
+        std::fs::write(dir.join("lib/space.fc"), "FuzzerType:LoopFixedStringsFuzzer:SPACE\n  list:\n    \" \"\n").unwrap();
+        std::fs::write(dir.join("main.fc"), "Include: lib/common.fc\nInclude: lib/space.fc\n\nFuzzChain:FuzzChain:FC01\n  HELLO\n  SPACE\n  WORLD\n\nFuzzerType:LoopFixedStringsFuzzer:WORLD\n  list:\n    World\n").unwrap();
+        let smain = dir.join("main.fc").to_str().unwrap().to_string();
+        let mut rtm = RunTimeManager::new_seeded(0);
+        let mut files = Vec::new();
+        parse_file_deps(&smain, &mut rtm, &mut files).unwrap();
+        assert_eq!(files.len(), 3);
+        assert_eq!(rtm.fchain_get("FC01", None), Some(Vec::from("Hello World")));
+        // names are unique across the files
+        std::fs::write(dir.join("dup.fc"), "Include: lib/common.fc\n\nFuzzerType:LoopFixedStringsFuzzer:SPACE\n  list:\n    _\n").unwrap();
+        let gotr = parse_file(dir.join("dup.fc").to_str().unwrap(), &mut RunTimeManager::new_seeded(0));
+        match gotr.unwrap_err() {
+            FuzzerKError::CfgFile { file, line, msg } => {
+                assert!(file.ends_with("dup.fc") && (line == 3) && msg.contains("Duplicate name SPACE"));
+            }
+            err => panic!("unexpected {}", err),
+        }
+        std::fs::write(dir.join("lib/space.fc"), "Include: ../main.fc\n").unwrap();
+        let gotr = parse_file(&smain, &mut RunTimeManager::new_seeded(0));
+        assert!(gotr.unwrap_err().to_string().contains("space.fc:1:ERRR:CfgFiles:Include:Cycle wrt ../main.fc"));
+        // a reload with issues retains the current fuzzers, else replaces them
+        let gotr = rtm.reload(&vec![smain.clone()]);
+        assert!(gotr.is_err());
+        assert_eq!(rtm.fchain_get("FC01", None), Some(Vec::from("Hello World")));
+        std::fs::write(dir.join("lib/space.fc"), "FuzzerType:LoopFixedStringsFuzzer:SPACE\n  list:\n    _\n").unwrap();
+        rtm.reload(&vec![smain]).unwrap();
+        assert_eq!(rtm.fchain_get("FC01", None), Some(Vec::from("Hello_World")));
+        // a library included by more than one of the files loaded together, is parsed once
+        std::fs::write(dir.join("a.fc"), "Include: lib/space.fc\n\nFuzzChain:FuzzChain:FCA\n  SPACE\n").unwrap();
+        std::fs::write(dir.join("b.fc"), "Include: lib/space.fc\n\nFuzzChain:FuzzChain:FCB\n  SPACE\n  SPACE\n").unwrap();
+        let cfgfcs = vec![dir.join("a.fc").to_str().unwrap().to_string(), dir.join("b.fc").to_str().unwrap().to_string()];
+        let mut rtm = RunTimeManager::new_seeded(0);
+        let mut files = Vec::new();
+        for cfgfc in &cfgfcs {
+            parse_file_deps(cfgfc, &mut rtm, &mut files).unwrap();
+        }
+        assert_eq!(files.len(), 3);
+        assert_eq!(rtm.fchain_get("FCB", None), Some(Vec::from("__")));
+        assert_eq!(rtm.reload(&cfgfcs).unwrap().len(), 3);
+        assert_eq!(rtm.fchain_get("FCA", None), Some(Vec::from("_")));
+        // the fuzzers and fuzzchains added by the program are retained across reloads
+        std::fs::write(dir.join("c.fc"), "Include: lib/space.fc\n\nFuzzChain:FuzzChain:FCC\n  PROG\n  SPACE\n  FCPROG\n").unwrap();
+        let cfgfcs = vec![dir.join("c.fc").to_str().unwrap().to_string()];
+        let mut rtm = RunTimeManager::new_seeded(0);
+        rtm.add_fuzzer("PROG", Rc::new(RefCell::new(RandomFixedStringsFuzzer::new(vec![Vec::from("prog")]))));
+        let mut fc = FuzzChain::new();
+        fc.append(Rc::new(RefCell::new(RandomFixedStringsFuzzer::new(vec![Vec::from("chain")]))));
+        rtm.add_fchain("FCPROG", fc);
+        parse_file(&cfgfcs[0], &mut rtm).unwrap();
+        rtm.reload(&cfgfcs).unwrap();
+        assert_eq!(rtm.fchain_get("FCC", None), Some(Vec::from("prog_chain")));
+        assert_eq!(rtm.fchain_get("FCPROG", None), Some(Vec::from("chain")));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn cfggroup_locate() {
+        assert_eq!(find_word("  HELLO HELL", "HELL"), Some(9));
+        assert_eq!(find_word("  HELLO_1", "HELLO"), None);
+        assert_eq!(col_of("  élan x", "x"), 8);
+        assert_eq!(col_of("   nothere", "x"), 4);
+        let mut rtm = RunTimeManager::new_seeded(0);
+        // the group starts at line 11, the issue is located using the msg from the actual handler
+        let mut locate = |lines: &[&str]| {
+            let mut cg = cfggroup(lines);
+            let msg = rtm.handle_cfggroup(&mut cg).unwrap_err();
+            let group = CfgGroupLoc { file: "t.fc".to_string(), linenos: (11..11+lines.len()).collect(), lines: lines.iter().map(|l| l.to_string()).collect() };
+            group.locate(cg.len(), &msg)
+        };
+        assert_eq!(locate(&["FuzzerType:LoopFixedStringsFuzzer:L1", "  list:", "    a", "     b"]), (14, 6));
+        assert_eq!(locate(&["FuzzerType:RandomRandomFuzzer:RR1", "  minlen: 1", "   maxlen: 2"]), (13, 4));
+        assert_eq!(locate(&["FuzzerType:NoSuchFuzzer:NS1", "  byte: Z"]), (11, 12));
+        assert_eq!(locate(&["FuzzerType:RandomRandomFuzzer:RR2", "  minlen: 8", "  maxlen: 4"]), (13, 3));
+        assert_eq!(locate(&["FuzzerType:LoopFixedStringsFuzzer:L2", "  list:", "    a\\qb"]), (13, 6));
+        assert_eq!(locate(&["FuzzerType:NumberFuzzer:N1", "  encoding: ébe"]), (12, 3));
+        // unknown references from fuzzers are found at the cfg end
+        let lines = ["FuzzerType:ChoiceFuzzer:CH1", "  choices:", "    NOPE:3", "    ALSONOPE"];
+        rtm.handle_cfggroup(&mut cfggroup(&lines)).unwrap();
+        let msg = rtm.handle_cfgend().unwrap_err().to_string();
+        let group = CfgGroupLoc { file: "t.fc".to_string(), linenos: (11..11+lines.len()).collect(), lines: lines.iter().map(|l| l.to_string()).collect() };
+        assert_eq!(group.locate(0, &msg), (13, 5));
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:LoopFixedStringsFuzzer:L3", "  list:", "    a"])).unwrap();
+        let mut cg = cfggroup(&["FuzzChain:FuzzChain: L3", "  L3"]);
+        let msg = rtm.handle_cfggroup(&mut cg).unwrap_err();
+        let group = CfgGroupLoc { file: "t.fc".to_string(), linenos: vec![21, 22], lines: cg.iter().map(|l| l.to_string()).collect() };
+        assert_eq!(group.locate(0, &msg), (21, 22));
+    }
+
 }
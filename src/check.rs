@@ -0,0 +1,212 @@
+//!
+//! Check: Validate the cfg (.fc) files and asm programs, without running anything,
+//! reporting all the issues found along with their file, line and column.
+//!
+//! HanishKVC, 2022
+//!
+
+use crate::cfgfiles::{CfgGroupLoc, col_of};
+pub use crate::cfgfiles::{Diagnostic, Severity};
+use crate::choice::ElementSpec;
+use crate::error::FuzzerKError;
+use crate::rtm::{RunTimeManager, TYPEMARKER_FUZZCHAIN};
+
+
+///
+/// Get the instance name of the fuzzer or fuzzchain defined by the cfggroup, if any
+///
+pub(crate) fn group_name(group: &CfgGroupLoc) -> Option<&str> {
+    let la: Vec<&str> = group.lines[0].split(':').collect();
+    if la.len() != 3 {
+        return None;
+    }
+    Some(la[2].trim())
+}
+
+///
+/// Check if the cfggroup defines a fuzzchain
+///
+pub(crate) fn group_isfchain(group: &CfgGroupLoc) -> bool {
+    group.lines[0].starts_with(TYPEMARKER_FUZZCHAIN)
+}
+
+///
+/// Check that the elements of each FuzzChain in the given cfggroups, refer to a known fuzzer or
+/// fuzzchain. References to fuzzers or fuzzchains which are defined in the cfggroups, but which
+/// couldnt be created due to issues with them (already reported), are not reported again.
+/// Any other fuzzchain which couldnt be created is part of a cycle, the passed issue explains it.
+/// The passed unknown references and cycle issue, which are wrt fuzzers (which refer to other
+/// fuzzers or fuzzchains by name), are reported wrt the fuzzer's cfggroup.
+///
+pub fn check_fchains(rtm: &RunTimeManager, groups: &Vec<CfgGroupLoc>, unknown: Vec<FuzzerKError>, cfgendissue: Option<FuzzerKError>) -> Vec<Diagnostic> {
+    let mut diags = Vec::new();
+    let defined = |name: &str| groups.iter().any(|group| group_name(group) == Some(name));
+    let mut cycle = Vec::new();
+    let cfgendissue = cfgendissue.map(|err| match err {
+        FuzzerKError::CfgFile { msg, .. } => msg,
+        err => err.to_string(),
+    });
+    if let Some(issue) = &cfgendissue {
+        if let Some((_, scycle)) = issue.split_once("Cycle ") {
+            cycle = scycle.split(" -> ").collect();
+        }
+    }
+    let mut fuzzerissues = unknown;
+    fuzzerissues.retain(|err| match err {
+        FuzzerKError::CfgFile { msg, .. } => msg.split_once("unknown fuzzer ").is_some_and(|(_, rname)| !defined(rname)),
+        _ => false,
+    });
+    for err in fuzzerissues {
+        if let FuzzerKError::CfgFile { file, line, msg } = err {
+            let group = groups.iter().find(|group| group.file == file && group.linenos[0] == line && !group_isfchain(group));
+            if let Some(group) = group {
+                let (line, col) = group.locate(0, &msg);
+                diags.push(Diagnostic::error(&group.file, line, col, &msg));
+            }
+        }
+    }
+    for group in groups.iter().filter(|group| !group_isfchain(group)) {
+        if let Some(name) = group_name(group).filter(|name| cycle.contains(name)) {
+            diags.push(Diagnostic::error(&group.file, group.linenos[0], col_of(&group.lines[0], name), cfgendissue.as_ref().unwrap()));
+        }
+    }
+    for group in groups {
+        let name = group_name(group);
+        if !group_isfchain(group) || name.is_none() {
+            continue;
+        }
+        let name = name.unwrap();
+        if rtm.fchain(name).is_some() {
+            continue;
+        }
+        let mut bunknown = false;
+        for i in 1..group.lines.len() {
+            let element = ElementSpec::parse(&group.lines[i]);
+            if element.is_err() {
+                continue;
+            }
+            let element = element.unwrap();
+            if rtm.fuzzer_or_fchain(&element.name).is_some() || defined(&element.name) {
+                continue;
+            }
+            bunknown = true;
+            diags.push(Diagnostic::error(&group.file, group.linenos[i], col_of(&group.lines[i], &element.name),
+                &format!("ERRR:Check:FuzzChain:{}:Reference to unknown fuzzer {}", name, element.name)));
+        }
+        if !bunknown && cycle.contains(&name) {
+            diags.push(Diagnostic::error(&group.file, group.linenos[0], col_of(&group.lines[0], name), cfgendissue.as_ref().unwrap()));
+        }
+    }
+    diags
+}
+
+///
+/// Warn about the fuzzers defined in the given cfggroups, which are not used by any
+/// fuzzchain or other fuzzer. Fuzzers referred to by fuzzchains or fuzzers, which couldnt
+/// be created due to issues with them (already reported), are not reported, as the runtime
+/// manager tracks the references, as the cfggroups are handled.
+///
+pub fn check_unused(rtm: &RunTimeManager, groups: &Vec<CfgGroupLoc>) -> Vec<Diagnostic> {
+    let unused = rtm.fuzzers_unused();
+    let mut diags = Vec::new();
+    for group in groups {
+        if group_isfchain(group) {
+            continue;
+        }
+        let name = group_name(group);
+        if name.is_none() || !unused.iter().any(|uname| uname == name.unwrap()) {
+            continue;
+        }
+        let name = name.unwrap();
+        diags.push(Diagnostic::warning(&group.file, group.linenos[0], col_of(&group.lines[0], name),
+            &format!("WARN:Check:Fuzzer {} is not used by any fuzzchain or fuzzer", name)));
+    }
+    diags
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfgfiles;
+
+    #[test]
+    fn cfgfile_check() {
+        use crate::vm::VM;
+        let dir = std::env::temp_dir().join(format!("fuzzerk-check-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cfg = [
+            "FuzzerType:RandomRandomFuzzer:RR01",
+            "  minlen: 8",
+            "# comments dont affect the line numbers",
+            "  maxlen: 4",
+            "",
+            "FuzzerType:NoSuchFuzzer:NS01",
+            "  byte: Z",
+            "",
+            "FuzzerType:LoopFixedStringsFuzzer:ESC",
+            "  list:",
+            "    bad\\qescape",
+            "",
+            "FuzzerType:LoopFixedStringsFuzzer:HELLO",
+            "  list:",
+            "    Hello",
+            "",
+            "FuzzerType:LoopFixedStringsFuzzer:UNUSED",
+            "  list:",
+            "    x",
+            "",
+            "FuzzChain:FuzzChain:FC01",
+            "  HELLO",
+            "  RR01",
+            "  ?NOPE:20",
+            "",
+            "FuzzChain:FuzzChain:FC02",
+            "  HELLO",
+            "",
+            "FuzzerType:ChoiceFuzzer:CH01",
+            "  choices:",
+            "    HELLO",
+            "    NOPE2:3",
+            "",
+        ];
+        let prg = [
+            "!label again",
+            "fcget FC01 buf",
+            "fcget FCX buf",
+            "fcget HELLO buf",
+            "call nofunc",
+            "  iflt.i 1 2 goto agian",
+            "boguscmd x",
+        ];
+        let scfg = dir.join("check.fc").to_str().unwrap().to_string();
+        let sprg = dir.join("check.prg").to_str().unwrap().to_string();
+        std::fs::write(&scfg, cfg.join("\n")).unwrap();
+        std::fs::write(&sprg, prg.join("\n")).unwrap();
+        let diags = VM::new().check(&vec![scfg.clone()], &sprg);
+        let got: Vec<(bool, usize, usize, bool, &str)> = diags.iter().map(|d| (d.file == scfg, d.line, d.col, d.severity == Severity::Error, d.msg.as_str())).collect();
+        let expected = [
+            (true, 4, 3, true, "minlen 8 > maxlen 4"),
+            (true, 6, 12, true, "UnknownFuzzer"),
+            (true, 11, 8, true, "Unsupported escape char q"),
+            (true, 17, 35, false, "Fuzzer UNUSED is not used"),
+            (true, 24, 4, true, "Reference to unknown fuzzer NOPE"),
+            (true, 32, 5, true, "Reference to unknown fuzzer NOPE2"),
+            (false, 3, 7, true, "Unknown fuzzchain FCX"),
+            (false, 4, 7, true, "Unknown fuzzchain HELLO"),
+            (false, 5, 6, true, "Unknown function nofunc"),
+            (false, 6, 19, true, "Unknown label agian"),
+            (false, 7, 1, true, "UnknownOp:boguscmd"),
+        ];
+        assert_eq!(got.len(), expected.len(), "{:#?}", diags);
+        for (got, expected) in got.iter().zip(expected.iter()) {
+            assert!(got.4.contains(expected.4), "{:?} != {:?}", got, expected);
+            assert_eq!((got.0, got.1, got.2, got.3), (expected.0, expected.1, expected.2, expected.3), "{}", got.4);
+        }
+        // loading reports the line with the issue, rather than where its cfggroup starts
+        let gotr = cfgfiles::parse_file(&scfg, &mut RunTimeManager::new_seeded(0));
+        assert!(gotr.unwrap_err().to_string().contains("check.fc:4:ERRR:RandomRandomFuzzer"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+}
@@ -0,0 +1,305 @@
+//!
+//! ChoiceFuzzers: Optional, repeated and weighted choice of fuzzers (or fuzzchains),
+//! so that fuzz chains need not be a fixed linear sequence.
+//!
+//! HanishKVC, 2022
+//!
+
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::cell::RefCell;
+use rand::{Rng, RngCore};
+
+use crate::{Fuzz, append_element};
+use crate::cfgfiles::FromVecStrings;
+use crate::rtm::{RunTimeManager, ResolveRefs};
+
+
+/// The default chance (in percent) of including a optional element
+pub const OPTIONAL_DEFAULT: usize = 50;
+
+
+///
+/// A element of a fuzz chain, as specified in the cfg file, ie
+/// * name
+/// * ?name or ?name:pct, include it with a chance of pct percent
+/// * name*{n} or name*{min,max}, repeat it n or min to max times
+/// * ?name:pct*{min,max}, both of the above
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElementSpec {
+    pub name: String,
+    /// The chance (in percent) of including the element, if optional
+    pub optional: Option<usize>,
+    /// The min and max number of times the element is repeated
+    pub repeat: Option<(usize, usize)>,
+}
+
+impl ElementSpec {
+
+    pub fn parse(selement: &str) -> Result<ElementSpec, String> {
+        let msgtag = "FuzzerK:ElementSpec:Parse";
+        let mut sname = selement.trim();
+        let mut repeat = None;
+        if let Some((sleft, sright)) = sname.split_once("*{") {
+            let srepeat = sright.strip_suffix('}').ok_or(format!("ERRR:{}:{}:Repeat missing closing }}", msgtag, selement))?;
+            let parse = |snum: &str| usize::from_str_radix(snum.trim(), 10).map_err(|e| format!("ERRR:{}:{}:Repeat count:{}", msgtag, selement, e));
+            let (min, max) = match srepeat.split_once(',') {
+                Some((smin, smax)) => (parse(smin)?, parse(smax)?),
+                None => {
+                    let cnt = parse(srepeat)?;
+                    (cnt, cnt)
+                }
+            };
+            if min > max {
+                return Err(format!("ERRR:{}:{}:Repeat min {} > max {}", msgtag, selement, min, max));
+            }
+            repeat = Some((min, max));
+            sname = sleft;
+        }
+        let mut optional = None;
+        if let Some(sopt) = sname.strip_prefix('?') {
+            sname = sopt;
+            let mut pct = OPTIONAL_DEFAULT;
+            if let Some((sleft, spct)) = sname.split_once(':') {
+                pct = usize::from_str_radix(spct.trim(), 10).map_err(|e| format!("ERRR:{}:{}:Optional percent:{}", msgtag, selement, e))?;
+                if pct > 100 {
+                    return Err(format!("ERRR:{}:{}:Optional percent {} > 100", msgtag, selement, pct));
+                }
+                sname = sleft;
+            }
+            optional = Some(pct);
+        }
+        if sname.len() == 0 {
+            return Err(format!("ERRR:{}:{}:Missing name", msgtag, selement));
+        }
+        Ok(ElementSpec {
+            name: sname.to_string(),
+            optional,
+            repeat,
+        })
+    }
+
+    ///
+    /// Wrap the given fuzzer, as required by the modifiers of this element
+    ///
+    pub fn wrap(&self, mut fuzzer: Rc<RefCell<dyn Fuzz>>) -> Rc<RefCell<dyn Fuzz>> {
+        if let Some((min, max)) = self.repeat {
+            fuzzer = Rc::new(RefCell::new(RepeatFuzzer::new(fuzzer, min, max)));
+        }
+        if let Some(pct) = self.optional {
+            fuzzer = Rc::new(RefCell::new(OptionalFuzzer::new(fuzzer, pct)));
+        }
+        fuzzer
+    }
+
+}
+
+
+///
+/// Include the output of the given fuzzer, with a chance of pct percent
+///
+pub struct OptionalFuzzer {
+    fuzzer: Rc<RefCell<dyn Fuzz>>,
+    pct: usize,
+}
+
+impl OptionalFuzzer {
+
+    pub fn new(fuzzer: Rc<RefCell<dyn Fuzz>>, pct: usize) -> OptionalFuzzer {
+        OptionalFuzzer {
+            fuzzer,
+            pct,
+        }
+    }
+
+}
+
+impl Fuzz for OptionalFuzzer {
+
+    fn append_fuzzed_immut(&self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        if (rng.gen::<usize>() % 100) < self.pct {
+            append_element(&self.fuzzer, step, rng, buf);
+        }
+    }
+
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        return self.append_fuzzed_immut(step, rng, buf);
+    }
+
+    fn derived_from(&self) -> Vec<Rc<RefCell<dyn Fuzz>>> {
+        self.fuzzer.borrow().derived_from()
+    }
+
+    fn wrapped(&self) -> Option<Rc<RefCell<dyn Fuzz>>> {
+        Some(self.fuzzer.clone())
+    }
+
+    fn append_derived(&self, step: usize, rng: &mut dyn RngCore, data: &Vec<u8>, buf: &mut Vec<u8>) {
+        if (rng.gen::<usize>() % 100) < self.pct {
+            self.fuzzer.borrow().append_derived(step, rng, data, buf);
+        }
+    }
+
+}
+
+
+///
+/// Repeat the output of the given fuzzer, a random number (min to max) of times
+///
+pub struct RepeatFuzzer {
+    fuzzer: Rc<RefCell<dyn Fuzz>>,
+    min: usize,
+    max: usize,
+}
+
+impl RepeatFuzzer {
+
+    pub fn new(fuzzer: Rc<RefCell<dyn Fuzz>>, min: usize, max: usize) -> RepeatFuzzer {
+        RepeatFuzzer {
+            fuzzer,
+            min,
+            max,
+        }
+    }
+
+}
+
+impl Fuzz for RepeatFuzzer {
+
+    fn append_fuzzed_immut(&self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let cnt = self.min + rng.gen::<usize>() % (self.max - self.min + 1);
+        for _i in 0..cnt {
+            append_element(&self.fuzzer, step, rng, buf);
+        }
+    }
+
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        return self.append_fuzzed_immut(step, rng, buf);
+    }
+
+    fn derived_from(&self) -> Vec<Rc<RefCell<dyn Fuzz>>> {
+        self.fuzzer.borrow().derived_from()
+    }
+
+    fn wrapped(&self) -> Option<Rc<RefCell<dyn Fuzz>>> {
+        Some(self.fuzzer.clone())
+    }
+
+    fn append_derived(&self, step: usize, rng: &mut dyn RngCore, data: &Vec<u8>, buf: &mut Vec<u8>) {
+        let cnt = self.min + rng.gen::<usize>() % (self.max - self.min + 1);
+        for _i in 0..cnt {
+            self.fuzzer.borrow().append_derived(step, rng, data, buf);
+        }
+    }
+
+}
+
+
+///
+/// Pick one of the given fuzzers (or fuzzchains) randomly, as per their weights
+///
+pub struct ChoiceFuzzer {
+    /// The names (as specified in the cfg file) and weights of the choices
+    names: Vec<(String, usize)>,
+    choices: Vec<(Rc<RefCell<dyn Fuzz>>, usize)>,
+}
+
+impl ChoiceFuzzer {
+
+    pub fn new(choices: Vec<(Rc<RefCell<dyn Fuzz>>, usize)>) -> ChoiceFuzzer {
+        ChoiceFuzzer {
+            names: Vec::new(),
+            choices,
+        }
+    }
+
+}
+
+impl ResolveRefs for ChoiceFuzzer {
+
+    fn refs(&self) -> Vec<String> {
+        self.names.iter().map(|(name, _weight)| name.clone()).collect()
+    }
+
+    fn resolve(&mut self, rtm: &RunTimeManager) -> Result<(), String> {
+        let mut choices = Vec::new();
+        for (name, weight) in &self.names {
+            let fuzzer = rtm.fuzzer_or_fchain(name);
+            if fuzzer.is_none() {
+                return Err(format!("ERRR:ChoiceFuzzer:Resolve:Reference to unknown fuzzer {}", name));
+            }
+            choices.push((fuzzer.unwrap(), *weight));
+        }
+        self.choices = choices;
+        Ok(())
+    }
+
+}
+
+impl Fuzz for ChoiceFuzzer {
+
+    fn append_fuzzed_immut(&self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let total: usize = self.choices.iter().map(|(_f, weight)| weight).sum();
+        if total == 0 {
+            return;
+        }
+        let mut pick = rng.gen::<usize>() % total;
+        for (fuzzer, weight) in &self.choices {
+            if pick < *weight {
+                append_element(fuzzer, step, rng, buf);
+                return;
+            }
+            pick -= weight;
+        }
+    }
+
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        return self.append_fuzzed_immut(step, rng, buf);
+    }
+
+}
+
+impl FromVecStrings for ChoiceFuzzer {
+
+    fn get_name() -> String {
+        return "ChoiceFuzzer".to_string();
+    }
+
+    ///
+    /// In the cfgfile use
+    /// ### FuzzerType:ChoiceFuzzer:InstanceName
+    /// * choices: list of fuzzer or fuzzchain names, each optionally followed by :weight (default 1)
+    ///
+    /// The fuzzers referenced are resolved by the runtime manager, once created.
+    ///
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<ChoiceFuzzer, String> {
+        let l = vs.pop_front();
+        if l.is_none() {
+            return Err("ERRR:ChoiceFuzzer:FromStringVec:Got empty vector".to_string());
+        }
+        let _l = l.unwrap(); // This should identify this particular type of Fuzzer and a runtime instance name
+        let spacesprefix = Self::get_spacesprefix(vs);
+        let choices = Self::get_values(vs, "choices", spacesprefix).map_err(|e| format!("ERRR:ChoiceFuzzer:GetChoices:{}", e))?;
+        let mut names = Vec::new();
+        for choice in choices {
+            let choice = String::from_utf8_lossy(&choice).trim().to_string();
+            let (name, weight) = match choice.split_once(':') {
+                Some((name, sweight)) => {
+                    let weight = usize::from_str_radix(sweight.trim(), 10).map_err(|e| format!("ERRR:ChoiceFuzzer:{}:Weight:{}", choice, e))?;
+                    (name.trim().to_string(), weight)
+                }
+                None => (choice.clone(), 1),
+            };
+            names.push((name, weight));
+        }
+        if names.iter().map(|(_n, weight)| weight).sum::<usize>() == 0 {
+            return Err("ERRR:ChoiceFuzzer:FromStringVec:Need atleast one choice with a non zero weight".to_string());
+        }
+        Ok(ChoiceFuzzer {
+            names,
+            choices: Vec::new(),
+        })
+    }
+
+}
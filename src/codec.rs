@@ -0,0 +1,445 @@
+//!
+//! Codec: Encodings applied on the output of fuzzers, before its sent,
+//! optionally with the encoding itself deliberately corrupted.
+//!
+//! HanishKVC, 2022
+//!
+
+use std::collections::HashMap;
+use rand::{Rng, RngCore};
+
+use crate::derived::ChecksumAlgo;
+
+
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const HEX_CHARS: &[u8; 16] = b"0123456789ABCDEF";
+
+/// The max size of the chunks, wrt http chunked transfer encoding
+pub const CHUNK_MAXSIZE: usize = 1024;
+/// The max length of lines, wrt quoted printable encoding
+const QP_MAXLINE: usize = 76;
+
+/// Deflate length codes 257 to 285: the base lengths and the number of extra bits
+const DEFLATE_LENBASE: [usize; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const DEFLATE_LENEXTRA: [usize; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+/// Deflate distance codes 0 to 29: the base distances and the number of extra bits
+const DEFLATE_DISTBASE: [usize; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DEFLATE_DISTEXTRA: [usize; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+const DEFLATE_WINDOW: usize = 32768;
+const DEFLATE_MAXMATCH: usize = 258;
+
+
+///
+/// The encodings supported
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Codec {
+    /// data is used as is
+    Raw,
+    /// percent encode all bytes other than unreserved chars (alphanumerics and - . _ ~)
+    Url,
+    /// url encode twice, ie % of the 1st round is encoded as %25
+    DoubleUrl,
+    /// non alphanumeric bytes as &#xNN; numeric char references
+    Html,
+    /// standard base64, with padding
+    Base64,
+    /// uppercase hex string
+    Hex,
+    /// gzip (RFC 1952) stream
+    Gzip,
+    /// zlib (RFC 1950) stream, as used by http deflate content encoding
+    Deflate,
+    /// http chunked transfer encoding
+    HttpChunked,
+    /// quoted printable (RFC 2045)
+    QuotedPrintable,
+}
+
+impl Codec {
+
+    ///
+    /// Map a encoding name like raw, url, doubleurl, html, base64, hex, gzip, deflate,
+    /// http-chunked, quoted-printable
+    ///
+    pub fn from_str(scodec: &str) -> Result<Codec, String> {
+        match scodec {
+            "raw" => Ok(Self::Raw),
+            "url" => Ok(Self::Url),
+            "doubleurl" => Ok(Self::DoubleUrl),
+            "html" => Ok(Self::Html),
+            "base64" => Ok(Self::Base64),
+            "hex" => Ok(Self::Hex),
+            "gzip" => Ok(Self::Gzip),
+            "deflate" => Ok(Self::Deflate),
+            "http-chunked" => Ok(Self::HttpChunked),
+            "quoted-printable" => Ok(Self::QuotedPrintable),
+            _ => Err(format!("ERRR:Codec:Unknown encoding:{}", scodec)),
+        }
+    }
+
+    ///
+    /// Append the encoded form of the given data to the passed buf
+    ///
+    pub fn encode(&self, data: &[u8], buf: &mut Vec<u8>) {
+        match self {
+            Self::Raw => buf.extend_from_slice(data),
+            Self::Url => url_encode(data, buf),
+            Self::DoubleUrl => {
+                let mut tbuf = Vec::new();
+                url_encode(data, &mut tbuf);
+                url_encode(&tbuf, buf);
+            }
+            Self::Html => html_encode(data, buf),
+            Self::Base64 => base64_encode(data, buf),
+            Self::Hex => hex_encode(data, buf),
+            Self::Gzip => gzip_encode(data, buf),
+            Self::Deflate => zlib_encode(data, buf),
+            Self::HttpChunked => chunked_encode(data, buf),
+            Self::QuotedPrintable => qp_encode(data, buf),
+        }
+    }
+
+    ///
+    /// Append the encoded form of the given data to the passed buf, with the encoding
+    /// deliberately corrupted, like bad padding, bad escapes, bad chunk sizes, truncated
+    /// or bad checksum wrt gzip / deflate.
+    ///
+    pub fn encode_corrupted(&self, data: &[u8], rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let mut tbuf = Vec::new();
+        match self {
+            Self::Raw => tbuf.extend_from_slice(data),
+            Self::Url | Self::DoubleUrl => {
+                self.encode(data, &mut tbuf);
+                let bad: [&[u8]; 3] = [b"%", b"%G1", b"%0"];
+                tbuf.extend_from_slice(bad[rng.gen::<usize>() % bad.len()]);
+            }
+            Self::Html => {
+                html_encode(data, &mut tbuf);
+                let bad: [&[u8]; 3] = [b"&#x3C", b"&#xZZ;", b"&#99999999999;"];
+                tbuf.extend_from_slice(bad[rng.gen::<usize>() % bad.len()]);
+            }
+            Self::Base64 => {
+                base64_encode(data, &mut tbuf);
+                match rng.gen::<usize>() % 3 {
+                    0 => {
+                        // missing or excess padding
+                        if tbuf.last() == Some(&b'=') {
+                            while tbuf.last() == Some(&b'=') {
+                                tbuf.pop();
+                            }
+                        } else {
+                            tbuf.extend_from_slice(b"==");
+                        }
+                    }
+                    1 => tbuf.insert(rng.gen::<usize>() % (tbuf.len() + 1), b'*'),
+                    _ => {
+                        let drop = (1 + rng.gen::<usize>() % 3).min(tbuf.len());
+                        tbuf.truncate(tbuf.len() - drop);
+                        tbuf.push(b'A');
+                    }
+                }
+            }
+            Self::Hex => {
+                hex_encode(data, &mut tbuf);
+                if (tbuf.len() > 0) && rng.gen::<bool>() {
+                    tbuf.pop();
+                } else {
+                    tbuf.insert(rng.gen::<usize>() % (tbuf.len() + 1), b'G');
+                }
+            }
+            Self::Gzip | Self::Deflate => {
+                self.encode(data, &mut tbuf);
+                match rng.gen::<usize>() % 3 {
+                    0 => {
+                        let drop = 1 + rng.gen::<usize>() % (tbuf.len() / 2);
+                        tbuf.truncate(tbuf.len() - drop);
+                    }
+                    1 => {
+                        // the checksum is at the end, wrt both
+                        let i = tbuf.len() - 1 - rng.gen::<usize>() % 4;
+                        tbuf[i] ^= 1 + rng.gen::<u8>() % 0xFF;
+                    }
+                    _ => tbuf[0] ^= 1 + rng.gen::<u8>() % 0xFF,
+                }
+            }
+            Self::HttpChunked => {
+                let chunks: Vec<&[u8]> = data.chunks(CHUNK_MAXSIZE).collect();
+                let kind = rng.gen::<usize>() % 4;
+                let badi = if chunks.len() > 0 { rng.gen::<usize>() % chunks.len() } else { 0 };
+                for (i, chunk) in chunks.iter().enumerate() {
+                    let mut size = format!("{:X}", chunk.len());
+                    if i == badi {
+                        size = match kind {
+                            0 => format!("{:X}", chunk.len() + 1 + rng.gen::<usize>() % 16),
+                            1 => format!("{:X}", chunk.len().saturating_sub(1 + rng.gen::<usize>() % 16)),
+                            2 => "ZZ".to_string(),
+                            _ => size,
+                        };
+                    }
+                    tbuf.extend_from_slice(format!("{}\r\n", size).as_bytes());
+                    tbuf.extend_from_slice(chunk);
+                    tbuf.extend_from_slice(b"\r\n");
+                }
+                if chunks.len() == 0 {
+                    // truncated terminating chunk
+                    tbuf.extend_from_slice(b"0\r\n");
+                } else if kind != 3 {
+                    tbuf.extend_from_slice(b"0\r\n\r\n");
+                }
+            }
+            Self::QuotedPrintable => {
+                qp_encode(data, &mut tbuf);
+                match rng.gen::<usize>() % 3 {
+                    0 => tbuf.insert(rng.gen::<usize>() % (tbuf.len() + 1), b'='),
+                    1 => tbuf.extend_from_slice(b"=G1"),
+                    _ => {
+                        // drop the soft line breaks, so lines become overlong
+                        let mut sbuf = Vec::new();
+                        let mut i = 0;
+                        while i < tbuf.len() {
+                            if tbuf[i..].starts_with(b"=\r\n") {
+                                i += 3;
+                                continue;
+                            }
+                            sbuf.push(tbuf[i]);
+                            i += 1;
+                        }
+                        tbuf = sbuf;
+                        tbuf.extend(vec![b'X'; QP_MAXLINE + 1]);
+                    }
+                }
+            }
+        }
+        buf.append(&mut tbuf);
+    }
+
+}
+
+
+pub fn url_encode(data: &[u8], buf: &mut Vec<u8>) {
+    for &b in data {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+            buf.push(b);
+        } else {
+            buf.extend_from_slice(&[b'%', HEX_CHARS[(b >> 4) as usize], HEX_CHARS[(b & 0xF) as usize]]);
+        }
+    }
+}
+
+pub fn html_encode(data: &[u8], buf: &mut Vec<u8>) {
+    for &b in data {
+        if b.is_ascii_alphanumeric() {
+            buf.push(b);
+        } else {
+            buf.extend_from_slice(format!("&#x{:02X};", b).as_bytes());
+        }
+    }
+}
+
+pub fn base64_encode(data: &[u8], buf: &mut Vec<u8>) {
+    for chunk in data.chunks(3) {
+        let mut group = [0u8; 3];
+        group[..chunk.len()].copy_from_slice(chunk);
+        let val = (group[0] as usize) << 16 | (group[1] as usize) << 8 | group[2] as usize;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                buf.push(BASE64_CHARS[(val >> (18 - 6*i)) & 0x3F]);
+            } else {
+                buf.push(b'=');
+            }
+        }
+    }
+}
+
+pub fn hex_encode(data: &[u8], buf: &mut Vec<u8>) {
+    for &b in data {
+        buf.extend_from_slice(&[HEX_CHARS[(b >> 4) as usize], HEX_CHARS[(b & 0xF) as usize]]);
+    }
+}
+
+///
+/// Encode as a series of chunks of upto CHUNK_MAXSIZE, followed by the terminating 0 sized chunk
+///
+pub fn chunked_encode(data: &[u8], buf: &mut Vec<u8>) {
+    for chunk in data.chunks(CHUNK_MAXSIZE) {
+        buf.extend_from_slice(format!("{:X}\r\n", chunk.len()).as_bytes());
+        buf.extend_from_slice(chunk);
+        buf.extend_from_slice(b"\r\n");
+    }
+    buf.extend_from_slice(b"0\r\n\r\n");
+}
+
+///
+/// Encode treating the data as binary, ie line breaks and whitespaces in data are also
+/// escaped, with soft line breaks to keep lines within QP_MAXLINE.
+///
+pub fn qp_encode(data: &[u8], buf: &mut Vec<u8>) {
+    let mut linelen = 0;
+    for (i, &b) in data.iter().enumerate() {
+        let mut enc = vec![b];
+        if !(33..=126).contains(&b) || (b == b'=') {
+            enc = vec![b'=', HEX_CHARS[(b >> 4) as usize], HEX_CHARS[(b & 0xF) as usize]];
+        }
+        // leave space for the soft line break, unless this is the last char
+        let maxlen = if i == data.len() - 1 { QP_MAXLINE } else { QP_MAXLINE - 1 };
+        if linelen + enc.len() > maxlen {
+            buf.extend_from_slice(b"=\r\n");
+            linelen = 0;
+        }
+        linelen += enc.len();
+        buf.append(&mut enc);
+    }
+}
+
+
+///
+/// Write bits into a byte buffer, starting from the least significant bit, as reqd by deflate
+///
+struct BitWriter {
+    buf: Vec<u8>,
+    bitcnt: usize,
+}
+
+impl BitWriter {
+
+    fn new() -> BitWriter {
+        BitWriter {
+            buf: Vec::new(),
+            bitcnt: 0,
+        }
+    }
+
+    fn write_bits(&mut self, val: usize, nbits: usize) {
+        for i in 0..nbits {
+            if self.bitcnt % 8 == 0 {
+                self.buf.push(0);
+            }
+            if (val >> i) & 1 != 0 {
+                *self.buf.last_mut().unwrap() |= 1 << (self.bitcnt % 8);
+            }
+            self.bitcnt += 1;
+        }
+    }
+
+    /// Huffman codes are written starting from their most significant bit
+    fn write_code(&mut self, code: usize, nbits: usize) {
+        for i in (0..nbits).rev() {
+            self.write_bits((code >> i) & 1, 1);
+        }
+    }
+
+    /// Write a literal/length symbol, using the fixed huffman codes
+    fn write_litlen(&mut self, sym: usize) {
+        match sym {
+            0..=143 => self.write_code(0x30 + sym, 8),
+            144..=255 => self.write_code(0x190 + sym - 144, 9),
+            256..=279 => self.write_code(sym - 256, 7),
+            _ => self.write_code(0xC0 + sym - 280, 8),
+        }
+    }
+
+}
+
+///
+/// Compress into a raw deflate (RFC 1951) stream, using a single block with fixed huffman
+/// codes and greedy LZ77 matching.
+///
+pub fn deflate_encode(data: &[u8], buf: &mut Vec<u8>) {
+    let mut bw = BitWriter::new();
+    bw.write_bits(1, 1); // BFINAL
+    bw.write_bits(1, 2); // BTYPE fixed huffman
+    let mut lastpos: HashMap<&[u8], usize> = HashMap::new();
+    let mut i = 0;
+    while i < data.len() {
+        let mut mlen = 0;
+        let mut mdist = 0;
+        if i + 3 <= data.len() {
+            if let Some(&j) = lastpos.get(&data[i..i+3]) {
+                if i - j <= DEFLATE_WINDOW {
+                    while (mlen < DEFLATE_MAXMATCH) && (i + mlen < data.len()) && (data[j + mlen] == data[i + mlen]) {
+                        mlen += 1;
+                    }
+                    mdist = i - j;
+                }
+            }
+            lastpos.insert(&data[i..i+3], i);
+        }
+        if mlen < 3 {
+            bw.write_litlen(data[i] as usize);
+            i += 1;
+            continue;
+        }
+        let lcode = DEFLATE_LENBASE.iter().rposition(|&base| base <= mlen).unwrap();
+        bw.write_litlen(257 + lcode);
+        bw.write_bits(mlen - DEFLATE_LENBASE[lcode], DEFLATE_LENEXTRA[lcode]);
+        let dcode = DEFLATE_DISTBASE.iter().rposition(|&base| base <= mdist).unwrap();
+        bw.write_code(dcode, 5);
+        bw.write_bits(mdist - DEFLATE_DISTBASE[dcode], DEFLATE_DISTEXTRA[dcode]);
+        for k in i+1..(i+mlen).min(data.len().saturating_sub(2)) {
+            lastpos.insert(&data[k..k+3], k);
+        }
+        i += mlen;
+    }
+    bw.write_litlen(256);
+    buf.append(&mut bw.buf);
+}
+
+pub fn zlib_encode(data: &[u8], buf: &mut Vec<u8>) {
+    buf.extend_from_slice(&[0x78, 0x01]);
+    deflate_encode(data, buf);
+    buf.extend_from_slice(&ChecksumAlgo::Adler32.calc(data).to_be_bytes());
+}
+
+pub fn gzip_encode(data: &[u8], buf: &mut Vec<u8>) {
+    // magic, deflate, no flags, no mtime, no extra flags, unknown os
+    buf.extend_from_slice(&[0x1F, 0x8B, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF]);
+    deflate_encode(data, buf);
+    buf.extend_from_slice(&ChecksumAlgo::Crc32.calc(data).to_le_bytes());
+    buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn codec_encode() {
+        use crate::derived::ChecksumAlgo;
+        let encode = |codec: Codec, data: &[u8]| {
+            let mut buf = Vec::new();
+            codec.encode(data, &mut buf);
+            buf
+        };
+        assert_eq!(encode(Codec::Hex, b"\x01\xAB"), Vec::from("01AB"));
+        assert_eq!(encode(Codec::HttpChunked, b"fuzz"), Vec::from("4\r\nfuzz\r\n0\r\n\r\n"));
+        assert_eq!(encode(Codec::HttpChunked, &vec![b'A'; CHUNK_MAXSIZE+1])[..7], Vec::from("400\r\nAA")[..]);
+        assert_eq!(encode(Codec::QuotedPrintable, b"a=b \r\n"), Vec::from("a=3Db=20=0D=0A"));
+        let qp = encode(Codec::QuotedPrintable, &vec![b'='; 100]);
+        assert!(qp.split(|&b| b == b'\n').all(|l| l.len() <= 77));
+        // fixed huffman codes, with the 2nd abc as a back reference
+        let mut deflated = Vec::new();
+        deflate_encode(b"abcabc", &mut deflated);
+        assert_eq!(deflated, vec![0x4B, 0x4C, 0x4A, 0x06, 0x22, 0x00]);
+        let gz = encode(Codec::Gzip, b"abcabc");
+        assert_eq!(gz[..3], [0x1F, 0x8B, 0x08]);
+        assert_eq!(gz[10..16], deflated[..]);
+        assert_eq!(gz[16..20], ChecksumAlgo::Crc32.calc(b"abcabc").to_le_bytes());
+        assert_eq!(gz[20..], [6, 0, 0, 0]);
+        let zl = encode(Codec::Deflate, b"abcabc");
+        assert_eq!(zl[..2], [0x78, 0x01]);
+        assert_eq!(zl[zl.len()-4..], ChecksumAlgo::Adler32.calc(b"abcabc").to_be_bytes());
+        // corrupted
+        let mut rng = StdRng::seed_from_u64(0);
+        for codec in [Codec::Base64, Codec::Hex, Codec::Gzip, Codec::HttpChunked, Codec::QuotedPrintable, Codec::Url] {
+            for _i in 0..16 {
+                let mut buf = Vec::new();
+                codec.encode_corrupted(b"fuzzerk", &mut rng, &mut buf);
+                assert_ne!(buf, encode(codec, b"fuzzerk"));
+            }
+        }
+    }
+
+}
@@ -0,0 +1,505 @@
+//!
+//! DerivedFuzzers: Length and checksum fields, whose value is derived from the output
+//! of sibling fuzzers in the same fuzz chain or of their own sub chain.
+//!
+//! HanishKVC, 2022
+//!
+
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::cell::RefCell;
+use rand::{Rng, RngCore};
+use loggerk::log_e;
+
+use crate::{Fuzz, FuzzChain};
+use crate::cfgfiles::FromVecStrings;
+use crate::number::NumEncoding;
+use crate::rtm::{RunTimeManager, ResolveRefs};
+
+
+///
+/// The data over which the length or checksum is calculated
+///
+enum Region {
+    /// The names of the sibling fuzzers in the same fuzz chain, and the fuzzers once resolved
+    Siblings(Vec<String>, Vec<Rc<RefCell<dyn Fuzz>>>),
+    /// The names of the fuzzers in the sub chain, and the sub chain once resolved.
+    /// The output of the sub chain follows the length or checksum.
+    SubChain(Vec<String>, FuzzChain),
+}
+
+impl Region {
+
+    ///
+    /// Get the region specified using either
+    /// * of: the list of sibling fuzzers
+    /// * chain: the list of fuzzers in the sub chain
+    ///
+    fn from_vs<T: FromVecStrings>(vs: &mut VecDeque<String>, spacesprefix: usize) -> Result<Region, String> {
+        let name = T::get_name();
+        let bsiblings = T::has_key(vs, "of", spacesprefix);
+        if !bsiblings && !T::has_key(vs, "chain", spacesprefix) {
+            return Err(format!("ERRR:{}:FromStringVec:Specify either of or chain", name));
+        }
+        let key = if bsiblings { "of" } else { "chain" };
+        let names = T::get_values(vs, key, spacesprefix).map_err(|e| format!("ERRR:{}:Get{}:{}", name, key, e))?;
+        if names.len() == 0 {
+            return Err(format!("ERRR:{}:FromStringVec:Empty {} list", name, key));
+        }
+        let names = names.iter().map(|n| String::from_utf8_lossy(n).trim().to_string()).collect();
+        if bsiblings {
+            return Ok(Region::Siblings(names, Vec::new()));
+        }
+        Ok(Region::SubChain(names, FuzzChain::new()))
+    }
+
+    fn names(&self) -> Vec<String> {
+        match self {
+            Region::Siblings(names, _) => names.clone(),
+            Region::SubChain(names, _) => names.clone(),
+        }
+    }
+
+    ///
+    /// Lookup the fuzzers referenced by name, which should have been created already
+    ///
+    fn resolve(&mut self, rtm: &RunTimeManager, msgtag: &str) -> Result<(), String> {
+        let (names, bsubchain) = match self {
+            Region::Siblings(names, _) => (names.clone(), false),
+            Region::SubChain(names, _) => (names.clone(), true),
+        };
+        let mut fuzzers = Vec::new();
+        for name in names {
+            let fuzzer = rtm.fuzzer_or_fchain(&name);
+            if fuzzer.is_none() {
+                return Err(format!("ERRR:{}:Resolve:Reference to unknown fuzzer {}", msgtag, name));
+            }
+            fuzzers.push(fuzzer.unwrap());
+        }
+        if !bsubchain {
+            if let Region::Siblings(_, sfuzzers) = self {
+                *sfuzzers = fuzzers;
+            }
+            return Ok(());
+        }
+        let mut fc = FuzzChain::new();
+        for fuzzer in fuzzers {
+            fc.append(fuzzer);
+        }
+        fc.check_derived().map_err(|e| format!("ERRR:{}:Resolve:SubChain:{}", msgtag, e))?;
+        if let Region::SubChain(_, sfc) = self {
+            *sfc = fc;
+        }
+        Ok(())
+    }
+
+    fn derived_from(&self) -> Vec<Rc<RefCell<dyn Fuzz>>> {
+        match self {
+            Region::Siblings(_, fuzzers) => fuzzers.clone(),
+            Region::SubChain(_, _) => Vec::new(),
+        }
+    }
+
+    fn need_mutable(&self) -> bool {
+        match self {
+            Region::Siblings(_, _) => false,
+            Region::SubChain(_, _) => true,
+        }
+    }
+
+}
+
+///
+/// Get the corrupt percent, ie the chance of deliberately corrupting the derived value
+///
+fn fromvs_corrupt<T: FromVecStrings>(vs: &mut VecDeque<String>, spacesprefix: usize) -> Result<usize, String> {
+    let corrupt = T::get_uvalue_or(vs, "corrupt", spacesprefix, 0).map_err(|e| format!("ERRR:{}:GetCorrupt:{}", T::get_name(), e))?;
+    if corrupt > 100 {
+        return Err(format!("ERRR:{}:FromStringVec:corrupt {} > 100", T::get_name(), corrupt));
+    }
+    Ok(corrupt)
+}
+
+
+///
+/// Generate the length of the data in its region, adjusted and encoded as specified.
+/// It can be deliberately corrupted with a chance of corrupt percent.
+///
+pub struct LengthField {
+    region: Region,
+    encoding: NumEncoding,
+    /// Added to the length of the data, ie to account for any header bytes and so
+    adjust: isize,
+    corrupt: usize,
+}
+
+impl LengthField {
+
+    ///
+    /// Create a length field, wrt the given sibling fuzzers (in the same fuzz chain)
+    ///
+    pub fn new_siblings(fuzzers: Vec<Rc<RefCell<dyn Fuzz>>>, encoding: NumEncoding, adjust: isize, corrupt: usize) -> LengthField {
+        LengthField {
+            region: Region::Siblings(Vec::new(), fuzzers),
+            encoding,
+            adjust,
+            corrupt,
+        }
+    }
+
+    ///
+    /// Create a length field followed by the output of the given sub chain
+    ///
+    pub fn new_subchain(fc: FuzzChain, encoding: NumEncoding, adjust: isize, corrupt: usize) -> LengthField {
+        LengthField {
+            region: Region::SubChain(Vec::new(), fc),
+            encoding,
+            adjust,
+            corrupt,
+        }
+    }
+
+    fn value(&self, rng: &mut dyn RngCore, datalen: usize) -> i128 {
+        let (emin, emax) = self.encoding.range();
+        let mut len = datalen as i128 + self.adjust as i128;
+        if (rng.gen::<usize>() % 100) < self.corrupt {
+            len = match rng.gen::<usize>() % 4 {
+                0 => len + 1 + (rng.gen::<usize>() % 16) as i128,
+                1 => len - 1 - (rng.gen::<usize>() % 16) as i128,
+                2 => if len == 0 { emax } else { 0 },
+                _ => emax,
+            };
+        }
+        len.max(emin).min(emax)
+    }
+
+}
+
+impl ResolveRefs for LengthField {
+
+    fn refs(&self) -> Vec<String> {
+        self.region.names()
+    }
+
+    fn resolve(&mut self, rtm: &RunTimeManager) -> Result<(), String> {
+        self.region.resolve(rtm, "LengthField")
+    }
+
+}
+
+impl Fuzz for LengthField {
+
+    fn append_fuzzed_immut(&self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        if self.region.need_mutable() {
+            log_e(&format!("ERRR:LengthField:AppendFuzzedImmut:Step {}:SubChain needs mutable access", step));
+            return;
+        }
+        self.append_derived(step, rng, &Vec::new(), buf);
+    }
+
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let mut data = match &mut self.region {
+            Region::SubChain(_, fc) => fc.get(Some(step), rng),
+            Region::Siblings(_, _) => return self.append_fuzzed_immut(step, rng, buf),
+        };
+        self.append_derived(step, rng, &data, buf);
+        buf.append(&mut data);
+    }
+
+    fn need_mutable(&self) -> bool {
+        self.region.need_mutable()
+    }
+
+    fn derived_from(&self) -> Vec<Rc<RefCell<dyn Fuzz>>> {
+        self.region.derived_from()
+    }
+
+    fn append_derived(&self, _step: usize, rng: &mut dyn RngCore, data: &Vec<u8>, buf: &mut Vec<u8>) {
+        let len = self.value(rng, data.len());
+        self.encoding.encode(len, buf);
+    }
+
+}
+
+impl FromVecStrings for LengthField {
+
+    fn get_name() -> String {
+        return "LengthField".to_string();
+    }
+
+    ///
+    /// In the cfgfile use
+    /// ### FuzzerType:LengthField:InstanceName
+    /// * of: list of sibling fuzzers (in the same fuzz chain), whose total output length is generated
+    ///   OR
+    ///   chain: list of fuzzers, whose total output length is generated, followed by their output
+    /// * encoding: as supported by NumberFuzzer (optional, default u32be)
+    /// * adjust: integer added to the length (optional, default 0)
+    /// * corrupt: 0 to 100 (optional, default 0), the percent chance of generating a wrong length
+    ///
+    /// The fuzzers referenced are resolved by the runtime manager, once created.
+    ///
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<LengthField, String> {
+        let l = vs.pop_front();
+        if l.is_none() {
+            return Err("ERRR:LengthField:FromStringVec:Got empty vector".to_string());
+        }
+        let _l = l.unwrap(); // This should identify this particular type of Fuzzer and a runtime instance name
+        let spacesprefix = Self::get_spacesprefix(vs);
+        let region = Region::from_vs::<Self>(vs, spacesprefix)?;
+        let sencoding = Self::get_svalue_or(vs, "encoding", spacesprefix, "u32be").map_err(|e| format!("ERRR:LengthField:GetEncoding:{}", e))?;
+        let encoding = NumEncoding::from_str(&sencoding).map_err(|e| format!("ERRR:LengthField:{}", e))?;
+        let mut adjust = 0;
+        if Self::has_key(vs, "adjust", spacesprefix) {
+            adjust = Self::get_ivalue(vs, "adjust", spacesprefix).map_err(|e| format!("ERRR:LengthField:GetAdjust:{}", e))?;
+        }
+        let corrupt = fromvs_corrupt::<Self>(vs, spacesprefix)?;
+        Ok(LengthField {
+            region,
+            encoding,
+            adjust,
+            corrupt,
+        })
+    }
+
+}
+
+
+///
+/// The supported checksum algorithms
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChecksumAlgo {
+    /// CRC-32 (IEEE 802.3), as used by ethernet, zip, png, ...
+    Crc32,
+    /// CRC-16/CCITT-FALSE
+    Crc16,
+    /// CRC-16/MODBUS
+    Crc16Modbus,
+    Adler32,
+    /// The 16 bit ones complement checksum, as used by IP, TCP, UDP, ICMP
+    Inet,
+    /// The sum of the bytes, modulo 256
+    Sum8,
+}
+
+impl ChecksumAlgo {
+
+    pub fn from_str(salgo: &str) -> Result<ChecksumAlgo, String> {
+        match salgo {
+            "crc32" => Ok(Self::Crc32),
+            "crc16" => Ok(Self::Crc16),
+            "crc16modbus" => Ok(Self::Crc16Modbus),
+            "adler32" => Ok(Self::Adler32),
+            "inet" => Ok(Self::Inet),
+            "sum8" => Ok(Self::Sum8),
+            _ => Err(format!("ERRR:ChecksumAlgo:Unknown algo:{}", salgo)),
+        }
+    }
+
+    ///
+    /// The size of the checksum in bytes
+    ///
+    pub fn width(&self) -> usize {
+        match self {
+            Self::Crc32 | Self::Adler32 => 4,
+            Self::Crc16 | Self::Crc16Modbus | Self::Inet => 2,
+            Self::Sum8 => 1,
+        }
+    }
+
+    pub fn calc(&self, data: &[u8]) -> u32 {
+        match self {
+            Self::Crc32 => {
+                let mut crc = 0xFFFFFFFFu32;
+                for b in data {
+                    crc ^= *b as u32;
+                    for _i in 0..8 {
+                        crc = if (crc & 1) != 0 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+                    }
+                }
+                !crc
+            }
+            Self::Crc16 => {
+                let mut crc = 0xFFFFu16;
+                for b in data {
+                    crc ^= (*b as u16) << 8;
+                    for _i in 0..8 {
+                        crc = if (crc & 0x8000) != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+                    }
+                }
+                crc as u32
+            }
+            Self::Crc16Modbus => {
+                let mut crc = 0xFFFFu16;
+                for b in data {
+                    crc ^= *b as u16;
+                    for _i in 0..8 {
+                        crc = if (crc & 1) != 0 { (crc >> 1) ^ 0xA001 } else { crc >> 1 };
+                    }
+                }
+                crc as u32
+            }
+            Self::Adler32 => {
+                let (mut a, mut b) = (1u32, 0u32);
+                for d in data {
+                    a = (a + *d as u32) % 65521;
+                    b = (b + a) % 65521;
+                }
+                (b << 16) | a
+            }
+            Self::Inet => {
+                let mut sum = 0u32;
+                for word in data.chunks(2) {
+                    let hi = word[0] as u32;
+                    let lo = if word.len() > 1 { word[1] as u32 } else { 0 };
+                    sum += (hi << 8) | lo;
+                    sum = (sum & 0xFFFF) + (sum >> 16);
+                }
+                !sum & 0xFFFF
+            }
+            Self::Sum8 => data.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) as u32,
+        }
+    }
+
+}
+
+
+///
+/// Generate the checksum of the data in its region, in the specified byte order.
+/// It can be deliberately corrupted with a chance of corrupt percent.
+///
+pub struct ChecksumField {
+    region: Region,
+    algo: ChecksumAlgo,
+    bbig: bool,
+    corrupt: usize,
+}
+
+impl ChecksumField {
+
+    ///
+    /// Create a checksum field, wrt the given sibling fuzzers (in the same fuzz chain)
+    ///
+    pub fn new_siblings(fuzzers: Vec<Rc<RefCell<dyn Fuzz>>>, algo: ChecksumAlgo, bbig: bool, corrupt: usize) -> ChecksumField {
+        ChecksumField {
+            region: Region::Siblings(Vec::new(), fuzzers),
+            algo,
+            bbig,
+            corrupt,
+        }
+    }
+
+    ///
+    /// Create a checksum field followed by the output of the given sub chain
+    ///
+    pub fn new_subchain(fc: FuzzChain, algo: ChecksumAlgo, bbig: bool, corrupt: usize) -> ChecksumField {
+        ChecksumField {
+            region: Region::SubChain(Vec::new(), fc),
+            algo,
+            bbig,
+            corrupt,
+        }
+    }
+
+}
+
+impl ResolveRefs for ChecksumField {
+
+    fn refs(&self) -> Vec<String> {
+        self.region.names()
+    }
+
+    fn resolve(&mut self, rtm: &RunTimeManager) -> Result<(), String> {
+        self.region.resolve(rtm, "ChecksumField")
+    }
+
+}
+
+impl Fuzz for ChecksumField {
+
+    fn append_fuzzed_immut(&self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        if self.region.need_mutable() {
+            log_e(&format!("ERRR:ChecksumField:AppendFuzzedImmut:Step {}:SubChain needs mutable access", step));
+            return;
+        }
+        self.append_derived(step, rng, &Vec::new(), buf);
+    }
+
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let mut data = match &mut self.region {
+            Region::SubChain(_, fc) => fc.get(Some(step), rng),
+            Region::Siblings(_, _) => return self.append_fuzzed_immut(step, rng, buf),
+        };
+        self.append_derived(step, rng, &data, buf);
+        buf.append(&mut data);
+    }
+
+    fn need_mutable(&self) -> bool {
+        self.region.need_mutable()
+    }
+
+    fn derived_from(&self) -> Vec<Rc<RefCell<dyn Fuzz>>> {
+        self.region.derived_from()
+    }
+
+    fn append_derived(&self, _step: usize, rng: &mut dyn RngCore, data: &Vec<u8>, buf: &mut Vec<u8>) {
+        let width = self.algo.width();
+        let mut sum = self.algo.calc(data);
+        if (rng.gen::<usize>() % 100) < self.corrupt {
+            let mask = if width == 4 { u32::MAX } else { (1u32 << (width*8)) - 1 };
+            sum ^= 1 + rng.gen::<u32>() % mask;
+        }
+        let mut bytes = sum.to_le_bytes()[..width].to_vec();
+        if self.bbig {
+            bytes.reverse();
+        }
+        buf.append(&mut bytes);
+    }
+
+}
+
+impl FromVecStrings for ChecksumField {
+
+    fn get_name() -> String {
+        return "ChecksumField".to_string();
+    }
+
+    ///
+    /// In the cfgfile use
+    /// ### FuzzerType:ChecksumField:InstanceName
+    /// * of: list of sibling fuzzers (in the same fuzz chain), whose output checksum is generated
+    ///   OR
+    ///   chain: list of fuzzers, whose output checksum is generated, followed by their output
+    /// * algo: crc32 | crc16 | crc16modbus | adler32 | inet | sum8
+    /// * endian: little | big (optional, default big)
+    /// * corrupt: 0 to 100 (optional, default 0), the percent chance of generating a wrong checksum
+    ///
+    /// The fuzzers referenced are resolved by the runtime manager, once created.
+    ///
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<ChecksumField, String> {
+        let l = vs.pop_front();
+        if l.is_none() {
+            return Err("ERRR:ChecksumField:FromStringVec:Got empty vector".to_string());
+        }
+        let _l = l.unwrap(); // This should identify this particular type of Fuzzer and a runtime instance name
+        let spacesprefix = Self::get_spacesprefix(vs);
+        let region = Region::from_vs::<Self>(vs, spacesprefix)?;
+        let salgo = Self::get_value(vs, "algo", spacesprefix).map_err(|e| format!("ERRR:ChecksumField:GetAlgo:{}", e))?;
+        let algo = ChecksumAlgo::from_str(String::from_utf8_lossy(&salgo).trim()).map_err(|e| format!("ERRR:ChecksumField:{}", e))?;
+        let endian = Self::get_svalue_or(vs, "endian", spacesprefix, "big").map_err(|e| format!("ERRR:ChecksumField:GetEndian:{}", e))?;
+        let bbig = match endian.as_str() {
+            "little" => false,
+            "big" => true,
+            _ => return Err(format!("ERRR:ChecksumField:Endian should be little or big, got {}", endian)),
+        };
+        let corrupt = fromvs_corrupt::<Self>(vs, spacesprefix)?;
+        Ok(ChecksumField {
+            region,
+            algo,
+            bbig,
+            corrupt,
+        })
+    }
+
+}
@@ -0,0 +1,296 @@
+//!
+//! DictionaryFuzzers: Generate data by inserting or overwriting tokens (like protocol keywords)
+//! at random offsets of the provided seed buffers.
+//!
+//! HanishKVC, 2022
+//!
+
+use std::collections::VecDeque;
+use std::fs;
+use rand::{Rng, RngCore};
+use loggerk::log_o;
+
+use crate::cfgfiles::{self, FromVecStrings};
+use crate::mutate::{fromvs_buf8s, fromvs_count, pick_buf8};
+
+
+///
+/// Parse the value part of a AFL style dictionary entry, ie the text within the double quotes.
+/// Supports \\, \" and \xNN escapes.
+///
+fn dict_value(sval: &str) -> Result<Vec<u8>, String> {
+    let mut vdata = Vec::new();
+    let bval = sval.as_bytes();
+    let mut i = 0;
+    while i < bval.len() {
+        if bval[i] != b'\\' {
+            vdata.push(bval[i]);
+            i += 1;
+            continue;
+        }
+        if i+1 >= bval.len() {
+            return Err(format!("Incomplete escape at end of {}", sval));
+        }
+        match bval[i+1] {
+            b'\\' | b'"' => {
+                vdata.push(bval[i+1]);
+                i += 2;
+            }
+            b'x' => {
+                if i+4 > bval.len() {
+                    return Err(format!("Incomplete \\x escape in {}", sval));
+                }
+                let hex = std::str::from_utf8(&bval[i+2..i+4]).map_err(|e| format!("Invalid \\x escape in {}:{}", sval, e))?;
+                let byte = u8::from_str_radix(hex, 16).map_err(|e| format!("Invalid \\x escape in {}:{}", sval, e))?;
+                vdata.push(byte);
+                i += 4;
+            }
+            _ => return Err(format!("Unknown escape \\{} in {}", bval[i+1] as char, sval)),
+        }
+    }
+    Ok(vdata)
+}
+
+///
+/// Parse the contents of a AFL style dictionary file, where each line is either
+/// * empty or a # comment
+/// * "value"
+/// * name="value" or name@level="value"
+///
+pub fn dict_parse(sdata: &str) -> Result<Vec<Vec<u8>>, String> {
+    let mut tokens = Vec::new();
+    for (i, l) in sdata.lines().enumerate() {
+        let l = l.trim();
+        if l.len() == 0 || l.starts_with("#") {
+            continue;
+        }
+        let sval = match l.find('"') {
+            Some(qpos) => &l[qpos..],
+            None => return Err(format!("ERRR:DictParse:Line {}:Missing quoted value:{}", i+1, l)),
+        };
+        if sval.len() < 2 || !sval.ends_with('"') {
+            return Err(format!("ERRR:DictParse:Line {}:Value not terminated by quote:{}", i+1, l));
+        }
+        let token = dict_value(&sval[1..sval.len()-1]).map_err(|e| format!("ERRR:DictParse:Line {}:{}", i+1, e))?;
+        if token.len() == 0 {
+            return Err(format!("ERRR:DictParse:Line {}:Empty value", i+1));
+        }
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+///
+/// Load the tokens from the specified AFL style dictionary file
+///
+pub fn dict_load(path: &str) -> Result<Vec<Vec<u8>>, String> {
+    let sdata = fs::read_to_string(path).map_err(|e| format!("ERRR:DictLoad:{}:{}", path, e))?;
+    let tokens = dict_parse(&sdata).map_err(|e| format!("ERRR:DictLoad:{}:{}", path, e))?;
+    log_o(&format!("INFO:DictLoad:Got {} tokens from {}", tokens.len(), path));
+    Ok(tokens)
+}
+
+///
+/// Get the tokens specified inline using list and or loaded from dictfile,
+/// along with the buf8s and count, which are common to the dictionary fuzzers.
+///
+fn fromvs_dictionary<T: FromVecStrings>(vs: &mut VecDeque<String>) -> Result<(Vec<Vec<u8>>, Vec<Vec<u8>>, usize), String> {
+    let name = T::get_name();
+    let (buf8s, spacesprefix) = fromvs_buf8s::<T>(vs, 1)?;
+    let mut tokens = Vec::new();
+    if T::has_key(vs, "list", spacesprefix) {
+        let mut list = T::get_values(vs, "list", spacesprefix).map_err(|e| format!("ERRR:{}:GetList:{}", name, e))?;
+        tokens.append(&mut list);
+    }
+    if T::has_key(vs, "dictfile", spacesprefix) {
+        let path = T::get_value(vs, "dictfile", spacesprefix).map_err(|e| format!("ERRR:{}:GetDictFile:{}", name, e))?;
+        let path = String::from_utf8_lossy(&path).trim().to_string();
+        let path = cfgfiles::cfg_path(&path).to_string_lossy().to_string();
+        let mut list = dict_load(&path).map_err(|e| format!("ERRR:{}:{}", name, e))?;
+        tokens.append(&mut list);
+    }
+    if tokens.len() == 0 {
+        return Err(format!("ERRR:{}:FromStringVec:No tokens, specify list and or dictfile", name));
+    }
+    let count = fromvs_count::<T>(vs, spacesprefix)?;
+    Ok((buf8s, tokens, count))
+}
+
+
+///
+/// Insert count number of randomly selected tokens, at random offsets
+/// of a randomly selected seed buffer.
+///
+#[derive(Debug)]
+pub struct DictionaryInsertFuzzer {
+    buf8s: Vec<Vec<u8>>,
+    tokens: Vec<Vec<u8>>,
+    count: usize,
+}
+
+impl DictionaryInsertFuzzer {
+
+    pub fn new(buf8s: Vec<Vec<u8>>, tokens: Vec<Vec<u8>>, count: usize) -> DictionaryInsertFuzzer {
+        DictionaryInsertFuzzer {
+            buf8s,
+            tokens,
+            count,
+        }
+    }
+
+}
+
+impl super::Fuzz for DictionaryInsertFuzzer {
+
+    fn append_fuzzed_immut(&self, _step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let mut inb = pick_buf8(&self.buf8s, rng);
+        for _i in 0..self.count {
+            let token = &self.tokens[rng.gen::<usize>() % self.tokens.len()];
+            let pos = rng.gen::<usize>() % (inb.len() + 1);
+            inb.splice(pos..pos, token.iter().cloned());
+        }
+        buf.append(&mut inb);
+    }
+
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        return self.append_fuzzed_immut(step, rng, buf);
+    }
+
+}
+
+impl FromVecStrings for DictionaryInsertFuzzer {
+
+    fn get_name() -> String {
+        return "DictionaryInsertFuzzer".to_string();
+    }
+
+    ///
+    /// In the cfgfile use
+    /// ### FuzzerType:DictionaryInsertFuzzer:InstanceName
+    /// * buf8s: a list of textual or hex strings
+    /// * list: a list of textual or hex tokens (optional, if dictfile is given)
+    /// * dictfile: path to a AFL style .dict file (optional, if list is given)
+    /// * count: +ve integer (optional, default 1), the number of tokens inserted
+    ///
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<DictionaryInsertFuzzer, String> {
+        let (buf8s, tokens, count) = fromvs_dictionary::<Self>(vs)?;
+        Ok(DictionaryInsertFuzzer::new(buf8s, tokens, count))
+    }
+
+}
+
+
+///
+/// Overwrite the bytes at random offsets of a randomly selected seed buffer,
+/// with count number of randomly selected tokens. The buffer grows, if a token
+/// overwrites beyond its end.
+///
+#[derive(Debug)]
+pub struct DictionaryOverwriteFuzzer {
+    buf8s: Vec<Vec<u8>>,
+    tokens: Vec<Vec<u8>>,
+    count: usize,
+}
+
+impl DictionaryOverwriteFuzzer {
+
+    pub fn new(buf8s: Vec<Vec<u8>>, tokens: Vec<Vec<u8>>, count: usize) -> DictionaryOverwriteFuzzer {
+        DictionaryOverwriteFuzzer {
+            buf8s,
+            tokens,
+            count,
+        }
+    }
+
+}
+
+impl super::Fuzz for DictionaryOverwriteFuzzer {
+
+    fn append_fuzzed_immut(&self, _step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let mut inb = pick_buf8(&self.buf8s, rng);
+        for _i in 0..self.count {
+            let token = &self.tokens[rng.gen::<usize>() % self.tokens.len()];
+            // Prefer offsets where the token fits within the buffer
+            let lastpos = if inb.len() >= token.len() { inb.len() - token.len() } else { 0 };
+            let pos = rng.gen::<usize>() % (lastpos + 1);
+            let end = inb.len().min(pos + token.len());
+            inb.splice(pos..end, token.iter().cloned());
+        }
+        buf.append(&mut inb);
+    }
+
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        return self.append_fuzzed_immut(step, rng, buf);
+    }
+
+}
+
+impl FromVecStrings for DictionaryOverwriteFuzzer {
+
+    fn get_name() -> String {
+        return "DictionaryOverwriteFuzzer".to_string();
+    }
+
+    ///
+    /// In the cfgfile use
+    /// ### FuzzerType:DictionaryOverwriteFuzzer:InstanceName
+    /// * buf8s: a list of textual or hex strings
+    /// * list: a list of textual or hex tokens (optional, if dictfile is given)
+    /// * dictfile: path to a AFL style .dict file (optional, if list is given)
+    /// * count: +ve integer (optional, default 1), the number of tokens overwritten
+    ///
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<DictionaryOverwriteFuzzer, String> {
+        let (buf8s, tokens, count) = fromvs_dictionary::<Self>(vs)?;
+        Ok(DictionaryOverwriteFuzzer::new(buf8s, tokens, count))
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::cfggroup;
+    use crate::rtm::RunTimeManager;
+    use crate::cfgfiles::HandleCfgGroup;
+    use crate::Fuzz;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn fuzzer_dictionary() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let tokens = dict_parse("# http\nkw1=\"GET\"\nkw2@2=\"\\x0d\\x0a\\\"\"\n\n\"Host: \"\n").unwrap();
+        assert_eq!(tokens, vec![b"GET".to_vec(), b"\r\n\"".to_vec(), b"Host: ".to_vec()]);
+        assert!(dict_parse("kw=\"\\x0\"").is_err());
+        assert!(dict_parse("kw=GET").is_err());
+        let dif = DictionaryInsertFuzzer::new(vec![b"AAAA".to_vec()], vec![b"XY".to_vec()], 2);
+        let dof = DictionaryOverwriteFuzzer::new(vec![b"AAAA".to_vec()], vec![b"XY".to_vec()], 1);
+        for _i in 0..8 {
+            let mut buf = Vec::new();
+            dif.append_fuzzed_immut(0, &mut rng, &mut buf);
+            assert_eq!(buf.len(), 8);
+            assert_eq!(buf.iter().filter(|&&b| b == b'A').count(), 4);
+            let mut buf = Vec::new();
+            dof.append_fuzzed_immut(0, &mut rng, &mut buf);
+            assert_eq!(buf.len(), 4);
+            assert_eq!(String::from_utf8(buf).unwrap().matches("XY").count(), 1);
+        }
+        // from cfg file, with tokens from both list and dictfile
+        let dictfile = std::env::temp_dir().join(format!("fuzzerk-{}.dict", std::process::id()));
+        std::fs::write(&dictfile, "\"Content-Length: \"\n").unwrap();
+        let mut rtm = RunTimeManager::new_seeded(0);
+        let sdictfile = format!("  dictfile: {}", dictfile.display());
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:DictionaryInsertFuzzer:DI01", "  buf8s: GET / HTTP/1.1", "  list:", "    $0x0d0a0d0a", &sdictfile, "  count: 2"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:DictionaryOverwriteFuzzer:DO01", "  buf8s: AB", &sdictfile])).unwrap();
+        std::fs::remove_file(&dictfile).unwrap();
+        let gotr = rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:DictionaryInsertFuzzer:DI02", "  buf8s: AB"]));
+        assert!(gotr.unwrap_err().contains("No tokens"));
+        let mut buf = Vec::new();
+        rtm.fuzzer("DO01").unwrap().borrow().append_fuzzed_immut(0, &mut rng, &mut buf);
+        assert_eq!(buf, b"Content-Length: ".to_vec());
+        let mut buf = Vec::new();
+        rtm.fuzzer("DI01").unwrap().borrow().append_fuzzed_immut(0, &mut rng, &mut buf);
+        assert!(buf.len() == 14+8 || buf.len() == 14+16+4 || buf.len() == 14+32);
+    }
+
+}
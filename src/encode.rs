@@ -0,0 +1,146 @@
+//!
+//! EncodeFuzzer: Encode the output of another fuzzer (or fuzzchain), like base64, url,
+//! hex, gzip, deflate, http chunked or quoted printable, optionally corrupting the encoding.
+//!
+//! HanishKVC, 2022
+//!
+
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::cell::RefCell;
+use rand::{Rng, RngCore};
+
+use crate::{Fuzz, append_element};
+use crate::cfgfiles::FromVecStrings;
+use crate::codec::Codec;
+use crate::rtm::{RunTimeManager, ResolveRefs};
+
+
+///
+/// Encode the output of the inner fuzzer as specified. The encoding is deliberately
+/// corrupted, with a chance of corrupt percent.
+///
+pub struct EncodeFuzzer {
+    /// The name of the inner fuzzer (as specified in the cfg file)
+    name: String,
+    inner: Option<Rc<RefCell<dyn Fuzz>>>,
+    codec: Codec,
+    corrupt: usize,
+}
+
+impl EncodeFuzzer {
+
+    pub fn new(inner: Rc<RefCell<dyn Fuzz>>, codec: Codec, corrupt: usize) -> EncodeFuzzer {
+        EncodeFuzzer {
+            name: String::new(),
+            inner: Some(inner),
+            codec,
+            corrupt,
+        }
+    }
+
+}
+
+impl ResolveRefs for EncodeFuzzer {
+
+    fn refs(&self) -> Vec<String> {
+        vec![self.name.clone()]
+    }
+
+    fn resolve(&mut self, rtm: &RunTimeManager) -> Result<(), String> {
+        let inner = rtm.fuzzer_or_fchain(&self.name);
+        if inner.is_none() {
+            return Err(format!("ERRR:EncodeFuzzer:Resolve:Reference to unknown fuzzer {}", self.name));
+        }
+        self.inner = inner;
+        Ok(())
+    }
+
+}
+
+impl Fuzz for EncodeFuzzer {
+
+    fn append_fuzzed_immut(&self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let mut data = Vec::new();
+        if let Some(inner) = &self.inner {
+            append_element(inner, step, rng, &mut data);
+        }
+        if (rng.gen::<usize>() % 100) < self.corrupt {
+            self.codec.encode_corrupted(&data, rng, buf);
+        } else {
+            self.codec.encode(&data, buf);
+        }
+    }
+
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        return self.append_fuzzed_immut(step, rng, buf);
+    }
+
+}
+
+impl FromVecStrings for EncodeFuzzer {
+
+    fn get_name() -> String {
+        return "EncodeFuzzer".to_string();
+    }
+
+    ///
+    /// In the cfgfile use
+    /// ### FuzzerType:EncodeFuzzer:InstanceName
+    /// * inner: the fuzzer or fuzzchain, whose output is encoded
+    /// * encoding: raw | url | doubleurl | html | base64 | hex | gzip | deflate | http-chunked | quoted-printable
+    /// * corrupt: 0 to 100 (optional, default 0), the percent chance of corrupting the encoding
+    ///
+    /// The inner fuzzer is resolved by the runtime manager, once created.
+    ///
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<EncodeFuzzer, String> {
+        let l = vs.pop_front();
+        if l.is_none() {
+            return Err("ERRR:EncodeFuzzer:FromStringVec:Got empty vector".to_string());
+        }
+        let _l = l.unwrap(); // This should identify this particular type of Fuzzer and a runtime instance name
+        let spacesprefix = Self::get_spacesprefix(vs);
+        let name = Self::get_value(vs, "inner", spacesprefix).map_err(|e| format!("ERRR:EncodeFuzzer:GetInner:{}", e))?;
+        let name = String::from_utf8_lossy(&name).trim().to_string();
+        let scodec = Self::get_value(vs, "encoding", spacesprefix).map_err(|e| format!("ERRR:EncodeFuzzer:GetEncoding:{}", e))?;
+        let codec = Codec::from_str(String::from_utf8_lossy(&scodec).trim()).map_err(|e| format!("ERRR:EncodeFuzzer:{}", e))?;
+        let corrupt = Self::get_uvalue_or(vs, "corrupt", spacesprefix, 0).map_err(|e| format!("ERRR:EncodeFuzzer:GetCorrupt:{}", e))?;
+        if corrupt > 100 {
+            return Err(format!("ERRR:EncodeFuzzer:FromStringVec:corrupt {} > 100", corrupt));
+        }
+        Ok(EncodeFuzzer {
+            name,
+            inner: None,
+            codec,
+            corrupt,
+        })
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::cfggroup;
+    use crate::rtm::RunTimeManager;
+    use crate::cfgfiles::HandleCfgGroup;
+    use crate::fixed::RandomFixedStringsFuzzer;
+    use std::{rc::Rc, cell::RefCell};
+
+    #[test]
+    fn fuzzer_encode() {
+        // from cfg
+        let mut rtm = RunTimeManager::new_seeded(0);
+        rtm.add_fuzzer("A", Rc::new(RefCell::new(RandomFixedStringsFuzzer::new(vec![Vec::from("fuzz")]))));
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC_AA", "  A", "  A"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:EncodeFuzzer:E01", "  inner: FC_AA", "  encoding: base64"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:EncodeFuzzer:E02", "  inner: E01", "  encoding: http-chunked", "  corrupt: 0"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC01", "  E02"])).unwrap();
+        assert_eq!(rtm.fchain_get("FC01", None).unwrap(), Vec::from("C\r\nZnV6emZ1eno=\r\n0\r\n\r\n"));
+        let gotr = rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:EncodeFuzzer:E03", "  inner: A", "  encoding: rot13"]));
+        assert!(gotr.unwrap_err().contains("Unknown encoding:rot13"));
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:EncodeFuzzer:E04", "  inner: B", "  encoding: hex"])).unwrap();
+        assert!(rtm.handle_cfgend().unwrap_err().to_string().contains("unknown fuzzer B"));
+    }
+
+}
@@ -0,0 +1,47 @@
+//!
+//! FuzzerKError - the errors returned by the library, when loading cfg files,
+//! compiling and running asm programs or setting up io bridges.
+//!
+//! HanishKVC, 2022
+//!
+
+use std::fmt;
+
+
+#[derive(Debug)]
+pub enum FuzzerKError {
+    /// Issue wrt the arguments passed to the library or program
+    Args(String),
+    /// Issue wrt accessing a file: path, msg
+    File(String, String),
+    /// Issue wrt the contents of a cfg (.fc) file
+    /// * line: the line number (1 based) with the issue, within the problematic cfggroup
+    CfgFile { file: String, line: usize, msg: String },
+    /// Issue wrt compiling a line of the asm program
+    /// * line: the source line number (1 based)
+    /// * src: the source line
+    Compile { line: u32, src: String, msg: String },
+    /// Issue wrt running a op of the asm program
+    /// * line: the source line number (1 based), from which the op was compiled
+    /// * op: the op being run
+    Run { line: u32, op: String, msg: String },
+    /// Issue wrt setting up a io bridge, the msg is already tagged by the io bridge logic
+    IOBridge(String),
+}
+
+impl fmt::Display for FuzzerKError {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Args(msg) => write!(f, "ERRR:FuzzerK:Args:{}", msg),
+            Self::File(path, msg) => write!(f, "ERRR:FuzzerK:File:{}:{}", path, msg),
+            Self::CfgFile { file, line, msg } => write!(f, "ERRR:FuzzerK:CfgFile:{}:{}:{}", file, line, msg),
+            Self::Compile { line, src, msg } => write!(f, "ERRR:FuzzerK:Compile:{}:[{}]:{}", line, src, msg),
+            Self::Run { line, op, msg } => write!(f, "ERRR:FuzzerK:Run:{}:{}:{}", line, op, msg),
+            Self::IOBridge(msg) => write!(f, "{}", msg),
+        }
+    }
+
+}
+
+impl std::error::Error for FuzzerKError {}
@@ -6,6 +6,7 @@
 
 use std::collections::VecDeque;
 
+use rand::{Rng, RngCore};
 use loggerk::log_e;
 
 ///
@@ -27,7 +28,7 @@ impl LoopFixedStringsFuzzer {
 }
 
 impl super::Fuzz for LoopFixedStringsFuzzer {
-    fn append_fuzzed_immut(&self, step: usize, buf: &mut Vec<u8>) {
+    fn append_fuzzed_immut(&self, step: usize, _rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
         if self.list.len() == 0 {
             log_e(&format!("ERRR:FixedStringsFuzzer:AppendFuzzed:Step {}: Empty list to work with", step));
             return;
@@ -39,9 +40,9 @@ impl super::Fuzz for LoopFixedStringsFuzzer {
         }
     }
 
-    fn append_fuzzed(&mut self, step: usize, buf: &mut Vec<u8>) {
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
         self.curi = step % self.list.len();
-        self.append_fuzzed_immut(step, buf);
+        self.append_fuzzed_immut(step, rng, buf);
     }
 }
 
@@ -58,15 +59,18 @@ impl crate::cfgfiles::FromVecStrings for LoopFixedStringsFuzzer {
     ///   * list
     ///     * specify list of strings to use for fuzz generation
     ///
-    fn from_vs(vs: &mut VecDeque<String>) -> LoopFixedStringsFuzzer {
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<LoopFixedStringsFuzzer, String> {
         let l = vs.pop_front();
         if l.is_none() {
-            panic!("ERRR:LoopFixedStringsFuzzer:FromStringVec:Got empty vector");
+            return Err("ERRR:LoopFixedStringsFuzzer:FromStringVec:Got empty vector".to_string());
         }
         let _l = l.unwrap(); // This should identify this particular type of Fuzzer and a runtime instance name
         let spacesprefix = Self::get_spacesprefix(vs);
-        let fixedlist = Self::get_values(vs, "list", spacesprefix).expect("ERRR:LoopFixedStringsFuzzer:GetList:");
-        LoopFixedStringsFuzzer::new(fixedlist)
+        let fixedlist = Self::get_values(vs, "list", spacesprefix).map_err(|e| format!("ERRR:LoopFixedStringsFuzzer:GetList:{}", e))?;
+        if fixedlist.len() == 0 {
+            return Err("ERRR:LoopFixedStringsFuzzer:FromStringVec:Empty list".to_string());
+        }
+        Ok(LoopFixedStringsFuzzer::new(fixedlist))
     }
 }
 
@@ -88,20 +92,20 @@ impl RandomFixedStringsFuzzer {
 }
 
 impl super::Fuzz for RandomFixedStringsFuzzer {
-    fn append_fuzzed_immut(&self, step: usize, buf: &mut Vec<u8>) {
+    fn append_fuzzed_immut(&self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
         if self.list.len() == 0 {
             log_e(&format!("ERRR:FixedStringsFuzzer:AppendFuzzed:Step {}: Empty list to work with", step));
             return;
         }
-        let curi = rand::random::<usize>() % self.list.len();
+        let curi = rng.gen::<usize>() % self.list.len();
         let tosend = self.list[curi].clone();
         for b in tosend {
             buf.push(b)
         }
     }
 
-    fn append_fuzzed(&mut self, step: usize, buf: &mut Vec<u8>) {
-        return self.append_fuzzed_immut(step, buf);
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        return self.append_fuzzed_immut(step, rng, buf);
     }
 }
 
@@ -118,14 +122,17 @@ impl crate::cfgfiles::FromVecStrings for RandomFixedStringsFuzzer {
     ///   * list
     ///     * specify list of strings to use for fuzz generation
     ///
-    fn from_vs(vs: &mut VecDeque<String>) -> RandomFixedStringsFuzzer {
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<RandomFixedStringsFuzzer, String> {
         let l = vs.pop_front();
         if l.is_none() {
-            panic!("ERRR:RandomFixedStringsFuzzer:FromStringVec:Got empty vector");
+            return Err("ERRR:RandomFixedStringsFuzzer:FromStringVec:Got empty vector".to_string());
         }
         let _l = l.unwrap(); // This should identify this particular type of Fuzzer and a runtime instance name
         let spacesprefix = Self::get_spacesprefix(vs);
-        let fixedlist = Self::get_values(vs, "list", spacesprefix).expect("ERRR:RandomFixedStringsFuzzer:GetList:");
-        RandomFixedStringsFuzzer::new(fixedlist)
+        let fixedlist = Self::get_values(vs, "list", spacesprefix).map_err(|e| format!("ERRR:RandomFixedStringsFuzzer:GetList:{}", e))?;
+        if fixedlist.len() == 0 {
+            return Err("ERRR:RandomFixedStringsFuzzer:FromStringVec:Empty list".to_string());
+        }
+        Ok(RandomFixedStringsFuzzer::new(fixedlist))
     }
 }
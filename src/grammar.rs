@@ -0,0 +1,753 @@
+//!
+//! GrammarFuzzer: Generate data as per a ABNF / BNF grammar, by building random
+//! derivation trees, which can inturn be mutated.
+//!
+//! HanishKVC, 2022
+//!
+
+use std::collections::VecDeque;
+use std::fs;
+use rand::{Rng, RngCore};
+use loggerk::log_o;
+
+use crate::cfgfiles::{self, FromVecStrings};
+
+
+/// The default max depth of the derivation tree, beyond which the shortest derivations are used
+pub const MAXDEPTH_DEFAULT: usize = 16;
+/// The max number of repetitions beyond the min, wrt repetitions without a upper limit
+const REPEAT_MAXEXTRA: usize = 4;
+/// Marks a rule which cant be derived into a string (ie infinite recursion)
+const MINDEPTH_INFINITE: usize = usize::MAX;
+/// The max value allowed in a num-val, ie the max unicode char
+const MAX_CHAR: u32 = 0x10FFFF;
+
+/// The core rules of ABNF (RFC5234), which can be overridden by the grammar file
+const ABNF_CORE: &str = r#"
+ALPHA = %x41-5A / %x61-7A
+BIT = "0" / "1"
+CHAR = %x01-7F
+CR = %x0D
+CRLF = CR LF
+CTL = %x00-1F / %x7F
+DIGIT = %x30-39
+DQUOTE = %x22
+HEXDIG = DIGIT / "A" / "B" / "C" / "D" / "E" / "F"
+HTAB = %x09
+LF = %x0A
+LWSP = *(WSP / CRLF WSP)
+OCTET = %x00-FF
+SP = %x20
+VCHAR = %x21-7E
+WSP = SP / HTAB
+"#;
+
+
+///
+/// The elements of the grammar rules
+///
+#[derive(Debug, Clone)]
+enum Node {
+    Literal(Vec<u8>),
+    /// A single char in the given range
+    Range(u32, u32),
+    /// The index of the rule referred to
+    Rule(usize),
+    Sequence(Vec<Node>),
+    /// The id of the alternation (to track how often each alternative is taken) and its alternatives
+    Alternation(usize, Vec<Node>),
+    /// The element repeated min to max (if any) times
+    Repeat(Box<Node>, usize, Option<usize>),
+}
+
+///
+/// A node of the derivation tree. Rule nodes contain the derivation of the rule as their children,
+/// while the other nodes contain the generated data.
+///
+#[derive(Debug, Clone)]
+struct DTree {
+    rule: Option<usize>,
+    data: Vec<u8>,
+    children: Vec<DTree>,
+}
+
+impl DTree {
+
+    fn flatten(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.data);
+        for child in &self.children {
+            child.flatten(buf);
+        }
+    }
+
+    ///
+    /// Collect the paths (child indexes from the root) of all rule nodes in the tree
+    ///
+    fn rule_paths(&self, path: &mut Vec<usize>, paths: &mut Vec<Vec<usize>>) {
+        if self.rule.is_some() {
+            paths.push(path.clone());
+        }
+        for (i, child) in self.children.iter().enumerate() {
+            path.push(i);
+            child.rule_paths(path, paths);
+            path.pop();
+        }
+    }
+
+    fn node_mut(&mut self, path: &[usize]) -> &mut DTree {
+        let mut node = self;
+        for i in path {
+            node = &mut node.children[*i];
+        }
+        node
+    }
+
+}
+
+
+///
+/// The parsed grammar
+///
+#[derive(Debug)]
+pub struct Grammar {
+    names: Vec<String>,
+    rules: Vec<Option<Node>>,
+    /// The depth of the shortest derivation of each rule
+    mindepths: Vec<usize>,
+    /// The number of alternations in the grammar
+    altcnt: usize,
+}
+
+impl Grammar {
+
+    fn rule_index(&mut self, name: &str) -> usize {
+        let name = name.to_lowercase();
+        let idx = self.names.iter().position(|n| *n == name);
+        if idx.is_some() {
+            return idx.unwrap();
+        }
+        self.names.push(name);
+        self.rules.push(None);
+        self.names.len() - 1
+    }
+
+    fn parse_num(snum: &str, radix: u32, msgtag: &str) -> Result<u32, String> {
+        let num = u32::from_str_radix(snum, radix).map_err(|e| format!("ERRR:{}:Number {}:{}", msgtag, snum, e))?;
+        if num > MAX_CHAR {
+            return Err(format!("ERRR:{}:Number {} beyond the max unicode char {:X}", msgtag, snum, MAX_CHAR));
+        }
+        Ok(num)
+    }
+
+    ///
+    /// Parse a num-val like %x41, %x41-5A, %x0D.0A, %d13, %b1010 (without the %)
+    ///
+    fn parse_numval(snum: &str, msgtag: &str) -> Result<Node, String> {
+        let radix = match &snum[..1] {
+            "x" | "X" => 16,
+            "d" | "D" => 10,
+            "b" | "B" => 2,
+            _ => return Err(format!("ERRR:{}:Unknown num-val %{}", msgtag, snum)),
+        };
+        let snum = &snum[1..];
+        if let Some((smin, smax)) = snum.split_once('-') {
+            let min = Self::parse_num(smin, radix, msgtag)?;
+            let max = Self::parse_num(smax, radix, msgtag)?;
+            if min > max {
+                return Err(format!("ERRR:{}:Range %{} min > max", msgtag, snum));
+            }
+            return Ok(Node::Range(min, max));
+        }
+        let mut data = Vec::new();
+        for sval in snum.split('.') {
+            Self::push_char(&mut data, Self::parse_num(sval, radix, msgtag)?);
+        }
+        Ok(Node::Literal(data))
+    }
+
+    ///
+    /// Values till 255 are treated as bytes, others as unicode chars, encoded as utf8
+    ///
+    fn push_char(data: &mut Vec<u8>, val: u32) {
+        if val < 256 {
+            data.push(val as u8);
+            return;
+        }
+        let mut cbuf = [0u8; 4];
+        let c = char::from_u32(val).unwrap_or(char::REPLACEMENT_CHARACTER);
+        data.extend_from_slice(c.encode_utf8(&mut cbuf).as_bytes());
+    }
+
+    fn skip_spaces(body: &[u8], pos: &mut usize) {
+        while *pos < body.len() && body[*pos].is_ascii_whitespace() {
+            *pos += 1;
+        }
+    }
+
+    fn parse_alternation(&mut self, body: &[u8], pos: &mut usize, msgtag: &str) -> Result<Node, String> {
+        let mut alts = vec![self.parse_sequence(body, pos, msgtag)?];
+        loop {
+            Self::skip_spaces(body, pos);
+            if *pos < body.len() && (body[*pos] == b'/' || body[*pos] == b'|') {
+                *pos += 1;
+                alts.push(self.parse_sequence(body, pos, msgtag)?);
+                continue;
+            }
+            break;
+        }
+        if alts.len() == 1 {
+            return Ok(alts.pop().unwrap());
+        }
+        self.altcnt += 1;
+        Ok(Node::Alternation(self.altcnt - 1, alts))
+    }
+
+    fn parse_sequence(&mut self, body: &[u8], pos: &mut usize, msgtag: &str) -> Result<Node, String> {
+        let mut elements = Vec::new();
+        loop {
+            Self::skip_spaces(body, pos);
+            if *pos >= body.len() || b"/|)]".contains(&body[*pos]) {
+                break;
+            }
+            elements.push(self.parse_repetition(body, pos, msgtag)?);
+        }
+        if elements.len() == 0 {
+            return Err(format!("ERRR:{}:Empty sequence at {}", msgtag, *pos));
+        }
+        if elements.len() == 1 {
+            return Ok(elements.pop().unwrap());
+        }
+        Ok(Node::Sequence(elements))
+    }
+
+    fn parse_digits(body: &[u8], pos: &mut usize) -> Option<usize> {
+        let start = *pos;
+        while *pos < body.len() && body[*pos].is_ascii_digit() {
+            *pos += 1;
+        }
+        if start == *pos {
+            return None;
+        }
+        std::str::from_utf8(&body[start..*pos]).unwrap().parse().ok()
+    }
+
+    ///
+    /// Parse a element with a optional repeat prefix like *, 1*, *3, 2*4, 3
+    ///
+    fn parse_repetition(&mut self, body: &[u8], pos: &mut usize, msgtag: &str) -> Result<Node, String> {
+        let min = Self::parse_digits(body, pos);
+        let mut max = min;
+        let mut brepeat = min.is_some();
+        if *pos < body.len() && body[*pos] == b'*' {
+            *pos += 1;
+            max = Self::parse_digits(body, pos);
+            brepeat = true;
+        }
+        let element = self.parse_element(body, pos, msgtag)?;
+        if !brepeat {
+            return Ok(element);
+        }
+        let min = min.unwrap_or(0);
+        if max.is_some() && max.unwrap() < min {
+            return Err(format!("ERRR:{}:Repeat min {} > max {}", msgtag, min, max.unwrap()));
+        }
+        Ok(Node::Repeat(Box::new(element), min, max))
+    }
+
+    fn parse_element(&mut self, body: &[u8], pos: &mut usize, msgtag: &str) -> Result<Node, String> {
+        if *pos >= body.len() {
+            return Err(format!("ERRR:{}:Missing element at end", msgtag));
+        }
+        let start = *pos;
+        match body[*pos] {
+            b'(' | b'[' => {
+                let close = if body[*pos] == b'(' { b')' } else { b']' };
+                *pos += 1;
+                let node = self.parse_alternation(body, pos, msgtag)?;
+                Self::skip_spaces(body, pos);
+                if *pos >= body.len() || body[*pos] != close {
+                    return Err(format!("ERRR:{}:Missing {} for group at {}", msgtag, close as char, start));
+                }
+                *pos += 1;
+                if close == b']' {
+                    return Ok(Node::Repeat(Box::new(node), 0, Some(1)));
+                }
+                Ok(node)
+            }
+            b'"' | b'\'' => {
+                let quote = body[*pos];
+                let end = body[start+1..].iter().position(|&c| c == quote);
+                if end.is_none() {
+                    return Err(format!("ERRR:{}:Missing closing quote for string at {}", msgtag, start));
+                }
+                *pos = start + 1 + end.unwrap() + 1;
+                Ok(Node::Literal(body[start+1..*pos-1].to_vec()))
+            }
+            b'%' => {
+                *pos += 1;
+                // case sensitive / insensitive strings (RFC7405)
+                if *pos+1 < body.len() && (body[*pos] == b's' || body[*pos] == b'i') && body[*pos+1] == b'"' {
+                    *pos += 1;
+                    return self.parse_element(body, pos, msgtag);
+                }
+                while *pos < body.len() && (body[*pos].is_ascii_alphanumeric() || body[*pos] == b'.' || body[*pos] == b'-') {
+                    *pos += 1;
+                }
+                let snum = String::from_utf8_lossy(&body[start+1..*pos]).to_string();
+                if snum.len() < 2 {
+                    return Err(format!("ERRR:{}:Missing num-val at {}", msgtag, start));
+                }
+                Self::parse_numval(&snum, msgtag)
+            }
+            b'<' => {
+                let end = body[start..].iter().position(|&c| c == b'>');
+                if end.is_none() {
+                    return Err(format!("ERRR:{}:Missing > for rule name at {}", msgtag, start));
+                }
+                *pos = start + end.unwrap() + 1;
+                let name = String::from_utf8_lossy(&body[start+1..*pos-1]).trim().to_string();
+                Ok(Node::Rule(self.rule_index(&name)))
+            }
+            c if c.is_ascii_alphabetic() => {
+                while *pos < body.len() && (body[*pos].is_ascii_alphanumeric() || body[*pos] == b'-' || body[*pos] == b'_') {
+                    *pos += 1;
+                }
+                let name = String::from_utf8_lossy(&body[start..*pos]).to_string();
+                Ok(Node::Rule(self.rule_index(&name)))
+            }
+            c => Err(format!("ERRR:{}:Unexpected char {} at {}", msgtag, c as char, start)),
+        }
+    }
+
+    ///
+    /// Remove ; comments (outside of strings and <> names)
+    ///
+    fn strip_comment(line: &str) -> &str {
+        match Self::find_unquoted(line, ';') {
+            Some(i) => &line[..i],
+            None => line,
+        }
+    }
+
+    ///
+    /// Get the position of the first occurance of the given char in the line,
+    /// which is not within a quoted string or a <name>.
+    ///
+    fn find_unquoted(line: &str, fc: char) -> Option<usize> {
+        let mut quote = None;
+        for (i, c) in line.char_indices() {
+            match quote {
+                Some(q) => if c == q { quote = None },
+                None => match c {
+                    '"' | '\'' => quote = Some(c),
+                    '<' => quote = Some('>'),
+                    c if c == fc => return Some(i),
+                    _ => (),
+                },
+            }
+        }
+        None
+    }
+
+    ///
+    /// Parse the rules in the given text. Each rule is of the form
+    /// * name = elements, or name =/ additional alternatives (ABNF)
+    /// * <name> ::= elements (BNF)
+    /// Lines starting with whitespace continue the previous rule.
+    ///
+    fn parse_rules(&mut self, stext: &str, bcore: bool, defined: &mut Vec<bool>) -> Result<(), String> {
+        let mut srules: Vec<(usize, String)> = Vec::new();
+        for (i, line) in stext.lines().enumerate() {
+            let line = Self::strip_comment(line);
+            if line.trim().len() == 0 {
+                continue;
+            }
+            if line.starts_with(|c: char| c.is_whitespace()) {
+                if srules.len() == 0 {
+                    return Err(format!("ERRR:Grammar:Line {}:Continuation line without a rule", i+1));
+                }
+                srules.last_mut().unwrap().1.push_str(line);
+                continue;
+            }
+            srules.push((i+1, line.to_string()));
+        }
+        for (lineno, srule) in srules {
+            let msgtag = format!("Grammar:Line {}", lineno);
+            let ieq = Self::find_unquoted(&srule, '=');
+            if ieq.is_none() {
+                return Err(format!("ERRR:{}:Missing = wrt rule", msgtag));
+            }
+            let (sname, sbody) = srule.split_at(ieq.unwrap());
+            let sbody = &sbody[1..];
+            let (sname, sbody, bincremental) = if let Some(sname) = sname.strip_suffix("::") {
+                (sname, sbody, false)
+            } else {
+                match sbody.strip_prefix('/') {
+                    Some(sbody) => (sname, sbody, true),
+                    None => (sname, sbody, false),
+                }
+            };
+            let sname = sname.trim().trim_start_matches('<').trim_end_matches('>').trim();
+            if sname.len() == 0 {
+                return Err(format!("ERRR:{}:Missing rule name", msgtag));
+            }
+            let idx = self.rule_index(sname);
+            if defined.len() <= idx {
+                defined.resize(idx+1, false);
+            }
+            let mut pos = 0;
+            let node = self.parse_alternation(sbody.as_bytes(), &mut pos, &msgtag)?;
+            if pos < sbody.len() {
+                return Err(format!("ERRR:{}:Unexpected char {} at {}", msgtag, sbody.as_bytes()[pos] as char, pos));
+            }
+            if bincremental {
+                let prev = self.rules[idx].take();
+                if prev.is_none() {
+                    return Err(format!("ERRR:{}:Rule {} extended with =/ before its defined", msgtag, sname));
+                }
+                let mut alts = match prev.unwrap() {
+                    Node::Alternation(_, alts) => alts,
+                    prev => vec![prev],
+                };
+                alts.push(node);
+                self.altcnt += 1;
+                self.rules[idx] = Some(Node::Alternation(self.altcnt - 1, alts));
+                continue;
+            }
+            if !bcore && defined[idx] {
+                return Err(format!("ERRR:{}:Rule {} redefined", msgtag, sname));
+            }
+            defined[idx] = !bcore;
+            self.rules[idx] = Some(node);
+        }
+        Ok(())
+    }
+
+    fn node_mindepth(&self, node: &Node) -> usize {
+        match node {
+            Node::Literal(_) | Node::Range(_, _) => 0,
+            Node::Rule(idx) => self.mindepths[*idx].saturating_add(1),
+            Node::Sequence(elements) => elements.iter().map(|e| self.node_mindepth(e)).max().unwrap(),
+            Node::Alternation(_, alts) => alts.iter().map(|a| self.node_mindepth(a)).min().unwrap(),
+            Node::Repeat(element, min, _) => if *min == 0 { 0 } else { self.node_mindepth(element) },
+        }
+    }
+
+    ///
+    /// Parse the given grammar text. The ABNF core rules (ALPHA, DIGIT, CRLF, ...) are
+    /// available, unless redefined by the grammar.
+    ///
+    pub fn parse(stext: &str) -> Result<Grammar, String> {
+        let mut grammar = Grammar {
+            names: Vec::new(),
+            rules: Vec::new(),
+            mindepths: Vec::new(),
+            altcnt: 0,
+        };
+        let mut defined = Vec::new();
+        grammar.parse_rules(ABNF_CORE, true, &mut defined)?;
+        grammar.parse_rules(stext, false, &mut defined)?;
+        for (idx, rule) in grammar.rules.iter().enumerate() {
+            if rule.is_none() {
+                return Err(format!("ERRR:Grammar:Rule {} used, but not defined", grammar.names[idx]));
+            }
+        }
+        // Find the shortest derivation depth of each rule, by iterating till nothing changes
+        grammar.mindepths = vec![MINDEPTH_INFINITE; grammar.rules.len()];
+        loop {
+            let mut bchanged = false;
+            for idx in 0..grammar.rules.len() {
+                let mindepth = grammar.node_mindepth(grammar.rules[idx].as_ref().unwrap());
+                if mindepth < grammar.mindepths[idx] {
+                    grammar.mindepths[idx] = mindepth;
+                    bchanged = true;
+                }
+            }
+            if !bchanged {
+                break;
+            }
+        }
+        for (idx, mindepth) in grammar.mindepths.iter().enumerate() {
+            if *mindepth == MINDEPTH_INFINITE {
+                return Err(format!("ERRR:Grammar:Rule {} never terminates", grammar.names[idx]));
+            }
+        }
+        Ok(grammar)
+    }
+
+    pub fn load(path: &str) -> Result<Grammar, String> {
+        let stext = fs::read_to_string(path).map_err(|e| format!("ERRR:Grammar:Load:{}:{}", path, e))?;
+        let grammar = Self::parse(&stext).map_err(|e| format!("ERRR:Grammar:Load:{}:{}", path, e))?;
+        log_o(&format!("INFO:Grammar:Load:Got {} rules from {}", grammar.rules.len(), path));
+        Ok(grammar)
+    }
+
+    pub fn rule(&self, name: &str) -> Option<usize> {
+        let name = name.to_lowercase();
+        self.names.iter().position(|n| *n == name)
+    }
+
+    ///
+    /// Generate the derivation of the given node and add it to the children of the current rule node.
+    /// Once beyond maxdepth, the shortest derivations are used. Alternatives taken less often are preferred.
+    ///
+    fn gen_node(&self, node: &Node, depth: usize, maxdepth: usize, rng: &mut dyn RngCore, altcounts: &mut Vec<Vec<usize>>, children: &mut Vec<DTree>) {
+        match node {
+            Node::Literal(data) => children.push(DTree { rule: None, data: data.clone(), children: Vec::new() }),
+            Node::Range(min, max) => {
+                let mut data = Vec::new();
+                Self::push_char(&mut data, *min + rng.gen::<u32>() % (*max - *min + 1));
+                children.push(DTree { rule: None, data, children: Vec::new() });
+            }
+            Node::Rule(idx) => children.push(self.gen_rule(*idx, depth+1, maxdepth, rng, altcounts)),
+            Node::Sequence(elements) => {
+                for element in elements {
+                    self.gen_node(element, depth, maxdepth, rng, altcounts, children);
+                }
+            }
+            Node::Alternation(id, alts) => {
+                let ialt;
+                if depth >= maxdepth {
+                    ialt = (0..alts.len()).min_by_key(|&i| self.node_mindepth(&alts[i])).unwrap();
+                } else {
+                    let counts = &mut altcounts[*id];
+                    if counts.len() != alts.len() {
+                        counts.resize(alts.len(), 0);
+                    }
+                    let weights: Vec<f64> = counts.iter().map(|&c| 1.0 / (1 + c) as f64).collect();
+                    let mut pick = rng.gen::<f64>() * weights.iter().sum::<f64>();
+                    let mut i = 0;
+                    while i < weights.len()-1 && pick >= weights[i] {
+                        pick -= weights[i];
+                        i += 1;
+                    }
+                    counts[i] += 1;
+                    ialt = i;
+                }
+                self.gen_node(&alts[ialt], depth, maxdepth, rng, altcounts, children);
+            }
+            Node::Repeat(element, min, max) => {
+                let mut cnt = *min;
+                if depth < maxdepth {
+                    let max = max.unwrap_or(*min + REPEAT_MAXEXTRA);
+                    cnt = *min + rng.gen::<usize>() % (max - *min + 1);
+                }
+                for _i in 0..cnt {
+                    self.gen_node(element, depth, maxdepth, rng, altcounts, children);
+                }
+            }
+        }
+    }
+
+    fn gen_rule(&self, idx: usize, depth: usize, maxdepth: usize, rng: &mut dyn RngCore, altcounts: &mut Vec<Vec<usize>>) -> DTree {
+        let mut children = Vec::new();
+        self.gen_node(self.rules[idx].as_ref().unwrap(), depth, maxdepth, rng, altcounts, &mut children);
+        DTree { rule: Some(idx), data: Vec::new(), children }
+    }
+
+}
+
+
+///
+/// The mutations that can be applied to the derivation tree
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TreeMutation {
+    /// Replace a subtree with a new derivation of the same rule
+    Replace,
+    /// Repeat a subtree, a few times
+    Repeat,
+    /// Drop a subtree
+    Drop,
+}
+
+
+///
+/// Generate data as per the given grammar, starting from the specified rule.
+/// A chance of mutate percent of the generated derivation trees are mutated,
+/// by replacing, repeating or dropping subtrees.
+///
+#[derive(Debug)]
+pub struct GrammarFuzzer {
+    grammar: Grammar,
+    start: usize,
+    maxdepth: usize,
+    mutate: usize,
+    /// How often each alternative of each alternation has been taken
+    altcounts: Vec<Vec<usize>>,
+}
+
+impl GrammarFuzzer {
+
+    pub fn new(grammar: Grammar, start: &str, maxdepth: usize, mutate: usize) -> Result<GrammarFuzzer, String> {
+        let istart = grammar.rule(start);
+        if istart.is_none() {
+            return Err(format!("ERRR:GrammarFuzzer:New:Unknown start rule {}", start));
+        }
+        let altcounts = vec![Vec::new(); grammar.altcnt];
+        Ok(GrammarFuzzer {
+            grammar,
+            start: istart.unwrap(),
+            maxdepth,
+            mutate,
+            altcounts,
+        })
+    }
+
+    fn mutate_tree(&self, tree: &mut DTree, rng: &mut dyn RngCore, altcounts: &mut Vec<Vec<usize>>) {
+        let mut paths = Vec::new();
+        tree.rule_paths(&mut Vec::new(), &mut paths);
+        let path = paths.swap_remove(rng.gen::<usize>() % paths.len());
+        let mut op = [TreeMutation::Replace, TreeMutation::Repeat, TreeMutation::Drop][rng.gen::<usize>() % 3];
+        if path.len() == 0 {
+            // The root can only be replaced
+            op = TreeMutation::Replace;
+        }
+        match op {
+            TreeMutation::Replace => {
+                let node = tree.node_mut(&path);
+                *node = self.grammar.gen_rule(node.rule.unwrap(), path.len(), self.maxdepth, rng, altcounts);
+            }
+            TreeMutation::Repeat => {
+                let parent = tree.node_mut(&path[..path.len()-1]);
+                let ichild = path[path.len()-1];
+                let child = parent.children[ichild].clone();
+                for _i in 0..(1 + rng.gen::<usize>() % 3) {
+                    parent.children.insert(ichild, child.clone());
+                }
+            }
+            TreeMutation::Drop => {
+                let parent = tree.node_mut(&path[..path.len()-1]);
+                parent.children.remove(path[path.len()-1]);
+            }
+        }
+    }
+
+    fn generate(&self, rng: &mut dyn RngCore, altcounts: &mut Vec<Vec<usize>>, buf: &mut Vec<u8>) {
+        let mut tree = self.grammar.gen_rule(self.start, 0, self.maxdepth, rng, altcounts);
+        if (rng.gen::<usize>() % 100) < self.mutate {
+            self.mutate_tree(&mut tree, rng, altcounts);
+        }
+        tree.flatten(buf);
+    }
+
+}
+
+impl super::Fuzz for GrammarFuzzer {
+
+    fn append_fuzzed_immut(&self, _step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let mut altcounts = self.altcounts.clone();
+        self.generate(rng, &mut altcounts, buf);
+    }
+
+    fn append_fuzzed(&mut self, _step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let mut altcounts = std::mem::take(&mut self.altcounts);
+        self.generate(rng, &mut altcounts, buf);
+        self.altcounts = altcounts;
+    }
+
+    fn need_mutable(&self) -> bool {
+        true
+    }
+
+}
+
+impl FromVecStrings for GrammarFuzzer {
+
+    fn get_name() -> String {
+        return "GrammarFuzzer".to_string();
+    }
+
+    ///
+    /// In the cfgfile use
+    /// ### FuzzerType:GrammarFuzzer:InstanceName
+    /// * grammarfile: path to the ABNF / BNF grammar file
+    /// * start: the rule to start generating from
+    /// * maxdepth: +ve integer (optional, default 16), beyond which the shortest derivations are used
+    /// * mutate: 0 to 100 (optional, default 0), the percent chance of mutating the derivation tree
+    ///
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<GrammarFuzzer, String> {
+        let l = vs.pop_front();
+        if l.is_none() {
+            return Err("ERRR:GrammarFuzzer:FromStringVec:Got empty vector".to_string());
+        }
+        let _l = l.unwrap(); // This should identify this particular type of Fuzzer and a runtime instance name
+        let spacesprefix = Self::get_spacesprefix(vs);
+        let path = Self::get_value(vs, "grammarfile", spacesprefix).map_err(|e| format!("ERRR:GrammarFuzzer:GetGrammarFile:{}", e))?;
+        let path = cfgfiles::cfg_path(String::from_utf8_lossy(&path).trim());
+        let grammar = Grammar::load(&path.to_string_lossy()).map_err(|e| format!("ERRR:GrammarFuzzer:{}", e))?;
+        let start = Self::get_value(vs, "start", spacesprefix).map_err(|e| format!("ERRR:GrammarFuzzer:GetStart:{}", e))?;
+        let maxdepth = Self::get_uvalue_or(vs, "maxdepth", spacesprefix, MAXDEPTH_DEFAULT).map_err(|e| format!("ERRR:GrammarFuzzer:GetMaxDepth:{}", e))?;
+        let mutate = Self::get_uvalue_or(vs, "mutate", spacesprefix, 0).map_err(|e| format!("ERRR:GrammarFuzzer:GetMutate:{}", e))?;
+        if mutate > 100 {
+            return Err(format!("ERRR:GrammarFuzzer:FromStringVec:mutate {} > 100", mutate));
+        }
+        GrammarFuzzer::new(grammar, String::from_utf8_lossy(&start).trim(), maxdepth, mutate)
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Fuzz;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn fuzzer_grammar() {
+        let sabnf = "; a tiny http request\nrequest = method SP path SP \"HTTP/1.\" DIGIT CRLF *header CRLF\nmethod = \"GET\" / \"POST\"\nmethod =/ %s\"PUT\"\npath = \"/\" *3(ALPHA / DIGIT) [ \"?\" 1*2DIGIT ]\nheader = \"X-\" 1*ALPHA \": \"\n    (nested / %x30-31) CRLF\nnested = \"(\" [nested] \")\" ; recursive\n";
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut gf = GrammarFuzzer::new(Grammar::parse(sabnf).unwrap(), "Request", 4, 0).unwrap();
+        let mut methods = Vec::new();
+        for i in 0..32 {
+            let mut buf = Vec::new();
+            gf.append_fuzzed(i, &mut rng, &mut buf);
+            let sbuf = String::from_utf8(buf).unwrap();
+            let (method, rest) = sbuf.split_once(' ').unwrap();
+            assert!(["GET", "POST", "PUT"].contains(&method));
+            methods.push(method.to_string());
+            assert!(rest.starts_with('/'));
+            assert!(sbuf.ends_with("\r\n\r\n") || sbuf.ends_with("\r\n"));
+            // the depth limit keeps recursion in check
+            assert!(!sbuf.contains("(((((("));
+        }
+        // rarely taken alternatives are preferred, so all get their turn
+        for method in ["GET", "POST", "PUT"] {
+            assert!(methods.iter().filter(|m| *m == method).count() >= 8);
+        }
+        // bnf style
+        let sbnf = "<greet> ::= <word> ' ' <word> | 'hi'\n<word> ::= 'a' | 'b'\n";
+        let gf = GrammarFuzzer::new(Grammar::parse(sbnf).unwrap(), "greet", 8, 0).unwrap();
+        let mut buf = Vec::new();
+        gf.append_fuzzed_immut(0, &mut rng, &mut buf);
+        assert!(["a a", "a b", "b a", "b b", "hi"].contains(&String::from_utf8(buf).unwrap().as_str()));
+        // mutated derivations still come from the grammar, but need not be valid
+        let gf = GrammarFuzzer::new(Grammar::parse("list = 4item\nitem = \"x\" / \"y\"\n").unwrap(), "list", 8, 100).unwrap();
+        let mut lens = Vec::new();
+        for _i in 0..16 {
+            let mut buf = Vec::new();
+            gf.append_fuzzed_immut(0, &mut rng, &mut buf);
+            assert!(buf.iter().all(|&c| c == b'x' || c == b'y'));
+            lens.push(buf.len());
+        }
+        assert!(lens.iter().any(|&l| l != 4));
+        // errors
+        assert!(Grammar::parse("a = b\n").unwrap_err().contains("Rule b used, but not defined"));
+        assert!(Grammar::parse("a = \"x\" a\n").unwrap_err().contains("Rule a never terminates"));
+        assert!(Grammar::parse("a = \"x\"\na = \"y\"\n").unwrap_err().contains("redefined"));
+        assert!(Grammar::parse("a = (\"x\"\n").unwrap_err().contains("Missing )"));
+        // the first = outside quotes, separates the rule name
+        let g = Grammar::parse("a = \"x::=y\" / b\n<b> ::= \"=\"\n").unwrap();
+        let gf = GrammarFuzzer::new(g, "a", 8, 0).unwrap();
+        for _i in 0..8 {
+            let mut buf = Vec::new();
+            gf.append_fuzzed_immut(0, &mut rng, &mut buf);
+            assert!((buf == b"x::=y") || (buf == b"="));
+        }
+        assert!(Grammar::parse("a = %x0-FFFFFFFF\n").unwrap_err().contains("beyond the max unicode char"));
+        assert!(Grammar::parse("a = %x110000\n").unwrap_err().contains("beyond the max unicode char"));
+        assert!(GrammarFuzzer::new(Grammar::parse("a = \"x\"\n").unwrap(), "b", 8, 0).is_err());
+    }
+
+}
@@ -8,63 +8,126 @@ use std::collections::HashMap;
 use std::io;
 use std::io::Read;
 use std::io::Write;
+use std::io::Seek;
 use std::net;
 use std::fs;
+use std::process::ExitStatus;
 use std::time::Duration;
-use boring::ssl;
+use boring::{ssl, x509, pkey, rsa, asn1, hash};
 
 use loggerk::{log_d, log_e, log_o};
 
+use crate::error::FuzzerKError;
+
+mod exec;
+pub use exec::ExecTarget;
+mod listener;
+pub use listener::ServerListener;
+mod corpus;
+pub use corpus::CorpusDir;
+#[cfg(unix)]
+mod unix;
+#[cfg(unix)]
+pub use unix::UnixSocket;
+
 
 pub enum IOBridge {
     None,
     Console(io::Stdout, io::Stdin),
     TcpClient(net::TcpStream),
-    TcpServer(net::TcpStream),
+    /// The listener (if any, used to accept the next client) and the current client
+    TcpServer(Option<ServerListener>, net::TcpStream),
     TlsClient(ssl::SslStream<net::TcpStream>),
+    /// The listener (if any, used to accept the next client) and the current client
+    TlsServer(Option<ServerListener>, ssl::SslStream<net::TcpStream>),
+    UdpClient(net::UdpSocket),
+    /// The socket and the last peer from which a datagram was got
+    UdpServer(net::UdpSocket, Option<net::SocketAddr>),
     FileWriter(fs::File),
+    /// The file and whether to start again from the beginning, at its end
+    FileReader(fs::File, bool),
+    CorpusDir(CorpusDir),
+    Exec(ExecTarget),
+    #[cfg(unix)]
+    Unix(UnixSocket),
 }
 
 impl IOBridge {
 
-    pub fn new_console() -> IOBridge {
-        Self::Console(io::stdout(), io::stdin())
+    pub fn new_console() -> Result<IOBridge, String> {
+        Ok(Self::Console(io::stdout(), io::stdin()))
     }
 
     ///
-    /// Supported IOArgs
-    /// * read_timeout=millisecs
+    /// Convert the read_timeout ioarg if specified, into a duration
     ///
-    pub fn new_tcpclient(addr: &str, ioargs: &HashMap<String, String>) -> IOBridge {
-        let invalid = String::from("INVALID");
-        let read_timeout = ioargs.get("read_timeout").or(Some(&invalid)).unwrap();
+    fn read_timeout_parse(read_timeout: Option<&String>, msgtag: &str) -> Result<Option<Duration>, String> {
+        if read_timeout.is_none() {
+            return Ok(None);
+        }
+        let read_timeout = read_timeout.unwrap();
+        let timeout_millis = u64::from_str_radix(read_timeout, 10);
+        if timeout_millis.is_err() {
+            return Err(format!("ERRR:{}:New:ReadTimeout:{}:{}", msgtag, read_timeout, timeout_millis.unwrap_err()));
+        }
+        Ok(Some(Duration::from_millis(timeout_millis.unwrap())))
+    }
 
-        let ts = net::TcpStream::connect(addr).expect("ERRR:FuzzerK:IOBridge:TcpClient:TcpStreamConnect");
-        if *read_timeout != invalid {
-            let timeout_millis = u64::from_str_radix(&read_timeout, 10).expect("ERRR:FuzzerK:IOBridge:TcpClient:New:ReadTimeout");
-            let tomillis = Duration::from_millis(timeout_millis);
-            ts.set_read_timeout(Some(tomillis)).expect("ERRR:FuzzerK:IOBridge:TcpClient:New:SetReadTimeout");
+    ///
+    /// Set the read timeout, if the read_timeout ioarg was specified
+    ///
+    fn tcpstream_setup(ts: &net::TcpStream, read_timeout: Option<&String>, msgtag: &str) -> Result<(), String> {
+        let tomillis = Self::read_timeout_parse(read_timeout, msgtag)?;
+        if tomillis.is_some() {
+            let gotr = ts.set_read_timeout(tomillis);
+            if gotr.is_err() {
+                return Err(format!("ERRR:{}:New:SetReadTimeout:{}", msgtag, gotr.unwrap_err()));
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// Set the read timeout, if the read_timeout ioarg was specified
+    ///
+    fn udpsocket_setup(us: &net::UdpSocket, read_timeout: Option<&String>, msgtag: &str) -> Result<(), String> {
+        let tomillis = Self::read_timeout_parse(read_timeout, msgtag)?;
+        if tomillis.is_some() {
+            let gotr = us.set_read_timeout(tomillis);
+            if gotr.is_err() {
+                return Err(format!("ERRR:{}:New:SetReadTimeout:{}", msgtag, gotr.unwrap_err()));
+            }
         }
-        Self::TcpClient(ts)
+        Ok(())
     }
 
     ///
     /// Supported IOArgs
     /// * read_timeout=millisecs
     ///
-    fn new_tcpserver(addr: &str, ioargs: &HashMap<String, String>) -> IOBridge {
-        let invalid = String::from("INVALID");
-        let read_timeout = ioargs.get("read_timeout").or(Some(&invalid)).unwrap();
-
-        let tl = net::TcpListener::bind(addr).expect("ERRR:FuzzerK:IOBridge:TcpServer:TcpListenerBind");
-        let (ts, sa) = tl.accept().expect("ERRR:FuzzerK:IOBridge:TcpServer:TcpListenerAccept");
-        log_o(&format!("INFO:FuzzerK:IOBridge:TcpServer:Client {} has connected to me", sa));
-        if *read_timeout != invalid {
-            let timeout_millis = u64::from_str_radix(&read_timeout, 10).expect("ERRR:FuzzerK:IOBridge:TcpServer:New:ReadTimeout");
-            let tomillis = Duration::from_millis(timeout_millis);
-            ts.set_read_timeout(Some(tomillis)).expect("ERRR:FuzzerK:IOBridge:TcpServer:New:SetReadTimeout");
+    pub fn new_tcpclient(addr: &str, ioargs: &HashMap<String, String>) -> Result<IOBridge, String> {
+        let msgtag = "FuzzerK:IOBridge:TcpClient";
+        let ts = net::TcpStream::connect(addr);
+        if ts.is_err() {
+            return Err(format!("ERRR:{}:TcpStreamConnect:{}:{}", msgtag, addr, ts.unwrap_err()));
         }
-        Self::TcpServer(ts)
+        let ts = ts.unwrap();
+        Self::tcpstream_setup(&ts, ioargs.get("read_timeout"), msgtag)?;
+        Ok(Self::TcpClient(ts))
+    }
+
+    ///
+    /// Wait for the 1st client to connect. The listener is kept alive, so that
+    /// subsequent clients can be accepted using accept or accept_new.
+    ///
+    /// Supported IOArgs
+    /// * read_timeout=millisecs
+    ///
+    fn new_tcpserver(addr: &str, ioargs: &HashMap<String, String>) -> Result<IOBridge, String> {
+        let msgtag = "FuzzerK:IOBridge:TcpServer";
+        let tl = ServerListener::bind(addr, None, ioargs.get("read_timeout"), msgtag)?;
+        let ts = tl.accept_tcp()?;
+        Ok(Self::TcpServer(Some(tl), ts))
     }
 
     ///
@@ -73,28 +136,176 @@ impl IOBridge {
     /// * domain=the.domain.name
     /// * read_timeout=millisecs
     ///
-    pub fn new_tlsclient(addr: &str, ioargs: &HashMap<String, String>) -> IOBridge {
+    pub fn new_tlsclient(addr: &str, ioargs: &HashMap<String, String>) -> Result<IOBridge, String> {
         let msgtag = "FuzzerK:IOBridge:TlsClient";
         let yes = String::from("yes");
-        let invalid = String::from("INVALID");
 
-        let read_timeout = ioargs.get("read_timeout").or(Some(&invalid)).unwrap();
         let servercertcheck = ioargs.get("server_cert_check").or(Some(&yes)).unwrap();
-        let domain = ioargs.get("domain").expect(&format!("ERRR:{}:domain missing", msgtag));
+        let domain = ioargs.get("domain");
+        if domain.is_none() {
+            return Err(format!("ERRR:{}:domain missing", msgtag));
+        }
+        let domain = domain.unwrap();
 
-        let mut tlsconnbldr = ssl::SslConnector::builder(ssl::SslMethod::tls()).expect(&format!("ERRR:{}:SslConnectorBuilder", msgtag));
+        let tlsconnbldr = ssl::SslConnector::builder(ssl::SslMethod::tls());
+        if tlsconnbldr.is_err() {
+            return Err(format!("ERRR:{}:SslConnectorBuilder:{}", msgtag, tlsconnbldr.err().unwrap()));
+        }
+        let mut tlsconnbldr = tlsconnbldr.unwrap();
         if servercertcheck == "no" {
             tlsconnbldr.set_verify(ssl::SslVerifyMode::NONE);
         }
         let tlsconn = tlsconnbldr.build();
-        let tcpstream = net::TcpStream::connect(addr).expect(&format!("ERRR:{}:TcpStreamConnect", msgtag));
-        if *read_timeout != invalid {
-            let timeout_millis = u64::from_str_radix(&read_timeout, 10).expect(&format!("ERRR:{}:New:ReadTimeout", msgtag));
-            let tomillis = Duration::from_millis(timeout_millis);
-            tcpstream.set_read_timeout(Some(tomillis)).expect(&format!("ERRR:{}:New:SetReadTimeout", msgtag));
+        let tcpstream = net::TcpStream::connect(addr);
+        if tcpstream.is_err() {
+            return Err(format!("ERRR:{}:TcpStreamConnect:{}:{}", msgtag, addr, tcpstream.unwrap_err()));
+        }
+        let tcpstream = tcpstream.unwrap();
+        Self::tcpstream_setup(&tcpstream, ioargs.get("read_timeout"), msgtag)?;
+        let tlsstream = tlsconn.connect(domain, tcpstream);
+        if tlsstream.is_err() {
+            return Err(format!("ERRR:{}:SslConnectorConnect:{}", msgtag, tlsstream.err().unwrap()));
+        }
+        Ok(Self::TlsClient(tlsstream.unwrap()))
+    }
+
+    ///
+    /// Generate a self-signed certificate and its private key, for the given common name
+    ///
+    fn tls_selfsigned(cn: &str, msgtag: &str) -> Result<(x509::X509, pkey::PKey<pkey::Private>), String> {
+        let emsg = |what: &str, e: boring::error::ErrorStack| format!("ERRR:{}:SelfSigned:{}:{}", msgtag, what, e);
+        let rsa = rsa::Rsa::generate(2048).map_err(|e| emsg("RsaGenerate", e))?;
+        let key = pkey::PKey::from_rsa(rsa).map_err(|e| emsg("PKeyFromRsa", e))?;
+        let mut name = x509::X509NameBuilder::new().map_err(|e| emsg("NameBuilder", e))?;
+        name.append_entry_by_text("CN", cn).map_err(|e| emsg("NameCN", e))?;
+        let name = name.build();
+        let mut cert = x509::X509Builder::new().map_err(|e| emsg("X509Builder", e))?;
+        cert.set_version(2).map_err(|e| emsg("SetVersion", e))?;
+        cert.set_subject_name(&name).map_err(|e| emsg("SetSubjectName", e))?;
+        cert.set_issuer_name(&name).map_err(|e| emsg("SetIssuerName", e))?;
+        cert.set_pubkey(&key).map_err(|e| emsg("SetPubKey", e))?;
+        let notbefore = asn1::Asn1Time::days_from_now(0).map_err(|e| emsg("NotBefore", e))?;
+        cert.set_not_before(&notbefore).map_err(|e| emsg("SetNotBefore", e))?;
+        let notafter = asn1::Asn1Time::days_from_now(365).map_err(|e| emsg("NotAfter", e))?;
+        cert.set_not_after(&notafter).map_err(|e| emsg("SetNotAfter", e))?;
+        cert.sign(&key, hash::MessageDigest::sha256()).map_err(|e| emsg("Sign", e))?;
+        Ok((cert.build(), key))
+    }
+
+    ///
+    /// Create the tls acceptor, using either the cert and key files specified
+    /// or else a self-signed certificate generated for the domain.
+    ///
+    fn tls_acceptor(ioargs: &HashMap<String, String>, msgtag: &str) -> Result<ssl::SslAcceptor, String> {
+        let localhost = String::from("localhost");
+        let cert = ioargs.get("cert");
+        let key = ioargs.get("key");
+        if cert.is_some() != key.is_some() {
+            return Err(format!("ERRR:{}:Both cert and key need to be specified, or neither", msgtag));
+        }
+        let tlsaccbldr = ssl::SslAcceptor::mozilla_intermediate(ssl::SslMethod::tls());
+        if tlsaccbldr.is_err() {
+            return Err(format!("ERRR:{}:SslAcceptorBuilder:{}", msgtag, tlsaccbldr.err().unwrap()));
+        }
+        let mut tlsaccbldr = tlsaccbldr.unwrap();
+        if cert.is_some() {
+            let (cert, key) = (cert.unwrap(), key.unwrap());
+            let gotr = tlsaccbldr.set_certificate_chain_file(cert);
+            if gotr.is_err() {
+                return Err(format!("ERRR:{}:Cert:{}:{}", msgtag, cert, gotr.err().unwrap()));
+            }
+            let gotr = tlsaccbldr.set_private_key_file(key, ssl::SslFiletype::PEM);
+            if gotr.is_err() {
+                return Err(format!("ERRR:{}:Key:{}:{}", msgtag, key, gotr.err().unwrap()));
+            }
+        } else {
+            let domain = ioargs.get("domain").or(Some(&localhost)).unwrap();
+            let (cert, key) = Self::tls_selfsigned(domain, msgtag)?;
+            log_o(&format!("INFO:{}:Using self-signed certificate for {}", msgtag, domain));
+            let gotr = tlsaccbldr.set_certificate(&cert);
+            if gotr.is_err() {
+                return Err(format!("ERRR:{}:SetCert:{}", msgtag, gotr.err().unwrap()));
+            }
+            let gotr = tlsaccbldr.set_private_key(&key);
+            if gotr.is_err() {
+                return Err(format!("ERRR:{}:SetKey:{}", msgtag, gotr.err().unwrap()));
+            }
+        }
+        let gotr = tlsaccbldr.check_private_key();
+        if gotr.is_err() {
+            return Err(format!("ERRR:{}:CheckKey:{}", msgtag, gotr.err().unwrap()));
+        }
+        Ok(tlsaccbldr.build())
+    }
+
+    ///
+    /// Wait for the 1st client to connect. The listener is kept alive, so that
+    /// subsequent clients can be accepted using accept or accept_new.
+    ///
+    /// Supported IOArgs
+    /// * cert=path/to/cert_chain.pem
+    /// * key=path/to/private_key.pem
+    ///   if both cert and key are not specified, a self-signed certificate is generated.
+    /// * domain=the.domain.name (default: localhost)
+    ///   the common name used wrt the self-signed certificate.
+    /// * read_timeout=millisecs
+    ///
+    pub fn new_tlsserver(addr: &str, ioargs: &HashMap<String, String>) -> Result<IOBridge, String> {
+        let msgtag = "FuzzerK:IOBridge:TlsServer";
+        let tlsacc = Self::tls_acceptor(ioargs, msgtag)?;
+        let tl = ServerListener::bind(addr, Some(tlsacc), ioargs.get("read_timeout"), msgtag)?;
+        let ss = tl.accept_tls()?;
+        Ok(Self::TlsServer(Some(tl), ss))
+    }
+
+    ///
+    /// Each write sends a single datagram to the specified server addr,
+    /// and each read receives a single datagram from it.
+    ///
+    /// Supported IOArgs
+    /// * read_timeout=millisecs
+    ///
+    pub fn new_udpclient(addr: &str, ioargs: &HashMap<String, String>) -> Result<IOBridge, String> {
+        let msgtag = "FuzzerK:IOBridge:UdpClient";
+        let saddr = net::ToSocketAddrs::to_socket_addrs(addr);
+        if saddr.is_err() {
+            return Err(format!("ERRR:{}:ResolveAddr:{}:{}", msgtag, addr, saddr.unwrap_err()));
+        }
+        let saddr = saddr.unwrap().next();
+        if saddr.is_none() {
+            return Err(format!("ERRR:{}:ResolveAddr:{}:No address found", msgtag, addr));
+        }
+        let saddr = saddr.unwrap();
+        let laddr = if saddr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+        let us = net::UdpSocket::bind(laddr);
+        if us.is_err() {
+            return Err(format!("ERRR:{}:UdpSocketBind:{}:{}", msgtag, laddr, us.unwrap_err()));
         }
-        let tlsstream = tlsconn.connect(domain, tcpstream).expect(&format!("ERRR:{}:SslConnectorConnect", msgtag));
-        Self::TlsClient(tlsstream)
+        let us = us.unwrap();
+        let gotr = us.connect(saddr);
+        if gotr.is_err() {
+            return Err(format!("ERRR:{}:UdpSocketConnect:{}:{}", msgtag, addr, gotr.unwrap_err()));
+        }
+        Self::udpsocket_setup(&us, ioargs.get("read_timeout"), msgtag)?;
+        Ok(Self::UdpClient(us))
+    }
+
+    ///
+    /// Each read receives a single datagram from any peer, and each write sends
+    /// a single datagram to the last peer, from which a datagram was got.
+    ///
+    /// Supported IOArgs
+    /// * read_timeout=millisecs
+    ///
+    pub fn new_udpserver(addr: &str, ioargs: &HashMap<String, String>) -> Result<IOBridge, String> {
+        let msgtag = "FuzzerK:IOBridge:UdpServer";
+        let us = net::UdpSocket::bind(addr);
+        if us.is_err() {
+            return Err(format!("ERRR:{}:UdpSocketBind:{}:{}", msgtag, addr, us.unwrap_err()));
+        }
+        let us = us.unwrap();
+        Self::udpsocket_setup(&us, ioargs.get("read_timeout"), msgtag)?;
+        Ok(Self::UdpServer(us, None))
     }
 
     ///
@@ -103,8 +314,8 @@ impl IOBridge {
     /// * create=yes/no (default: no)
     ///   create will truncate any existing file
     ///
-    pub fn new_filewriter(addr: &str, ioargs: &HashMap<String, String>) -> IOBridge {
-        let msgtag = "FuzzerK:IOBridge:FileWriter:New:";
+    pub fn new_filewriter(addr: &str, ioargs: &HashMap<String, String>) -> Result<IOBridge, String> {
+        let msgtag = "FuzzerK:IOBridge:FileWriter:New";
         let yes = String::from("yes");
         let maybe = String::from("maybe");
 
@@ -115,17 +326,77 @@ impl IOBridge {
             append = &yes;
         }
 
-        let file: fs::File;
+        let file;
         if append == "yes" {
-            file = fs::File::options().append(true).open(addr).expect(&format!("ERRR:{}:OpenAppend", msgtag));
+            file = fs::File::options().append(true).open(addr);
         } else {
             if create == "yes" {
-                file = fs::File::create(addr).expect(&format!("ERRR:{}:Create", msgtag));
+                file = fs::File::create(addr);
             } else {
-                panic!("ERRR:{}:Either append or create ioarg needs to be specified and inturn yes", msgtag);
+                return Err(format!("ERRR:{}:Either append or create ioarg needs to be specified and inturn yes", msgtag));
             }
         }
-        Self::FileWriter(file)
+        if file.is_err() {
+            return Err(format!("ERRR:{}:Open:{}:{}", msgtag, addr, file.unwrap_err()));
+        }
+        Ok(Self::FileWriter(file.unwrap()))
+    }
+
+    ///
+    /// Unix domain socket client (bserver=false) or server (bserver=true)
+    ///
+    /// Supported IOArgs
+    /// * type=stream/dgram (default: stream)
+    /// * read_timeout=millisecs
+    ///
+    #[cfg(unix)]
+    pub fn new_unix(path: &str, bserver: bool, ioargs: &HashMap<String, String>) -> Result<IOBridge, String> {
+        Ok(Self::Unix(UnixSocket::new(path, bserver, ioargs)?))
+    }
+
+    ///
+    /// Read the file in chunks of the buffer length passed to read.
+    ///
+    /// Supported IOArgs
+    /// * loop=yes/no (default: no)
+    ///   whether to read again from the beginning, once the end of the file is reached,
+    ///   else read returns 0 at the end.
+    ///
+    pub fn new_filereader(addr: &str, ioargs: &HashMap<String, String>) -> Result<IOBridge, String> {
+        let msgtag = "FuzzerK:IOBridge:FileReader:New";
+        let no = String::from("no");
+        let bloop = ioargs.get("loop").or(Some(&no)).unwrap() == "yes";
+        let file = fs::File::open(addr);
+        if file.is_err() {
+            return Err(format!("ERRR:{}:Open:{}:{}", msgtag, addr, file.unwrap_err()));
+        }
+        Ok(Self::FileReader(file.unwrap(), bloop))
+    }
+
+    ///
+    /// Each read gets the contents of the next file in the directory (sorted by name).
+    ///
+    /// Supported IOArgs
+    /// * loop=yes/no (default: no)
+    ///   whether to start again from the 1st file, after the last file has been read,
+    ///   else read returns 0 at the end.
+    ///
+    pub fn new_corpusdir(addr: &str, ioargs: &HashMap<String, String>) -> Result<IOBridge, String> {
+        let no = String::from("no");
+        let bloop = ioargs.get("loop").or(Some(&no)).unwrap() == "yes";
+        Ok(Self::CorpusDir(CorpusDir::new(addr, bloop)?))
+    }
+
+    ///
+    /// Spawn the program specified in cmdline (path followed by args if any) and
+    /// talk to it through its stdin and stdout (+ stderr).
+    ///
+    /// Supported IOArgs
+    /// * read_timeout=millisecs
+    /// * stderr=yes/no (default: yes)
+    ///
+    pub fn new_exec(cmdline: &str, ioargs: &HashMap<String, String>) -> Result<IOBridge, String> {
+        Ok(Self::Exec(ExecTarget::new(cmdline, ioargs)?))
     }
 
     ///
@@ -135,158 +406,219 @@ impl IOBridge {
     /// * tcpclient:addr:port
     /// * tcpserver:addr:port
     /// * tlsclient:addr:port
+    /// * tlsserver:addr:port
+    /// * udpclient:addr:port
+    /// * udpserver:addr:port
+    /// * unixclient:path/to/socket
+    /// * unixserver:path/to/socket
     /// * filewriter:path/to/file
+    /// * filereader:path/to/file
+    /// * corpusdir:path/to/dir
+    /// * exec:path/to/program arg1 'arg 2' ...
+    ///   (a arg with whitespace can be single or double quoted)
     ///
     /// NOTE: Address could be ip address or domain name
     ///
-    pub fn new(ioaddr: &str, ioargs: &HashMap<String, String>) -> IOBridge {
-        let ioaddr = ioaddr.to_lowercase();
-        if ioaddr == "none" {
-            return Self::None;
-        }
-        if ioaddr == "console" {
-            return Self::new_console()
+    pub fn new(ioaddr: &str, ioargs: &HashMap<String, String>) -> Result<IOBridge, FuzzerKError> {
+        let lioaddr = ioaddr.to_lowercase();
+        if lioaddr == "none" {
+            return Ok(Self::None);
         }
-        let ioa = ioaddr.split_once(':').expect("ERRR:FuzzerK:IOBridge:New:Setting up nw");
-        if ioa.0 == "tcpclient" {
-            return Self::new_tcpclient(ioa.1, ioargs);
+        let gotr;
+        if lioaddr == "console" {
+            gotr = Self::new_console();
+        } else {
+            let ioa = ioaddr.split_once(':');
+            if ioa.is_none() {
+                return Err(FuzzerKError::IOBridge(format!("ERRR:FuzzerK:IOBridge:New:Invalid ioaddr:{}", ioaddr)));
+            }
+            let ioa = ioa.unwrap();
+            gotr = match ioa.0.to_lowercase().as_str() {
+                "tcpclient" => Self::new_tcpclient(ioa.1, ioargs),
+                "tcpserver" => Self::new_tcpserver(ioa.1, ioargs),
+                "tlsclient" => Self::new_tlsclient(ioa.1, ioargs),
+                "tlsserver" => Self::new_tlsserver(ioa.1, ioargs),
+                "udpclient" => Self::new_udpclient(ioa.1, ioargs),
+                "udpserver" => Self::new_udpserver(ioa.1, ioargs),
+                #[cfg(unix)]
+                "unixclient" => Self::new_unix(ioa.1, false, ioargs),
+                #[cfg(unix)]
+                "unixserver" => Self::new_unix(ioa.1, true, ioargs),
+                "filewriter" => Self::new_filewriter(ioa.1, ioargs),
+                "filereader" => Self::new_filereader(ioa.1, ioargs),
+                "corpusdir" => Self::new_corpusdir(ioa.1, ioargs),
+                "exec" => Self::new_exec(ioa.1, ioargs),
+                _ => Err(format!("ERRR:FuzzerK:IOBridge:New:Unknown ioaddr type:{}", ioa.0)),
+            };
         }
-        if ioa.0 == "tcpserver" {
-            return Self::new_tcpserver(ioa.1, ioargs);
+        gotr.map_err(|msg| FuzzerKError::IOBridge(msg))
+    }
+
+    ///
+    /// The type of the io bridge, used to tag messages
+    ///
+    pub fn name(&self) -> &str {
+        match self {
+            Self::None => "None",
+            Self::Console(_, _) => "Console",
+            Self::TcpClient(_) => "TcpClient",
+            Self::TcpServer(_, _) => "TcpServer",
+            Self::TlsClient(_) => "TlsClient",
+            Self::TlsServer(_, _) => "TlsServer",
+            Self::UdpClient(_) => "UdpClient",
+            Self::UdpServer(_, _) => "UdpServer",
+            Self::FileWriter(_) => "FileWriter",
+            Self::FileReader(_, _) => "FileReader",
+            Self::CorpusDir(_) => "CorpusDir",
+            Self::Exec(_) => "Exec",
+            #[cfg(unix)]
+            Self::Unix(us) => us.name(),
         }
-        if ioa.0 == "tlsclient" {
-            return Self::new_tlsclient(ioa.1, ioargs);
+    }
+
+    ///
+    /// Write the buf, returning the underlying io error as is, if any.
+    /// This allows the caller to classify the failure, if required.
+    ///
+    pub fn write_raw(&mut self, buf: &Vec<u8>) -> io::Result<usize> {
+        match self {
+            Self::None => return Err(io::Error::new(io::ErrorKind::Unsupported, "Why me???")),
+            Self::Console(so, _si ) => so.lock().write_all(buf)?,
+            Self::TcpClient(ts) => ts.write_all(buf)?,
+            Self::TcpServer(_, ts) => ts.write_all(buf)?,
+            Self::TlsClient(ss) => ss.write_all(buf)?,
+            Self::TlsServer(_, ss) => ss.write_all(buf)?,
+            Self::UdpClient(us) => return Self::udp_sent(us.send(buf)?, buf.len()),
+            Self::UdpServer(us, peer) => {
+                if peer.is_none() {
+                    return Err(io::Error::new(io::ErrorKind::NotConnected, "No peer to reply to, yet"));
+                }
+                return Self::udp_sent(us.send_to(buf, peer.unwrap())?, buf.len());
+            }
+            Self::FileWriter(file) => file.write_all(buf)?,
+            Self::FileReader(_, _) => return Err(io::Error::new(io::ErrorKind::Unsupported, "Not supported")),
+            Self::CorpusDir(_) => return Err(io::Error::new(io::ErrorKind::Unsupported, "Not supported")),
+            Self::Exec(et) => et.write(buf)?,
+            #[cfg(unix)]
+            Self::Unix(us) => us.write(buf)?,
         }
-        if ioa.0 == "filewriter" {
-            return Self::new_filewriter(ioa.1, ioargs);
+        Ok(buf.len())
+    }
+
+    ///
+    /// A datagram is sent as a whole or not at all, but check to be safe
+    ///
+    fn udp_sent(sent: usize, len: usize) -> io::Result<usize> {
+        if sent != len {
+            return Err(io::Error::new(io::ErrorKind::WriteZero, format!("Sent only {} of {} bytes", sent, len)));
         }
-        Self::None
+        Ok(sent)
     }
 
     pub fn write(&mut self, buf: &Vec<u8>) -> Result<usize, String> {
+        self.write_raw(buf).map_err(|e| format!("ERRR:FuzzerK:IOBridge:Write:{}:{}", self.name(), e))
+    }
+
+    pub fn flush_raw(&mut self) -> io::Result<()> {
         match self {
-            Self::None => todo!("ERRR:FuzzerK:IOBridge:Write:None:Why me???"),
-            Self::Console(so, _si ) => {
-                let mut so = so.lock();
-                let gotr = so.write_all(buf);
-                if gotr.is_err() {
-                    return Err(format!("ERRR:FuzzerK:IOBridge:Write:Console:{}", gotr.unwrap_err()))
-                }
-                return Ok(buf.len());
-            },
-            Self::TcpClient(ts) => {
-                let gotr = ts.write_all(buf);
-                if gotr.is_err() {
-                    return Err(format!("ERRR:FuzzerK:IOBridge:Write:TcpClient:{}", gotr.unwrap_err()))
-                }
-                return Ok(buf.len());
-            },
-            Self::TcpServer(ts) => {
-                let gotr = ts.write_all(buf);
-                if gotr.is_err() {
-                    return Err(format!("ERRR:FuzzerK:IOBridge:Write:TcpServer:{}", gotr.unwrap_err()))
-                }
-                return Ok(buf.len());
-            },
-            Self::TlsClient(ss) => {
-                let gotr = ss.write_all(buf);
-                if gotr.is_err() {
-                    return Err(format!("ERRR:FuzzerK:IOBridge:Write:TlsClient:{}", gotr.unwrap_err()))
-                }
-                return Ok(buf.len());
-            },
-            Self::FileWriter(file) => {
-                let gotr = file.write_all(buf);
-                if gotr.is_err() {
-                    return Err(format!("ERRR:FuzzerK:IOBridge:Write:FileWriter:{}", gotr.unwrap_err()))
-                }
-                return Ok(buf.len());
-            }
+            Self::None => Err(io::Error::new(io::ErrorKind::Unsupported, "Why me???")),
+            Self::Console(so, _si ) => so.lock().flush(),
+            Self::TcpClient(ts) => ts.flush(),
+            Self::TcpServer(_, ts) => ts.flush(),
+            Self::TlsClient(ss) => ss.flush(),
+            Self::TlsServer(_, ss) => ss.flush(),
+            Self::UdpClient(_) => Ok(()),
+            Self::UdpServer(_, _) => Ok(()),
+            Self::FileWriter(file) => file.flush(),
+            Self::FileReader(_, _) => Ok(()),
+            Self::CorpusDir(_) => Ok(()),
+            Self::Exec(et) => et.flush(),
+            #[cfg(unix)]
+            Self::Unix(us) => us.flush(),
         }
-        //Ok(0)
     }
 
     pub fn flush(&mut self) -> Result<(), String> {
+        self.flush_raw().map_err(|e| format!("ERRR:FuzzerK:IOBridge:Flush:{}:{}", self.name(), e))
+    }
+
+    ///
+    /// Read into the buf, returning the underlying io error as is, if any.
+    /// This allows the caller to classify the failure, if required.
+    ///
+    pub fn read_raw(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
         match self {
-            Self::None => todo!("ERRR:FuzzerK:IOBridge:Flush:None:Why me???"),
-            Self::Console(so, _si ) => {
-                let mut so = so.lock();
-                let gotr = so.flush();
-                if gotr.is_err() {
-                    return Err(format!("ERRR:FuzzerK:IOBridge:Flush:Console:{}", gotr.unwrap_err()))
-                }
-                return Ok(());
-            },
-            Self::TcpClient(ts) => {
-                let gotr = ts.flush();
-                if gotr.is_err() {
-                    return Err(format!("ERRR:FuzzerK:IOBridge:Flush:TcpClient:{}", gotr.unwrap_err()))
-                }
-                return Ok(());
-            },
-            Self::TcpServer(ts) => {
-                let gotr = ts.flush();
-                if gotr.is_err() {
-                    return Err(format!("ERRR:FuzzerK:IOBridge:Flush:TcpServer:{}", gotr.unwrap_err()))
-                }
-                return Ok(());
-            },
-            Self::TlsClient(ss) => {
-                let gotr = ss.flush();
-                if gotr.is_err() {
-                    return Err(format!("ERRR:FuzzerK:IOBridge:Flush:TlsClient:{}", gotr.unwrap_err()))
-                }
-                return Ok(());
-            },
-            Self::FileWriter(file) => {
-                let gotr = file.flush();
-                if gotr.is_err() {
-                    return Err(format!("ERRR:FuzzerK:IOBridge:Flush:FileWriter:{}", gotr.unwrap_err()))
+            Self::None => Err(io::Error::new(io::ErrorKind::Unsupported, "Why me???")),
+            //Self::Console(_so, si) => si.lock().read_to_end(buf),
+            Self::Console(_so, si ) => si.lock().read(buf),
+            Self::TcpClient(ts) => ts.read(buf),
+            Self::TcpServer(_, ts) => ts.read(buf),
+            Self::TlsClient(ss) => ss.read(buf),
+            Self::TlsServer(_, ss) => ss.read(buf),
+            Self::UdpClient(us) => us.recv(buf),
+            Self::UdpServer(us, peer) => {
+                let (size, from) = us.recv_from(buf)?;
+                *peer = Some(from);
+                Ok(size)
+            }
+            Self::FileWriter(_file) => Err(io::Error::new(io::ErrorKind::Unsupported, "Not supported")),
+            Self::FileReader(file, bloop) => {
+                let size = file.read(buf)?;
+                if size == 0 && *bloop && buf.len() > 0 {
+                    file.seek(io::SeekFrom::Start(0))?;
+                    return file.read(buf);
                 }
-                return Ok(());
-            },
+                Ok(size)
+            }
+            Self::CorpusDir(cd) => cd.read(buf),
+            Self::Exec(et) => et.read(buf),
+            #[cfg(unix)]
+            Self::Unix(us) => us.read(buf),
         }
-        //Ok(())
     }
 
     pub fn read(&mut self, buf: &mut Vec<u8>) -> Result<usize, String> {
+        self.read_raw(buf).map_err(|e| format!("ERRR:FuzzerK:IOBridge:Read:{}:{}", self.name(), e))
+    }
+
+    ///
+    /// Returns the exit status of the spawned program, the first time its exit is noticed.
+    /// For io bridges other than exec, it is always None.
+    ///
+    pub fn take_exit_status(&mut self) -> Option<ExitStatus> {
         match self {
-            Self::None => todo!("ERRR:FuzzerK:IOBridge:Read:None:Why me???"),
-            Self::Console(_so, si ) => {
-                let mut si = si.lock();
-                //let gotr = si.read_to_end(buf);
-                let gotr = si.read(buf);
-                if gotr.is_err() {
-                    return Err(format!("ERRR:FuzzerK:IOBridge:Read:Console:{}", gotr.unwrap_err()))
-                }
-                return Ok(gotr.unwrap());
-            },
-            Self::TcpClient(ts) => {
-                let gotr = ts.read(buf);
-                if gotr.is_err() {
-                    return Err(format!("ERRR:FuzzerK:IOBridge:Read:TcpClient:{}", gotr.unwrap_err()))
-                }
-                return Ok(gotr.unwrap());
-            },
-            Self::TcpServer(ts) => {
-                let gotr = ts.read(buf);
-                if gotr.is_err() {
-                    return Err(format!("ERRR:FuzzerK:IOBridge:Read:TcpServer:{}", gotr.unwrap_err()))
-                }
-                return Ok(gotr.unwrap());
-            },
-            Self::TlsClient(ss) => {
-                let gotr = ss.read(buf);
-                if gotr.is_err() {
-                    return Err(format!("ERRR:FuzzerK:IOBridge:Read:TlsClient:{}", gotr.unwrap_err()))
-                }
-                return Ok(gotr.unwrap());
-            },
-            Self::FileWriter(_file) => {
-                panic!("ERRR:FuzzerK:IOBridge:Read:FileWriter:Not supported");
-            }
+            Self::Exec(et) => et.take_exit_status(),
+            _ => None,
+        }
+    }
+
+    ///
+    /// Wait for the next client to connect to the tcp / tls server, and return a
+    /// new io bridge wrt it. The new io bridge doesnt own any listener.
+    ///
+    pub fn accept_new(&self) -> Result<IOBridge, String> {
+        match self {
+            Self::TcpServer(Some(tl), _) | Self::TlsServer(Some(tl), _) => tl.accept(),
+            _ => Err(format!("ERRR:FuzzerK:IOBridge:AcceptNew:{}:Not a listening server", self.name())),
         }
-        //Ok(0)
+    }
+
+    ///
+    /// Close the current client of the tcp / tls server, and wait for the next client
+    /// to connect, which inturn replaces the current client.
+    ///
+    pub fn accept(&mut self) -> Result<(), String> {
+        let gotr = self.close();
+        if gotr.is_err() {
+            log_e(&format!("ERRR:FuzzerK:IOBridge:Accept:Close4Next:{}", gotr.unwrap_err()));
+        }
+        let next = self.accept_new()?;
+        match (self, next) {
+            (Self::TcpServer(_, ts), Self::TcpServer(_, nts)) => *ts = nts,
+            (Self::TlsServer(_, ss), Self::TlsServer(_, nss)) => *ss = nss,
+            _ => return Err("ERRR:FuzzerK:IOBridge:Accept:Mismatched client???".to_string()),
+        }
+        Ok(())
     }
 
     pub fn close(&mut self) -> Result<(), String> {
@@ -298,7 +630,7 @@ impl IOBridge {
                 }
                 return Ok(());
             },
-            Self::TcpServer(ts) => {
+            Self::TcpServer(_, ts) => {
                 let gotr = ts.shutdown(net::Shutdown::Both);
                 if gotr.is_err() {
                     return Err(format!("ERRR:FuzzerK:IOBridge:Close:TcpServer:{}", gotr.unwrap_err()))
@@ -318,6 +650,19 @@ impl IOBridge {
                 // Rather keeping it simple, ignoring any additional data that might be there to read etc
                 return Ok(());
             },
+            Self::TlsServer(_, ss) => {
+                let gotr = ss.shutdown();
+                if gotr.is_err() {
+                    return Err(format!("ERRR:FuzzerK:IOBridge:Close:TlsServer:S1:{}", gotr.unwrap_err()))
+                }
+                if *gotr.as_ref().unwrap() == ssl::ShutdownResult::Sent {
+                    log_d("DBUG:FuzzerK:IOBridge:Close:TlsServer:S1:GotSent");
+                } else {
+                    log_e(&format!("ERRR:FuzzerK:IOBridge:Close:TlsServer:S1:NotSent???:{:?}", gotr.unwrap()));
+                }
+                // Rather keeping it simple, ignoring any additional data that might be there to read etc
+                return Ok(());
+            },
             Self::FileWriter(file) => {
                 let gotr = file.sync_all();
                 if gotr.is_err() {
@@ -326,9 +671,193 @@ impl IOBridge {
                 drop(file);
                 return Ok(());
             }
+            Self::Exec(et) => {
+                let gotr = et.close();
+                if gotr.is_err() {
+                    return Err(format!("ERRR:FuzzerK:IOBridge:Close:Exec:{}", gotr.unwrap_err()))
+                }
+                return Ok(());
+            }
+            #[cfg(unix)]
+            Self::Unix(us) => {
+                let gotr = us.close();
+                if gotr.is_err() {
+                    return Err(format!("ERRR:FuzzerK:IOBridge:Close:{}:{}", us.name(), gotr.unwrap_err()))
+                }
+                return Ok(());
+            }
             _ => {},
         }
         Ok(())
     }
 
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfgfiles::FromVecStrings;
+    use crate::fixed;
+    use crate::Fuzz;
+    use std::collections::VecDeque;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn iob_udp() {
+        let mut ioargs = std::collections::HashMap::new();
+        ioargs.insert("read_timeout".to_string(), "2000".to_string());
+        let mut server = IOBridge::new("udpserver:127.0.0.1:0", &ioargs).unwrap();
+        assert!(server.write(&b"early".to_vec()).is_err());
+        let saddr = match &server {
+            IOBridge::UdpServer(us, _) => us.local_addr().unwrap(),
+            _ => panic!("expected udpserver"),
+        };
+        let mut client = IOBridge::new(&format!("udpclient:{}", saddr), &ioargs).unwrap();
+        client.write(&b"ping".to_vec()).unwrap();
+        client.write(&b"pong2".to_vec()).unwrap();
+        let mut buf = vec![0u8; 16];
+        assert_eq!(server.read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf[..4], b"ping");
+        assert_eq!(server.read(&mut buf).unwrap(), 5);
+        server.write(&b"reply".to_vec()).unwrap();
+        let got = client.read(&mut buf).unwrap();
+        assert_eq!(&buf[..got], b"reply");
+    }
+
+    #[test]
+    fn iob_tlsserver() {
+        let mut ioargs = std::collections::HashMap::new();
+        ioargs.insert("cert".to_string(), "cert.pem".to_string());
+        assert!(IOBridge::new("tlsserver:127.0.0.1:0", &ioargs).is_err());
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let client = std::thread::spawn(move || {
+            let mut ioargs = std::collections::HashMap::new();
+            ioargs.insert("domain".to_string(), "localhost".to_string());
+            ioargs.insert("server_cert_check".to_string(), "no".to_string());
+            for _i in 0..50 {
+                let zenio = IOBridge::new(&format!("tlsclient:127.0.0.1:{}", port), &ioargs);
+                if zenio.is_ok() {
+                    let mut zenio = zenio.unwrap();
+                    zenio.write(&b"hello".to_vec()).unwrap();
+                    zenio.flush().unwrap();
+                    return;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(100));
+            }
+            panic!("tlsclient couldnt connect");
+        });
+        let mut server = IOBridge::new(&format!("tlsserver:127.0.0.1:{}", port), &std::collections::HashMap::new()).unwrap();
+        assert_eq!(server.name(), "TlsServer");
+        let mut buf = vec![0u8; 16];
+        let got = server.read(&mut buf).unwrap();
+        assert_eq!(&buf[..got], b"hello");
+        client.join().unwrap();
+    }
+
+    #[test]
+    fn corpus_files() {
+        let dir = std::env::temp_dir().join(format!("fuzzerk-corpus-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a"), b"AAA").unwrap();
+        std::fs::write(dir.join("b"), b"BBBBB").unwrap();
+        let sdir = dir.to_str().unwrap();
+        // corpusdir
+        let mut ioargs = std::collections::HashMap::new();
+        let mut zenio = IOBridge::new(&format!("corpusdir:{}", sdir), &ioargs).unwrap();
+        let mut buf = vec![0u8; 4];
+        assert_eq!(zenio.read(&mut buf).unwrap(), 3);
+        assert_eq!(zenio.read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf, b"BBBB");
+        assert_eq!(zenio.read(&mut buf).unwrap(), 0);
+        // filereader
+        ioargs.insert("loop".to_string(), "yes".to_string());
+        let mut zenio = IOBridge::new(&format!("filereader:{}/b", sdir), &ioargs).unwrap();
+        assert_eq!(zenio.read(&mut buf).unwrap(), 4);
+        assert_eq!(zenio.read(&mut buf).unwrap(), 1);
+        assert_eq!(zenio.read(&mut buf).unwrap(), 4);
+        assert!(zenio.write(&buf).is_err());
+        // cfgfile values
+        let mut vs = VecDeque::new();
+        vs.push_back("FuzzerType:LoopFixedStringsFuzzer:L1".to_string());
+        vs.push_back("  list:".to_string());
+        vs.push_back(format!("    $dir:{}", sdir));
+        vs.push_back(format!("    $file:{}/a", sdir));
+        let mut lfsf = fixed::LoopFixedStringsFuzzer::from_vs(&mut vs).unwrap();
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut buf = Vec::new();
+        for i in 0..3 {
+            lfsf.append_fuzzed(i, &mut rng, &mut buf);
+        }
+        assert_eq!(buf, b"AAABBBBBAAA");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn iob_unix() {
+        for stype in ["stream", "dgram"] {
+            let path = std::env::temp_dir().join(format!("fuzzerk-unix-{}-{}.sock", std::process::id(), stype));
+            let saddr = path.to_str().unwrap().to_string();
+            let mut ioargs = std::collections::HashMap::new();
+            ioargs.insert("type".to_string(), stype.to_string());
+            ioargs.insert("read_timeout".to_string(), "2000".to_string());
+            let cioargs = ioargs.clone();
+            let caddr = saddr.clone();
+            let client = std::thread::spawn(move || {
+                for _i in 0..50 {
+                    let zenio = IOBridge::new(&format!("unixclient:{}", caddr), &cioargs);
+                    if zenio.is_ok() {
+                        let mut zenio = zenio.unwrap();
+                        zenio.write(&b"ping".to_vec()).unwrap();
+                        let mut buf = vec![0u8; 16];
+                        let got = zenio.read(&mut buf).unwrap();
+                        zenio.close().unwrap();
+                        return buf[..got].to_vec();
+                    }
+                    std::thread::sleep(std::time::Duration::from_millis(100));
+                }
+                panic!("unixclient couldnt connect");
+            });
+            let mut server = IOBridge::new(&format!("unixserver:{}", saddr), &ioargs).unwrap();
+            let mut buf = vec![0u8; 16];
+            let got = server.read(&mut buf).unwrap();
+            assert_eq!(&buf[..got], b"ping");
+            server.write(&b"pong".to_vec()).unwrap();
+            assert_eq!(client.join().unwrap(), b"pong");
+            server.close().unwrap();
+            assert!(!path.exists());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn iob_exec() {
+        use crate::oracle::{Oracle, Verdict};
+        let mut ioargs = std::collections::HashMap::new();
+        ioargs.insert("read_timeout".to_string(), "2000".to_string());
+        let mut zenio = IOBridge::new("exec:cat", &ioargs).unwrap();
+        zenio.write(&b"hello".to_vec()).unwrap();
+        zenio.flush().unwrap();
+        let mut buf = vec![0u8; 16];
+        let got = zenio.read(&mut buf).unwrap();
+        assert_eq!(&buf[..got], b"hello");
+        zenio.close().unwrap();
+        let status = zenio.take_exit_status().unwrap();
+        assert!(status.success());
+        assert_eq!(zenio.take_exit_status(), None);
+        // args containing whitespace can be quoted
+        let mut zenio = IOBridge::new("exec:sh -c 'kill -SEGV $$'", &ioargs).unwrap();
+        assert_eq!(zenio.read(&mut buf).unwrap(), 0);
+        let status = zenio.take_exit_status().unwrap();
+        let oracle = Oracle::new();
+        assert_eq!(oracle.check_exit("tgt", &status, 1, 1), Verdict::Crash(11));
+        let mut buf = vec![0u8; 64];
+        let mut zenio = IOBridge::new(r#"exec:printf "%s|" 'a  b' "c \"d\"" e\ f ''"#, &ioargs).unwrap();
+        let got = zenio.read(&mut buf).unwrap();
+        assert_eq!(&buf[..got], b"a  b|c \"d\"|e f||");
+        zenio.close().unwrap();
+        assert!(IOBridge::new("exec:sh -c 'exit", &ioargs).is_err());
+    }
+
+}
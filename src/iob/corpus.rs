@@ -0,0 +1,75 @@
+//!
+//! CorpusDir - read the files in a directory, one file per read,
+//! so that existing seed inputs can be used by the asm program.
+//!
+//! HanishKVC, 2022
+//!
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use loggerk::log_o;
+
+
+pub struct CorpusDir {
+    /// The files in the directory, sorted by name
+    files: Vec<PathBuf>,
+    /// The index of the file to return in the next read
+    next: usize,
+    /// Whether to start again from the 1st file, after the last file
+    bloop: bool,
+}
+
+impl CorpusDir {
+
+    ///
+    /// Collect the files (not sub directories) in the given directory.
+    ///
+    pub fn new(path: &str, bloop: bool) -> Result<CorpusDir, String> {
+        let msgtag = "FuzzerK:IOBridge:CorpusDir:New";
+        let entries = fs::read_dir(path);
+        if entries.is_err() {
+            return Err(format!("ERRR:{}:ReadDir:{}:{}", msgtag, path, entries.unwrap_err()));
+        }
+        let mut files = Vec::new();
+        for entry in entries.unwrap() {
+            if entry.is_err() {
+                return Err(format!("ERRR:{}:ReadDir:{}:{}", msgtag, path, entry.unwrap_err()));
+            }
+            let entry = entry.unwrap().path();
+            if entry.is_file() {
+                files.push(entry);
+            }
+        }
+        if files.len() == 0 {
+            return Err(format!("ERRR:{}:No files in {}", msgtag, path));
+        }
+        files.sort();
+        log_o(&format!("INFO:{}:{} files in {}", msgtag, files.len(), path));
+        Ok(CorpusDir {
+            files,
+            next: 0,
+            bloop,
+        })
+    }
+
+    ///
+    /// Read the contents of the next file into buf, truncating it to the length of buf.
+    /// Once all files have been read, 0 is returned, unless looping.
+    ///
+    pub fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.next >= self.files.len() {
+            if !self.bloop {
+                return Ok(0);
+            }
+            self.next = 0;
+        }
+        let data = fs::read(&self.files[self.next])?;
+        self.next += 1;
+        let len = buf.len().min(data.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        Ok(len)
+    }
+
+}
@@ -0,0 +1,283 @@
+//!
+//! Exec - run the program under test as a child process and talk to it
+//! through its stdin and stdout (+ stderr).
+//!
+//! HanishKVC, 2022
+//!
+
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::process::{Child, ChildStdin, Command, ExitStatus, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use loggerk::{log_d, log_o};
+
+
+/// How long to wait for the child to exit on its own, before killing it, when closing.
+const CLOSE_WAIT_MSEC: u64 = 500;
+/// How long to wait for the child to exit, once it has closed its stdout and stderr.
+const EOF_WAIT_MSEC: u64 = 100;
+
+
+pub struct ExecTarget {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    /// Data read from the child's stdout (and stderr), by the reader threads
+    rx: mpsc::Receiver<Vec<u8>>,
+    /// Data got from the reader threads, which didnt fit into the buffer passed to read
+    pending: Vec<u8>,
+    read_timeout: Option<Duration>,
+    /// The exit status of the child, once it has exited
+    status: Option<ExitStatus>,
+    /// Whether the exit status has already been handed out
+    bstatustaken: bool,
+}
+
+///
+/// Split the cmdline into the program and its args, like a minimal shell would.
+/// * whitespace seperates the args
+/// * chars within single quotes are taken as is
+/// * chars within double quotes are taken as is, except for \" and \\
+/// * a \ outside quotes, escapes the char following it
+///
+fn split_cmdline(cmdline: &str) -> Result<Vec<String>, String> {
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    let mut bpart = false;
+    let mut chars = cmdline.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                bpart = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => part.push(c),
+                        None => return Err("Unterminated single quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                bpart = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) if (c == '"') || (c == '\\') => part.push(c),
+                            Some(c) => { part.push('\\'); part.push(c); }
+                            None => return Err("Unterminated double quote".to_string()),
+                        },
+                        Some(c) => part.push(c),
+                        None => return Err("Unterminated double quote".to_string()),
+                    }
+                }
+            }
+            '\\' => {
+                bpart = true;
+                if let Some(c) = chars.next() {
+                    part.push(c);
+                }
+            }
+            c if c.is_whitespace() => {
+                if bpart {
+                    parts.push(std::mem::take(&mut part));
+                    bpart = false;
+                }
+            }
+            c => {
+                bpart = true;
+                part.push(c);
+            }
+        }
+    }
+    if bpart {
+        parts.push(part);
+    }
+    Ok(parts)
+}
+
+impl ExecTarget {
+
+    fn spawn_reader<R: Read + Send + 'static>(mut reader: R, tx: mpsc::Sender<Vec<u8>>) {
+        thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+            loop {
+                match reader.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => {
+                        if tx.send(buf[..n].to_vec()).is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    ///
+    /// Spawn the program specified in cmdline (path to binary followed by its args, if any,
+    /// seperated by whitespace). A arg containing whitespace can be quoted (refer split_cmdline).
+    ///
+    /// Supported IOArgs
+    /// * read_timeout=millisecs
+    /// * stderr=yes/no (default: yes)
+    ///   whether the child's stderr should be read along with its stdout.
+    ///
+    pub fn new(cmdline: &str, ioargs: &HashMap<String, String>) -> Result<ExecTarget, String> {
+        let msgtag = "FuzzerK:IOBridge:Exec:New";
+        let yes = String::from("yes");
+
+        let parts = split_cmdline(cmdline).map_err(|e| format!("ERRR:{}:{}:{}", msgtag, cmdline, e))?;
+        if parts.len() == 0 {
+            return Err(format!("ERRR:{}:Program to run missing", msgtag));
+        }
+        let prg = &parts[0];
+        let args = &parts[1..];
+
+        let mut read_timeout = None;
+        let sread_timeout = ioargs.get("read_timeout");
+        if sread_timeout.is_some() {
+            let sread_timeout = sread_timeout.unwrap();
+            let timeout_millis = u64::from_str_radix(sread_timeout, 10);
+            if timeout_millis.is_err() {
+                return Err(format!("ERRR:{}:ReadTimeout:{}:{}", msgtag, sread_timeout, timeout_millis.unwrap_err()));
+            }
+            read_timeout = Some(Duration::from_millis(timeout_millis.unwrap()));
+        }
+        let bstderr = ioargs.get("stderr").or(Some(&yes)).unwrap() == "yes";
+
+        let mut cmd = Command::new(prg);
+        cmd.args(args).stdin(Stdio::piped()).stdout(Stdio::piped());
+        if bstderr {
+            cmd.stderr(Stdio::piped());
+        } else {
+            cmd.stderr(Stdio::null());
+        }
+        let child = cmd.spawn();
+        if child.is_err() {
+            return Err(format!("ERRR:{}:Spawn:{}:{}", msgtag, cmdline, child.unwrap_err()));
+        }
+        let mut child = child.unwrap();
+        log_o(&format!("INFO:{}:Spawned {} [{}]", msgtag, cmdline, child.id()));
+
+        let (tx, rx) = mpsc::channel();
+        Self::spawn_reader(child.stdout.take().unwrap(), tx.clone());
+        if bstderr {
+            Self::spawn_reader(child.stderr.take().unwrap(), tx.clone());
+        }
+        let stdin = child.stdin.take();
+        Ok(ExecTarget {
+            child,
+            stdin,
+            rx,
+            pending: Vec::new(),
+            read_timeout,
+            status: None,
+            bstatustaken: false,
+        })
+    }
+
+    pub fn write(&mut self, buf: &[u8]) -> io::Result<()> {
+        match self.stdin.as_mut() {
+            Some(stdin) => stdin.write_all(buf),
+            None => Err(io::Error::new(io::ErrorKind::NotConnected, "stdin already closed")),
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        match self.stdin.as_mut() {
+            Some(stdin) => stdin.flush(),
+            None => Ok(()),
+        }
+    }
+
+    ///
+    /// Read the data generated by the child on its stdout (and stderr).
+    /// * if read_timeout was specified and no data is got within that time, TimedOut error is returned.
+    /// * once the child has closed its stdout and stderr, 0 is returned.
+    ///
+    pub fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pending.len() == 0 {
+            let got = match self.read_timeout {
+                Some(timeout) => self.rx.recv_timeout(timeout),
+                None => self.rx.recv().map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+            };
+            match got {
+                Ok(data) => self.pending = data,
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    return Err(io::Error::new(io::ErrorKind::TimedOut, "no data from child within read_timeout"));
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    self.wait(Duration::from_millis(EOF_WAIT_MSEC));
+                    return Ok(0);
+                }
+            }
+        }
+        let len = buf.len().min(self.pending.len());
+        buf[..len].copy_from_slice(&self.pending[..len]);
+        self.pending.drain(..len);
+        Ok(len)
+    }
+
+    ///
+    /// Wait for the child to exit, for atmost the given duration.
+    ///
+    fn wait(&mut self, duration: Duration) -> Option<ExitStatus> {
+        let start = Instant::now();
+        loop {
+            if self.status.is_some() {
+                return self.status;
+            }
+            if let Ok(Some(status)) = self.child.try_wait() {
+                log_d(&format!("DBUG:FuzzerK:IOBridge:Exec:Child [{}] exited:{}", self.child.id(), status));
+                self.status = Some(status);
+                return self.status;
+            }
+            if start.elapsed() >= duration {
+                return None;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    ///
+    /// Returns the exit status of the child, the first time its exit is noticed.
+    ///
+    pub fn take_exit_status(&mut self) -> Option<ExitStatus> {
+        if self.bstatustaken {
+            return None;
+        }
+        let status = self.wait(Duration::ZERO);
+        if status.is_some() {
+            self.bstatustaken = true;
+        }
+        status
+    }
+
+    ///
+    /// Close the child's stdin and give it some time to exit on its own, else kill it.
+    ///
+    pub fn close(&mut self) -> io::Result<()> {
+        drop(self.stdin.take());
+        if self.wait(Duration::from_millis(CLOSE_WAIT_MSEC)).is_none() {
+            log_d(&format!("DBUG:FuzzerK:IOBridge:Exec:Killing child [{}]", self.child.id()));
+            self.child.kill()?;
+            self.status = Some(self.child.wait()?);
+            // Killed by us, so nothing to report
+            self.bstatustaken = true;
+        }
+        Ok(())
+    }
+
+}
+
+impl Drop for ExecTarget {
+    fn drop(&mut self) {
+        if self.status.is_none() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
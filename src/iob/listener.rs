@@ -0,0 +1,84 @@
+//!
+//! ServerListener - the listening socket of the tcp and tls server io bridges,
+//! kept alive so that new clients can be accepted, one after the other or concurrently.
+//!
+//! HanishKVC, 2022
+//!
+
+use std::net;
+use boring::ssl;
+
+use loggerk::log_o;
+
+use super::IOBridge;
+
+
+pub struct ServerListener {
+    tl: net::TcpListener,
+    /// Set wrt tls servers, to do the tls handshake with the accepted clients
+    tlsacc: Option<ssl::SslAcceptor>,
+    /// The read_timeout ioarg, applied to each accepted client
+    read_timeout: Option<String>,
+    msgtag: String,
+}
+
+impl ServerListener {
+
+    ///
+    /// Bind to the given addr and start listening for clients.
+    ///
+    /// NOTE: On unix, rust's std sets SO_REUSEADDR on the listening socket, so that
+    /// the addr can be bound to again, even if older connections are in TIME_WAIT.
+    ///
+    pub fn bind(addr: &str, tlsacc: Option<ssl::SslAcceptor>, read_timeout: Option<&String>, msgtag: &str) -> Result<ServerListener, String> {
+        let tl = net::TcpListener::bind(addr);
+        if tl.is_err() {
+            return Err(format!("ERRR:{}:TcpListenerBind:{}:{}", msgtag, addr, tl.unwrap_err()));
+        }
+        Ok(ServerListener {
+            tl: tl.unwrap(),
+            tlsacc,
+            read_timeout: read_timeout.cloned(),
+            msgtag: msgtag.to_string(),
+        })
+    }
+
+    ///
+    /// Wait for the next client to connect
+    ///
+    pub fn accept_tcp(&self) -> Result<net::TcpStream, String> {
+        let gotr = self.tl.accept();
+        if gotr.is_err() {
+            return Err(format!("ERRR:{}:TcpListenerAccept:{}", self.msgtag, gotr.unwrap_err()));
+        }
+        let (ts, sa) = gotr.unwrap();
+        log_o(&format!("INFO:{}:Client {} has connected to me", self.msgtag, sa));
+        IOBridge::tcpstream_setup(&ts, self.read_timeout.as_ref(), &self.msgtag)?;
+        Ok(ts)
+    }
+
+    ///
+    /// Wait for the next client to connect and inturn do the tls handshake with it
+    ///
+    pub fn accept_tls(&self) -> Result<ssl::SslStream<net::TcpStream>, String> {
+        let tlsacc = self.tlsacc.as_ref().ok_or_else(|| format!("ERRR:{}:AcceptTls:Not a tls listener", self.msgtag))?;
+        let ts = self.accept_tcp()?;
+        let tlsstream = tlsacc.accept(ts);
+        if tlsstream.is_err() {
+            return Err(format!("ERRR:{}:SslAcceptorAccept:{}", self.msgtag, tlsstream.err().unwrap()));
+        }
+        Ok(tlsstream.unwrap())
+    }
+
+    ///
+    /// Wait for the next client and return a io bridge wrt it, which inturn doesnt
+    /// own any listener.
+    ///
+    pub fn accept(&self) -> Result<IOBridge, String> {
+        if self.tlsacc.is_some() {
+            return Ok(IOBridge::TlsServer(None, self.accept_tls()?));
+        }
+        Ok(IOBridge::TcpServer(None, self.accept_tcp()?))
+    }
+
+}
@@ -0,0 +1,199 @@
+//!
+//! UnixSocket - talk to local programs over unix domain sockets,
+//! both stream and datagram types.
+//!
+//! HanishKVC, 2022
+//!
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixDatagram, UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use loggerk::log_o;
+
+
+/// Used to generate a unique path, to which datagram clients bind, so that servers can reply
+static DGRAMCLIENT_CNT: AtomicUsize = AtomicUsize::new(0);
+
+
+pub enum UnixSocket {
+    Client(UnixStream),
+    /// The stream wrt the client and the path of the socket file created by the server
+    Server(UnixStream, PathBuf),
+    /// The socket and the path to which it is bound
+    DgramClient(UnixDatagram, PathBuf),
+    /// The socket, the path to which it is bound and the last peer from which a datagram was got
+    DgramServer(UnixDatagram, PathBuf, Option<PathBuf>),
+}
+
+impl UnixSocket {
+
+    ///
+    /// Remove the socket file left behind by a previous run, if any.
+    /// Files other than sockets are not touched.
+    ///
+    fn unlink_stale(path: &str, msgtag: &str) -> Result<(), String> {
+        let meta = fs::symlink_metadata(path);
+        if meta.is_err() {
+            return Ok(());
+        }
+        if !meta.unwrap().file_type().is_socket() {
+            return Err(format!("ERRR:{}:{} exists and is not a socket", msgtag, path));
+        }
+        fs::remove_file(path).map_err(|e| format!("ERRR:{}:RemoveStale:{}:{}", msgtag, path, e))
+    }
+
+    fn read_timeout(ioargs: &HashMap<String, String>, msgtag: &str) -> Result<Option<Duration>, String> {
+        let read_timeout = ioargs.get("read_timeout");
+        if read_timeout.is_none() {
+            return Ok(None);
+        }
+        let read_timeout = read_timeout.unwrap();
+        let timeout_millis = u64::from_str_radix(read_timeout, 10);
+        if timeout_millis.is_err() {
+            return Err(format!("ERRR:{}:ReadTimeout:{}:{}", msgtag, read_timeout, timeout_millis.unwrap_err()));
+        }
+        Ok(Some(Duration::from_millis(timeout_millis.unwrap())))
+    }
+
+    ///
+    /// Create a unix domain socket client or server.
+    ///
+    /// A stream server waits for a client to connect. A datagram client binds to a unique
+    /// path in the temp dir, so that the server can reply to it.
+    ///
+    /// Supported IOArgs
+    /// * type=stream/dgram (default: stream)
+    /// * read_timeout=millisecs
+    ///
+    pub fn new(path: &str, bserver: bool, ioargs: &HashMap<String, String>) -> Result<UnixSocket, String> {
+        let stream = String::from("stream");
+        let stype = ioargs.get("type").or(Some(&stream)).unwrap().as_str();
+        let msgtag = format!("FuzzerK:IOBridge:Unix{}{}:New", if stype == "dgram" { "Dgram" } else { "" }, if bserver { "Server" } else { "Client" });
+        let msgtag = msgtag.as_str();
+        let read_timeout = Self::read_timeout(ioargs, msgtag)?;
+        let us = match (stype, bserver) {
+            ("stream", false) => {
+                let us = UnixStream::connect(path).map_err(|e| format!("ERRR:{}:Connect:{}:{}", msgtag, path, e))?;
+                us.set_read_timeout(read_timeout).map_err(|e| format!("ERRR:{}:SetReadTimeout:{}", msgtag, e))?;
+                Self::Client(us)
+            }
+            ("stream", true) => {
+                Self::unlink_stale(path, msgtag)?;
+                let ul = UnixListener::bind(path).map_err(|e| format!("ERRR:{}:Bind:{}:{}", msgtag, path, e))?;
+                let (us, _sa) = ul.accept().map_err(|e| format!("ERRR:{}:Accept:{}:{}", msgtag, path, e))?;
+                log_o(&format!("INFO:{}:Client has connected to me at {}", msgtag, path));
+                us.set_read_timeout(read_timeout).map_err(|e| format!("ERRR:{}:SetReadTimeout:{}", msgtag, e))?;
+                Self::Server(us, PathBuf::from(path))
+            }
+            ("dgram", false) => {
+                let cnt = DGRAMCLIENT_CNT.fetch_add(1, Ordering::SeqCst);
+                let bindpath = std::env::temp_dir().join(format!("fuzzerk-{}-{}.sock", process::id(), cnt));
+                let _ = fs::remove_file(&bindpath);
+                let us = UnixDatagram::bind(&bindpath).map_err(|e| format!("ERRR:{}:Bind:{}:{}", msgtag, bindpath.display(), e))?;
+                let gotr = us.connect(path);
+                if gotr.is_err() {
+                    let _ = fs::remove_file(&bindpath);
+                    return Err(format!("ERRR:{}:Connect:{}:{}", msgtag, path, gotr.unwrap_err()));
+                }
+                us.set_read_timeout(read_timeout).map_err(|e| format!("ERRR:{}:SetReadTimeout:{}", msgtag, e))?;
+                Self::DgramClient(us, bindpath)
+            }
+            ("dgram", true) => {
+                Self::unlink_stale(path, msgtag)?;
+                let us = UnixDatagram::bind(path).map_err(|e| format!("ERRR:{}:Bind:{}:{}", msgtag, path, e))?;
+                us.set_read_timeout(read_timeout).map_err(|e| format!("ERRR:{}:SetReadTimeout:{}", msgtag, e))?;
+                Self::DgramServer(us, PathBuf::from(path), None)
+            }
+            _ => return Err(format!("ERRR:{}:Unknown type:{}", msgtag, stype)),
+        };
+        Ok(us)
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Client(_) => "UnixClient",
+            Self::Server(_, _) => "UnixServer",
+            Self::DgramClient(_, _) => "UnixDgramClient",
+            Self::DgramServer(_, _, _) => "UnixDgramServer",
+        }
+    }
+
+    ///
+    /// A datagram is sent as a whole or not at all, but check to be safe
+    ///
+    fn dgram_sent(sent: usize, len: usize) -> io::Result<()> {
+        if sent != len {
+            return Err(io::Error::new(io::ErrorKind::WriteZero, format!("Sent only {} of {} bytes", sent, len)));
+        }
+        Ok(())
+    }
+
+    pub fn write(&mut self, buf: &[u8]) -> io::Result<()> {
+        match self {
+            Self::Client(us) | Self::Server(us, _) => us.write_all(buf),
+            Self::DgramClient(us, _) => Self::dgram_sent(us.send(buf)?, buf.len()),
+            Self::DgramServer(us, _, peer) => {
+                if peer.is_none() {
+                    return Err(io::Error::new(io::ErrorKind::NotConnected, "No peer to reply to, yet"));
+                }
+                Self::dgram_sent(us.send_to(buf, peer.as_ref().unwrap())?, buf.len())
+            }
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Client(us) | Self::Server(us, _) => us.flush(),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Client(us) | Self::Server(us, _) => us.read(buf),
+            Self::DgramClient(us, _) => us.recv(buf),
+            Self::DgramServer(us, _, peer) => {
+                let (size, from) = us.recv_from(buf)?;
+                // Unbound clients cant be replied to
+                *peer = from.as_pathname().map(Path::to_path_buf);
+                Ok(size)
+            }
+        }
+    }
+
+    ///
+    /// Shutdown the stream and remove the socket file created by us, if any.
+    ///
+    pub fn close(&mut self) -> io::Result<()> {
+        match self {
+            Self::Client(us) => us.shutdown(net::Shutdown::Both),
+            Self::Server(us, path) => {
+                let gotr = us.shutdown(net::Shutdown::Both);
+                let _ = fs::remove_file(path);
+                gotr
+            }
+            Self::DgramClient(_, path) => fs::remove_file(path),
+            Self::DgramServer(_, path, _) => fs::remove_file(path),
+        }
+    }
+
+}
+
+impl Drop for UnixSocket {
+    fn drop(&mut self) {
+        match self {
+            Self::Client(_) => (),
+            Self::Server(_, path) | Self::DgramClient(_, path) | Self::DgramServer(_, path, _) => {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+}
@@ -0,0 +1,462 @@
+//!
+//! JsonFuzzer: Generate structure aware mutations of a sample json document
+//!
+//! HanishKVC, 2022
+//!
+
+use std::collections::VecDeque;
+use rand::{Rng, RngCore};
+
+use crate::cfgfiles::FromVecStrings;
+
+
+/// The default number of levels of nesting, wrt deep nesting
+pub const NESTING_DEFAULT: usize = 1000;
+
+/// Numbers which are huge, tiny or beyond the precision / range of common number types
+pub(crate) const HUGE_NUMBERS: &[&str] = &[
+    "1e999999",
+    "-1e999999",
+    "1.7976931348623157e309",
+    "4.9e-325",
+    "123456789012345678901234567890123456789",
+    "9007199254740993",
+    "18446744073709551616",
+    "-9223372036854775809",
+    "-0",
+    "0.00000000000000000000000000000000000001",
+];
+
+const INVALID_ESCAPES: &[&str] = &[
+    "\\x41",
+    "\\u12",
+    "\\uD800",
+    "\\uDC00\\uD800",
+    "\\q",
+    "\\U0001F600",
+    "\\",
+    "\u{1}",
+];
+
+
+///
+/// A json value. Strings, keys and numbers are kept as in the source text (ie not unescaped),
+/// so that they are regenerated as is.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum JValue {
+    Null,
+    Bool(bool),
+    Number(String),
+    Str(String),
+    Array(Vec<JValue>),
+    Object(Vec<(String, JValue)>),
+    /// Text inserted as is, like malformed tokens
+    Raw(String),
+}
+
+impl JValue {
+
+    ///
+    /// Parse the given json text
+    ///
+    pub fn parse(sjson: &str) -> Result<JValue, String> {
+        let chars: Vec<char> = sjson.chars().collect();
+        let mut i = 0;
+        let value = Self::parse_value(&chars, &mut i)?;
+        Self::skip_ws(&chars, &mut i);
+        if i < chars.len() {
+            return Err(format!("ERRR:JValue:Parse:Unexpected data at {}", i));
+        }
+        Ok(value)
+    }
+
+    fn skip_ws(chars: &Vec<char>, i: &mut usize) {
+        while (*i < chars.len()) && chars[*i].is_ascii_whitespace() {
+            *i += 1;
+        }
+    }
+
+    fn expect(chars: &Vec<char>, i: &mut usize, expected: char) -> Result<(), String> {
+        Self::skip_ws(chars, i);
+        if (*i >= chars.len()) || (chars[*i] != expected) {
+            return Err(format!("ERRR:JValue:Parse:Expected {} at {}", expected, i));
+        }
+        *i += 1;
+        Ok(())
+    }
+
+    fn parse_string(chars: &Vec<char>, i: &mut usize) -> Result<String, String> {
+        Self::expect(chars, i, '"')?;
+        let start = *i;
+        while *i < chars.len() {
+            match chars[*i] {
+                '"' => {
+                    *i += 1;
+                    return Ok(chars[start..*i-1].iter().collect());
+                }
+                '\\' => *i += 2,
+                _ => *i += 1,
+            }
+        }
+        Err(format!("ERRR:JValue:Parse:Unterminated string at {}", start-1))
+    }
+
+    fn parse_value(chars: &Vec<char>, i: &mut usize) -> Result<JValue, String> {
+        Self::skip_ws(chars, i);
+        if *i >= chars.len() {
+            return Err(format!("ERRR:JValue:Parse:Missing value at {}", i));
+        }
+        match chars[*i] {
+            '{' => {
+                *i += 1;
+                let mut entries = Vec::new();
+                Self::skip_ws(chars, i);
+                if (*i < chars.len()) && (chars[*i] == '}') {
+                    *i += 1;
+                    return Ok(JValue::Object(entries));
+                }
+                loop {
+                    let key = Self::parse_string(chars, i)?;
+                    Self::expect(chars, i, ':')?;
+                    entries.push((key, Self::parse_value(chars, i)?));
+                    Self::skip_ws(chars, i);
+                    if (*i < chars.len()) && (chars[*i] == ',') {
+                        *i += 1;
+                        continue;
+                    }
+                    Self::expect(chars, i, '}')?;
+                    return Ok(JValue::Object(entries));
+                }
+            }
+            '[' => {
+                *i += 1;
+                let mut values = Vec::new();
+                Self::skip_ws(chars, i);
+                if (*i < chars.len()) && (chars[*i] == ']') {
+                    *i += 1;
+                    return Ok(JValue::Array(values));
+                }
+                loop {
+                    values.push(Self::parse_value(chars, i)?);
+                    Self::skip_ws(chars, i);
+                    if (*i < chars.len()) && (chars[*i] == ',') {
+                        *i += 1;
+                        continue;
+                    }
+                    Self::expect(chars, i, ']')?;
+                    return Ok(JValue::Array(values));
+                }
+            }
+            '"' => Ok(JValue::Str(Self::parse_string(chars, i)?)),
+            _ => {
+                let start = *i;
+                while (*i < chars.len()) && (chars[*i].is_ascii_alphanumeric() || "+-.".contains(chars[*i])) {
+                    *i += 1;
+                }
+                let token: String = chars[start..*i].iter().collect();
+                match token.as_str() {
+                    "null" => Ok(JValue::Null),
+                    "true" => Ok(JValue::Bool(true)),
+                    "false" => Ok(JValue::Bool(false)),
+                    _ => {
+                        if token.parse::<f64>().is_err() || token.starts_with('+') || token.starts_with('.') {
+                            return Err(format!("ERRR:JValue:Parse:Unknown token {} at {}", token, start));
+                        }
+                        Ok(JValue::Number(token))
+                    }
+                }
+            }
+        }
+    }
+
+    ///
+    /// Append the json text (without any whitespaces) to the passed buf
+    ///
+    pub fn serialize(&self, buf: &mut Vec<u8>) {
+        match self {
+            JValue::Null => buf.extend_from_slice(b"null"),
+            JValue::Bool(b) => buf.extend_from_slice(if *b { b"true" } else { b"false" }),
+            JValue::Number(n) => buf.extend_from_slice(n.as_bytes()),
+            JValue::Str(s) => buf.extend_from_slice(format!("\"{}\"", s).as_bytes()),
+            JValue::Array(values) => {
+                buf.push(b'[');
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        buf.push(b',');
+                    }
+                    value.serialize(buf);
+                }
+                buf.push(b']');
+            }
+            JValue::Object(entries) => {
+                buf.push(b'{');
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        buf.push(b',');
+                    }
+                    buf.extend_from_slice(format!("\"{}\":", key).as_bytes());
+                    value.serialize(buf);
+                }
+                buf.push(b'}');
+            }
+            JValue::Raw(r) => buf.extend_from_slice(r.as_bytes()),
+        }
+    }
+
+    ///
+    /// Get the paths (child indexes from the root) of all the values in the tree
+    ///
+    fn paths(&self, path: &mut Vec<usize>, paths: &mut Vec<Vec<usize>>) {
+        paths.push(path.clone());
+        let children: Vec<&JValue> = match self {
+            JValue::Array(values) => values.iter().collect(),
+            JValue::Object(entries) => entries.iter().map(|(_k, v)| v).collect(),
+            _ => return,
+        };
+        for (i, child) in children.iter().enumerate() {
+            path.push(i);
+            child.paths(path, paths);
+            path.pop();
+        }
+    }
+
+    fn value_mut(&mut self, path: &[usize]) -> &mut JValue {
+        if path.len() == 0 {
+            return self;
+        }
+        match self {
+            JValue::Array(values) => values[path[0]].value_mut(&path[1..]),
+            JValue::Object(entries) => entries[path[0]].1.value_mut(&path[1..]),
+            _ => self,
+        }
+    }
+
+    fn type_index(&self) -> usize {
+        match self {
+            JValue::Null => 0,
+            JValue::Bool(_) => 1,
+            JValue::Number(_) => 2,
+            JValue::Str(_) => 3,
+            JValue::Array(_) => 4,
+            JValue::Object(_) => 5,
+            JValue::Raw(_) => 6,
+        }
+    }
+
+    ///
+    /// Get a value of a type different from this value's type
+    ///
+    fn confused(&self, rng: &mut dyn RngCore) -> JValue {
+        let mut text = Vec::new();
+        self.serialize(&mut text);
+        let text = String::from_utf8_lossy(&text).replace('\\', "\\\\").replace('"', "\\\"");
+        let choices = vec![
+            JValue::Null,
+            JValue::Bool(rng.gen()),
+            JValue::Number(if let JValue::Str(s) = self { s.clone() } else { "1".to_string() }),
+            JValue::Str(text),
+            JValue::Array(vec![self.clone()]),
+            JValue::Object(vec![("".to_string(), self.clone())]),
+        ];
+        let mut choice = rng.gen::<usize>() % choices.len();
+        if choice == self.type_index() {
+            choice = (choice + 1) % choices.len();
+        }
+        choices[choice].clone()
+    }
+
+}
+
+
+///
+/// Generate structure aware mutations of a sample json document, with a chance of
+/// mutate percent, applying upto maxmutations of the below at random places in the tree
+/// * type confusion, deep nesting, huge numbers, duplicate keys, unterminated strings, invalid escapes
+///
+#[derive(Debug)]
+pub struct JsonFuzzer {
+    sample: JValue,
+    mutate: usize,
+    maxmutations: usize,
+    nesting: usize,
+}
+
+impl JsonFuzzer {
+
+    pub fn new(sample: JValue, mutate: usize, maxmutations: usize, nesting: usize) -> JsonFuzzer {
+        JsonFuzzer {
+            sample,
+            mutate,
+            maxmutations,
+            nesting,
+        }
+    }
+
+    fn mutate_value(&self, root: &mut JValue, rng: &mut dyn RngCore) {
+        let mut paths = Vec::new();
+        root.paths(&mut Vec::new(), &mut paths);
+        let path = paths[rng.gen::<usize>() % paths.len()].clone();
+        let kind = rng.gen::<usize>() % 6;
+        if kind == 3 {
+            // duplicate a key of the nearest object, with a different value
+            let mut objpath = None;
+            for p in paths.iter().filter(|p| path.starts_with(p)) {
+                if matches!(root.value_mut(p), JValue::Object(entries) if entries.len() > 0) {
+                    objpath = Some(p.clone());
+                }
+            }
+            let value = root.value_mut(&objpath.unwrap_or(path));
+            if let JValue::Object(entries) = value {
+                let (key, evalue) = entries[rng.gen::<usize>() % entries.len()].clone();
+                entries.push((key, evalue.confused(rng)));
+            } else {
+                *value = JValue::Object(vec![("a".to_string(), JValue::Null), ("a".to_string(), value.clone())]);
+            }
+            return;
+        }
+        let value = root.value_mut(&path);
+        match kind {
+            0 => *value = value.confused(rng),
+            1 => {
+                // kept as text, so that deep nesting doesnt need deep recursion wrt the tree
+                let mut inner = Vec::new();
+                value.serialize(&mut inner);
+                let (open, close) = if rng.gen::<bool>() { ("{\"a\":", "}") } else { ("[", "]") };
+                *value = JValue::Raw(format!("{}{}{}", open.repeat(self.nesting), String::from_utf8_lossy(&inner), close.repeat(self.nesting)));
+            }
+            2 => *value = JValue::Number(HUGE_NUMBERS[rng.gen::<usize>() % HUGE_NUMBERS.len()].to_string()),
+            4 => {
+                let content = if let JValue::Str(s) = value { s.clone() } else { "unterminated".to_string() };
+                *value = JValue::Raw(format!("\"{}", content));
+            }
+            _ => {
+                let content = if let JValue::Str(s) = value { s.clone() } else { String::new() };
+                let at = rng.gen::<usize>() % (content.chars().count() + 1);
+                let mut content: Vec<char> = content.chars().collect();
+                let bad: Vec<char> = INVALID_ESCAPES[rng.gen::<usize>() % INVALID_ESCAPES.len()].chars().collect();
+                content.splice(at..at, bad);
+                *value = JValue::Raw(format!("\"{}\"", content.iter().collect::<String>()));
+            }
+        }
+    }
+
+}
+
+impl crate::Fuzz for JsonFuzzer {
+
+    fn append_fuzzed_immut(&self, _step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let mut root = self.sample.clone();
+        if (rng.gen::<usize>() % 100) < self.mutate {
+            let cnt = 1 + rng.gen::<usize>() % self.maxmutations;
+            for _i in 0..cnt {
+                self.mutate_value(&mut root, rng);
+            }
+        }
+        root.serialize(buf);
+    }
+
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        return self.append_fuzzed_immut(step, rng, buf);
+    }
+
+}
+
+///
+/// Get the mutate, maxmutations and nesting keys (all optional) used by the structure aware fuzzers
+///
+pub(crate) fn fromvs_structmutate<T: FromVecStrings>(vs: &mut VecDeque<String>, spacesprefix: usize) -> Result<(usize, usize, usize), String> {
+    let name = T::get_name();
+    let mutate = T::get_uvalue_or(vs, "mutate", spacesprefix, 100).map_err(|e| format!("ERRR:{}:GetMutate:{}", name, e))?;
+    if mutate > 100 {
+        return Err(format!("ERRR:{}:FromStringVec:mutate {} > 100", name, mutate));
+    }
+    let maxmutations = T::get_uvalue_or(vs, "maxmutations", spacesprefix, 1).map_err(|e| format!("ERRR:{}:GetMaxMutations:{}", name, e))?;
+    if maxmutations == 0 {
+        return Err(format!("ERRR:{}:FromStringVec:maxmutations should be atleast 1", name));
+    }
+    let nesting = T::get_uvalue_or(vs, "nesting", spacesprefix, NESTING_DEFAULT).map_err(|e| format!("ERRR:{}:GetNesting:{}", name, e))?;
+    Ok((mutate, maxmutations, nesting))
+}
+
+impl FromVecStrings for JsonFuzzer {
+
+    fn get_name() -> String {
+        return "JsonFuzzer".to_string();
+    }
+
+    ///
+    /// In the cfgfile use
+    /// ### FuzzerType:JsonFuzzer:InstanceName
+    /// * sample: the sample json document, inline or from a file using $file:path
+    /// * mutate: 0 to 100 (optional, default 100), the percent chance of mutating the document
+    /// * maxmutations: (optional, default 1), the max number of mutations applied at a time
+    /// * nesting: (optional, default 1000), the levels of nesting wrt deep nesting
+    ///
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<JsonFuzzer, String> {
+        let l = vs.pop_front();
+        if l.is_none() {
+            return Err("ERRR:JsonFuzzer:FromStringVec:Got empty vector".to_string());
+        }
+        let _l = l.unwrap(); // This should identify this particular type of Fuzzer and a runtime instance name
+        let spacesprefix = Self::get_spacesprefix(vs);
+        let sample = Self::get_value(vs, "sample", spacesprefix).map_err(|e| format!("ERRR:JsonFuzzer:GetSample:{}", e))?;
+        let sample = String::from_utf8(sample).map_err(|e| format!("ERRR:JsonFuzzer:Sample:{}", e))?;
+        let sample = JValue::parse(&sample).map_err(|e| format!("ERRR:JsonFuzzer:Sample:{}", e))?;
+        let (mutate, maxmutations, nesting) = fromvs_structmutate::<Self>(vs, spacesprefix)?;
+        Ok(JsonFuzzer::new(sample, mutate, maxmutations, nesting))
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::cfggroup;
+    use crate::rtm::RunTimeManager;
+    use crate::cfgfiles::HandleCfgGroup;
+    use crate::Fuzz;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn fuzzer_json() {
+        let sjson = "{ \"id\": 12, \"name\": \"a\\\"b\", \"tags\": [true, null, -1.5e3], \"sub\": {} }";
+        let json = JValue::parse(sjson).unwrap();
+        let mut buf = Vec::new();
+        json.serialize(&mut buf);
+        assert_eq!(String::from_utf8(buf).unwrap(), sjson.replace(' ', ""));
+        assert!(JValue::parse("{\"a\": 1,}").is_err());
+        assert!(JValue::parse("[1, 2").is_err());
+        assert!(JValue::parse("\"abc").unwrap_err().contains("Unterminated string"));
+        // mutations
+        let mut rng = StdRng::seed_from_u64(0);
+        let jf = JsonFuzzer::new(json.clone(), 100, 2, 64);
+        let (mut cntvalid, mut cntdeep) = (0, 0);
+        for _i in 0..64 {
+            let mut buf = Vec::new();
+            jf.append_fuzzed_immut(0, &mut rng, &mut buf);
+            let sbuf = String::from_utf8_lossy(&buf).to_string();
+            if JValue::parse(&sbuf).is_ok() {
+                cntvalid += 1;
+            }
+            if sbuf.contains(&"[".repeat(64)) || sbuf.contains(&"{\"a\":".repeat(64)) {
+                cntdeep += 1;
+            }
+        }
+        assert!(cntvalid > 0 && cntvalid < 64);
+        assert!(cntdeep > 0);
+        let jf = JsonFuzzer::new(json.clone(), 0, 1, 64);
+        let mut buf = Vec::new();
+        jf.append_fuzzed_immut(0, &mut rng, &mut buf);
+        assert_eq!(JValue::parse(&String::from_utf8(buf).unwrap()).unwrap(), json);
+        // from cfg
+        let mut rtm = RunTimeManager::new_seeded(0);
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:JsonFuzzer:J01", "  sample: {\"a\": [1, 2]}", "  mutate: 0"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC01", "  J01"])).unwrap();
+        assert_eq!(rtm.fchain_get("FC01", None).unwrap(), b"{\"a\":[1,2]}");
+        let gotr = rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:JsonFuzzer:J02", "  sample: {\"a\" 1}"]));
+        assert!(gotr.unwrap_err().contains("Expected :"));
+    }
+
+}
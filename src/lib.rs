@@ -4,63 +4,215 @@
 //! HanishKVC, 2022
 //!
 
+use std::borrow::Cow;
 use std::rc::Rc;
 use std::cell::RefCell;
 
+use rand::RngCore;
+
 
 ///
 /// The trait that needs to be implemented by the different fuzzers
 ///
-trait Fuzz {
+/// Custom fuzzers implementing this trait can be
+/// * appended to FuzzChains directly, or
+/// * registered with rtm::RunTimeManager under a type name, so that they can be
+///   created from the FuzzChains cfg file, like the predefined fuzzers.
+///
+pub trait Fuzz {
     /// Generate the next fuzzed output and append to the passed buf
     /// The fuzzer can update itself if reqd
-    fn append_fuzzed(&mut self, step: usize, buf: &mut Vec<u8>);
+    /// Any randomness required should be got from the passed rng
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>);
 
     /// Generate the next fuzzed output and append to the passed buf
     /// The fuzzer cant/doesnt update itself in this case.
-    fn append_fuzzed_immut(&self, step: usize, buf: &mut Vec<u8>);
+    /// Any randomness required should be got from the passed rng
+    fn append_fuzzed_immut(&self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>);
 
     /// Fuzzer can override this to indicate that it needs a mutable reference
     /// to itself, when fuzz chain will call its append_fuzzed
     fn need_mutable(&self) -> bool {
         false
     }
+
+    /// Fuzzers whose output is derived from the output of other fuzzers in the same
+    /// fuzz chain (like length or checksum fields), can override this to return those
+    /// fuzzers. Fuzz chain will call its append_derived, after the others have generated.
+    fn derived_from(&self) -> Vec<Rc<RefCell<dyn Fuzz>>> {
+        Vec::new()
+    }
+
+    /// Generate the output derived from the passed data (ie the outputs of the fuzzers
+    /// returned by derived_from, in chain order) and append to the passed buf.
+    fn append_derived(&self, step: usize, rng: &mut dyn RngCore, _data: &Vec<u8>, buf: &mut Vec<u8>) {
+        self.append_fuzzed_immut(step, rng, buf)
+    }
+
+    /// Fuzzers which wrap another fuzzer (like the element modifiers), can override this to
+    /// return the wrapped fuzzer, so that derived fuzzers referring to it can find it.
+    fn wrapped(&self) -> Option<Rc<RefCell<dyn Fuzz>>> {
+        None
+    }
 }
 
-mod fixed;
-mod random;
+pub mod error;
+pub mod fixed;
+pub mod random;
+pub mod mutate;
+pub mod dictionary;
+pub mod number;
+pub mod unicode;
+pub mod codec;
+pub mod payload;
+pub mod encode;
+pub mod derived;
+pub mod choice;
+pub mod grammar;
+pub mod template;
+pub mod json;
+pub mod xml;
+pub mod stateful;
 pub mod cfgfiles;
 pub mod rtm;
+pub mod check;
 pub mod iob;
+pub mod oracle;
 pub mod vm;
 
 
+///
+/// Append the output of the given fuzzer (a element of a fuzz chain or so) to the passed buf,
+/// by calling its mutable or immutable version, as required by the fuzzer.
+///
+pub(crate) fn append_element(fuzzer: &Rc<RefCell<dyn Fuzz>>, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+    let imfuzzer = fuzzer.borrow();
+    if imfuzzer.need_mutable() {
+        drop(imfuzzer);
+        let mut mfuzzer = fuzzer.borrow_mut();
+        mfuzzer.append_fuzzed(step, rng, buf);
+        drop(mfuzzer);
+    } else {
+        imfuzzer.append_fuzzed_immut(step, rng, buf);
+        drop(imfuzzer);
+    }
+}
+
+
 ///
 /// Allow a chain of muttable fuzzers (whose internal contexts can be modified) to be created,
 /// so that byte buffer with the reqd pattern of data can be generated.
 ///
-#[allow(dead_code)]
 pub struct FuzzChain {
     chain: Vec<Rc<RefCell<dyn Fuzz>>>,
     /// step allows the fuzzer to know (if it wants to) that
     /// it is being called as part of the same step,
     /// if multiple instances of it are in the fuzz chain.
     step: usize,
+    /// The positions of the fuzzers, which each derived element depends on (None if not derived),
+    /// as found by check_derived. Empty if check_derived not called after the last append.
+    derived: Vec<Option<Vec<usize>>>,
 }
 
-#[allow(dead_code)]
 impl FuzzChain {
 
     pub fn new() -> FuzzChain {
         FuzzChain {
             chain: Vec::new(),
             step: 0,
+            derived: Vec::new(),
         }
     }
 
     /// Chain a muttable fuzzer, as part of setting up to achieve the reqd data pattern
-    fn append(&mut self, fuzzer: Rc<RefCell<dyn Fuzz>>) {
+    pub fn append(&mut self, fuzzer: Rc<RefCell<dyn Fuzz>>) {
         self.chain.push(fuzzer);
+        self.derived.clear();
+    }
+
+    ///
+    /// Check if the given element of the chain is the specified fuzzer, or wraps it.
+    ///
+    fn element_is(element: &Rc<RefCell<dyn Fuzz>>, fuzzer: &Rc<RefCell<dyn Fuzz>>) -> bool {
+        if Rc::as_ptr(element) as *const u8 == Rc::as_ptr(fuzzer) as *const u8 {
+            return true;
+        }
+        match element.borrow().wrapped() {
+            Some(wrapped) => Self::element_is(&wrapped, fuzzer),
+            None => false,
+        }
+    }
+
+    ///
+    /// Get the positions in the chain, of the fuzzers that the specified (derived) fuzzer
+    /// depends on. Returns the first fuzzer that is not part of the chain, as error.
+    ///
+    fn derived_positions(&self, fuzzer: &Rc<RefCell<dyn Fuzz>>) -> Result<Vec<usize>, usize> {
+        let mut positions = Vec::new();
+        for (i, from) in fuzzer.borrow().derived_from().iter().enumerate() {
+            let mut bfound = false;
+            for (j, sibling) in self.chain.iter().enumerate() {
+                if Self::element_is(sibling, from) {
+                    positions.push(j);
+                    bfound = true;
+                }
+            }
+            if !bfound {
+                return Err(i);
+            }
+        }
+        positions.sort();
+        positions.dedup();
+        Ok(positions)
+    }
+
+    ///
+    /// Check that the fuzzers, which the derived fuzzers (if any) in the chain depend on,
+    /// are part of the chain and that derived fuzzers dont depend on each other cyclicly.
+    /// The positions of the fuzzers depended on are remembered, for use when generating.
+    ///
+    pub fn check_derived(&mut self) -> Result<(), String> {
+        let mut deps = Vec::new();
+        for (i, fuzzer) in self.chain.iter().enumerate() {
+            let positions = self.derived_positions(fuzzer);
+            if positions.is_err() {
+                return Err(format!("ERRR:FuzzChain:CheckDerived:Element {} depends on a fuzzer (reference {}) not in the chain", i, positions.unwrap_err()));
+            }
+            deps.push(positions.unwrap());
+        }
+        for i in 0..deps.len() {
+            // Follow the dependencies, a cycle will return to where it started
+            let mut tocheck = deps[i].clone();
+            let mut checked = vec![false; deps.len()];
+            while let Some(j) = tocheck.pop() {
+                if j == i {
+                    return Err(format!("ERRR:FuzzChain:CheckDerived:Element {} depends on itself", i));
+                }
+                if !checked[j] {
+                    checked[j] = true;
+                    tocheck.extend(deps[j].iter());
+                }
+            }
+        }
+        self.derived = deps.into_iter().map(|positions| if positions.len() > 0 { Some(positions) } else { None }).collect();
+        Ok(())
+    }
+
+    ///
+    /// Get the positions of the fuzzers, which each derived element depends on (None if not derived).
+    /// If check_derived hasnt been called after the last append, these are found now.
+    ///
+    fn derived_deps(&self) -> Cow<'_, [Option<Vec<usize>>]> {
+        if self.derived.len() == self.chain.len() {
+            return Cow::Borrowed(&self.derived);
+        }
+        Cow::Owned(self.chain.iter().map(|fuzzer| {
+            // Missing dependencies will be caught by check_derived, if called
+            match self.derived_positions(fuzzer) {
+                Ok(positions) if positions.len() == 0 => None,
+                positions => Some(positions.unwrap_or_default()),
+            }
+        }).collect())
     }
 
     /// Get a byte buffer, whose data matches the pattern specified by the
@@ -69,25 +221,19 @@ impl FuzzChain {
     /// step: indicates a new call wrt fuzz chain fuzzed data generation.
     ///       Calls to a fuzzer, where step is same, indicates that, that fuzzer has been chained more than once.
     ///       If specified, same will be passed to fuzzers, else the internally maintained step counter's value will be passed.
-    fn get(&mut self, step: Option<usize>) -> Vec<u8> {
+    ///
+    /// rng: the random generator passed to the fuzzers. Using a seeded rng allows the generated data to be reproduced.
+    ///
+    /// Derived fuzzers are called after the fuzzers they depend on, but their output is placed
+    /// at their position in the chain.
+    pub fn get(&mut self, step: Option<usize>, rng: &mut dyn RngCore) -> Vec<u8> {
         let ustep;
         if step.is_none() {
             ustep = self.step;
         } else {
             ustep = step.unwrap();
         }
-        let mut buf: Vec<u8> = Vec::new();
-        for fuzzer in &mut self.chain {
-            let imfuzzer = fuzzer.borrow();
-            if imfuzzer.need_mutable() {
-                let mut mfuzzer = fuzzer.borrow_mut();
-                mfuzzer.append_fuzzed(ustep, &mut buf);
-                drop(mfuzzer);
-            } else {
-                imfuzzer.append_fuzzed_immut(ustep, &mut buf);
-            }
-            drop(imfuzzer);
-        }
+        let buf = self.generate(ustep, rng);
         if step.is_none() {
             self.step += 1;
         } else {
@@ -96,6 +242,59 @@ impl FuzzChain {
         buf
     }
 
+    ///
+    /// Generate the data wrt the given step, without updating the step counter of the chain.
+    ///
+    fn generate(&self, ustep: usize, rng: &mut dyn RngCore) -> Vec<u8> {
+        let mut segs: Vec<Option<Vec<u8>>> = vec![None; self.chain.len()];
+        let deps = self.derived_deps();
+        let mut derived = Vec::new();
+        for (i, fuzzer) in self.chain.iter().enumerate() {
+            if let Some(positions) = &deps[i] {
+                derived.push((i, positions));
+                continue;
+            }
+            let mut buf: Vec<u8> = Vec::new();
+            append_element(fuzzer, ustep, rng, &mut buf);
+            segs[i] = Some(buf);
+        }
+        while derived.len() > 0 {
+            let mut ready = derived.iter().position(|(_i, positions)| positions.iter().all(|&j| segs[j].is_some()));
+            if ready.is_none() {
+                // Cyclic dependency, use what ever has been generated till now
+                ready = Some(0);
+            }
+            let (i, positions) = derived.remove(ready.unwrap());
+            let mut data = Vec::new();
+            for &j in positions {
+                if let Some(seg) = &segs[j] {
+                    data.extend_from_slice(seg);
+                }
+            }
+            let mut buf: Vec<u8> = Vec::new();
+            self.chain[i].borrow().append_derived(ustep, rng, &data, &mut buf);
+            segs[i] = Some(buf);
+        }
+        segs.into_iter().flatten().flatten().collect()
+    }
+
+}
+
+///
+/// Allow a fuzz chain to be used as a element of another fuzz chain (ie nested),
+/// where ever a fuzzer can be used. The step of the parent chain is passed through.
+///
+impl Fuzz for FuzzChain {
+
+    fn append_fuzzed_immut(&self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let mut inb = self.generate(step, rng);
+        buf.append(&mut inb);
+    }
+
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        return self.append_fuzzed_immut(step, rng, buf);
+    }
+
 }
 
 
@@ -103,7 +302,9 @@ impl FuzzChain {
 #[cfg(test)]
 mod tests {
     use crate::{fixed::{self, RandomFixedStringsFuzzer}, random::{self, RandomFixedFuzzer}, Fuzz, FuzzChain};
-    use std::{rc::Rc, cell::RefCell};
+    use crate::{rtm::RunTimeManager, cfgfiles::HandleCfgGroup};
+    use std::{rc::Rc, cell::RefCell, collections::VecDeque};
+    use rand::{Rng, SeedableRng, rngs::StdRng};
 
     #[test]
     fn it_works() {
@@ -113,11 +314,12 @@ mod tests {
 
     #[test]
     fn fuzzer_fixedstrings() {
+        let mut rng = StdRng::seed_from_u64(0);
         // LoopFixedStringsFuzzer
         let mut fsf = fixed::LoopFixedStringsFuzzer::new(vec![Vec::from("Hello"), Vec::from("World")]);
         let mut buf:Vec<u8> = Vec::new();
         for i in 0..16 {
-            fsf.append_fuzzed(i, &mut buf)
+            fsf.append_fuzzed(i, &mut rng, &mut buf)
         }
         println!("TEST:FuzzerLoopFixedStrings:{:?}", buf);
         println!("TEST:FuzzerLoopFixedStrings:{:?}", String::from_utf8(buf));
@@ -125,7 +327,7 @@ mod tests {
         let mut fsf = fixed::RandomFixedStringsFuzzer::new(vec![Vec::from("Hello"), Vec::from("World")]);
         let mut buf:Vec<u8> = Vec::new();
         for i in 0..16 {
-            fsf.append_fuzzed(i, &mut buf)
+            fsf.append_fuzzed(i, &mut rng, &mut buf)
         }
         println!("TEST:FuzzerRandomFixedStrings:{:?}", buf);
         println!("TEST:FuzzerRandomFixedStrings:{:?}", String::from_utf8(buf));
@@ -135,19 +337,20 @@ mod tests {
     fn fuzzer_randomrandom() {
         const MINLEN: usize = 3;
         const MAXLEN: usize = 5;
+        let mut rng = StdRng::seed_from_u64(0);
         let mut rrf = random::RandomRandomFuzzer::new(MINLEN, MAXLEN);
         let mut buf:Vec<u8> = Vec::new();
         for i in 0..16 {
-            rrf.append_fuzzed(i, &mut buf);
+            rrf.append_fuzzed(i, &mut rng, &mut buf);
             println!("TEST:FuzzerRandomRandom:{}:BufLen:{}", i, buf.len());
         }
         println!("TEST:FuzzerRandomRandom:{:?}", buf);
     }
 
-    fn gen_randbytes(size: usize) -> Vec<u8> {
+    fn gen_randbytes(rng: &mut StdRng, size: usize) -> Vec<u8> {
         let mut randbytes = Vec::new();
         for _i in 0..size {
-            randbytes.push(rand::random());
+            randbytes.push(rng.gen());
         }
         randbytes
     }
@@ -156,11 +359,12 @@ mod tests {
     fn fuzzer_randomfixed() {
         const MINLEN: usize = 3;
         const MAXLEN: usize = 10;
+        let mut rng = StdRng::seed_from_u64(0);
         // RandomFixedFuzzer - binary
-        let mut rfb = random::RandomFixedFuzzer::new(MINLEN, MAXLEN, gen_randbytes(128));
+        let mut rfb = random::RandomFixedFuzzer::new(MINLEN, MAXLEN, gen_randbytes(&mut rng, 128));
         let mut buf:Vec<u8> = Vec::new();
         for i in 0..16 {
-            rfb.append_fuzzed(i, &mut buf);
+            rfb.append_fuzzed(i, &mut rng, &mut buf);
             println!("TEST:FuzzerRandomFixed<Binary>:{}:BufLen:{}", i, buf.len());
         }
         println!("TEST:FuzzerRandomFixed<Binary>:{:?}", buf);
@@ -168,14 +372,13 @@ mod tests {
         buf.clear();
         let mut rfp = random::RandomFixedFuzzer::new_printables(MINLEN, MAXLEN);
         for i in 0..16 {
-            rfp.append_fuzzed(i, &mut buf);
+            rfp.append_fuzzed(i, &mut rng, &mut buf);
             println!("TEST:FuzzerRandomFixed<Printables>:{}:BufLen:{}", i, buf.len());
         }
         println!("TEST:FuzzerRandomFixed<Printables>:{:?}", String::from_utf8(buf));
     }
 
-    #[test]
-    fn fuzzchain_t1() {
+    fn fuzzchain_t1_setup() -> FuzzChain {
         let mut fc1 = FuzzChain::new();
 
         let rfsf = RandomFixedStringsFuzzer::new(vec![Vec::from("Hello"), Vec::from("World")]);
@@ -195,10 +398,159 @@ mod tests {
         fc1.append(rspacesf2);
         fc1.append(rfpf); // The same fuzzer instance can be chained multiple times, if data pattern reqd dictates it.
         fc1.append(rfpf2);
+        fc1
+    }
+
+    #[test]
+    fn fuzzchain_t1() {
+        let mut fc1 = fuzzchain_t1_setup();
+        let mut rng = StdRng::seed_from_u64(0);
         for i in 0..8 {
-            let fuzzed = fc1.get(None);
+            let fuzzed = fc1.get(None, &mut rng);
             println!("TEST:FuzzChainT1:{}:{:?}:{:?}", i, fuzzed.clone(), String::from_utf8(fuzzed));
         }
     }
 
+    #[test]
+    fn fuzzchain_seeded_reproducible() {
+        let mut vfuzzed = Vec::new();
+        for _run in 0..2 {
+            let mut fc1 = fuzzchain_t1_setup();
+            let mut rng = StdRng::seed_from_u64(0x5EED);
+            let mut fuzzed = Vec::new();
+            for _i in 0..8 {
+                fuzzed.append(&mut fc1.get(None, &mut rng));
+            }
+            vfuzzed.push(fuzzed);
+        }
+        assert_eq!(vfuzzed[0], vfuzzed[1]);
+    }
+
+    /// Create a cfggroup from the given lines, used by the tests of the other modules also
+    pub(crate) fn cfggroup(lines: &[&str]) -> VecDeque<String> {
+        lines.iter().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn fuzzchain_nested() {
+        let mut rtm = RunTimeManager::new_seeded(0);
+        rtm.add_fuzzer("A", Rc::new(RefCell::new(RandomFixedStringsFuzzer::new(vec![Vec::from("A")]))));
+        rtm.add_fuzzer("B", Rc::new(RefCell::new(RandomFixedStringsFuzzer::new(vec![Vec::from("B")]))));
+        // FC02 refers to FC01 before its defined
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC02", "  B", "  FC01", "  FC01"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC01", "  A", "  B"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC03", "  FC02", "  A"])).unwrap();
+        assert!(rtm.fchain_get("FC02", None).is_none());
+        rtm.handle_cfgend().unwrap();
+        assert_eq!(rtm.fchain_get("FC02", None), Some(Vec::from("BABAB")));
+        assert_eq!(rtm.fchain_get("FC03", None), Some(Vec::from("BABABA")));
+        // cycles are reported
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC11", "  A", "  FC12"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC12", "  FC13"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC13", "  B", "  FC12"])).unwrap();
+        let gotr = rtm.handle_cfgend();
+        assert!(gotr.unwrap_err().to_string().contains("Cycle FC12 -> FC13 -> FC12"));
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC21", "  FC21"])).unwrap();
+        let gotr = rtm.handle_cfgend();
+        assert!(gotr.unwrap_err().to_string().contains("Cycle FC21 -> FC21"));
+        // fuzzers can refer to fuzzers and fuzzchains defined later
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:EncodeFuzzer:E31", "  inner: FC31", "  encoding: hex"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:LengthField:LEN31", "  of: E31", "  encoding: u8"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC31", "  A", "  CH31"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:ChoiceFuzzer:CH31", "  choices:", "    B"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC32", "  LEN31", "  E31"])).unwrap();
+        assert!(rtm.fuzzer("E31").is_none());
+        rtm.handle_cfgend().unwrap();
+        assert_eq!(rtm.fchain_get("FC32", None), Some(Vec::from("\x044142")));
+        // including cycles through fuzzers
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:EncodeFuzzer:E41", "  inner: FC41", "  encoding: hex"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC41", "  E41"])).unwrap();
+        let gotr = rtm.handle_cfgend();
+        assert!(gotr.unwrap_err().to_string().contains("Cycle E41 -> FC41 -> E41"));
+    }
+
+    #[test]
+    fn fuzzchain_modifiers() {
+        use crate::choice::ElementSpec;
+        assert_eq!(ElementSpec::parse(" ?HDR:30*{0,5} ").unwrap(), ElementSpec { name: "HDR".to_string(), optional: Some(30), repeat: Some((0, 5)) });
+        assert_eq!(ElementSpec::parse("HDR*{2}").unwrap().repeat, Some((2, 2)));
+        assert_eq!(ElementSpec::parse("?HDR").unwrap().optional, Some(50));
+        assert!(ElementSpec::parse("HDR*{5,2}").is_err());
+        assert!(ElementSpec::parse("?HDR:101").is_err());
+        assert!(ElementSpec::parse("HDR*{1,2").is_err());
+        let mut rtm = RunTimeManager::new_seeded(0);
+        rtm.add_fuzzer("A", Rc::new(RefCell::new(RandomFixedStringsFuzzer::new(vec![Vec::from("A")]))));
+        rtm.add_fuzzer("B", Rc::new(RefCell::new(RandomFixedStringsFuzzer::new(vec![Vec::from("B")]))));
+        rtm.add_fuzzer("C", Rc::new(RefCell::new(RandomFixedStringsFuzzer::new(vec![Vec::from("C")]))));
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC_BC", "  B", "  C"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:ChoiceFuzzer:CH01", "  choices:", "    A:3", "    FC_BC", "    C:0"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC01", "  ?A:0", "  ?B:100", "  C*{3}", "  CH01*{0,4}"])).unwrap();
+        let gotr = rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:ChoiceFuzzer:CH02", "  choices: A:0"]));
+        assert!(gotr.unwrap_err().contains("non zero weight"));
+        let gotr = rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC02", "  A*{x}"]));
+        assert!(gotr.unwrap_err().contains("Repeat count"));
+        let (mut cnta, mut cntbc) = (0, 0);
+        for _i in 0..32 {
+            let buf = String::from_utf8(rtm.fchain_get("FC01", None).unwrap()).unwrap();
+            assert!(buf.starts_with("BCCC"));
+            let choices = buf[4..].replace("BC", "-");
+            assert!(choices.len() <= 4);
+            assert!(choices.chars().all(|c| c == 'A' || c == '-'));
+            cnta += choices.matches('A').count();
+            cntbc += choices.matches('-').count();
+        }
+        assert!(cnta > cntbc);
+        // Derived fuzzers wrapped by modifiers still see the fuzzers they are derived from
+        rtm.add_fuzzer("BODY", Rc::new(RefCell::new(RandomFixedStringsFuzzer::new(vec![Vec::from("HELLO")]))));
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:LengthField:LEN", "  of: BODY", "  encoding: u8"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:ChecksumField:CS", "  of: BODY", "  algo: sum8"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC03", "  ?LEN:100", "  BODY", "  CS*{2}"])).unwrap();
+        assert_eq!(rtm.fchain_get("FC03", None).unwrap(), vec![5, b'H', b'E', b'L', b'L', b'O', 116, 116]);
+        // as well as fuzzers they are derived from, which are wrapped by modifiers
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC04", "  LEN", "  BODY*{2}", "  ?BODY:0", "  CS"])).unwrap();
+        assert_eq!(rtm.fchain_get("FC04", None).unwrap(), vec![10, b'H', b'E', b'L', b'L', b'O', b'H', b'E', b'L', b'L', b'O', 232]);
+    }
+
+    #[test]
+    fn fuzzchain_derived() {
+        use crate::derived::ChecksumAlgo;
+        for (salgo, sum) in [("crc32", 0xCBF43926), ("crc16", 0x29B1), ("crc16modbus", 0x4B37), ("adler32", 0x091E01DE), ("sum8", 0xDD)] {
+            assert_eq!(ChecksumAlgo::from_str(salgo).unwrap().calc(b"123456789"), sum);
+        }
+        assert_eq!(ChecksumAlgo::Inet.calc(&[0x45, 0x00, 0x00, 0x73, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11, 0x00, 0x00, 0xc0, 0xa8, 0x00, 0x01, 0xc0, 0xa8, 0x00, 0xc7]), 0xb861);
+        let mut rtm = RunTimeManager::new_seeded(0);
+        let cgs: [&[&str]; 7] = [
+            &["FuzzerType:RandomFixedFuzzerPrintables:BODY", "  minlen: 1", "  maxlen: 20"],
+            &["FuzzerType:RandomFixedStringsFuzzer:HDR", "  list: HD"],
+            &["FuzzerType:LengthField:LEN", "  of:", "    HDR", "    BODY", "  encoding: u16be", "  adjust: 1"],
+            &["FuzzerType:ChecksumField:CRC", "  of: BODY", "  algo: crc32", "  endian: little"],
+            &["FuzzerType:LengthField:TLV", "  chain: BODY", "  encoding: u8"],
+            &["FuzzChain:FuzzChain:PKT", "  LEN", "  HDR", "  BODY", "  CRC", "  TLV"],
+            &["FuzzChain:FuzzChain:BAD", "  LEN", "  BODY"],
+        ];
+        for cg in &cgs[..6] {
+            rtm.handle_cfggroup(&mut cfggroup(cg)).unwrap();
+        }
+        let gotr = rtm.handle_cfggroup(&mut cfggroup(cgs[6]));
+        assert!(gotr.unwrap_err().contains("not in the chain"));
+        let gotr = rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:ChecksumField:CS02", "  algo: crc32"]));
+        assert!(gotr.unwrap_err().contains("Specify either of or chain"));
+        for _i in 0..8 {
+            let pkt = rtm.fchain_get("PKT", None).unwrap();
+            let len = ((pkt[0] as usize) << 8) | pkt[1] as usize;
+            assert_eq!(&pkt[2..4], b"HD");
+            let bodylen = len - 1 - 2;
+            let body = &pkt[4..4+bodylen];
+            let crc = ChecksumAlgo::Crc32.calc(body).to_le_bytes();
+            assert_eq!(&pkt[4+bodylen..8+bodylen], &crc);
+            let tlv = &pkt[8+bodylen..];
+            assert_eq!(tlv[0] as usize, tlv.len() - 1);
+        }
+        // always corrupted
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:ChecksumField:CSBAD", "  of: HDR", "  algo: sum8", "  corrupt: 100"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:PKTBAD", "  HDR", "  CSBAD"])).unwrap();
+        let pkt = rtm.fchain_get("PKTBAD", None).unwrap();
+        assert_ne!(pkt[2], b'H'.wrapping_add(b'D'));
+    }
+
 }
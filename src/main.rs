@@ -9,11 +9,38 @@ use std::{collections::HashMap, process};
 use loggerk::*;
 use argsclsk;
 use fuzzerk::vm;
+use fuzzerk::error::FuzzerKError;
+use fuzzerk::check::Severity;
 
 
+///
+/// Report the error and exit, with a exit code indicating the kind of failure
+/// * 1: invalid cmdline args
+/// * 2: issue with the cfg or asm files
+/// * 3: issue compiling the asm program
+/// * 4: issue running the asm program
+///
+fn exit_onerror(err: FuzzerKError) -> ! {
+    log_e(&err.to_string());
+    let code = match err {
+        FuzzerKError::Args(_) => 1,
+        FuzzerKError::File(_, _) | FuzzerKError::CfgFile { .. } => 2,
+        FuzzerKError::Compile { .. } => 3,
+        FuzzerKError::Run { .. } | FuzzerKError::IOBridge(_) => 4,
+    };
+    process::exit(code);
+}
+
+///
+/// Check the cfgfc files and asm program (if any) for issues, without running anything
+/// * check
 ///
 /// Specify the config file which sets up the fuzzers and the fuzzchains
 /// * --cfgfc <path/file>
+///   * can be repeated to load multiple cfg files, in the order specified
+///
+/// Reload the fuzzers and fuzzchains, if the cfg files are edited, while running
+/// * --cfgwatch <true|yes>
 ///
 /// Specify the fuzzchain to run
 /// * --fc <fcname>
@@ -26,23 +53,56 @@ use fuzzerk::vm;
 ///   * console
 ///   * tcpclient:ipaddress:port
 ///   * tlsclient:ipaddress:port
+///   * tlsserver:ipaddress:port
+///   * udpclient:ipaddress:port
+///   * udpserver:ipaddress:port
+///   * unixclient:path/to/socket
+///   * unixserver:path/to/socket
+///   * exec:path/to/program arg1 'arg 2' ... (quote it wrt the shell)
 ///
 /// Specify additional arguments if any for the io modules
 /// * --ioarg <key>=<value>
 ///
+/// Specify the seed to use for the random generator, so that a previous run can be reproduced
+/// * --seed <number>
+///
+/// Specify the directory, where the recent fuzzed data is saved, if the target fails
+/// * --crashdir <path/dir>
+///
+/// Specify how many of the recent fuzzed data (fcget buffers) should be saved
+/// * --crashhistory <number>
+///
 /// Enable logging/printing of debug messages, if required using
 /// * --blogdebug <true|yes>
 ///
-fn handle_cmdline() -> (String, String, usize, String, HashMap<String, String>, String, bool) {
+fn handle_cmdline() -> (bool, Vec<String>, bool, String, usize, String, HashMap<String, String>, String, Option<u64>, String, Option<usize>, bool) {
     let mut clargs = argsclsk::ArgsCmdLineSimpleManager::new();
 
-    let mut cfgfc = String::new();
+    let mut bcheck = false;
+    let mut check_handler = |_iarg: usize, _args: &Vec<String>|-> usize {
+        bcheck = true;
+        0
+    };
+    clargs.add_handler("check", &mut check_handler);
+
+    let mut cfgfcs = Vec::new();
     let mut cfgfc_handler = |iarg: usize, args: &Vec<String>|-> usize {
-        cfgfc = args[iarg+1].clone();
+        cfgfcs.push(args[iarg+1].clone());
         1
     };
     clargs.add_handler("--cfgfc", &mut cfgfc_handler);
 
+    let mut bcfgwatch = false;
+    let mut cfgwatch_handler = |iarg: usize, args: &Vec<String>| -> usize {
+        let scfgwatch = &args[iarg+1];
+        let vyes = vec!["yes", "true"];
+        if vyes.contains(&scfgwatch.as_str()) {
+            bcfgwatch = true;
+        }
+        1
+    };
+    clargs.add_handler("--cfgwatch", &mut cfgwatch_handler);
+
     let mut asmfile = String::new();
     let mut asmfile_handler = |iarg: usize, args: &Vec<String>|-> usize {
         asmfile = args[iarg+1].clone();
@@ -59,7 +119,11 @@ fn handle_cmdline() -> (String, String, usize, String, HashMap<String, String>,
 
     let mut loopcnt = 1usize;
     let mut loopcnt_handler = |iarg: usize, args: &Vec<String>|-> usize {
-        loopcnt = usize::from_str_radix(&args[iarg+1], 10).expect(&format!("ERRR:MFuzzerKU:HandleCmdline:Invalid loopcnt:{}", loopcnt));
+        let gotr = usize::from_str_radix(&args[iarg+1], 10);
+        if gotr.is_err() {
+            exit_onerror(FuzzerKError::Args(format!("Invalid loopcnt:{}", args[iarg+1])));
+        }
+        loopcnt = gotr.unwrap();
         1
     };
     clargs.add_handler("--loopcnt", &mut loopcnt_handler);
@@ -74,12 +138,45 @@ fn handle_cmdline() -> (String, String, usize, String, HashMap<String, String>,
     let mut ioargs: HashMap<String, String> = HashMap::new();
     let mut ioarg_handler = |iarg: usize, args: &Vec<String>|-> usize {
         let ioarg = args[iarg+1].clone();
-        let (k,v) = ioarg.split_once("=").expect(&format!("ERRR:MFuzzerKU:HandleCmdline:IOArg:{}", ioarg));
+        let kv = ioarg.split_once("=");
+        if kv.is_none() {
+            exit_onerror(FuzzerKError::Args(format!("Invalid ioarg, expected key=value:{}", ioarg)));
+        }
+        let (k,v) = kv.unwrap();
         ioargs.insert(k.to_string(), v.to_string());
         1
     };
     clargs.add_handler("--ioarg", &mut ioarg_handler);
 
+    let mut seed = None;
+    let mut seed_handler = |iarg: usize, args: &Vec<String>|-> usize {
+        let useed = u64::from_str_radix(&args[iarg+1], 10);
+        if useed.is_err() {
+            exit_onerror(FuzzerKError::Args(format!("Invalid seed:{}", args[iarg+1])));
+        }
+        seed = Some(useed.unwrap());
+        1
+    };
+    clargs.add_handler("--seed", &mut seed_handler);
+
+    let mut crashdir = String::new();
+    let mut crashdir_handler = |iarg: usize, args: &Vec<String>|-> usize {
+        crashdir = args[iarg+1].clone();
+        1
+    };
+    clargs.add_handler("--crashdir", &mut crashdir_handler);
+
+    let mut crashhistory = None;
+    let mut crashhistory_handler = |iarg: usize, args: &Vec<String>|-> usize {
+        let ucrashhistory = usize::from_str_radix(&args[iarg+1], 10);
+        if ucrashhistory.is_err() {
+            exit_onerror(FuzzerKError::Args(format!("Invalid crashhistory:{}", args[iarg+1])));
+        }
+        crashhistory = Some(ucrashhistory.unwrap());
+        1
+    };
+    clargs.add_handler("--crashhistory", &mut crashhistory_handler);
+
     let mut blogdebug = false;
     let mut blogdebug_handler = |iarg: usize, args: &Vec<String>| -> usize {
         let slogdebug = &args[iarg+1];
@@ -93,23 +190,71 @@ fn handle_cmdline() -> (String, String, usize, String, HashMap<String, String>,
 
     clargs.process_args();
 
-    return (cfgfc, fc, loopcnt, ioaddr, ioargs, asmfile, blogdebug);
+    return (bcheck, cfgfcs, bcfgwatch, fc, loopcnt, ioaddr, ioargs, asmfile, seed, crashdir, crashhistory, blogdebug);
 }
 
 
 
+///
+/// Check the cfgfc files and asm program, report the issues found and exit.
+/// Exits with 2, if any errors were found, warnings alone dont affect the exit code.
+///
+fn check(cfgfcs: &Vec<String>, asmfile: &str) -> ! {
+    if (cfgfcs.len() == 0) && (asmfile.len() == 0) {
+        exit_onerror(FuzzerKError::Args("check needs --cfgfc <Fuzz++CfgFile> and or --asmfile <ThePrgFile>".to_string()));
+    }
+    let mut vm = vm::VM::new();
+    let diags = vm.check(cfgfcs, asmfile);
+    let mut errors = 0;
+    for diag in &diags {
+        match diag.severity {
+            Severity::Error => {
+                errors += 1;
+                log_e(&diag.to_string());
+            }
+            Severity::Warning => log_w(&diag.to_string()),
+        }
+    }
+    log_o(&format!("INFO:FuzzerK:Check:{} errors, {} warnings", errors, diags.len() - errors));
+    if errors > 0 {
+        process::exit(2);
+    }
+    process::exit(0);
+}
+
+
 fn main() {
     log_init();
     log_o("MinimalFuzzerKUtil");
 
-    let (cfgfc, fc, loopcnt, ioaddr, ioargs, asmfile, blogdebug) = handle_cmdline();
+    let (bcheck, cfgfcs, bcfgwatch, fc, loopcnt, ioaddr, ioargs, asmfile, seed, crashdir, crashhistory, blogdebug) = handle_cmdline();
     log_config(true, true, true, blogdebug, true);
 
+    if bcheck {
+        check(&cfgfcs, &asmfile);
+    }
+
     let mut vm = vm::VM::new();
-    if cfgfc.len() == 0 {
+    if seed.is_some() {
+        vm.set_seed(seed.unwrap());
+    }
+    log_o(&format!("INFO:FuzzerK:Seed:{}", vm.seed()));
+    if crashdir.len() > 0 {
+        vm.set_crashdir(&crashdir);
+    }
+    if crashhistory.is_some() {
+        vm.set_crashhistory(crashhistory.unwrap());
+    }
+    if cfgfcs.len() == 0 {
         log_o(&format!("NOTE:FuzzerK:Args: --cfgfc <Fuzz++CfgFile> is a simple mechanism to create fuzzers and fuzzchains, usable in most cases"));
     }
-    vm.load_fcrtm(&cfgfc);
+    for cfgfc in &cfgfcs {
+        let gotr = vm.load_fcrtm(cfgfc);
+        if gotr.is_err() {
+            exit_onerror(gotr.unwrap_err());
+        }
+    }
+    vm.set_cfgwatch(bcfgwatch);
     if asmfile.len() == 0 {
         if loopcnt <= 1 {
             log_o(&format!("NOTE:FuzzerK:Args: --loopcnt <ANumber> allows one to control how many times to loop through fuzzchain generation and io handshake"));
@@ -118,10 +263,19 @@ fn main() {
             log_w(&format!("WARN:FuzzerK:Args: If no --asmfile <ThePrgFile>, then --fc <FuzzChainId> is needed along with --cfgfc <Fuzz++Cfgfile>"));
             process::exit(1);
         }
-        vm.predefined_asmprg(&fc, loopcnt, &ioaddr, &ioargs);
+        let gotr = vm.predefined_asmprg(&fc, loopcnt, &ioaddr, &ioargs);
+        if gotr.is_err() {
+            exit_onerror(gotr.unwrap_err());
+        }
     } else {
-        vm.load_asmprg(&asmfile);
+        let gotr = vm.load_asmprg(&asmfile);
+        if gotr.is_err() {
+            exit_onerror(gotr.unwrap_err());
+        }
     }
 
-    vm.run();
+    let gotr = vm.run();
+    if gotr.is_err() {
+        exit_onerror(gotr.unwrap_err());
+    }
 }
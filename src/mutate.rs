@@ -0,0 +1,771 @@
+//!
+//! MutateFuzzers: Generate data by mutating the provided seed buffers,
+//! using bit flips, arithmetic, interesting values, block ops and splicing.
+//!
+//! HanishKVC, 2022
+//!
+
+use std::collections::VecDeque;
+use rand::{Rng, RngCore};
+
+use crate::cfgfiles::FromVecStrings;
+
+
+/// The max value added to or subtracted from the selected int, by ArithFuzzer
+pub const ARITH_MAXDELTA: usize = 35;
+/// The max length of the block operated on by BlockFuzzer and HavocFuzzer
+pub const BLOCK_MAXLEN: usize = 16;
+/// The max number of mutations stacked by HavocFuzzer
+pub const HAVOC_MAXOPS: usize = 16;
+
+/// Interesting 8 bit values, as used by AFL
+const INTERESTING_8: [i32; 9] = [-128, -1, 0, 1, 16, 32, 64, 100, 127];
+/// Interesting 16 bit values (in addition to the 8 bit ones), as used by AFL
+const INTERESTING_16: [i32; 10] = [-32768, -129, 128, 255, 256, 512, 1000, 1024, 4096, 32767];
+/// Interesting 32 bit values (in addition to the 8 and 16 bit ones), as used by AFL
+const INTERESTING_32: [i32; 8] = [-2147483648, -100663046, -32769, 32768, 65535, 65536, 100663045, 2147483647];
+
+
+///
+/// Flip width number of consecutive bits, starting at the given bit position.
+/// Bit position 0 is the msb of the 1st byte.
+///
+fn bitflip_at(buf: &mut Vec<u8>, bitpos: usize, width: usize) {
+    for i in bitpos..(bitpos+width) {
+        buf[i/8] ^= 0x80 >> (i%8);
+    }
+}
+
+fn bitflip(rng: &mut dyn RngCore, buf: &mut Vec<u8>, width: usize) {
+    let nbits = buf.len()*8;
+    if nbits < width {
+        return;
+    }
+    let bitpos = rng.gen::<usize>() % (nbits - width + 1);
+    bitflip_at(buf, bitpos, width);
+}
+
+///
+/// Get the width bytes sized int at pos, in the specified endianness
+///
+fn int_get(buf: &Vec<u8>, pos: usize, width: usize, bbig: bool) -> u32 {
+    let mut val: u32 = 0;
+    for i in 0..width {
+        let b = if bbig { buf[pos+i] } else { buf[pos+width-1-i] };
+        val = (val << 8) | b as u32;
+    }
+    val
+}
+
+///
+/// Set the width bytes sized int at pos, in the specified endianness
+///
+fn int_set(buf: &mut Vec<u8>, pos: usize, width: usize, bbig: bool, mut val: u32) {
+    for i in 0..width {
+        let ipos = if bbig { pos+width-1-i } else { pos+i };
+        buf[ipos] = (val & 0xFF) as u8;
+        val >>= 8;
+    }
+}
+
+///
+/// Add or subtract a random delta (1..=maxdelta) to a random width bytes sized int
+///
+fn arith(rng: &mut dyn RngCore, buf: &mut Vec<u8>, width: usize, bbig: bool, maxdelta: usize) {
+    if buf.len() < width || maxdelta == 0 {
+        return;
+    }
+    let pos = rng.gen::<usize>() % (buf.len() - width + 1);
+    let delta = (1 + rng.gen::<usize>() % maxdelta) as u32;
+    let val = int_get(buf, pos, width, bbig);
+    let val = if rng.gen::<bool>() { val.wrapping_add(delta) } else { val.wrapping_sub(delta) };
+    int_set(buf, pos, width, bbig, val);
+}
+
+///
+/// Overwrite a random width bytes sized int with a interesting value, which fits within it
+///
+fn interesting(rng: &mut dyn RngCore, buf: &mut Vec<u8>, width: usize, bbig: bool) {
+    if buf.len() < width {
+        return;
+    }
+    let mut vals = INTERESTING_8.to_vec();
+    if width >= 2 {
+        vals.extend(INTERESTING_16);
+    }
+    if width >= 4 {
+        vals.extend(INTERESTING_32);
+    }
+    let pos = rng.gen::<usize>() % (buf.len() - width + 1);
+    let val = vals[rng.gen::<usize>() % vals.len()];
+    int_set(buf, pos, width, bbig, val as u32);
+}
+
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BlockOp {
+    /// Copy a block from the buffer and insert it at a random position
+    Duplicate,
+    Delete,
+    /// Insert a block of random bytes
+    Insert,
+    /// Swap two non overlapping blocks
+    Swap,
+}
+
+impl BlockOp {
+
+    const ALL: [BlockOp; 4] = [BlockOp::Duplicate, BlockOp::Delete, BlockOp::Insert, BlockOp::Swap];
+
+    pub fn from_str(sop: &str) -> Result<Option<BlockOp>, String> {
+        match sop {
+            "random" => Ok(None),
+            "duplicate" => Ok(Some(BlockOp::Duplicate)),
+            "delete" => Ok(Some(BlockOp::Delete)),
+            "insert" => Ok(Some(BlockOp::Insert)),
+            "swap" => Ok(Some(BlockOp::Swap)),
+            _ => Err(format!("ERRR:BlockOp:Unknown op:{}", sop)),
+        }
+    }
+
+}
+
+fn block(rng: &mut dyn RngCore, buf: &mut Vec<u8>, op: BlockOp, minlen: usize, maxlen: usize) {
+    let len = buf.len();
+    let mut blen = minlen + rng.gen::<usize>() % (maxlen - minlen + 1);
+    match op {
+        BlockOp::Duplicate => {
+            blen = blen.min(len);
+            if blen == 0 {
+                return;
+            }
+            let src = rng.gen::<usize>() % (len - blen + 1);
+            let dst = rng.gen::<usize>() % (len + 1);
+            let blk: Vec<u8> = buf[src..src+blen].to_vec();
+            buf.splice(dst..dst, blk);
+        }
+        BlockOp::Delete => {
+            // Keep atleast one byte
+            blen = blen.min(len.saturating_sub(1));
+            if blen == 0 {
+                return;
+            }
+            let start = rng.gen::<usize>() % (len - blen + 1);
+            buf.drain(start..start+blen);
+        }
+        BlockOp::Insert => {
+            let dst = rng.gen::<usize>() % (len + 1);
+            let blk: Vec<u8> = (0..blen).map(|_| rng.gen::<u8>()).collect();
+            buf.splice(dst..dst, blk);
+        }
+        BlockOp::Swap => {
+            blen = blen.min(len/2);
+            if blen == 0 {
+                return;
+            }
+            let a = rng.gen::<usize>() % (len - 2*blen + 1);
+            let b = a + blen + rng.gen::<usize>() % (len - a - 2*blen + 1);
+            for i in 0..blen {
+                buf.swap(a+i, b+i);
+            }
+        }
+    }
+}
+
+///
+/// Join the head of buffer a with the tail of buffer b, at random split points
+///
+fn splice(rng: &mut dyn RngCore, a: &Vec<u8>, b: &Vec<u8>) -> Vec<u8> {
+    if a.len() < 2 || b.len() == 0 {
+        return a.clone();
+    }
+    let pa = 1 + rng.gen::<usize>() % (a.len() - 1);
+    let pb = rng.gen::<usize>() % b.len();
+    let mut out = a[..pa].to_vec();
+    out.extend_from_slice(&b[pb..]);
+    out
+}
+
+
+///
+/// Pick one of the seed buffers randomly
+///
+pub(crate) fn pick_buf8(buf8s: &Vec<Vec<u8>>, rng: &mut dyn RngCore) -> Vec<u8> {
+    buf8s[rng.gen::<usize>() % buf8s.len()].clone()
+}
+
+///
+/// Check the cfggroup's fuzzer type and get the buf8s list of seed buffers, which is common
+/// to all mutate fuzzers. Returns the seed buffers and the spacesprefix wrt the keys.
+///
+pub(crate) fn fromvs_buf8s<T: FromVecStrings>(vs: &mut VecDeque<String>, minbufs: usize) -> Result<(Vec<Vec<u8>>, usize), String> {
+    let name = T::get_name();
+    let l = vs.pop_front();
+    if l.is_none() {
+        return Err(format!("ERRR:{}:FromStringVec:Got empty vector", name));
+    }
+    let l = l.unwrap(); // This should identify this particular type of Fuzzer and a runtime instance name
+    let la: Vec<&str> = l.split(":").collect();
+    if (la.len() < 2) || (la[1] != name) {
+        return Err(format!("ERRR:{}:FromStringVec:Mismatch wrt Fuzzer Type????", name));
+    }
+    let spacesprefix = T::get_spacesprefix(vs);
+    let buf8s = T::get_values(vs, "buf8s", spacesprefix).map_err(|e| format!("ERRR:{}:GetBuf8s:{}", name, e))?;
+    if buf8s.len() < minbufs {
+        return Err(format!("ERRR:{}:FromStringVec:Need atleast {} buf8s, got {}", name, minbufs, buf8s.len()));
+    }
+    Ok((buf8s, spacesprefix))
+}
+
+fn fromvs_width<T: FromVecStrings>(vs: &mut VecDeque<String>, spacesprefix: usize, default: usize, allowed: &[usize]) -> Result<usize, String> {
+    let width = T::get_uvalue_or(vs, "width", spacesprefix, default).map_err(|e| format!("ERRR:{}:GetWidth:{}", T::get_name(), e))?;
+    if !allowed.contains(&width) {
+        return Err(format!("ERRR:{}:Width {} not in {:?}", T::get_name(), width, allowed));
+    }
+    Ok(width)
+}
+
+///
+/// Returns true, if big endian
+///
+fn fromvs_endian<T: FromVecStrings>(vs: &mut VecDeque<String>, spacesprefix: usize) -> Result<bool, String> {
+    let endian = T::get_svalue_or(vs, "endian", spacesprefix, "little").map_err(|e| format!("ERRR:{}:GetEndian:{}", T::get_name(), e))?;
+    match endian.as_str() {
+        "little" => Ok(false),
+        "big" => Ok(true),
+        _ => Err(format!("ERRR:{}:Endian should be little or big, got {}", T::get_name(), endian)),
+    }
+}
+
+pub(crate) fn fromvs_count<T: FromVecStrings>(vs: &mut VecDeque<String>, spacesprefix: usize) -> Result<usize, String> {
+    let count = T::get_uvalue_or(vs, "count", spacesprefix, 1).map_err(|e| format!("ERRR:{}:GetCount:{}", T::get_name(), e))?;
+    if count == 0 {
+        return Err(format!("ERRR:{}:Count should be atleast 1", T::get_name()));
+    }
+    Ok(count)
+}
+
+fn fromvs_minmax<T: FromVecStrings>(vs: &mut VecDeque<String>, spacesprefix: usize, keys: (&str, &str), defaults: (usize, usize)) -> Result<(usize, usize), String> {
+    let min = T::get_uvalue_or(vs, keys.0, spacesprefix, defaults.0).map_err(|e| format!("ERRR:{}:Get{}:{}", T::get_name(), keys.0, e))?;
+    let max = T::get_uvalue_or(vs, keys.1, spacesprefix, defaults.1).map_err(|e| format!("ERRR:{}:Get{}:{}", T::get_name(), keys.1, e))?;
+    if min == 0 || min > max {
+        return Err(format!("ERRR:{}:Need 0 < {} {} <= {} {}", T::get_name(), keys.0, min, keys.1, max));
+    }
+    Ok((min, max))
+}
+
+
+///
+/// Flip bits in a randomly selected seed buffer
+/// * random mode: flip count number of randomly positioned width bits
+/// * walk mode: flip width bits, walking thro the bit positions of each seed buffer,
+///   one position per step.
+///
+#[derive(Debug)]
+pub struct BitFlipFuzzer {
+    buf8s: Vec<Vec<u8>>,
+    bwalk: bool,
+    /// The number of consecutive bits flipped
+    width: usize,
+    count: usize,
+}
+
+impl BitFlipFuzzer {
+
+    pub fn new(buf8s: Vec<Vec<u8>>, bwalk: bool, width: usize, count: usize) -> BitFlipFuzzer {
+        BitFlipFuzzer {
+            buf8s,
+            bwalk,
+            width,
+            count,
+        }
+    }
+
+    ///
+    /// Map the step to a seed buffer and the bit position within it
+    ///
+    fn walk_pos(&self, step: usize) -> Option<(usize, usize)> {
+        let mut total = 0;
+        for buf in &self.buf8s {
+            let nbits = buf.len()*8;
+            if nbits >= self.width {
+                total += nbits - self.width + 1;
+            }
+        }
+        if total == 0 {
+            return None;
+        }
+        let mut ipos = step % total;
+        for (i, buf) in self.buf8s.iter().enumerate() {
+            let nbits = buf.len()*8;
+            if nbits < self.width {
+                continue;
+            }
+            let npos = nbits - self.width + 1;
+            if ipos < npos {
+                return Some((i, ipos));
+            }
+            ipos -= npos;
+        }
+        None
+    }
+
+}
+
+impl super::Fuzz for BitFlipFuzzer {
+
+    fn append_fuzzed_immut(&self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        if self.bwalk {
+            let wpos = self.walk_pos(step);
+            if wpos.is_none() {
+                return;
+            }
+            let (ibuf, bitpos) = wpos.unwrap();
+            let mut inb = self.buf8s[ibuf].clone();
+            bitflip_at(&mut inb, bitpos, self.width);
+            buf.append(&mut inb);
+            return;
+        }
+        let mut inb = pick_buf8(&self.buf8s, rng);
+        for _i in 0..self.count {
+            bitflip(rng, &mut inb, self.width);
+        }
+        buf.append(&mut inb);
+    }
+
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        return self.append_fuzzed_immut(step, rng, buf);
+    }
+
+}
+
+impl FromVecStrings for BitFlipFuzzer {
+
+    fn get_name() -> String {
+        return "BitFlipFuzzer".to_string();
+    }
+
+    ///
+    /// In the cfgfile use
+    /// ### FuzzerType:BitFlipFuzzer:InstanceName
+    /// * buf8s: a list of textual or hex strings
+    /// * mode: random | walk (optional, default random)
+    /// * width: 1 | 2 | 4 | 8 (optional, default 1), number of consecutive bits flipped
+    /// * count: +ve integer (optional, default 1), number of flips wrt random mode
+    ///
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<BitFlipFuzzer, String> {
+        let (buf8s, spacesprefix) = fromvs_buf8s::<Self>(vs, 1)?;
+        let mode = Self::get_svalue_or(vs, "mode", spacesprefix, "random").map_err(|e| format!("ERRR:BitFlipFuzzer:GetMode:{}", e))?;
+        let bwalk = match mode.as_str() {
+            "random" => false,
+            "walk" => true,
+            _ => return Err(format!("ERRR:BitFlipFuzzer:Mode should be random or walk, got {}", mode)),
+        };
+        let width = fromvs_width::<Self>(vs, spacesprefix, 1, &[1, 2, 4, 8])?;
+        let count = fromvs_count::<Self>(vs, spacesprefix)?;
+        Ok(BitFlipFuzzer::new(buf8s, bwalk, width, count))
+    }
+
+}
+
+
+///
+/// Add or subtract small values to randomly selected byte/word/dword sized ints,
+/// in a randomly selected seed buffer.
+///
+#[derive(Debug)]
+pub struct ArithFuzzer {
+    buf8s: Vec<Vec<u8>>,
+    /// The size of the int in bytes
+    width: usize,
+    bbig: bool,
+    maxdelta: usize,
+    count: usize,
+}
+
+impl ArithFuzzer {
+
+    pub fn new(buf8s: Vec<Vec<u8>>, width: usize, bbig: bool, maxdelta: usize, count: usize) -> ArithFuzzer {
+        ArithFuzzer {
+            buf8s,
+            width,
+            bbig,
+            maxdelta,
+            count,
+        }
+    }
+
+}
+
+impl super::Fuzz for ArithFuzzer {
+
+    fn append_fuzzed_immut(&self, _step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let mut inb = pick_buf8(&self.buf8s, rng);
+        for _i in 0..self.count {
+            arith(rng, &mut inb, self.width, self.bbig, self.maxdelta);
+        }
+        buf.append(&mut inb);
+    }
+
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        return self.append_fuzzed_immut(step, rng, buf);
+    }
+
+}
+
+impl FromVecStrings for ArithFuzzer {
+
+    fn get_name() -> String {
+        return "ArithFuzzer".to_string();
+    }
+
+    ///
+    /// In the cfgfile use
+    /// ### FuzzerType:ArithFuzzer:InstanceName
+    /// * buf8s: a list of textual or hex strings
+    /// * width: 1 | 2 | 4 (optional, default 1), the size of the int in bytes
+    /// * endian: little | big (optional, default little)
+    /// * maxdelta: +ve integer (optional, default 35)
+    /// * count: +ve integer (optional, default 1), number of ints modified
+    ///
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<ArithFuzzer, String> {
+        let (buf8s, spacesprefix) = fromvs_buf8s::<Self>(vs, 1)?;
+        let width = fromvs_width::<Self>(vs, spacesprefix, 1, &[1, 2, 4])?;
+        let bbig = fromvs_endian::<Self>(vs, spacesprefix)?;
+        let maxdelta = Self::get_uvalue_or(vs, "maxdelta", spacesprefix, ARITH_MAXDELTA).map_err(|e| format!("ERRR:ArithFuzzer:GetMaxDelta:{}", e))?;
+        if maxdelta == 0 {
+            return Err("ERRR:ArithFuzzer:MaxDelta should be atleast 1".to_string());
+        }
+        let count = fromvs_count::<Self>(vs, spacesprefix)?;
+        Ok(ArithFuzzer::new(buf8s, width, bbig, maxdelta, count))
+    }
+
+}
+
+
+///
+/// Overwrite randomly selected byte/word/dword sized ints, in a randomly selected
+/// seed buffer, with interesting values like 0, -1, 0x7f, 0x80, 0xffff, ...
+///
+#[derive(Debug)]
+pub struct InterestingFuzzer {
+    buf8s: Vec<Vec<u8>>,
+    /// The size of the int in bytes
+    width: usize,
+    bbig: bool,
+    count: usize,
+}
+
+impl InterestingFuzzer {
+
+    pub fn new(buf8s: Vec<Vec<u8>>, width: usize, bbig: bool, count: usize) -> InterestingFuzzer {
+        InterestingFuzzer {
+            buf8s,
+            width,
+            bbig,
+            count,
+        }
+    }
+
+}
+
+impl super::Fuzz for InterestingFuzzer {
+
+    fn append_fuzzed_immut(&self, _step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let mut inb = pick_buf8(&self.buf8s, rng);
+        for _i in 0..self.count {
+            interesting(rng, &mut inb, self.width, self.bbig);
+        }
+        buf.append(&mut inb);
+    }
+
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        return self.append_fuzzed_immut(step, rng, buf);
+    }
+
+}
+
+impl FromVecStrings for InterestingFuzzer {
+
+    fn get_name() -> String {
+        return "InterestingFuzzer".to_string();
+    }
+
+    ///
+    /// In the cfgfile use
+    /// ### FuzzerType:InterestingFuzzer:InstanceName
+    /// * buf8s: a list of textual or hex strings
+    /// * width: 1 | 2 | 4 (optional, default 1), the size of the int in bytes
+    /// * endian: little | big (optional, default little)
+    /// * count: +ve integer (optional, default 1), number of ints overwritten
+    ///
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<InterestingFuzzer, String> {
+        let (buf8s, spacesprefix) = fromvs_buf8s::<Self>(vs, 1)?;
+        let width = fromvs_width::<Self>(vs, spacesprefix, 1, &[1, 2, 4])?;
+        let bbig = fromvs_endian::<Self>(vs, spacesprefix)?;
+        let count = fromvs_count::<Self>(vs, spacesprefix)?;
+        Ok(InterestingFuzzer::new(buf8s, width, bbig, count))
+    }
+
+}
+
+
+///
+/// Duplicate / delete / insert / swap blocks of bytes in a randomly selected seed buffer.
+///
+#[derive(Debug)]
+pub struct BlockFuzzer {
+    buf8s: Vec<Vec<u8>>,
+    /// The block op to apply, None means a randomly selected op each time
+    op: Option<BlockOp>,
+    minlen: usize,
+    maxlen: usize,
+    count: usize,
+}
+
+impl BlockFuzzer {
+
+    pub fn new(buf8s: Vec<Vec<u8>>, op: Option<BlockOp>, minlen: usize, maxlen: usize, count: usize) -> BlockFuzzer {
+        BlockFuzzer {
+            buf8s,
+            op,
+            minlen,
+            maxlen,
+            count,
+        }
+    }
+
+}
+
+impl super::Fuzz for BlockFuzzer {
+
+    fn append_fuzzed_immut(&self, _step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let mut inb = pick_buf8(&self.buf8s, rng);
+        for _i in 0..self.count {
+            let op = self.op.unwrap_or_else(|| BlockOp::ALL[rng.gen::<usize>() % BlockOp::ALL.len()]);
+            block(rng, &mut inb, op, self.minlen, self.maxlen);
+        }
+        buf.append(&mut inb);
+    }
+
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        return self.append_fuzzed_immut(step, rng, buf);
+    }
+
+}
+
+impl FromVecStrings for BlockFuzzer {
+
+    fn get_name() -> String {
+        return "BlockFuzzer".to_string();
+    }
+
+    ///
+    /// In the cfgfile use
+    /// ### FuzzerType:BlockFuzzer:InstanceName
+    /// * buf8s: a list of textual or hex strings
+    /// * op: random | duplicate | delete | insert | swap (optional, default random)
+    /// * minlen: +ve integer (optional, default 1), min length of the block
+    /// * maxlen: +ve integer (optional, default 16), max length of the block
+    /// * count: +ve integer (optional, default 1), number of block ops applied
+    ///
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<BlockFuzzer, String> {
+        let (buf8s, spacesprefix) = fromvs_buf8s::<Self>(vs, 1)?;
+        let sop = Self::get_svalue_or(vs, "op", spacesprefix, "random").map_err(|e| format!("ERRR:BlockFuzzer:GetOp:{}", e))?;
+        let op = BlockOp::from_str(&sop).map_err(|e| format!("ERRR:BlockFuzzer:{}", e))?;
+        let (minlen, maxlen) = fromvs_minmax::<Self>(vs, spacesprefix, ("minlen", "maxlen"), (1, BLOCK_MAXLEN))?;
+        let count = fromvs_count::<Self>(vs, spacesprefix)?;
+        Ok(BlockFuzzer::new(buf8s, op, minlen, maxlen, count))
+    }
+
+}
+
+
+///
+/// Join the head of a randomly selected seed buffer with the tail of another.
+///
+#[derive(Debug)]
+pub struct SpliceFuzzer {
+    buf8s: Vec<Vec<u8>>,
+}
+
+impl SpliceFuzzer {
+
+    pub fn new(buf8s: Vec<Vec<u8>>) -> SpliceFuzzer {
+        SpliceFuzzer {
+            buf8s,
+        }
+    }
+
+}
+
+impl super::Fuzz for SpliceFuzzer {
+
+    fn append_fuzzed_immut(&self, _step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let ia = rng.gen::<usize>() % self.buf8s.len();
+        let ib = (ia + 1 + rng.gen::<usize>() % (self.buf8s.len() - 1)) % self.buf8s.len();
+        let mut inb = splice(rng, &self.buf8s[ia], &self.buf8s[ib]);
+        buf.append(&mut inb);
+    }
+
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        return self.append_fuzzed_immut(step, rng, buf);
+    }
+
+}
+
+impl FromVecStrings for SpliceFuzzer {
+
+    fn get_name() -> String {
+        return "SpliceFuzzer".to_string();
+    }
+
+    ///
+    /// In the cfgfile use
+    /// ### FuzzerType:SpliceFuzzer:InstanceName
+    /// * buf8s: a list of atleast 2 textual or hex strings
+    ///
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<SpliceFuzzer, String> {
+        let (buf8s, _spacesprefix) = fromvs_buf8s::<Self>(vs, 2)?;
+        Ok(SpliceFuzzer::new(buf8s))
+    }
+
+}
+
+
+///
+/// Apply a random number of randomly selected mutations (bit flip, arith, interesting,
+/// block ops and splice if more than one seed buffer), one on top of the other,
+/// to a randomly selected seed buffer.
+///
+#[derive(Debug)]
+pub struct HavocFuzzer {
+    buf8s: Vec<Vec<u8>>,
+    minops: usize,
+    maxops: usize,
+}
+
+impl HavocFuzzer {
+
+    pub fn new(buf8s: Vec<Vec<u8>>, minops: usize, maxops: usize) -> HavocFuzzer {
+        HavocFuzzer {
+            buf8s,
+            minops,
+            maxops,
+        }
+    }
+
+}
+
+impl super::Fuzz for HavocFuzzer {
+
+    fn append_fuzzed_immut(&self, _step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let mut inb = pick_buf8(&self.buf8s, rng);
+        let nops = self.minops + rng.gen::<usize>() % (self.maxops - self.minops + 1);
+        let nchoices = if self.buf8s.len() > 1 { 8 } else { 7 };
+        for _i in 0..nops {
+            let width = [1, 2, 4][rng.gen::<usize>() % 3];
+            match rng.gen::<usize>() % nchoices {
+                0 => {
+                    let bits = [1, 2, 4, 8][rng.gen::<usize>() % 4];
+                    bitflip(rng, &mut inb, bits);
+                }
+                1 => {
+                    let bbig = rng.gen::<bool>();
+                    arith(rng, &mut inb, width, bbig, ARITH_MAXDELTA);
+                }
+                2 => {
+                    let bbig = rng.gen::<bool>();
+                    interesting(rng, &mut inb, width, bbig);
+                }
+                3 => block(rng, &mut inb, BlockOp::Duplicate, 1, BLOCK_MAXLEN),
+                4 => block(rng, &mut inb, BlockOp::Delete, 1, BLOCK_MAXLEN),
+                5 => block(rng, &mut inb, BlockOp::Insert, 1, BLOCK_MAXLEN),
+                6 => block(rng, &mut inb, BlockOp::Swap, 1, BLOCK_MAXLEN),
+                _ => {
+                    let other = pick_buf8(&self.buf8s, rng);
+                    inb = splice(rng, &inb, &other);
+                }
+            }
+        }
+        buf.append(&mut inb);
+    }
+
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        return self.append_fuzzed_immut(step, rng, buf);
+    }
+
+}
+
+impl FromVecStrings for HavocFuzzer {
+
+    fn get_name() -> String {
+        return "HavocFuzzer".to_string();
+    }
+
+    ///
+    /// In the cfgfile use
+    /// ### FuzzerType:HavocFuzzer:InstanceName
+    /// * buf8s: a list of textual or hex strings
+    /// * minops: +ve integer (optional, default 1), min number of mutations stacked
+    /// * maxops: +ve integer (optional, default 16), max number of mutations stacked
+    ///
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<HavocFuzzer, String> {
+        let (buf8s, spacesprefix) = fromvs_buf8s::<Self>(vs, 1)?;
+        let (minops, maxops) = fromvs_minmax::<Self>(vs, spacesprefix, ("minops", "maxops"), (1, HAVOC_MAXOPS))?;
+        Ok(HavocFuzzer::new(buf8s, minops, maxops))
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::cfggroup;
+    use crate::rtm::RunTimeManager;
+    use crate::cfgfiles::HandleCfgGroup;
+    use crate::Fuzz;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn fuzzer_mutate() {
+        let mut rng = StdRng::seed_from_u64(0);
+        // walking bit flips go thro each bit position of each seed buffer
+        let bff = BitFlipFuzzer::new(vec![vec![0x00], vec![0x00, 0x00]], true, 1, 1);
+        let mut buf = Vec::new();
+        for step in [0, 7, 8, 23, 24] {
+            bff.append_fuzzed_immut(step, &mut rng, &mut buf);
+        }
+        assert_eq!(buf, vec![0x80, 0x01, 0x80, 0x00, 0x00, 0x01, 0x80]);
+        let bf = BlockFuzzer::new(vec![vec![1, 2, 3, 4]], Some(BlockOp::Delete), 2, 2, 1);
+        let mut buf = Vec::new();
+        bf.append_fuzzed_immut(0, &mut rng, &mut buf);
+        assert_eq!(buf.len(), 2);
+        // from cfg file, with optional keys
+        let mut rtm = RunTimeManager::new_seeded(0);
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:InterestingFuzzer:IF01", "  buf8s: $0x00000000", "  width: 4", "  endian: big"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:ArithFuzzer:AF01", "  buf8s: $0x0000", "  width: 2", "  maxdelta: 1"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:SpliceFuzzer:SF01", "  buf8s:", "    AAAA", "    BBBB"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:HavocFuzzer:HF01", "  buf8s: Hello World", "  maxops: 4"])).unwrap();
+        let gotr = rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:SpliceFuzzer:SF02", "  buf8s: AAAA"]));
+        assert!(gotr.unwrap_err().contains("Need atleast 2 buf8s"));
+        let gotr = rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:ArithFuzzer:AF02", "  buf8s: AAAA", "  width: 3"]));
+        assert!(gotr.unwrap_err().contains("Width 3"));
+        let mut buf = Vec::new();
+        rtm.fuzzer("AF01").unwrap().borrow().append_fuzzed_immut(0, &mut rng, &mut buf);
+        assert!(buf == vec![0x01, 0x00] || buf == vec![0xFF, 0xFF]);
+        for _i in 0..8 {
+            let mut buf = Vec::new();
+            rtm.fuzzer("SF01").unwrap().borrow().append_fuzzed_immut(0, &mut rng, &mut buf);
+            assert!(buf.len() >= 2 && buf.len() <= 7);
+            let mut buf = Vec::new();
+            rtm.fuzzer("IF01").unwrap().borrow().append_fuzzed_immut(0, &mut rng, &mut buf);
+            assert_eq!(buf.len(), 4);
+            let mut buf = Vec::new();
+            rtm.fuzzer("HF01").unwrap().borrow().append_fuzzed_immut(0, &mut rng, &mut buf);
+            assert!(buf.len() > 0);
+        }
+    }
+
+}
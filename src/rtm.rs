@@ -5,100 +5,548 @@
 //! HanishKVC, 2022
 //!
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::cell::RefCell;
 
-use loggerk::log_d;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use loggerk::{log_d, log_o};
 
 use crate::{Fuzz, FuzzChain};
-use crate::cfgfiles::{FromVecStrings, HandleCfgGroup};
-use crate::{fixed, random};
+use crate::cfgfiles::{self, FromVecStrings, HandleCfgGroup};
+use crate::error::FuzzerKError;
+use crate::{fixed, random, mutate, dictionary, number, derived, choice, grammar, template, unicode, payload, encode, json, xml, stateful};
+use crate::choice::ElementSpec;
 
 
 const TYPEMARKER_FUZZER: &str = "FuzzerType";
-const TYPEMARKER_FUZZCHAIN: &str = "FuzzChain";
+pub(crate) const TYPEMARKER_FUZZCHAIN: &str = "FuzzChain";
+
+///
+/// Create a fuzzer instance, from the cfggroup (FuzzerType:TypeName:InstanceName + its key-values)
+/// passed to it. The runtime manager is also passed, in case the fuzzer being created wants to
+/// make use of any of the fuzzers or fuzzchains already created.
+/// If the cfggroup is not valid, a message explaining the issue should be returned.
+///
+pub type FuzzerBuilder = Box<dyn Fn(&RunTimeManager, &mut VecDeque<String>) -> Result<Rc<RefCell<dyn Fuzz>>, String>>;
+
+///
+/// Fuzzers which refer to other fuzzers or fuzzchains by name in their cfggroup. The runtime
+/// manager looks up the names, once the fuzzers or fuzzchains referred to have been created,
+/// so that they can be defined later in the cfg file.
+///
+pub trait ResolveRefs {
+    /// The names of the fuzzers or fuzzchains referred to
+    fn refs(&self) -> Vec<String>;
+    /// Lookup the fuzzers or fuzzchains referred to, from the runtime manager
+    fn resolve(&mut self, rtm: &RunTimeManager) -> Result<(), String>;
+}
+
+/// Lookup the names referred to by a already created fuzzer
+type Resolver = Box<dyn Fn(&RunTimeManager) -> Result<(), String>>;
+
+/// Create a fuzzer instance, which refers to other fuzzers, along with the names referred to and its resolver
+type RefsBuilder = Box<dyn Fn(&mut VecDeque<String>) -> Result<(Rc<RefCell<dyn Fuzz>>, Vec<String>, Resolver), String>>;
+
+enum PendingKind {
+    FuzzChain(Vec<ElementSpec>),
+    /// The fuzzer, which is already created, but yet to be resolved
+    Fuzzer(Rc<RefCell<dyn Fuzz>>, Resolver),
+}
+
+///
+/// A fuzzchain or fuzzer, which refers to fuzzers or fuzzchains not yet defined
+///
+struct Pending {
+    name: String,
+    refs: Vec<String>,
+    kind: PendingKind,
+    /// The file and line of its cfggroup
+    cfgloc: (String, usize),
+}
 
 pub struct RunTimeManager {
     fuzzers: HashMap<String, Rc<RefCell<dyn Fuzz>>>,
-    fchains: HashMap<String, FuzzChain>,
+    fchains: HashMap<String, Rc<RefCell<FuzzChain>>>,
+    /// The fuzzers and fuzzchains added from the program (and not the cfg files), retained across reloads
+    progfuzzers: HashMap<String, Rc<RefCell<dyn Fuzz>>>,
+    progfchains: HashMap<String, Rc<RefCell<FuzzChain>>>,
+    /// The fuzzchains and fuzzers, which refer to fuzzers or fuzzchains not yet defined
+    pending: Vec<Pending>,
+    /// The names referred to by the fuzzchains and fuzzers defined in the cfg files,
+    /// recorded as their cfggroups are handled, even if they couldnt be created.
+    referred: HashSet<String>,
+    /// The file and line (at which it starts) of the cfggroup being handled
+    cfgloc: (String, usize),
+    /// The builders wrt the fuzzer types that can be created from the cfg files
+    fuzzertypes: HashMap<String, FuzzerBuilder>,
+    /// The builders wrt the fuzzer types, which refer to other fuzzers or fuzzchains
+    refsfuzzertypes: HashMap<String, RefsBuilder>,
+    /// The seed used to setup the rng, remember it so that a run can be reproduced.
+    seed: u64,
+    /// The single random generator used by all fuzzchains (and inturn their fuzzers) and the vm.
+    /// NOTE: StdRng output is reproducible for a given seed, only wrt a given build of the program.
+    rng: StdRng,
 }
 
 impl RunTimeManager {
 
+    ///
+    /// Create a instance, whose rng is seeded with a randomly generated seed
+    ///
     pub fn new() -> RunTimeManager {
-        RunTimeManager {
+        Self::new_seeded(rand::random())
+    }
+
+    ///
+    /// Create a instance, whose rng is seeded with the given seed
+    ///
+    pub fn new_seeded(seed: u64) -> RunTimeManager {
+        let mut rtm = RunTimeManager {
             fuzzers: HashMap::new(),
             fchains: HashMap::new(),
+            progfuzzers: HashMap::new(),
+            progfchains: HashMap::new(),
+            pending: Vec::new(),
+            referred: HashSet::new(),
+            cfgloc: (String::new(), 0),
+            fuzzertypes: HashMap::new(),
+            refsfuzzertypes: HashMap::new(),
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        };
+        rtm.register_fuzzertype_fromvs::<fixed::LoopFixedStringsFuzzer>("LoopFixedStringsFuzzer");
+        rtm.register_fuzzertype_fromvs::<fixed::RandomFixedStringsFuzzer>("RandomFixedStringsFuzzer");
+        rtm.register_fuzzertype_fromvs::<random::RandomRandomFuzzer>("RandomRandomFuzzer");
+        rtm.register_fuzzertype_fromvs::<random::RandomFixedFuzzer>("RandomFixedFuzzer");
+        rtm.register_fuzzertype_fromvs::<random::RandomFixedFuzzer>("RandomFixedFuzzerPrintables");
+        rtm.register_fuzzertype_fromvs::<random::Buf8sRandomizeFuzzer>("Buf8sRandomizeFuzzer");
+        rtm.register_fuzzertype_fromvs::<mutate::BitFlipFuzzer>("BitFlipFuzzer");
+        rtm.register_fuzzertype_fromvs::<mutate::ArithFuzzer>("ArithFuzzer");
+        rtm.register_fuzzertype_fromvs::<mutate::InterestingFuzzer>("InterestingFuzzer");
+        rtm.register_fuzzertype_fromvs::<mutate::BlockFuzzer>("BlockFuzzer");
+        rtm.register_fuzzertype_fromvs::<mutate::SpliceFuzzer>("SpliceFuzzer");
+        rtm.register_fuzzertype_fromvs::<mutate::HavocFuzzer>("HavocFuzzer");
+        rtm.register_fuzzertype_fromvs::<dictionary::DictionaryInsertFuzzer>("DictionaryInsertFuzzer");
+        rtm.register_fuzzertype_fromvs::<dictionary::DictionaryOverwriteFuzzer>("DictionaryOverwriteFuzzer");
+        rtm.register_fuzzertype_fromvs::<number::NumberFuzzer>("NumberFuzzer");
+        rtm.register_fuzzertype_fromvs::<grammar::GrammarFuzzer>("GrammarFuzzer");
+        rtm.register_fuzzertype_fromvs::<unicode::UnicodeFuzzer>("UnicodeFuzzer");
+        rtm.register_fuzzertype_fromvs::<payload::PayloadFuzzer>("PayloadFuzzer");
+        rtm.register_fuzzertype_fromvs::<json::JsonFuzzer>("JsonFuzzer");
+        rtm.register_fuzzertype_fromvs::<xml::XmlFuzzer>("XmlFuzzer");
+        rtm.register_fuzzertype_fromvs::<stateful::SequenceFuzzer>("SequenceFuzzer");
+        rtm.register_fuzzertype_fromvs::<stateful::SessionIdFuzzer>("SessionIdFuzzer");
+        rtm.register_fuzzertype_fromvs::<stateful::TimeFuzzer>("TimeFuzzer");
+        rtm.register_fuzzertype_refs::<derived::LengthField>("LengthField");
+        rtm.register_fuzzertype_refs::<choice::ChoiceFuzzer>("ChoiceFuzzer");
+        rtm.register_fuzzertype_refs::<derived::ChecksumField>("ChecksumField");
+        rtm.register_fuzzertype_refs::<encode::EncodeFuzzer>("EncodeFuzzer");
+        rtm.register_fuzzertype_refs::<template::TemplateFuzzer>("TemplateFuzzer");
+        rtm
+    }
+
+    ///
+    /// Restart the rng using the given seed
+    ///
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn seed(&self) -> u64 {
+        return self.seed;
+    }
+
+    pub fn rng(&mut self) -> &mut StdRng {
+        return &mut self.rng;
+    }
+
+    ///
+    /// Register a builder for the given fuzzer type name, so that
+    /// FuzzerType:TypeName:InstanceName cfggroups in the cfg files will use it.
+    /// If a builder already exists for the given type name, it will be replaced.
+    ///
+    pub fn register_fuzzertype(&mut self, typename: &str, builder: FuzzerBuilder) {
+        self.refsfuzzertypes.remove(typename);
+        self.fuzzertypes.insert(typename.to_string(), builder);
+    }
+
+    ///
+    /// Register a fuzzer type, which knows how to create itself from a cfggroup and
+    /// refers to other fuzzers or fuzzchains by name (refer ResolveRefs).
+    ///
+    pub fn register_fuzzertype_refs<T: Fuzz + FromVecStrings + ResolveRefs + 'static>(&mut self, typename: &str) {
+        self.fuzzertypes.remove(typename);
+        self.refsfuzzertypes.insert(typename.to_string(), Box::new(|vs: &mut VecDeque<String>| {
+            let tfuzzer = Rc::new(RefCell::new(T::from_vs(vs)?));
+            let refs = tfuzzer.borrow().refs();
+            let fuzzer: Rc<RefCell<dyn Fuzz>> = tfuzzer.clone();
+            let resolver: Resolver = Box::new(move |rtm: &RunTimeManager| tfuzzer.borrow_mut().resolve(rtm));
+            Ok((fuzzer, refs, resolver))
+        }));
+    }
+
+    ///
+    /// Register a fuzzer type, which knows how to create itself from a cfggroup,
+    /// by implementing the cfgfiles::FromVecStrings trait.
+    ///
+    pub fn register_fuzzertype_fromvs<T: Fuzz + FromVecStrings + 'static>(&mut self, typename: &str) {
+        self.register_fuzzertype(typename, Box::new(|_rtm: &RunTimeManager, vs: &mut VecDeque<String>| {
+            let fuzzer: Rc<RefCell<dyn Fuzz>> = Rc::new(RefCell::new(T::from_vs(vs)?));
+            Ok(fuzzer)
+        }));
+    }
+
+    ///
+    /// Add a already created fuzzer, so that it can be used by fuzzchains
+    /// defined in the cfg files, which are loaded after this.
+    ///
+    pub fn add_fuzzer(&mut self, name: &str, fuzzer: Rc<RefCell<dyn Fuzz>>) {
+        self.progfuzzers.insert(name.to_string(), fuzzer.clone());
+        self.fuzzers.insert(name.to_string(), fuzzer);
+    }
+
+    pub fn fuzzer(&self, name: &str) -> Option<Rc<RefCell<dyn Fuzz>>> {
+        let fuzzer = self.fuzzers.get(name);
+        if fuzzer.is_none() {
+            return None;
         }
+        return Some(fuzzer.unwrap().clone());
     }
 
-    pub fn fchain(&mut self, name: &str) -> Option<&mut FuzzChain> {
-        let fchains = self.fchains.get_mut(name);
-        if fchains.is_none() {
+    ///
+    /// Add a already created fuzzchain, so that it can be used like the
+    /// fuzzchains defined in the cfg files.
+    ///
+    pub fn add_fchain(&mut self, name: &str, fchain: FuzzChain) {
+        let fchain = Rc::new(RefCell::new(fchain));
+        self.progfchains.insert(name.to_string(), fchain.clone());
+        self.fchains.insert(name.to_string(), fchain);
+    }
+
+    pub fn fchain(&self, name: &str) -> Option<Rc<RefCell<FuzzChain>>> {
+        let fchain = self.fchains.get(name);
+        if fchain.is_none() {
             return None;
         }
-        let fchains = fchains.unwrap();
-        return Some(fchains);
+        return Some(fchain.unwrap().clone());
+    }
+
+    ///
+    /// Get the fuzzer with the given name, else the fuzzchain with the given name,
+    /// so that fuzzchains can be used where ever a fuzzer can be used.
+    ///
+    pub fn fuzzer_or_fchain(&self, name: &str) -> Option<Rc<RefCell<dyn Fuzz>>> {
+        let fuzzer = self.fuzzer(name);
+        if fuzzer.is_some() {
+            return fuzzer;
+        }
+        let fchain = self.fchains.get(name);
+        if fchain.is_none() {
+            return None;
+        }
+        let fchain: Rc<RefCell<dyn Fuzz>> = fchain.unwrap().clone();
+        return Some(fchain);
+    }
+
+    ///
+    /// Generate fuzzed data using the specified fuzzchain and the rng of the runtime manager.
+    ///
+    pub fn fchain_get(&mut self, name: &str, step: Option<usize>) -> Option<Vec<u8>> {
+        let fchain = self.fchains.get(name);
+        if fchain.is_none() {
+            return None;
+        }
+        let fchain = fchain.unwrap();
+        return Some(fchain.borrow_mut().get(step, &mut self.rng));
+    }
+
+    ///
+    /// Create the fuzzchain from the specified elements (fuzzers or fuzzchains),
+    /// wrapped as required by their modifiers (optional, repeat).
+    ///
+    fn fchain_create(&mut self, name: &str, elements: &Vec<ElementSpec>) -> Result<(), String> {
+        let mut fc = FuzzChain::new();
+        for element in elements {
+            let fuzzer = self.fuzzer_or_fchain(&element.name);
+            if fuzzer.is_none() {
+                return Err(format!("ERRR:RunTimeManager:FChainCreate:{}:Reference to unknown fuzzer {}", name, element.name));
+            }
+            fc.append(element.wrap(fuzzer.unwrap()));
+        }
+        let gotr = fc.check_derived();
+        if gotr.is_err() {
+            return Err(format!("ERRR:RunTimeManager:FChainCreate:{}:{}", name, gotr.unwrap_err()));
+        }
+        log_d(&format!("DBUG:RunTimeManager:FChainCreate:Created FuzzChain [{}]", name));
+        self.fchains.insert(name.to_string(), Rc::new(RefCell::new(fc)));
+        Ok(())
+    }
+
+    ///
+    /// Check if the given name is already used by a fuzzer or fuzzchain (including pending ones)
+    ///
+    fn name_used(&self, name: &str) -> bool {
+        self.fuzzers.contains_key(name) || self.fchains.contains_key(name) || self.pending.iter().any(|pending| pending.name == name)
+    }
+
+    ///
+    /// Get the names of the fuzzers, which are not referred to by any fuzzchain or other
+    /// fuzzer (refer ResolveRefs) defined in the cfg files.
+    ///
+    pub fn fuzzers_unused(&self) -> Vec<String> {
+        let mut unused: Vec<String> = self.fuzzers.keys().filter(|name| !self.referred.contains(*name)).cloned().collect();
+        unused.sort();
+        unused
+    }
+
+    ///
+    /// Recreate the fuzzers and fuzzchains from the given cfg files, replacing the current ones.
+    /// The registered fuzzer types, seed and rng are retained, as are the fuzzers and fuzzchains
+    /// added using add_fuzzer and add_fchain. If there is any issue with the cfg files, the
+    /// current fuzzers and fuzzchains are retained and the issue returned.
+    ///
+    /// The files parsed (ie the given files and the files included by them) are returned.
+    ///
+    pub fn reload(&mut self, cfgfcs: &Vec<String>) -> Result<Vec<PathBuf>, FuzzerKError> {
+        let fuzzers = std::mem::replace(&mut self.fuzzers, self.progfuzzers.clone());
+        let fchains = std::mem::replace(&mut self.fchains, self.progfchains.clone());
+        let referred = std::mem::take(&mut self.referred);
+        let mut files = Vec::new();
+        for cfgfc in cfgfcs {
+            let gotr = cfgfiles::parse_file_deps(cfgfc, self, &mut files);
+            if gotr.is_err() {
+                self.fuzzers = fuzzers;
+                self.fchains = fchains;
+                self.referred = referred;
+                self.pending.clear();
+                return Err(gotr.unwrap_err());
+            }
+        }
+        log_o(&format!("INFO:RunTimeManager:Reload:{} fuzzers, {} fuzzchains", self.fuzzers.len(), self.fchains.len()));
+        Ok(files)
+    }
+
+    ///
+    /// Explain why the pending fuzzchains and fuzzers cant be created, ie either they refer to
+    /// a unknown fuzzer or there is a cycle in how they refer to each other. The issue is returned
+    /// wrt the cfgloc of the fuzzchain or fuzzer with the unknown reference or starting the cycle.
+    ///
+    fn pending_issue(&self) -> FuzzerKError {
+        let pending = |name: &str| self.pending.iter().position(|pending| pending.name == name);
+        let mut path = Vec::new();
+        let mut cur = 0;
+        loop {
+            let Pending { name, refs, cfgloc: (file, line), .. } = &self.pending[cur];
+            let issue = |msg: String| FuzzerKError::CfgFile { file: file.clone(), line: *line, msg };
+            if let Some(ipath) = path.iter().position(|pname| pname == name) {
+                path.push(name.clone());
+                return issue(format!("ERRR:RunTimeManager:Pending:Cycle {}", path[ipath..].join(" -> ")));
+            }
+            path.push(name.clone());
+            for rname in refs {
+                if self.fuzzer_or_fchain(rname).is_none() && pending(rname).is_none() {
+                    return issue(format!("ERRR:RunTimeManager:Pending:{}:Reference to unknown fuzzer {}", name, rname));
+                }
+            }
+            // Follow a reference, to a fuzzchain or fuzzer still pending
+            let next = refs.iter().find_map(|rname| if self.fuzzer_or_fchain(rname).is_none() { pending(rname) } else { None });
+            cur = next.unwrap();
+        }
+    }
+
+    ///
+    /// Return the references to unknown fuzzers, from all the pending fuzzchains and fuzzers,
+    /// wrt their cfgloc. Used by check, as handle_cfgend stops at the first issue.
+    ///
+    pub fn pending_unknown(&self) -> Vec<FuzzerKError> {
+        let mut issues = Vec::new();
+        for Pending { name, refs, cfgloc: (file, line), .. } in &self.pending {
+            for rname in refs {
+                if self.fuzzer_or_fchain(rname).is_none() && !self.pending.iter().any(|pending| &pending.name == rname) {
+                    let msg = format!("ERRR:RunTimeManager:Pending:{}:Reference to unknown fuzzer {}", name, rname);
+                    issues.push(FuzzerKError::CfgFile { file: file.clone(), line: *line, msg });
+                }
+            }
+        }
+        issues
     }
 
 }
 
 impl HandleCfgGroup for RunTimeManager {
 
-    fn handle_cfggroup(&mut self, cg: &mut VecDeque<String>) {
+    fn handle_cfgloc(&mut self, file: &str, line: usize) {
+        self.cfgloc = (file.to_string(), line);
+    }
+
+    fn handle_cfggroup(&mut self, cg: &mut VecDeque<String>) -> Result<(), String> {
         let l = cg.front().unwrap().clone();
         let mut la: Vec<&str> = l.split(':').collect();
+        if la.len() != 3 {
+            return Err(format!("ERRR:RunTimeManager:HandleCfgGroup:Expected Type:SubType:Name, got {}", l.trim()));
+        }
         la[2] = la[2].trim();
+        if self.name_used(la[2]) {
+            return Err(format!("ERRR:RunTimeManager:HandleCfgGroup:Duplicate name {}, already used by a fuzzer or fuzzchain", la[2]));
+        }
         if la[0] == TYPEMARKER_FUZZER {
-            match la[1] {
-                "LoopFixedStringsFuzzer" => {
-                    let fuzzer = fixed::LoopFixedStringsFuzzer::from_vs(cg);
-                    let fuzzer = Rc::new(RefCell::new(fuzzer));
-                    log_d(&format!("DBUG:RunTimeManager:HandleCfgGroup:Created LoopFixedStringsFuzzer [{}] ie [{:?}]", la[2], fuzzer));
-                    self.fuzzers.insert(la[2].to_string(), fuzzer);
-                },
-                "RandomFixedStringsFuzzer" => {
-                    let fuzzer = fixed::RandomFixedStringsFuzzer::from_vs(cg);
-                    let fuzzer = Rc::new(RefCell::new(fuzzer));
-                    log_d(&format!("DBUG:RunTimeManager:HandleCfgGroup:Created RandomFixedStringsFuzzer [{}] ie [{:?}]", la[2], fuzzer));
-                    self.fuzzers.insert(la[2].to_string(), fuzzer);
-                },
-                "RandomRandomFuzzer" => {
-                    let fuzzer = random::RandomRandomFuzzer::from_vs(cg);
-                    let fuzzer = Rc::new(RefCell::new(fuzzer));
-                    log_d(&format!("DBUG:RunTimeManager:HandleCfgGroup:Created RandomRandomFuzzer [{}] ie [{:?}]", la[2], fuzzer));
-                    self.fuzzers.insert(la[2].to_string(), fuzzer);
-                },
-                "RandomFixedFuzzer" | "RandomFixedFuzzerPrintables" => {
-                    let fuzzer = random::RandomFixedFuzzer::from_vs(cg);
-                    let fuzzer = Rc::new(RefCell::new(fuzzer));
-                    log_d(&format!("DBUG:RunTimeManager:HandleCfgGroup:Created RandomFixedFuzzer [{}] ie [{:?}]", la[2], fuzzer));
-                    self.fuzzers.insert(la[2].to_string(), fuzzer);
-                },
-                "Buf8sRandomizeFuzzer" => {
-                    let fuzzer = random::Buf8sRandomizeFuzzer::from_vs(cg);
-                    let fuzzer = Rc::new(RefCell::new(fuzzer));
-                    log_d(&format!("DBUG:RunTimeManager:HandleCfgGroup:Created Buf8sRandomizeFuzzer [{}] ie [{:?}]", la[2], fuzzer));
-                    self.fuzzers.insert(la[2].to_string(), fuzzer);
-                },
-                _ => panic!("ERRR:RunTimeManager:HandleCfgGroup:UnknownFuzzer:{:?}",la),
+            if let Some(builder) = self.refsfuzzertypes.get(la[1]) {
+                let (fuzzer, refs, resolver) = builder(cg)?;
+                self.referred.extend(refs.iter().cloned());
+                if refs.iter().any(|name| self.fuzzer_or_fchain(name).is_none()) {
+                    // May refer to a fuzzer or fuzzchain defined later in the cfg file
+                    log_d(&format!("DBUG:RunTimeManager:HandleCfgGroup:Deferred {} [{}]", la[1], la[2]));
+                    self.pending.push(Pending { name: la[2].to_string(), refs, kind: PendingKind::Fuzzer(fuzzer, resolver), cfgloc: self.cfgloc.clone() });
+                    return Ok(());
+                }
+                resolver(self)?;
+                log_d(&format!("DBUG:RunTimeManager:HandleCfgGroup:Created {} [{}]", la[1], la[2]));
+                self.fuzzers.insert(la[2].to_string(), fuzzer);
+                return Ok(());
+            }
+            let builder = self.fuzzertypes.get(la[1]);
+            if builder.is_none() {
+                return Err(format!("ERRR:RunTimeManager:HandleCfgGroup:UnknownFuzzer:{:?}",la));
             }
+            let fuzzer = builder.unwrap()(self, cg)?;
+            log_d(&format!("DBUG:RunTimeManager:HandleCfgGroup:Created {} [{}]", la[1], la[2]));
+            self.fuzzers.insert(la[2].to_string(), fuzzer);
         } else if la[0] == TYPEMARKER_FUZZCHAIN {
-            let mut fc = FuzzChain::new();
             let _l = cg.pop_front(); // Skip the Type identifier
-            for l in cg {
-                let l = l.trim();
-                let fuzzer = self.fuzzers.get(l);
-                if fuzzer.is_none() {
-                    panic!("ERRR:RunTimeManager:HandleCfgGroup:Reference to unknown fuzzer {}", l);
+            let mut elements = Vec::new();
+            while let Some(l) = cg.pop_front() {
+                let element = ElementSpec::parse(&l).map_err(|e| format!("ERRR:RunTimeManager:HandleCfgGroup:FuzzChain:{}:{}", la[2], e))?;
+                self.referred.insert(element.name.clone());
+                elements.push(element);
+            }
+            if elements.iter().any(|element| self.fuzzer_or_fchain(&element.name).is_none()) {
+                // May refer to a fuzzchain or fuzzer defined later in the cfg file
+                log_d(&format!("DBUG:RunTimeManager:HandleCfgGroup:Deferred FuzzChain [{}]", la[2]));
+                let refs = elements.iter().map(|element| element.name.clone()).collect();
+                self.pending.push(Pending { name: la[2].to_string(), refs, kind: PendingKind::FuzzChain(elements), cfgloc: self.cfgloc.clone() });
+                return Ok(());
+            }
+            self.fchain_create(la[2], &elements)?;
+        } else {
+            return Err(format!("ERRR:RunTimeManager:HandleCfgGroup:Unknown cfggroup type {}", la[0]));
+        }
+        Ok(())
+    }
+
+    ///
+    /// Create the fuzzchains and fuzzers, which refer to fuzzchains or fuzzers defined after them.
+    ///
+    fn handle_cfgend(&mut self) -> Result<(), FuzzerKError> {
+        while self.pending.len() > 0 {
+            let ready = self.pending.iter().position(|pending| pending.refs.iter().all(|name| self.fuzzer_or_fchain(name).is_some()));
+            if ready.is_none() {
+                let issue = self.pending_issue();
+                self.pending.clear();
+                return Err(issue);
+            }
+            let Pending { name, kind, cfgloc: (file, line), .. } = self.pending.remove(ready.unwrap());
+            let gotr = match kind {
+                PendingKind::FuzzChain(elements) => self.fchain_create(&name, &elements),
+                PendingKind::Fuzzer(fuzzer, resolver) => {
+                    let gotr = resolver(self);
+                    if gotr.is_ok() {
+                        log_d(&format!("DBUG:RunTimeManager:HandleCfgEnd:Created [{}]", name));
+                        self.fuzzers.insert(name, fuzzer);
+                    }
+                    gotr
                 }
-                let fuzzer = fuzzer.unwrap();
-                fc.append(fuzzer.clone());
+            };
+            if gotr.is_err() {
+                self.pending.clear();
+                return Err(FuzzerKError::CfgFile { file, line, msg: gotr.unwrap_err() });
             }
-            log_d(&format!("DBUG:RunTimeManager:HandleCfgGroup:Created FuzzChain [{}]", la[2]));
-            self.fchains.insert(la[2].to_string(), fc);
         }
+        Ok(())
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::cfggroup;
+    use super::*;
+    use crate::fixed::RandomFixedStringsFuzzer;
+    use rand::RngCore;
+
+    /// A custom fuzzer, which repeats a given byte, step number of times
+    struct RepeatStepFuzzer {
+        byte: u8,
+    }
+
+    impl Fuzz for RepeatStepFuzzer {
+        fn append_fuzzed_immut(&self, step: usize, _rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+            for _i in 0..step {
+                buf.push(self.byte);
+            }
+        }
+
+        fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+            self.append_fuzzed_immut(step, rng, buf);
+        }
+    }
+
+    impl FromVecStrings for RepeatStepFuzzer {
+        fn get_name() -> String {
+            return "RepeatStepFuzzer".to_string();
+        }
+
+        fn from_vs(vs: &mut VecDeque<String>) -> Result<RepeatStepFuzzer, String> {
+            let _l = vs.pop_front();
+            let spacesprefix = Self::get_spacesprefix(vs);
+            let byte = Self::get_value(vs, "byte", spacesprefix)?;
+            Ok(RepeatStepFuzzer { byte: byte[0] })
+        }
+    }
+
+
+    #[test]
+    fn rtm_custom_fuzzertype() {
+        let mut rtm = RunTimeManager::new_seeded(0);
+        rtm.register_fuzzertype_fromvs::<RepeatStepFuzzer>("RepeatStepFuzzer");
+        rtm.add_fuzzer("HELLO", Rc::new(RefCell::new(RandomFixedStringsFuzzer::new(vec![Vec::from("Hello")]))));
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:RepeatStepFuzzer:RSF01", "  byte: Z"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC01", "  HELLO", "  RSF01"])).unwrap();
+        assert_eq!(rtm.fchain_get("FC01", Some(3)), Some(Vec::from("HelloZZZ")));
+    }
+
+    #[test]
+    fn rtm_cfggroup_errors() {
+        use crate::error::FuzzerKError;
+        let mut rtm = RunTimeManager::new_seeded(0);
+        let gotr = rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:NoSuchFuzzer:NSF01", "  byte: Z"]));
+        assert!(gotr.unwrap_err().contains("UnknownFuzzer"));
+        let gotr = rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:RandomRandomFuzzer:RRF01", "  minlen: 8", "  maxlen: 4"]));
+        assert!(gotr.unwrap_err().contains("minlen 8 > maxlen 4"));
+        // a unknown element could be a fuzzchain defined later, so its reported at the end
+        // reported wrt the file and line, at which the fuzzchain starts
+        rtm.handle_cfgloc("test.fc", 7);
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC01", "  RRF01"])).unwrap();
+        rtm.handle_cfgloc("test.fc", 10);
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:LoopFixedStringsFuzzer:LFS01", "  list:", "    x"])).unwrap();
+        match rtm.handle_cfgend().unwrap_err() {
+            FuzzerKError::CfgFile { file, line, msg } => {
+                assert!((file == "test.fc") && (line == 7) && msg.contains("unknown fuzzer RRF01"));
+            }
+            err => panic!("unexpected {}", err),
+        }
+        // fuzzers referred to by fuzzchains or fuzzers are used, even if the referring ones couldnt be created
+        let mut rtm = RunTimeManager::new_seeded(0);
+        for name in ["U1", "U2", "U3"] {
+            rtm.handle_cfggroup(&mut cfggroup(&[&format!("FuzzerType:LoopFixedStringsFuzzer:{}", name), "  list:", "    x"])).unwrap();
+        }
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:EncodeFuzzer:E01", "  inner: U1", "  encoding: hex"])).unwrap();
+        assert!(rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC01", "  U2", "  U2*{5,2}"])).is_err());
+        assert_eq!(rtm.fuzzers_unused(), vec!["E01", "U3"]);
     }
 
 }
@@ -0,0 +1,514 @@
+//!
+//! StatefulFuzzers: Sequence numbers, session ids and timestamps, which track the step
+//! (ie the FuzzChain::get call) they are generated for.
+//!
+//! HanishKVC, 2022
+//!
+
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+use rand::{Rng, RngCore};
+use loggerk::log_w;
+
+use crate::cfgfiles::FromVecStrings;
+use crate::number::{NumEncoding, NumberFuzzer};
+
+
+const ALNUM_CHARS: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const HEX_CHARS: &[u8; 16] = b"0123456789abcdef";
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// The max number of seconds, a out of order timestamp goes back by
+const TIME_MAXBACK: i64 = 3600;
+
+
+///
+/// Get the corrupt percent, ie the chance of deliberately generating a off-by-one or out of order value
+///
+fn fromvs_corrupt<T: FromVecStrings>(vs: &mut VecDeque<String>, spacesprefix: usize) -> Result<usize, String> {
+    let corrupt = T::get_uvalue_or(vs, "corrupt", spacesprefix, 0).map_err(|e| format!("ERRR:{}:GetCorrupt:{}", T::get_name(), e))?;
+    if corrupt > 100 {
+        return Err(format!("ERRR:{}:FromStringVec:corrupt {} > 100", T::get_name(), corrupt));
+    }
+    Ok(corrupt)
+}
+
+
+///
+/// Generate a monotonically increasing sequence number, which advances once per step
+/// (so that all its uses in a fuzz chain get the same value) or on every call.
+/// The value wraps around as per the range of its encoding.
+///
+/// With a chance of corrupt percent, a off-by-one or out of order (a earlier or skipped
+/// ahead) value is generated instead, without affecting the sequence.
+///
+#[derive(Debug)]
+pub struct SequenceFuzzer {
+    start: i128,
+    increment: i128,
+    encoding: NumEncoding,
+    /// Advance once per step, rather than on every call
+    bperstep: bool,
+    corrupt: usize,
+    cur: Option<i128>,
+    laststep: Option<usize>,
+}
+
+impl SequenceFuzzer {
+
+    pub fn new(start: i128, increment: i128, encoding: NumEncoding, bperstep: bool, corrupt: usize) -> SequenceFuzzer {
+        SequenceFuzzer {
+            start,
+            increment,
+            encoding,
+            bperstep,
+            corrupt,
+            cur: None,
+            laststep: None,
+        }
+    }
+
+    fn advance(&mut self) {
+        self.cur = Some(match self.cur {
+            None => self.start,
+            Some(cur) => cur + self.increment,
+        });
+    }
+
+    fn emit(&self, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let mut val = self.cur.unwrap_or(self.start);
+        if (rng.gen::<usize>() % 100) < self.corrupt {
+            let delta = 1 + rng.gen::<usize>() % 4;
+            val = match rng.gen::<usize>() % 4 {
+                0 => val + 1,
+                1 => val - 1,
+                2 => val - self.increment * delta as i128,
+                _ => val + self.increment * delta as i128,
+            };
+        }
+        let (emin, emax) = self.encoding.range();
+        if (val < emin) || (val > emax) {
+            val = emin + (val - emin).rem_euclid(emax - emin + 1);
+        }
+        self.encoding.encode(val, buf);
+    }
+
+}
+
+impl crate::Fuzz for SequenceFuzzer {
+
+    fn append_fuzzed_immut(&self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        log_w(&format!("WARN:SequenceFuzzer:AppendFuzzedImmut:Step {}:Sequence not advanced", step));
+        self.emit(rng, buf);
+    }
+
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        if !self.bperstep || (self.laststep != Some(step)) {
+            self.advance();
+            self.laststep = Some(step);
+        }
+        self.emit(rng, buf);
+    }
+
+    fn need_mutable(&self) -> bool {
+        true
+    }
+
+}
+
+impl FromVecStrings for SequenceFuzzer {
+
+    fn get_name() -> String {
+        return "SequenceFuzzer".to_string();
+    }
+
+    ///
+    /// In the cfgfile use
+    /// ### FuzzerType:SequenceFuzzer:InstanceName
+    /// * start: (optional, default 0)
+    /// * increment: (optional, default 1)
+    /// * encoding: as supported by NumberFuzzer (optional, default decimal)
+    /// * advance: step | call (optional, default step)
+    /// * corrupt: 0 to 100 (optional, default 0)
+    ///
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<SequenceFuzzer, String> {
+        let l = vs.pop_front();
+        if l.is_none() {
+            return Err("ERRR:SequenceFuzzer:FromStringVec:Got empty vector".to_string());
+        }
+        let _l = l.unwrap(); // This should identify this particular type of Fuzzer and a runtime instance name
+        let spacesprefix = Self::get_spacesprefix(vs);
+        let start = NumberFuzzer::get_num_or(vs, "start", spacesprefix, 0).map_err(|e| format!("ERRR:SequenceFuzzer:GetStart:{}", e))?;
+        let increment = NumberFuzzer::get_num_or(vs, "increment", spacesprefix, 1).map_err(|e| format!("ERRR:SequenceFuzzer:GetIncrement:{}", e))?;
+        let sencoding = Self::get_svalue_or(vs, "encoding", spacesprefix, "decimal").map_err(|e| format!("ERRR:SequenceFuzzer:GetEncoding:{}", e))?;
+        let encoding = NumEncoding::from_str(&sencoding).map_err(|e| format!("ERRR:SequenceFuzzer:{}", e))?;
+        let sadvance = Self::get_svalue_or(vs, "advance", spacesprefix, "step").map_err(|e| format!("ERRR:SequenceFuzzer:GetAdvance:{}", e))?;
+        let bperstep = match sadvance.as_str() {
+            "step" => true,
+            "call" => false,
+            _ => return Err(format!("ERRR:SequenceFuzzer:FromStringVec:Unknown advance {}", sadvance)),
+        };
+        let corrupt = fromvs_corrupt::<Self>(vs, spacesprefix)?;
+        Ok(SequenceFuzzer::new(start, increment, encoding, bperstep, corrupt))
+    }
+
+}
+
+
+///
+/// The format of the session id
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IdFormat {
+    Hex,
+    Alnum,
+    /// random (version 4) uuid
+    Uuid,
+}
+
+impl IdFormat {
+
+    pub fn from_str(sformat: &str) -> Result<IdFormat, String> {
+        match sformat {
+            "hex" => Ok(Self::Hex),
+            "alnum" => Ok(Self::Alnum),
+            "uuid" => Ok(Self::Uuid),
+            _ => Err(format!("ERRR:IdFormat:Unknown format:{}", sformat)),
+        }
+    }
+
+}
+
+
+///
+/// Generate a random session id, which stays the same within a step (ie a FuzzChain::get
+/// call), but changes between steps.
+///
+/// With a chance of corrupt percent, a off-by-one (last char changed) or out of order
+/// (the previous step's id) session id is generated instead.
+///
+#[derive(Debug)]
+pub struct SessionIdFuzzer {
+    format: IdFormat,
+    /// The length of hex and alnum ids
+    len: usize,
+    corrupt: usize,
+    cur: Vec<u8>,
+    prev: Vec<u8>,
+    laststep: Option<usize>,
+}
+
+impl SessionIdFuzzer {
+
+    pub fn new(format: IdFormat, len: usize, corrupt: usize) -> SessionIdFuzzer {
+        SessionIdFuzzer {
+            format,
+            len,
+            corrupt,
+            cur: Vec::new(),
+            prev: Vec::new(),
+            laststep: None,
+        }
+    }
+
+    fn generate(&self, rng: &mut dyn RngCore) -> Vec<u8> {
+        match self.format {
+            IdFormat::Hex => (0..self.len).map(|_| HEX_CHARS[rng.gen::<usize>() % HEX_CHARS.len()]).collect(),
+            IdFormat::Alnum => (0..self.len).map(|_| ALNUM_CHARS[rng.gen::<usize>() % ALNUM_CHARS.len()]).collect(),
+            IdFormat::Uuid => {
+                let mut id = Vec::new();
+                for i in 0..32 {
+                    if [8, 12, 16, 20].contains(&i) {
+                        id.push(b'-');
+                    }
+                    let c = match i {
+                        12 => b'4',
+                        16 => HEX_CHARS[8 + rng.gen::<usize>() % 4],
+                        _ => HEX_CHARS[rng.gen::<usize>() % HEX_CHARS.len()],
+                    };
+                    id.push(c);
+                }
+                id
+            }
+        }
+    }
+
+    fn emit(&self, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let mut id = self.cur.clone();
+        if (rng.gen::<usize>() % 100) < self.corrupt {
+            if !self.prev.is_empty() && rng.gen::<bool>() {
+                id = self.prev.clone();
+            } else if let Some(last) = id.last_mut() {
+                *last = if *last == b'0' { b'1' } else { *last - 1 };
+            }
+        }
+        buf.extend_from_slice(&id);
+    }
+
+}
+
+impl crate::Fuzz for SessionIdFuzzer {
+
+    fn append_fuzzed_immut(&self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        if self.laststep != Some(step) {
+            log_w(&format!("WARN:SessionIdFuzzer:AppendFuzzedImmut:Step {}:Session id not updated", step));
+        }
+        self.emit(rng, buf);
+    }
+
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        if self.laststep != Some(step) {
+            let id = self.generate(rng);
+            self.prev = std::mem::replace(&mut self.cur, id);
+            self.laststep = Some(step);
+        }
+        self.emit(rng, buf);
+    }
+
+    fn need_mutable(&self) -> bool {
+        true
+    }
+
+}
+
+impl FromVecStrings for SessionIdFuzzer {
+
+    fn get_name() -> String {
+        return "SessionIdFuzzer".to_string();
+    }
+
+    ///
+    /// In the cfgfile use
+    /// ### FuzzerType:SessionIdFuzzer:InstanceName
+    /// * format: hex | alnum | uuid (optional, default hex)
+    /// * len: length of hex and alnum ids (optional, default 32)
+    /// * corrupt: 0 to 100 (optional, default 0)
+    ///
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<SessionIdFuzzer, String> {
+        let l = vs.pop_front();
+        if l.is_none() {
+            return Err("ERRR:SessionIdFuzzer:FromStringVec:Got empty vector".to_string());
+        }
+        let _l = l.unwrap(); // This should identify this particular type of Fuzzer and a runtime instance name
+        let spacesprefix = Self::get_spacesprefix(vs);
+        let sformat = Self::get_svalue_or(vs, "format", spacesprefix, "hex").map_err(|e| format!("ERRR:SessionIdFuzzer:GetFormat:{}", e))?;
+        let format = IdFormat::from_str(&sformat).map_err(|e| format!("ERRR:SessionIdFuzzer:{}", e))?;
+        let len = Self::get_uvalue_or(vs, "len", spacesprefix, 32).map_err(|e| format!("ERRR:SessionIdFuzzer:GetLen:{}", e))?;
+        if len == 0 {
+            return Err("ERRR:SessionIdFuzzer:FromStringVec:len should be atleast 1".to_string());
+        }
+        let corrupt = fromvs_corrupt::<Self>(vs, spacesprefix)?;
+        Ok(SessionIdFuzzer::new(format, len, corrupt))
+    }
+
+}
+
+
+///
+/// The format of the timestamp
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeFormat {
+    /// seconds since unix epoch
+    Epoch,
+    /// milliseconds since unix epoch
+    EpochMs,
+    /// like Sun, 06 Nov 1994 08:49:37 GMT
+    Rfc1123,
+    /// like 1994-11-06T08:49:37Z
+    Iso8601,
+}
+
+impl TimeFormat {
+
+    pub fn from_str(sformat: &str) -> Result<TimeFormat, String> {
+        match sformat {
+            "epoch" => Ok(Self::Epoch),
+            "epochms" => Ok(Self::EpochMs),
+            "rfc1123" => Ok(Self::Rfc1123),
+            "iso8601" => Ok(Self::Iso8601),
+            _ => Err(format!("ERRR:TimeFormat:Unknown format:{}", sformat)),
+        }
+    }
+
+    ///
+    /// The size of the smallest unit of the format, in milliseconds
+    ///
+    fn unit(&self) -> i64 {
+        match self {
+            Self::EpochMs => 1,
+            _ => 1000,
+        }
+    }
+
+    ///
+    /// Convert days since unix epoch to year, month and day (as per the proleptic gregorian calendar)
+    ///
+    fn civil_from_days(days: i64) -> (i64, usize, i64) {
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe/1460 + doe/36524 - doe/146096) / 365;
+        let doy = doe - (365*yoe + yoe/4 - yoe/100);
+        let mp = (5*doy + 2) / 153;
+        let day = doy - (153*mp + 2)/5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era*400 + if month <= 2 { 1 } else { 0 };
+        (year, month as usize, day)
+    }
+
+    ///
+    /// Append the given time (milliseconds since unix epoch) as per this format
+    ///
+    pub fn format(&self, millis: i64, buf: &mut Vec<u8>) {
+        let secs = millis.div_euclid(1000);
+        let days = secs.div_euclid(86400);
+        let daysecs = secs.rem_euclid(86400);
+        let (year, month, day) = Self::civil_from_days(days);
+        let (hour, min, sec) = (daysecs / 3600, (daysecs / 60) % 60, daysecs % 60);
+        let stime = match self {
+            Self::Epoch => secs.to_string(),
+            Self::EpochMs => millis.to_string(),
+            Self::Rfc1123 => format!("{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT", WEEKDAYS[(days + 4).rem_euclid(7) as usize], day, MONTHS[month-1], year, hour, min, sec),
+            Self::Iso8601 => format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, hour, min, sec),
+        };
+        buf.extend_from_slice(stime.as_bytes());
+    }
+
+}
+
+
+///
+/// Generate the current time (adjusted by the offset seconds), in the specified format.
+///
+/// With a chance of corrupt percent, a off-by-one (+/- 1 unit of the format) or out of
+/// order (upto a hour in the past) timestamp is generated instead.
+///
+#[derive(Debug)]
+pub struct TimeFuzzer {
+    format: TimeFormat,
+    /// Seconds added to the current time
+    offset: isize,
+    corrupt: usize,
+}
+
+impl TimeFuzzer {
+
+    pub fn new(format: TimeFormat, offset: isize, corrupt: usize) -> TimeFuzzer {
+        TimeFuzzer {
+            format,
+            offset,
+            corrupt,
+        }
+    }
+
+}
+
+impl crate::Fuzz for TimeFuzzer {
+
+    fn append_fuzzed_immut(&self, _step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as i64).unwrap_or(0);
+        let mut millis = now + self.offset as i64 * 1000;
+        if (rng.gen::<usize>() % 100) < self.corrupt {
+            millis += match rng.gen::<usize>() % 3 {
+                0 => self.format.unit(),
+                1 => -self.format.unit(),
+                _ => -1000 * (1 + rng.gen::<i64>().rem_euclid(TIME_MAXBACK)),
+            };
+        }
+        self.format.format(millis, buf);
+    }
+
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        return self.append_fuzzed_immut(step, rng, buf);
+    }
+
+}
+
+impl FromVecStrings for TimeFuzzer {
+
+    fn get_name() -> String {
+        return "TimeFuzzer".to_string();
+    }
+
+    ///
+    /// In the cfgfile use
+    /// ### FuzzerType:TimeFuzzer:InstanceName
+    /// * format: epoch | epochms | rfc1123 | iso8601 (optional, default epoch)
+    /// * offset: seconds added to the current time, can be -ve (optional, default 0)
+    /// * corrupt: 0 to 100 (optional, default 0)
+    ///
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<TimeFuzzer, String> {
+        let l = vs.pop_front();
+        if l.is_none() {
+            return Err("ERRR:TimeFuzzer:FromStringVec:Got empty vector".to_string());
+        }
+        let _l = l.unwrap(); // This should identify this particular type of Fuzzer and a runtime instance name
+        let spacesprefix = Self::get_spacesprefix(vs);
+        let sformat = Self::get_svalue_or(vs, "format", spacesprefix, "epoch").map_err(|e| format!("ERRR:TimeFuzzer:GetFormat:{}", e))?;
+        let format = TimeFormat::from_str(&sformat).map_err(|e| format!("ERRR:TimeFuzzer:{}", e))?;
+        let mut offset = 0;
+        if Self::has_key(vs, "offset", spacesprefix) {
+            offset = Self::get_ivalue(vs, "offset", spacesprefix).map_err(|e| format!("ERRR:TimeFuzzer:GetOffset:{}", e))?;
+        }
+        let corrupt = fromvs_corrupt::<Self>(vs, spacesprefix)?;
+        Ok(TimeFuzzer::new(format, offset, corrupt))
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::cfggroup;
+    use crate::rtm::RunTimeManager;
+    use crate::cfgfiles::HandleCfgGroup;
+
+    #[test]
+    fn fuzzer_stateful() {
+        let time = |format: TimeFormat, millis: i64| {
+            let mut buf = Vec::new();
+            format.format(millis, &mut buf);
+            String::from_utf8(buf).unwrap()
+        };
+        assert_eq!(time(TimeFormat::Rfc1123, 784111777000), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(time(TimeFormat::Iso8601, 784111777000), "1994-11-06T08:49:37Z");
+        assert_eq!(time(TimeFormat::Iso8601, 951782400000), "2000-02-29T00:00:00Z");
+        assert_eq!(time(TimeFormat::Rfc1123, -1000), "Wed, 31 Dec 1969 23:59:59 GMT");
+        assert_eq!(time(TimeFormat::Epoch, 784111777999), "784111777");
+        assert_eq!(time(TimeFormat::EpochMs, 784111777999), "784111777999");
+        let mut rtm = RunTimeManager::new_seeded(0);
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:SequenceFuzzer:SEQ", "  start: 0xFFFE", "  encoding: u16be"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:SequenceFuzzer:CNT", "  start: 1", "  increment: 1", "  advance: call"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:SessionIdFuzzer:SID", "  format: uuid"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:TimeFuzzer:NOW", "  format: iso8601", "  offset: -86400"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC01", "  SEQ", "  SEQ", "  CNT", "  CNT", "  SID", "  SID", "  NOW"])).unwrap();
+        let mut sids = Vec::new();
+        let seqs = [[0xFF, 0xFE], [0xFF, 0xFF], [0x00, 0x00]];
+        for i in 0..3 {
+            let buf = rtm.fchain_get("FC01", None).unwrap();
+            let seq = seqs[i];
+            assert_eq!(buf[..4], [seq[0], seq[1], seq[0], seq[1]]);
+            assert_eq!(String::from_utf8(buf[4..6].to_vec()).unwrap(), format!("{}{}", 1 + 2*i, 2 + 2*i));
+            let sid = &buf[6..42];
+            assert_eq!(sid, &buf[42..78]);
+            assert_eq!(sid[14], b'4');
+            sids.push(sid.to_vec());
+            assert!(buf[78..].ends_with(b"Z") && buf.len() == 78 + 20);
+        }
+        assert!(sids[0] != sids[1] && sids[1] != sids[2]);
+        // corrupted sequences go off by one or out of order
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:SequenceFuzzer:BADSEQ", "  start: 100", "  corrupt: 100"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC02", "  BADSEQ"])).unwrap();
+        for i in 0..16 {
+            let buf = rtm.fchain_get("FC02", None).unwrap();
+            let val = i128::from_str_radix(&String::from_utf8(buf).unwrap(), 10).unwrap();
+            assert!(val != 100 + i && (val - 100 - i).abs() <= 4);
+        }
+        let gotr = rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:TimeFuzzer:T02", "  format: rfc822"]));
+        assert!(gotr.unwrap_err().contains("Unknown format:rfc822"));
+    }
+
+}
@@ -0,0 +1,230 @@
+//!
+//! TemplateFuzzer: Fill the {name} placeholders in a template, with the output of the
+//! named fuzzers (or fuzzchains), while retaining the literal text of the template.
+//!
+//! HanishKVC, 2022
+//!
+
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::cell::RefCell;
+use rand::RngCore;
+
+use crate::{Fuzz, append_element};
+use crate::cfgfiles::FromVecStrings;
+use crate::rtm::{RunTimeManager, ResolveRefs};
+
+
+///
+/// A part of the template
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplatePart {
+    Literal(Vec<u8>),
+    /// The name of the fuzzer (or fuzzchain) filling this placeholder
+    Placeholder(String),
+}
+
+///
+/// Split the template into its literal and placeholder parts
+/// * {name} is a placeholder
+/// * {{ and }} stand for a literal { and } respectively
+///
+pub fn template_parse(template: &[u8]) -> Result<Vec<TemplatePart>, String> {
+    let msgtag = "ERRR:TemplateFuzzer:TemplateParse";
+    let mut parts = Vec::new();
+    let mut literal = Vec::new();
+    let mut i = 0;
+    while i < template.len() {
+        let c = template[i];
+        if (c == b'{' || c == b'}') && (i+1 < template.len()) && (template[i+1] == c) {
+            literal.push(c);
+            i += 2;
+            continue;
+        }
+        if c == b'}' {
+            return Err(format!("{}:Unmatched }} at {}, use }}}} for a literal }}", msgtag, i));
+        }
+        if c != b'{' {
+            literal.push(c);
+            i += 1;
+            continue;
+        }
+        let iend = template[i+1..].iter().position(|&x| x == b'}');
+        if iend.is_none() {
+            return Err(format!("{}:Unterminated {{ at {}, use {{{{ for a literal {{", msgtag, i));
+        }
+        let iend = i + 1 + iend.unwrap();
+        let name = String::from_utf8_lossy(&template[i+1..iend]).trim().to_string();
+        if name.len() == 0 {
+            return Err(format!("{}:Empty placeholder at {}", msgtag, i));
+        }
+        if name.contains('{') {
+            return Err(format!("{}:Nested {{ in placeholder at {}", msgtag, i));
+        }
+        if literal.len() > 0 {
+            parts.push(TemplatePart::Literal(literal));
+            literal = Vec::new();
+        }
+        parts.push(TemplatePart::Placeholder(name));
+        i = iend + 1;
+    }
+    if literal.len() > 0 {
+        parts.push(TemplatePart::Literal(literal));
+    }
+    Ok(parts)
+}
+
+
+///
+/// Generate the template, with each of its placeholders filled with the output of
+/// the corresponding fuzzer. A fuzzer used in multiple placeholders is invoked for
+/// each of them, with the same step (like a fuzzer chained more than once).
+///
+pub struct TemplateFuzzer {
+    parts: Vec<TemplatePart>,
+    /// The fuzzers filling the placeholders, in the order of the placeholders
+    fuzzers: Vec<Rc<RefCell<dyn Fuzz>>>,
+}
+
+impl TemplateFuzzer {
+
+    ///
+    /// Create a template fuzzer, with the fuzzers to use for the placeholders in the template.
+    ///
+    pub fn new(template: &[u8], fuzzers: Vec<(String, Rc<RefCell<dyn Fuzz>>)>) -> Result<TemplateFuzzer, String> {
+        let mut tf = TemplateFuzzer {
+            parts: template_parse(template)?,
+            fuzzers: Vec::new(),
+        };
+        tf.fuzzers = tf.placeholders().iter().map(|name| {
+            fuzzers.iter().find(|(fname, _)| fname == name).map(|(_, fuzzer)| fuzzer.clone())
+                .ok_or(format!("ERRR:TemplateFuzzer:New:No fuzzer for placeholder {}", name))
+        }).collect::<Result<Vec<_>, String>>()?;
+        Ok(tf)
+    }
+
+    ///
+    /// The names of the placeholders, in the order they occur in the template
+    ///
+    pub fn placeholders(&self) -> Vec<String> {
+        self.parts.iter().filter_map(|part| match part {
+            TemplatePart::Placeholder(name) => Some(name.clone()),
+            TemplatePart::Literal(_) => None,
+        }).collect()
+    }
+
+}
+
+impl ResolveRefs for TemplateFuzzer {
+
+    fn refs(&self) -> Vec<String> {
+        let mut names = self.placeholders();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    fn resolve(&mut self, rtm: &RunTimeManager) -> Result<(), String> {
+        let mut fuzzers = Vec::new();
+        for name in self.placeholders() {
+            let fuzzer = rtm.fuzzer_or_fchain(&name);
+            if fuzzer.is_none() {
+                return Err(format!("ERRR:TemplateFuzzer:Resolve:Reference to unknown fuzzer {}", name));
+            }
+            fuzzers.push(fuzzer.unwrap());
+        }
+        self.fuzzers = fuzzers;
+        Ok(())
+    }
+
+}
+
+impl Fuzz for TemplateFuzzer {
+
+    fn append_fuzzed_immut(&self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let mut fuzzers = self.fuzzers.iter();
+        for part in &self.parts {
+            match part {
+                TemplatePart::Literal(literal) => buf.extend_from_slice(literal),
+                TemplatePart::Placeholder(_) => append_element(fuzzers.next().unwrap(), step, rng, buf),
+            }
+        }
+    }
+
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        return self.append_fuzzed_immut(step, rng, buf);
+    }
+
+}
+
+impl FromVecStrings for TemplateFuzzer {
+
+    fn get_name() -> String {
+        return "TemplateFuzzer".to_string();
+    }
+
+    ///
+    /// In the cfgfile use
+    /// ### FuzzerType:TemplateFuzzer:InstanceName
+    /// * template: the template text, with {fuzzername} placeholders and {{ / }} for literal braces
+    ///
+    /// The fuzzers referenced are resolved by the runtime manager, once created.
+    ///
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<TemplateFuzzer, String> {
+        let l = vs.pop_front();
+        if l.is_none() {
+            return Err("ERRR:TemplateFuzzer:FromStringVec:Got empty vector".to_string());
+        }
+        let _l = l.unwrap(); // This should identify this particular type of Fuzzer and a runtime instance name
+        let spacesprefix = Self::get_spacesprefix(vs);
+        let template = Self::get_value(vs, "template", spacesprefix).map_err(|e| format!("ERRR:TemplateFuzzer:GetTemplate:{}", e))?;
+        Ok(TemplateFuzzer {
+            parts: template_parse(&template)?,
+            fuzzers: Vec::new(),
+        })
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::cfggroup;
+    use crate::rtm::RunTimeManager;
+    use crate::cfgfiles::HandleCfgGroup;
+    use crate::fixed::{self, RandomFixedStringsFuzzer};
+    use crate::Fuzz;
+    use std::{rc::Rc, cell::RefCell};
+    use rand::{SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn fuzzer_template() {
+        let parts = template_parse(b"{{x}} {A}-{ B }}}").unwrap();
+        assert_eq!(parts, vec![TemplatePart::Literal(Vec::from("{x} ")), TemplatePart::Placeholder("A".to_string()),
+            TemplatePart::Literal(Vec::from("-")), TemplatePart::Placeholder("B".to_string()), TemplatePart::Literal(Vec::from("}"))]);
+        assert!(template_parse(b"a}b").unwrap_err().contains("Unmatched }"));
+        assert!(template_parse(b"a{b").unwrap_err().contains("Unterminated {"));
+        assert!(template_parse(b"a{}b").unwrap_err().contains("Empty placeholder"));
+        let mut rng = StdRng::seed_from_u64(0);
+        let fa: Rc<RefCell<dyn Fuzz>> = Rc::new(RefCell::new(fixed::LoopFixedStringsFuzzer::new(vec![Vec::from("a1"), Vec::from("a2")])));
+        let tf = TemplateFuzzer::new(b"[{A}|{A}]", vec![("A".to_string(), fa.clone())]).unwrap();
+        let mut buf = Vec::new();
+        tf.append_fuzzed_immut(1, &mut rng, &mut buf);
+        assert_eq!(buf, Vec::from("[a2|a2]"));
+        assert!(TemplateFuzzer::new(b"{B}", vec![("A".to_string(), fa)]).is_err());
+        // from cfg
+        let mut rtm = RunTimeManager::new_seeded(0);
+        rtm.add_fuzzer("PATH", Rc::new(RefCell::new(RandomFixedStringsFuzzer::new(vec![Vec::from("index.html")]))));
+        rtm.add_fuzzer("MINOR", Rc::new(RefCell::new(RandomFixedStringsFuzzer::new(vec![Vec::from("1")]))));
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC_HOST", "  PATH", "  MINOR"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:TemplateFuzzer:REQ", "  template: \"GET /{PATH} HTTP/1.{MINOR}\\r\\nHost: {FC_HOST}\\r\\nX: {{}}\\r\\n\""])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC01", "  REQ"])).unwrap();
+        let buf = rtm.fchain_get("FC01", None).unwrap();
+        assert_eq!(buf, Vec::from("GET /index.html HTTP/1.1\r\nHost: index.html1\r\nX: {}\r\n"));
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:TemplateFuzzer:REQ2", "  template: {UNKNOWN}"])).unwrap();
+        assert!(rtm.handle_cfgend().unwrap_err().to_string().contains("unknown fuzzer UNKNOWN"));
+    }
+
+}
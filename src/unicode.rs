@@ -0,0 +1,423 @@
+//!
+//! UnicodeFuzzer: Generate valid and deliberately malformed text, from selected classes
+//! of unicode chars, encoded as UTF-8, UTF-16LE/BE or Latin-1.
+//!
+//! HanishKVC, 2022
+//!
+
+use std::collections::VecDeque;
+use rand::{Rng, RngCore};
+
+use crate::cfgfiles::FromVecStrings;
+
+
+///
+/// The classes of unicode chars (code points) to pick from
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CharClass {
+    /// printable ascii
+    Ascii,
+    /// C0 and C1 control chars, including DEL
+    Control,
+    /// the NUL char
+    Nul,
+    /// printable Latin-1 supplement
+    Latin1,
+    /// rest of the basic multilingual plane, except surrogates
+    Bmp,
+    /// supplementary planes, ie beyond the BMP
+    Astral,
+    /// combining diacritical marks
+    Combining,
+    /// bidirectional text controls like RTL override
+    Bidi,
+    /// the byte order mark (or zero width no break space)
+    Bom,
+    /// zero width chars
+    ZeroWidth,
+    /// ascii and unicode whitespaces and line / paragraph separators
+    Whitespace,
+    /// emoticons
+    Emoji,
+}
+
+impl CharClass {
+
+    ///
+    /// Map a class name like ascii, control, nul, latin1, bmp, astral, combining, bidi, bom,
+    /// zerowidth, whitespace, emoji
+    ///
+    pub fn from_str(sclass: &str) -> Result<CharClass, String> {
+        match sclass {
+            "ascii" => Ok(Self::Ascii),
+            "control" => Ok(Self::Control),
+            "nul" => Ok(Self::Nul),
+            "latin1" => Ok(Self::Latin1),
+            "bmp" => Ok(Self::Bmp),
+            "astral" => Ok(Self::Astral),
+            "combining" => Ok(Self::Combining),
+            "bidi" => Ok(Self::Bidi),
+            "bom" => Ok(Self::Bom),
+            "zerowidth" => Ok(Self::ZeroWidth),
+            "whitespace" => Ok(Self::Whitespace),
+            "emoji" => Ok(Self::Emoji),
+            _ => Err(format!("ERRR:CharClass:Unknown class:{}", sclass)),
+        }
+    }
+
+    pub fn all() -> Vec<CharClass> {
+        vec![Self::Ascii, Self::Control, Self::Nul, Self::Latin1, Self::Bmp, Self::Astral, Self::Combining,
+            Self::Bidi, Self::Bom, Self::ZeroWidth, Self::Whitespace, Self::Emoji]
+    }
+
+    ///
+    /// The code point ranges (inclusive) belonging to this class
+    ///
+    pub fn ranges(&self) -> &'static [(u32, u32)] {
+        match self {
+            Self::Ascii => &[(0x20, 0x7E)],
+            Self::Control => &[(0x00, 0x1F), (0x7F, 0x9F)],
+            Self::Nul => &[(0x00, 0x00)],
+            Self::Latin1 => &[(0xA0, 0xFF)],
+            Self::Bmp => &[(0x100, 0xD7FF), (0xE000, 0xFFFD)],
+            Self::Astral => &[(0x10000, 0x10FFFD)],
+            Self::Combining => &[(0x300, 0x36F), (0x1AB0, 0x1AFF), (0x20D0, 0x20FF)],
+            Self::Bidi => &[(0x200E, 0x200F), (0x202A, 0x202E), (0x2066, 0x2069), (0x061C, 0x061C)],
+            Self::Bom => &[(0xFEFF, 0xFEFF)],
+            Self::ZeroWidth => &[(0x200B, 0x200D), (0x2060, 0x2060)],
+            Self::Whitespace => &[(0x09, 0x0D), (0x20, 0x20), (0x85, 0x85), (0xA0, 0xA0), (0x2000, 0x200A), (0x2028, 0x2029), (0x3000, 0x3000)],
+            Self::Emoji => &[(0x1F300, 0x1F64F), (0x1F900, 0x1F9FF)],
+        }
+    }
+
+    ///
+    /// Pick a random code point from this class
+    ///
+    pub fn pick(&self, rng: &mut dyn RngCore) -> u32 {
+        let ranges = self.ranges();
+        let (start, end) = ranges[rng.gen::<usize>() % ranges.len()];
+        start + rng.gen::<u32>() % (end - start + 1)
+    }
+
+}
+
+
+///
+/// How the generated text is encoded into bytes
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextEncoding {
+    Utf8,
+    /// big endian
+    Utf16(bool),
+    /// chars beyond 0xFF are replaced with ?
+    Latin1,
+}
+
+impl TextEncoding {
+
+    ///
+    /// Map a encoding name like utf8, utf16le, utf16be, latin1
+    ///
+    pub fn from_str(senc: &str) -> Result<TextEncoding, String> {
+        match senc {
+            "utf8" => Ok(Self::Utf8),
+            "utf16le" => Ok(Self::Utf16(false)),
+            "utf16be" => Ok(Self::Utf16(true)),
+            "latin1" => Ok(Self::Latin1),
+            _ => Err(format!("ERRR:TextEncoding:Unknown encoding:{}", senc)),
+        }
+    }
+
+    fn push_u16(bbig: bool, unit: u16, buf: &mut Vec<u8>) {
+        if bbig {
+            buf.extend_from_slice(&unit.to_be_bytes());
+        } else {
+            buf.extend_from_slice(&unit.to_le_bytes());
+        }
+    }
+
+    ///
+    /// Append the given code point (which should be a valid unicode scalar value), as encoded.
+    ///
+    pub fn encode(&self, cp: u32, buf: &mut Vec<u8>) {
+        let ch = char::from_u32(cp).unwrap_or('\u{FFFD}');
+        match self {
+            Self::Utf8 => {
+                let mut tbuf = [0u8; 4];
+                buf.extend_from_slice(ch.encode_utf8(&mut tbuf).as_bytes());
+            }
+            Self::Utf16(bbig) => {
+                let mut tbuf = [0u16; 2];
+                for unit in ch.encode_utf16(&mut tbuf) {
+                    Self::push_u16(*bbig, *unit, buf);
+                }
+            }
+            Self::Latin1 => {
+                if cp <= 0xFF {
+                    buf.push(cp as u8);
+                } else {
+                    buf.push(b'?');
+                }
+            }
+        }
+    }
+
+    ///
+    /// Append a malformed sequence, as applicable to this encoding
+    /// * utf8: overlong encoding, lone surrogate, truncated sequence, stray continuation byte,
+    ///   invalid byte, code point beyond 0x10FFFF, Latin-1 byte
+    /// * utf16: lone high or low surrogate, swapped surrogate pair, odd trailing byte, UTF-8 bytes
+    /// * latin1: UTF-8 bytes of a Latin-1 char, UTF-16 code unit, undefined C1 byte
+    ///
+    pub fn encode_malformed(&self, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        match self {
+            Self::Utf8 => {
+                match rng.gen::<usize>() % 7 {
+                    0 => {
+                        let cp = rng.gen::<u32>() % 0x80;
+                        match rng.gen::<usize>() % 3 {
+                            0 => buf.extend_from_slice(&[0xC0 | (cp >> 6) as u8, 0x80 | (cp & 0x3F) as u8]),
+                            1 => buf.extend_from_slice(&[0xE0, 0x80 | (cp >> 6) as u8, 0x80 | (cp & 0x3F) as u8]),
+                            _ => buf.extend_from_slice(&[0xF0, 0x80, 0x80 | (cp >> 6) as u8, 0x80 | (cp & 0x3F) as u8]),
+                        }
+                    }
+                    1 => {
+                        let cp = 0xD800 + rng.gen::<u32>() % 0x800;
+                        buf.extend_from_slice(&[0xE0 | (cp >> 12) as u8, 0x80 | ((cp >> 6) & 0x3F) as u8, 0x80 | (cp & 0x3F) as u8]);
+                    }
+                    2 => {
+                        let mut tbuf = Vec::new();
+                        let class = if rng.gen::<bool>() { CharClass::Bmp } else { CharClass::Astral };
+                        self.encode(class.pick(rng), &mut tbuf);
+                        let keep = 1 + rng.gen::<usize>() % (tbuf.len() - 1);
+                        buf.extend_from_slice(&tbuf[..keep]);
+                    }
+                    3 => buf.push(0x80 + rng.gen::<u8>() % 0x40),
+                    4 => {
+                        let invalid = [0xC0, 0xC1, 0xF5, 0xF6, 0xF7, 0xF8, 0xF9, 0xFA, 0xFB, 0xFC, 0xFD, 0xFE, 0xFF];
+                        buf.push(invalid[rng.gen::<usize>() % invalid.len()]);
+                    }
+                    5 => {
+                        let cp = 0x110000 + rng.gen::<u32>() % 0xF0000;
+                        buf.extend_from_slice(&[0xF0 | (cp >> 18) as u8, 0x80 | ((cp >> 12) & 0x3F) as u8, 0x80 | ((cp >> 6) & 0x3F) as u8, 0x80 | (cp & 0x3F) as u8]);
+                    }
+                    _ => buf.push(CharClass::Latin1.pick(rng) as u8),
+                }
+            }
+            Self::Utf16(bbig) => {
+                let high = 0xD800 + rng.gen::<u16>() % 0x400;
+                let low = 0xDC00 + rng.gen::<u16>() % 0x400;
+                match rng.gen::<usize>() % 5 {
+                    0 => Self::push_u16(*bbig, high, buf),
+                    1 => Self::push_u16(*bbig, low, buf),
+                    2 => {
+                        Self::push_u16(*bbig, low, buf);
+                        Self::push_u16(*bbig, high, buf);
+                    }
+                    3 => buf.push(rng.gen()),
+                    _ => Self::Utf8.encode(CharClass::Bmp.pick(rng), buf),
+                }
+            }
+            Self::Latin1 => {
+                match rng.gen::<usize>() % 3 {
+                    0 => Self::Utf8.encode(CharClass::Latin1.pick(rng), buf),
+                    1 => Self::Utf16(rng.gen()).encode(CharClass::Ascii.pick(rng), buf),
+                    _ => buf.push(0x80 + rng.gen::<u8>() % 0x20),
+                }
+            }
+        }
+    }
+
+}
+
+
+///
+/// Generate text of length within the specified limits of min and max len, measured in
+/// code points or bytes, using chars from the specified classes. A malformed sequence
+/// is generated instead of a char, with a chance of malformed percent.
+///
+/// When length is in bytes, chars are added as long as they fit, so the text may be
+/// shorter than the chosen length, if the classes dont contain single byte chars.
+///
+#[derive(Debug)]
+pub struct UnicodeFuzzer {
+    encoding: TextEncoding,
+    classes: Vec<CharClass>,
+    minlen: usize,
+    maxlen: usize,
+    /// Whether the length is in bytes, rather than code points
+    bbytes: bool,
+    malformed: usize,
+}
+
+impl UnicodeFuzzer {
+
+    pub fn new(encoding: TextEncoding, classes: Vec<CharClass>, minlen: usize, maxlen: usize, bbytes: bool, malformed: usize) -> UnicodeFuzzer {
+        UnicodeFuzzer {
+            encoding,
+            classes,
+            minlen,
+            maxlen,
+            bbytes,
+            malformed,
+        }
+    }
+
+    fn append_char(&self, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        if (rng.gen::<usize>() % 100) < self.malformed {
+            return self.encoding.encode_malformed(rng, buf);
+        }
+        let class = self.classes[rng.gen::<usize>() % self.classes.len()];
+        self.encoding.encode(class.pick(rng), buf);
+    }
+
+}
+
+impl crate::Fuzz for UnicodeFuzzer {
+
+    fn append_fuzzed_immut(&self, _step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let curlen = self.minlen + rng.gen::<usize>() % (self.maxlen-self.minlen+1);
+        if !self.bbytes {
+            for _i in 0..curlen {
+                self.append_char(rng, buf);
+            }
+            return;
+        }
+        let mut data = Vec::new();
+        let mut misses = 0;
+        while (data.len() < curlen) && (misses < 8) {
+            let mut tbuf = Vec::new();
+            self.append_char(rng, &mut tbuf);
+            if data.len() + tbuf.len() > curlen {
+                misses += 1;
+                continue;
+            }
+            data.append(&mut tbuf);
+        }
+        buf.append(&mut data);
+    }
+
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        return self.append_fuzzed_immut(step, rng, buf);
+    }
+
+}
+
+impl FromVecStrings for UnicodeFuzzer {
+
+    fn get_name() -> String {
+        return "UnicodeFuzzer".to_string();
+    }
+
+    ///
+    /// In the cfgfile use
+    /// ### FuzzerType:UnicodeFuzzer:InstanceName
+    /// * encoding: utf8 | utf16le | utf16be | latin1 (optional, default utf8)
+    /// * classes: list of char classes (optional, default all of them)
+    /// * minlen
+    /// * maxlen
+    /// * lenunit: codepoints | bytes (optional, default codepoints)
+    /// * malformed: 0 to 100 (optional, default 0), the percent chance of a malformed sequence
+    ///
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<UnicodeFuzzer, String> {
+        let l = vs.pop_front();
+        if l.is_none() {
+            return Err("ERRR:UnicodeFuzzer:FromStringVec:Got empty vector".to_string());
+        }
+        let _l = l.unwrap(); // This should identify this particular type of Fuzzer and a runtime instance name
+        let spacesprefix = Self::get_spacesprefix(vs);
+        let sencoding = Self::get_svalue_or(vs, "encoding", spacesprefix, "utf8").map_err(|e| format!("ERRR:UnicodeFuzzer:GetEncoding:{}", e))?;
+        let encoding = TextEncoding::from_str(&sencoding).map_err(|e| format!("ERRR:UnicodeFuzzer:{}", e))?;
+        let mut classes = CharClass::all();
+        if Self::has_key(vs, "classes", spacesprefix) {
+            let sclasses = Self::get_values(vs, "classes", spacesprefix).map_err(|e| format!("ERRR:UnicodeFuzzer:GetClasses:{}", e))?;
+            classes = Vec::new();
+            for sclass in sclasses {
+                let sclass = String::from_utf8_lossy(&sclass).trim().to_string();
+                classes.push(CharClass::from_str(&sclass).map_err(|e| format!("ERRR:UnicodeFuzzer:{}", e))?);
+            }
+            if classes.len() == 0 {
+                return Err("ERRR:UnicodeFuzzer:FromStringVec:Empty classes list".to_string());
+            }
+        }
+        let minlen = Self::get_uvalue(vs, "minlen", spacesprefix).map_err(|e| format!("ERRR:UnicodeFuzzer:GetMinLen:{}", e))?;
+        let maxlen = Self::get_uvalue(vs, "maxlen", spacesprefix).map_err(|e| format!("ERRR:UnicodeFuzzer:GetMaxLen:{}", e))?;
+        if minlen > maxlen {
+            return Err(format!("ERRR:UnicodeFuzzer:FromStringVec:minlen {} > maxlen {}", minlen, maxlen));
+        }
+        let slenunit = Self::get_svalue_or(vs, "lenunit", spacesprefix, "codepoints").map_err(|e| format!("ERRR:UnicodeFuzzer:GetLenUnit:{}", e))?;
+        let bbytes = match slenunit.as_str() {
+            "codepoints" => false,
+            "bytes" => true,
+            _ => return Err(format!("ERRR:UnicodeFuzzer:FromStringVec:Unknown lenunit {}", slenunit)),
+        };
+        let malformed = Self::get_uvalue_or(vs, "malformed", spacesprefix, 0).map_err(|e| format!("ERRR:UnicodeFuzzer:GetMalformed:{}", e))?;
+        if malformed > 100 {
+            return Err(format!("ERRR:UnicodeFuzzer:FromStringVec:malformed {} > 100", malformed));
+        }
+        Ok(UnicodeFuzzer::new(encoding, classes, minlen, maxlen, bbytes, malformed))
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::cfggroup;
+    use crate::rtm::RunTimeManager;
+    use crate::cfgfiles::HandleCfgGroup;
+    use crate::Fuzz;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn fuzzer_unicode() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut buf = Vec::new();
+        TextEncoding::Utf16(true).encode(0x1F600, &mut buf);
+        TextEncoding::Utf16(false).encode(0xE9, &mut buf);
+        TextEncoding::Latin1.encode(0xE9, &mut buf);
+        TextEncoding::Latin1.encode(0x20AC, &mut buf);
+        assert_eq!(buf, vec![0xD8, 0x3D, 0xDE, 0x00, 0xE9, 0x00, 0xE9, b'?']);
+        // valid text, length in code points
+        let uf = UnicodeFuzzer::new(TextEncoding::Utf8, vec![CharClass::Astral, CharClass::Bidi, CharClass::Combining], 4, 8, false, 0);
+        for _i in 0..32 {
+            let mut buf = Vec::new();
+            uf.append_fuzzed_immut(0, &mut rng, &mut buf);
+            let sbuf = String::from_utf8(buf).unwrap();
+            let cnt = sbuf.chars().count();
+            assert!(cnt >= 4 && cnt <= 8);
+            assert!(sbuf.chars().all(|c| c as u32 >= 0x300));
+        }
+        let uf = UnicodeFuzzer::new(TextEncoding::Utf16(false), CharClass::all(), 10, 10, false, 0);
+        let mut buf = Vec::new();
+        uf.append_fuzzed_immut(0, &mut rng, &mut buf);
+        let units: Vec<u16> = buf.chunks(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+        assert_eq!(String::from_utf16(&units).unwrap().chars().count(), 10);
+        // length in bytes
+        let uf = UnicodeFuzzer::new(TextEncoding::Utf8, vec![CharClass::Ascii, CharClass::Emoji], 5, 5, true, 0);
+        let mut buf = Vec::new();
+        uf.append_fuzzed_immut(0, &mut rng, &mut buf);
+        assert_eq!(buf.len(), 5);
+        assert!(String::from_utf8(buf).is_ok());
+        // malformed
+        let uf = UnicodeFuzzer::new(TextEncoding::Utf8, vec![CharClass::Ascii], 1, 1, false, 100);
+        for _i in 0..64 {
+            let mut buf = Vec::new();
+            uf.append_fuzzed_immut(0, &mut rng, &mut buf);
+            assert!(String::from_utf8(buf).is_err());
+        }
+        // from cfg
+        let mut rtm = RunTimeManager::new_seeded(0);
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:UnicodeFuzzer:U01", "  encoding: utf16be", "  classes:", "    bom", "    nul", "  minlen: 3", "  maxlen: 3"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC01", "  U01"])).unwrap();
+        let buf = rtm.fchain_get("FC01", None).unwrap();
+        assert_eq!(buf.len(), 6);
+        assert!(buf.chunks(2).all(|c| c == [0xFE, 0xFF] || c == [0, 0]));
+        let gotr = rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:UnicodeFuzzer:U02", "  classes:", "    klingon", "  minlen: 3", "  maxlen: 3"]));
+        assert!(gotr.unwrap_err().contains("Unknown class:klingon"));
+    }
+
+}
@@ -6,20 +6,22 @@
 
 use std::collections::HashMap;
 use std::fs;
-use std::process;
+use std::path::PathBuf;
 use std::thread;
 use std::time::Duration;
-use std::panic;
 
 use rand::Rng;
 
-use loggerk::{log_w, log_e, log_d, ldebug};
+use loggerk::{log_w, log_e, log_d, log_o, ldebug};
 use datautilsk::variant::Variant;
 use tokensk::{self, TStrX};
 
 use crate::iob::IOBridge;
+use crate::oracle::Oracle;
 use crate::rtm::RunTimeManager;
-use crate::cfgfiles;
+use crate::cfgfiles::{self, CfgWatch, CfgCheck, HandleCfgGroup};
+use crate::check::{self, Diagnostic};
+use crate::error::FuzzerKError;
 
 mod xopdata;
 mod datam;
@@ -57,8 +59,23 @@ pub(crate) struct Context {
     /// Used for FuzzChain logic
     stepu: usize,
     fcrtm: RunTimeManager,
+    /// Classifies the io exchanges and saves the fuzzed data on failures
+    oracle: Oracle,
     iptr: usize,
     iptr_commonupdate: bool,
+    /// Set by the end op, to stop the running program
+    bend: bool,
+    // Concurrent clients related
+    /// The cfgfc files and program loaded, used to setup a fresh vm wrt each concurrent client
+    srccfgfcs: Vec<String>,
+    srcprg: Vec<String>,
+    /// The cfgfc files loaded, including the files included by them
+    srccfgdeps: Vec<PathBuf>,
+    /// If set, the fuzzers and fuzzchains are reloaded, when the cfgfc files are edited
+    cfgwatch: Option<CfgWatch>,
+    /// The threads handling the concurrent clients (along with their seed), and their count till now
+    clients: Vec<(u64, thread::JoinHandle<Result<(), FuzzerKError>>)>,
+    clientscnt: u64,
     // Funcs related
     /// This maintains the address of the call op and not next instruction bcas
     /// the vm auto increments the instruction pointer, and the same is used wrt ret also.
@@ -87,8 +104,16 @@ impl Context {
             lbls: HashMap::new(),
             stepu: 0,
             fcrtm: RunTimeManager::new(),
+            oracle: Oracle::new(),
             iptr: 0,
             iptr_commonupdate: true,
+            bend: false,
+            srccfgfcs: Vec::new(),
+            srcprg: Vec::new(),
+            srccfgdeps: Vec::new(),
+            cfgwatch: None,
+            clients: Vec::new(),
+            clientscnt: 0,
             callretstack: Vec::new(),
             funcs: HashMap::new(),
             fargsmapstack: Vec::new(),
@@ -135,8 +160,8 @@ impl Context {
     /// * if the name corresponds to a local variable
     /// * if the name corresponds to a global variable
     ///
-    pub fn var_get(&self, datakind: &DataKind, vname: &str) -> Option<&Variant> {
-        let (vnamespace, vname) = self.var_farg2real_ifreqd(datakind, vname);
+    pub fn var_get(&self, datakind: &DataKind, vname: &str) -> Result<&Variant, String> {
+        let (vnamespace, vname) = self.var_farg2real_ifreqd(datakind, vname)?;
 
         let ovhm = match vnamespace {
             VarSpace::Either => self.localsstack.last(),
@@ -145,20 +170,18 @@ impl Context {
         };
         if ovhm.is_some() {
             let vhm = ovhm.unwrap();
-            let vval = vhm.get(&vname);
-            if vval.is_some() {
-                return vval;
+            if let Some(vval) = vhm.get(&vname) {
+                return Ok(vval);
             }
         }
         // Either checked for in local space and not found, so check in global space
         if vnamespace == VarSpace::Either {
-            let vval = self.globals.get(&vname);
-            if vval.is_some() {
-                return vval;
+            if let Some(vval) = self.globals.get(&vname) {
+                return Ok(vval);
             }
         }
 
-        None
+        Err(format!("Unknown:{}", vname))
     }
 
     ///
@@ -196,18 +219,23 @@ impl Context {
     /// * and the location/variable space where it can be found.
     ///   * for local variable it includes the stack index wrt the multiple local var spaces in the stack.
     ///
-    pub fn var_farg2real_ifreqd(&self, datakind: &DataKind, vname: &str) -> (VarSpace, String) {
+    /// A func arg can be mapped only while running a function, else a error is returned.
+    ///
+    pub fn var_farg2real_ifreqd(&self, datakind: &DataKind, vname: &str) -> Result<(VarSpace, String), String> {
         if let DataKind::FuncArg = datakind {
-            let fargs = self.fargsmapstack.last().expect("ERRR:FuzzerK:VM:Ctxt:FArg2Real:Can be called only from run phase");
-            let rname = fargs.get(vname);
+            let fargs = self.fargsmapstack.last();
+            if fargs.is_none() {
+                return Err(format!("Ctxt:FArg2Real:{}:Not within a function call", vname));
+            }
+            let rname = fargs.unwrap().get(vname);
             if rname.is_none() {
-                panic!("DBUG:FuzzerK:VM:Ctxt:Farg2Real:FArg:{:?}:{}:not in fargsmapstack", datakind, vname);
+                return Err(format!("Ctxt:FArg2Real:FArg:{:?}:{}:not in fargsmapstack", datakind, vname));
             }
             ldebug!(&format!("DBUG:FuzzerK:VM:Ctxt:FArg2Real:{:?}:{}=>{:?}", datakind, vname, rname.unwrap()));
             let rname = rname.unwrap();
-            return (rname.0.clone(), rname.1.to_string());
+            return Ok((rname.0.clone(), rname.1.to_string()));
         }
-        return (VarSpace::Either, vname.to_string());
+        return Ok((VarSpace::Either, vname.to_string()));
     }
 
 }
@@ -308,39 +336,39 @@ impl CondOp {
     /// The Ne check gets replaced as follows
     /// a != b  ==>  !(a == b)
     ///
-    fn check(&self, ctxt: &mut Context, val1: &DataM, val2: &DataM) -> bool {
+    fn check(&self, ctxt: &mut Context, val1: &DataM, val2: &DataM) -> Result<bool, String> {
         match self {
             CondOp::IfLtInt => {
-                let val1 = val1.get_isize(ctxt).expect("FuzzerK:Vm:CondOp:IfLtInt:Val1");
-                let val2 = val2.get_isize(ctxt).expect("FuzzerK:Vm:CondOp:IfLtInt:Val2");
+                let val1 = val1.get_isize(ctxt).map_err(|e| format!("ERRR:FuzzerK:Vm:CondOp:IfLtInt:Val1:{}", e))?;
+                let val2 = val2.get_isize(ctxt).map_err(|e| format!("ERRR:FuzzerK:Vm:CondOp:IfLtInt:Val2:{}", e))?;
                 ldebug!(&format!("DBUG:CondOp:IfLtInt:[{}] vs [{}]", val1, val2));
                 if val1 < val2 {
-                    return true;
+                    return Ok(true);
                 }
-                return false;
+                return Ok(false);
             },
             CondOp::IfGtInt => {
                 return CondOp::IfLtInt.check(ctxt, val2, val1);
             },
             CondOp::IfLeInt => {
-                let adjval2 = val2.get_isize(ctxt).expect("FuzzerK:Vm:CondOp:IfLeInt:Val2") + 1;
+                let adjval2 = val2.get_isize(ctxt).map_err(|e| format!("ERRR:FuzzerK:Vm:CondOp:IfLeInt:Val2:{}", e))? + 1;
                 return CondOp::IfLtInt.check(ctxt, val1, &DataM::Value(Variant::IntValue(adjval2)));
             },
             CondOp::IfGeInt => {
-                let adjval1 = val1.get_isize(ctxt).expect("FuzzerK:Vm:CondOp:IfGeInt:Val1") + 1;
+                let adjval1 = val1.get_isize(ctxt).map_err(|e| format!("ERRR:FuzzerK:Vm:CondOp:IfGeInt:Val1:{}", e))? + 1;
                 return CondOp::IfLtInt.check(ctxt, val2, &DataM::Value(Variant::IntValue(adjval1)));
             },
             CondOp::IfEqBuf => {
-                let val1 = val1.get_bufvu8(ctxt).expect("FuzzerK:Vm:CondOp:IfEqBuf:Val1");
-                let val2 = val2.get_bufvu8(ctxt).expect("FuzzerK:Vm:CondOp:IfEqBuf:Val2");
+                let val1 = val1.get_bufvu8(ctxt).map_err(|e| format!("ERRR:FuzzerK:Vm:CondOp:IfEqBuf:Val1:{}", e))?;
+                let val2 = val2.get_bufvu8(ctxt).map_err(|e| format!("ERRR:FuzzerK:Vm:CondOp:IfEqBuf:Val2:{}", e))?;
                 ldebug!(&format!("DBUG:CondOp:IfEqBuf:[{:?}] vs [{:?}]", val1, val2));
                 if val1 == val2 {
-                    return true;
+                    return Ok(true);
                 }
-                return false;
+                return Ok(false);
             },
             CondOp::IfNeBuf => {
-                return !CondOp::IfEqBuf.check(ctxt, val1, val2);
+                return Ok(!CondOp::IfEqBuf.check(ctxt, val1, val2)?);
             }
         }
     }
@@ -383,6 +411,7 @@ enum Op {
     IobFlush(String),
     IobRead(String, DataM),
     IobClose(String),
+    IobAccept(String, Option<String>),
     If(CondOp, DataM, DataM, Box<Op>),
     CheckJump(DataM, DataM, String, String, String),
     JumpRaw(String),
@@ -435,22 +464,22 @@ impl Op {
     /// Uses current source line number and index of the argument in the argument list to generate
     /// a name for the auto created temp variable.
     ///
-    fn compile_literals2autotempvars(ctxt: &mut Context, vargsin: Vec<String>, msgtag: &str) -> Vec<String> {
+    fn compile_literals2autotempvars(ctxt: &mut Context, vargsin: Vec<String>, msgtag: &str) -> Result<Vec<String>, String> {
         let mut vargs: Vec<String> = Vec::new();
         let mut itok = 0;
         for stok in vargsin {
             itok += 1;
-            let dm = DataM::compile(ctxt, &stok, "any", &format!("{}:Lit2AutoTempVars:Args check +", msgtag));
+            let dm = DataM::compile(ctxt, &stok, "any", &format!("{}:Lit2AutoTempVars:Args check +", msgtag))?;
             if dm.is_variable() {
                 vargs.push(stok);
                 continue;
             }
             let odtype = dm.get_type_char(ctxt);
             if odtype == '?' {
-                todo!();
+                return Err(format!("ERRR:{}:Lit2AutoTempVars:Unknown type of literal:{}", msgtag, stok));
             }
             let autovar = format!("ATV_{}_{}_AtVaTv", ctxt.compilingline, itok);
-            let avdm = DataM::compile(ctxt, &autovar, "any", &format!("{}:Lit2AutoTempVars:Auto var:{}", msgtag, autovar));
+            let avdm = DataM::compile(ctxt, &autovar, "any", &format!("{}:Lit2AutoTempVars:Auto var:{}", msgtag, autovar))?;
             if ctxt.bcompilingfunc {
                 ctxt.preops.push(Op::LetLocal(odtype, avdm, dm));
             } else {
@@ -458,10 +487,10 @@ impl Op {
             }
             vargs.push(autovar);
         }
-        vargs
+        Ok(vargs)
     }
 
-    fn opcompile_opdatatype_autoinfer_ifreqd(sop: &str, ctxt: &Context, srcdm: &DataM, smsg: &str) -> char {
+    fn opcompile_opdatatype_autoinfer_ifreqd(sop: &str, ctxt: &Context, srcdm: &DataM, smsg: &str) -> Result<char, String> {
         let (_op, tm) = sop.split_once('.').unwrap_or(("DUMMY", "?")); // extract explicit typemarker if any
         let srctype = match tm {
             "?" => {
@@ -474,9 +503,9 @@ impl Op {
             "b" => 'b',
             "s" => 's',
             "i" => 'i',
-            _ => panic!("ERRR:{}:OpDestDataType:Unknown Variant:{}", smsg, sop)
+            _ => return Err(format!("ERRR:{}:OpDestDataType:Unknown Variant:{}", smsg, sop))
         };
-        return srctype;
+        return Ok(srctype);
     }
 
     /// Has Jump can occur either on its own or through if,
@@ -493,21 +522,21 @@ impl Op {
 
     /// Has Call can occur either on its own or through if,
     /// so put its handling into a common helper.
-    fn opcompile_call(ctxt: &mut Context, sop: &str, sargs: &str, msgtag: &str) -> Op {
+    fn opcompile_call(ctxt: &mut Context, sop: &str, sargs: &str, msgtag: &str) -> Result<Op, String> {
         let theop;
         match sop {
             "callvo" => {
-                let na = Op::name_args(ctxt, sargs).expect(&format!("ERRR:{}:{}:Extract name and args:{}", msgtag, sop, sargs));
+                let na = Op::name_args(ctxt, sargs).map_err(|e| format!("ERRR:{}:{}:Extract name and args:{}:{}", msgtag, sop, sargs, e))?;
                 theop = Op::Call(na.0, na.1);
             }
             "call" => {
-                let na = Op::name_args(ctxt, sargs).expect(&format!("ERRR:{}:{}:Extract name and args:{}", msgtag, sop, sargs));
-                let destargs = Op::compile_literals2autotempvars(ctxt, na.1, &format!("{}:{}:{}", msgtag, sop, sargs));
+                let na = Op::name_args(ctxt, sargs).map_err(|e| format!("ERRR:{}:{}:Extract name and args:{}:{}", msgtag, sop, sargs, e))?;
+                let destargs = Op::compile_literals2autotempvars(ctxt, na.1, &format!("{}:{}:{}", msgtag, sop, sargs))?;
                 theop = Op::Call(na.0, destargs);
             }
-            _ => panic!("ERRR:{}:Unknown type of call op:{}:{}", msgtag, sop, sargs),
+            _ => return Err(format!("ERRR:{}:Unknown type of call op:{}:{}", msgtag, sop, sargs)),
         }
-        return theop;
+        return Ok(theop);
     }
 
     fn compile(opplus: &str, ctxt: &mut Context) -> Result<Op, String> {
@@ -531,25 +560,25 @@ impl Op {
             }
 
             "letint" | "letstr" | "letbuf" | "letglobal.i" | "letglobal.s" | "letglobal.b" | "letglobal" => {
-                let (vid, sval) = sargs.split_once(' ').expect(&format!("ERRR:{}:LetGlobal:{}:{}", msgtag, sop, sargs));
-                let viddm = DataM::compile(ctxt, vid, "any", &format!("{}:LetGlobal:{}:Var:{}", msgtag, sop, vid));
-                let valdm = DataM::compile(ctxt, sval, "any", &format!("{}:LetGlobal:{}:Value:{}", msgtag, sop, sval));
+                let (vid, sval) = sargs.split_once(' ').ok_or_else(|| format!("ERRR:{}:LetGlobal:{}:{}", msgtag, sop, sargs))?;
+                let viddm = DataM::compile(ctxt, vid, "any", &format!("{}:LetGlobal:{}:Var:{}", msgtag, sop, vid))?;
+                let valdm = DataM::compile(ctxt, sval, "any", &format!("{}:LetGlobal:{}:Value:{}", msgtag, sop, sval))?;
                 let opdatatype = match sop {
                     "letint" | "letglobal.i" => 'i',
                     "letstr" | "letglobal.s" => 's',
                     "letbuf" | "letglobal.b" => 'b',
-                    "letglobal" => Op::opcompile_opdatatype_autoinfer_ifreqd(sop, ctxt, &valdm, &format!("{}:LetGlobal", msgtag)),
+                    "letglobal" => Op::opcompile_opdatatype_autoinfer_ifreqd(sop, ctxt, &valdm, &format!("{}:LetGlobal", msgtag))?,
                     _ => todo!(),
                 };
                 return Ok(Op::LetGlobal(opdatatype, viddm, valdm));
             }
 
             "inc" => {
-                let viddm = DataM::compile(ctxt, sargs, "any", &format!("{}:Inc:Var:{}", msgtag, sargs));
+                let viddm = DataM::compile(ctxt, sargs, "any", &format!("{}:Inc:Var:{}", msgtag, sargs))?;
                 return Ok(Op::Inc(viddm));
             }
             "dec" => {
-                let viddm = DataM::compile(ctxt, sargs, "any", &format!("{}:Dec:Var:{}", msgtag, sargs));
+                let viddm = DataM::compile(ctxt, sargs, "any", &format!("{}:Dec:Var:{}", msgtag, sargs))?;
                 return Ok(Op::Dec(viddm));
             }
             "add" | "sub" | "mult" | "div" | "mod" => {
@@ -561,10 +590,13 @@ impl Op {
                     "mod" => AluAOP::Mod,
                     _ => todo!(),
                 };
-                let args = ctxt.tstrx.from_str(sargs, true).tokens_vec(' ', true, false).expect(&format!("ERRR:{}:{}:extracting operands:[{}]", msgtag, sop, sargs));
-                let dmdst = DataM::compile(ctxt, &args[0], "any", &format!("{}:{}:Dest:{}", msgtag, sop, args[0]));
-                let dmsrc1 = DataM::compile(ctxt, &args[1], "any", &format!("{}:{}:SrcArg1:{}", msgtag, sop, args[1]));
-                let dmsrc2 = DataM::compile(ctxt, &args[2], "any", &format!("{}:{}:SrcArg2:{}", msgtag, sop, args[1]));
+                let args = ctxt.tstrx.from_str(sargs, true).tokens_vec(' ', true, false).map_err(|e| format!("ERRR:{}:{}:extracting operands:[{}]:{}", msgtag, sop, sargs, e))?;
+                if args.len() != 3 && !(sop == "not" && args.len() == 2) {
+                    return Err(format!("ERRR:{}:{}:Insufficient or extra operands:[{}]", msgtag, sop, sargs));
+                }
+                let dmdst = DataM::compile(ctxt, &args[0], "any", &format!("{}:{}:Dest:{}", msgtag, sop, args[0]))?;
+                let dmsrc1 = DataM::compile(ctxt, &args[1], "any", &format!("{}:{}:SrcArg1:{}", msgtag, sop, args[1]))?;
+                let dmsrc2 = DataM::compile(ctxt, &args[2], "any", &format!("{}:{}:SrcArg2:{}", msgtag, sop, args[1]))?;
                 return Ok(Op::AluArith(aluop, dmdst, dmsrc1, dmsrc2));
             }
             "and" | "or" | "not" | "xor" | "slb" | "srb" => {
@@ -577,12 +609,15 @@ impl Op {
                     "srb" => AluLOP::Srb,
                     _ => todo!(),
                 };
-                let args = ctxt.tstrx.from_str(sargs, true).tokens_vec(' ', true, false).expect(&format!("ERRR:{}:{}:extracting operands:[{}]", msgtag, sop, sargs));
-                let dmdst = DataM::compile(ctxt, &args[0], "any", &format!("{}:{}:Dest:{}", msgtag, sop, args[0]));
-                let dmsrc1 = DataM::compile(ctxt, &args[1], "any", &format!("{}:{}:SrcArg1:{}", msgtag, sop, args[1]));
+                let args = ctxt.tstrx.from_str(sargs, true).tokens_vec(' ', true, false).map_err(|e| format!("ERRR:{}:{}:extracting operands:[{}]:{}", msgtag, sop, sargs, e))?;
+                if args.len() != 3 && !(sop == "not" && args.len() == 2) {
+                    return Err(format!("ERRR:{}:{}:Insufficient or extra operands:[{}]", msgtag, sop, sargs));
+                }
+                let dmdst = DataM::compile(ctxt, &args[0], "any", &format!("{}:{}:Dest:{}", msgtag, sop, args[0]))?;
+                let dmsrc1 = DataM::compile(ctxt, &args[1], "any", &format!("{}:{}:SrcArg1:{}", msgtag, sop, args[1]))?;
                 let dmsrc2;
                 if aluop != AluLOP::Not {
-                    dmsrc2 = DataM::compile(ctxt, &args[2], "any", &format!("{}:{}:SrcArg2:{}", msgtag, sop, args[1]));
+                    dmsrc2 = DataM::compile(ctxt, &args[2], "any", &format!("{}:{}:SrcArg2:{}", msgtag, sop, args[1]))?;
                 } else {
                     dmsrc2 = dmsrc1.clone();
                 }
@@ -590,12 +625,17 @@ impl Op {
             }
 
             "iobnew" => {
-                let args = ctxt.tstrx.from_str(sargs, true).splitn(3, ' ').expect(&format!("ERRR:{}:{}:Extracting arguments[{}]", msgtag, sop, sargs));
+                let args = ctxt.tstrx.from_str(sargs, true).splitn(3, ' ').map_err(|e| format!("ERRR:{}:{}:Extracting arguments[{}]:{}", msgtag, sop, sargs, e))?;
                 if args.len() < 2 {
-                    panic!("ERRR:{}:IobNew:InsufficientArgs:{}:[{:?}]", msgtag, sargs, args);
+                    return Err(format!("ERRR:{}:IobNew:InsufficientArgs:{}:[{:?}]", msgtag, sargs, args));
                 }
                 let ioid = args[0].clone();
-                let ioaddr = args[1].clone();
+                let mut ioaddr = args[1].as_str();
+                // ioaddr can be quoted, if it contains spaces, like exec:path/to/program arg1 arg2
+                if ioaddr.len() >= 2 && ioaddr.starts_with('"') && ioaddr.ends_with('"') {
+                    ioaddr = &ioaddr[1..ioaddr.len()-1];
+                }
+                let ioaddr = ioaddr.to_string();
                 let mut sioargs = "";
                 if args.len() == 3 {
                     sioargs = args[2].as_str();
@@ -603,41 +643,51 @@ impl Op {
                 let mut ioargs = HashMap::new();
                 let lioargs = ctxt.tstrx.from_str(sioargs, true)
                     .tokens_vec(' ', true, false)
-                    .expect(&format!("ERRR:{}:{}:IoArgs:{}", msgtag, sop, sioargs));
+                    .map_err(|e| format!("ERRR:{}:{}:IoArgs:{}:{}", msgtag, sop, sioargs, e))?;
                 for sioarg in lioargs {
-                    let (k, v) = sioarg.split_once("=").expect(&format!("ERRR:{}:{}:Extracting IoArg:{}", msgtag, sop, sioarg));
+                    let (k, v) = sioarg.split_once("=").ok_or_else(|| format!("ERRR:{}:{}:Extracting IoArg:{}", msgtag, sop, sioarg))?;
                     ioargs.insert(k.to_string(), v.to_string());
                 }
                 return Ok(Op::IobNew(ioid, ioaddr, ioargs));
             }
             "iobwrite" => {
-                let (ioid, bufid) = sargs.split_once(' ').expect(&format!("ERRR:{}:IobWrite:{}", msgtag, sargs));
-                let dmsrc = DataM::compile(ctxt, bufid, "any", &format!("{}:{}:Src", msgtag, sop));
+                let (ioid, bufid) = sargs.split_once(' ').ok_or_else(|| format!("ERRR:{}:IobWrite:{}", msgtag, sargs))?;
+                let dmsrc = DataM::compile(ctxt, bufid, "any", &format!("{}:{}:Src", msgtag, sop))?;
                 return Ok(Op::IobWrite(ioid.to_string(), dmsrc));
             }
             "iobflush" => {
                 return Ok(Op::IobFlush(sargs.to_string()));
             }
             "iobread" => {
-                let (ioid, bufid) = sargs.split_once(' ').expect(&format!("ERRR:{}:IobRead:{}", msgtag, sargs));
-                let dmdst = DataM::compile(ctxt, bufid, "any", &format!("{}:IobRead:Dst:{}", msgtag, bufid));
+                let (ioid, bufid) = sargs.split_once(' ').ok_or_else(|| format!("ERRR:{}:IobRead:{}", msgtag, sargs))?;
+                let dmdst = DataM::compile(ctxt, bufid, "any", &format!("{}:IobRead:Dst:{}", msgtag, bufid))?;
                 return Ok(Op::IobRead(ioid.to_string(), dmdst));
             }
             "iobclose" => {
                 return Ok(Op::IobClose(sargs.to_string()));
             }
+            "iobaccept" => {
+                let args: Vec<&str> = sargs.split_whitespace().collect();
+                if args.len() == 1 {
+                    return Ok(Op::IobAccept(args[0].to_string(), None));
+                }
+                if args.len() != 3 || args[1] != "spawn" {
+                    return Err(format!("ERRR:{}:IobAccept:Expected <ioid> [spawn <label>]:{}", msgtag, sargs));
+                }
+                return Ok(Op::IobAccept(args[0].to_string(), Some(args[2].to_string())));
+            }
 
             "iflt" | "iflt.i" | "ifgt" | "ifgt.i" | "ifeq" | "ifeq.b" | "ifeq.i" | "ifeq.s" | "ifne" | "ifne.b" | "ifne.i" | "ifne.s" | "ifle" | "ifle.i" | "ifge" | "ifge.i" => {
-                let vargs = ctxt.tstrx.from_str(sargs, true).splitn(4, ' ').expect(&format!("ERRR:{}:{}:Extracting operands:{}", msgtag, sop, sargs));
+                let vargs = ctxt.tstrx.from_str(sargs, true).splitn(4, ' ').map_err(|e| format!("ERRR:{}:{}:Extracting operands:{}:{}", msgtag, sop, sargs, e))?;
                 if vargs.len() != 4 {
-                    panic!("ERRR:{}:{}:Insufficient args:{}", msgtag, sop, sargs);
+                    return Err(format!("ERRR:{}:{}:Insufficient args:{}", msgtag, sop, sargs));
                 }
                 let arg0 = vargs[0].as_str();
                 let arg1 = vargs[1].as_str();
                 let desttype = vargs[2].as_str();
                 let destdata = vargs[3].as_str();
-                let val1dm = DataM::compile(ctxt, arg0, "any", &format!("{}:{}:CheckValue1:{}", msgtag, sop, arg0));
-                let val2dm = DataM::compile(ctxt, arg1, "any", &format!("{}:{}:CheckValue2:{}", msgtag, sop, arg1));
+                let val1dm = DataM::compile(ctxt, arg0, "any", &format!("{}:{}:CheckValue1:{}", msgtag, sop, arg0))?;
+                let val2dm = DataM::compile(ctxt, arg1, "any", &format!("{}:{}:CheckValue2:{}", msgtag, sop, arg1))?;
                 let cop = match sop {
                     "iflt" | "iflt.i" => CondOp::IfLtInt,
                     "ifgt" | "ifgt.i" => CondOp::IfGtInt,
@@ -653,26 +703,26 @@ impl Op {
                         nxtop = Op::opcompile_jump(destdata);
                     }
                     "callvo" | "call" => {
-                        nxtop = Op::opcompile_call(ctxt, desttype, destdata, &format!("{}:{}", msgtag, sop));
+                        nxtop = Op::opcompile_call(ctxt, desttype, destdata, &format!("{}:{}", msgtag, sop))?;
                     }
-                    _ => todo!()
+                    _ => return Err(format!("ERRR:{}:{}:Unknown action {}, expected goto|call|callvo", msgtag, sop, desttype)),
                 }
                 return Ok(Op::If(cop, val1dm, val2dm, Box::new(nxtop)));
             }
             "checkjump" => {
-                let args = ctxt.tstrx.from_str(sargs, true).splitn(5, ' ').expect(&format!("ERRR:{}:{}:Extracting operands:{}", msgtag, sop, sargs));
+                let args = ctxt.tstrx.from_str(sargs, true).splitn(5, ' ').map_err(|e| format!("ERRR:{}:{}:Extracting operands:{}:{}", msgtag, sop, sargs, e))?;
                 if args.len() != 5 {
-                    panic!("ERRR:{}:CheckJump:Insufficient args:{}", msgtag, sargs);
+                    return Err(format!("ERRR:{}:CheckJump:Insufficient args:{}", msgtag, sargs));
                 }
-                let arg1dm = DataM::compile(ctxt, &args[0], "isize", &format!("{}:CheckJump:Arg1:{}", msgtag, args[0]));
-                let arg2dm = DataM::compile(ctxt, &args[1], "isize", &format!("{}:CheckJump:Arg2:{}", msgtag, args[1]));
+                let arg1dm = DataM::compile(ctxt, &args[0], "isize", &format!("{}:CheckJump:Arg1:{}", msgtag, args[0]))?;
+                let arg2dm = DataM::compile(ctxt, &args[1], "isize", &format!("{}:CheckJump:Arg2:{}", msgtag, args[1]))?;
                 return Ok(Op::CheckJump(arg1dm, arg2dm, args[2].to_string(), args[3].to_string(), args[4].to_string()));
             }
             "jump" | "goto" => {
                 return Ok(Op::opcompile_jump(sargs));
             }
             "callvo" | "call" => {
-                return Ok(Op::opcompile_call(ctxt, sop, sargs, &format!("{}:{}", msgtag, sop)));
+                return Op::opcompile_call(ctxt, sop, sargs, &format!("{}:{}", msgtag, sop));
             }
             "ret" => {
                 ctxt.bcompilingfunc = false;
@@ -681,28 +731,31 @@ impl Op {
             }
 
             "sleepmsec" => {
-                let msecdm = DataM::compile(ctxt, sargs, "isize", &format!("{}:SleepMSec:Value:{}", msgtag, sargs));
+                let msecdm = DataM::compile(ctxt, sargs, "isize", &format!("{}:SleepMSec:Value:{}", msgtag, sargs))?;
                 return Ok(Op::SleepMSec(msecdm));
             }
 
             "fcget" => {
-                let (fcid, destvid) = sargs.split_once(' ').expect(&format!("ERRR:{}:FcGet:{}", msgtag, sargs));
-                let dm = DataM::compile(ctxt, destvid, "any", &format!("{}:FCGet:Dest:{}", msgtag, destvid));
+                let (fcid, destvid) = sargs.split_once(' ').ok_or_else(|| format!("ERRR:{}:FcGet:{}", msgtag, sargs))?;
+                let dm = DataM::compile(ctxt, destvid, "any", &format!("{}:FCGet:Dest:{}", msgtag, destvid))?;
                 return Ok(Op::FcGet(fcid.to_string(), dm));
             }
 
             "bufnew" => {
-                let (bufid, bufsize) = sargs.split_once(' ').expect(&format!("ERRR:{}:BufNew:{}", msgtag, sargs));
-                let bufid = DataM::compile(ctxt, bufid, "any", &format!("{}:BufNew:Dest:{}", msgtag, bufid));
-                let dmbufsize = DataM::compile(ctxt, bufsize, "any", &format!("{}:BufNew:Size:{}", msgtag, bufsize));
+                let (bufid, bufsize) = sargs.split_once(' ').ok_or_else(|| format!("ERRR:{}:BufNew:{}", msgtag, sargs))?;
+                let bufid = DataM::compile(ctxt, bufid, "any", &format!("{}:BufNew:Dest:{}", msgtag, bufid))?;
+                let dmbufsize = DataM::compile(ctxt, bufsize, "any", &format!("{}:BufNew:Size:{}", msgtag, bufsize))?;
                 return Ok(Op::BufNew(bufid, dmbufsize));
             }
             "buf8randomize" => {
                 let parts = ctxt.tstrx.from_str(sargs, true)
                     .tokens_vec(' ', true, false)
-                    .expect(&format!("ERRR:{}:{}:Extracting args:{}", msgtag, sop, sargs));
+                    .map_err(|e| format!("ERRR:{}:{}:Extracting args:{}:{}", msgtag, sop, sargs, e))?;
+                if parts.len() == 0 {
+                    return Err(format!("ERRR:{}:Buf8Randomize:Buf missing", msgtag));
+                }
                 let bufid = parts[0].as_ref();
-                let bufid = DataM::compile(ctxt, bufid, "any", &format!("{}:Buf8Randomize:TheBuf:{}", msgtag, bufid));
+                let bufid = DataM::compile(ctxt, bufid, "any", &format!("{}:Buf8Randomize:TheBuf:{}", msgtag, bufid))?;
 
                 let dmrandcount;
                 let dmstartoffset;
@@ -717,49 +770,49 @@ impl Op {
                 } else {
                     thepart = "-1";
                 }
-                dmrandcount = DataM::compile(ctxt, thepart, "isize", &format!("{}:Buf8Randomize:RandCount:{}", msgtag, thepart));
+                dmrandcount = DataM::compile(ctxt, thepart, "isize", &format!("{}:Buf8Randomize:RandCount:{}", msgtag, thepart))?;
 
                 if parts.len() >= 3 {
                     thepart = parts[2].as_ref();
                 } else {
                     thepart = "-1";
                 }
-                dmstartoffset = DataM::compile(ctxt, thepart, "isize", &format!("{}:Buf8Randomize:StartOffset:{}", msgtag, thepart));
+                dmstartoffset = DataM::compile(ctxt, thepart, "isize", &format!("{}:Buf8Randomize:StartOffset:{}", msgtag, thepart))?;
 
                 if parts.len() >= 4 {
                     thepart = parts[3].as_ref();
                 } else {
                     thepart = "-1";
                 }
-                dmendoffset = DataM::compile(ctxt, thepart, "isize", &format!("{}:Buf8Randomize:EndOffset:{}", msgtag, thepart));
+                dmendoffset = DataM::compile(ctxt, thepart, "isize", &format!("{}:Buf8Randomize:EndOffset:{}", msgtag, thepart))?;
 
                 if parts.len() >= 5 {
                     thepart = parts[4].as_ref();
                 } else {
                     thepart = "0";
                 }
-                dmstartval = DataM::compile(ctxt, thepart, "isize", &format!("{}:Buf8Randomize:StartVal:{}", msgtag, thepart));
+                dmstartval = DataM::compile(ctxt, thepart, "isize", &format!("{}:Buf8Randomize:StartVal:{}", msgtag, thepart))?;
 
                 if parts.len() == 6 {
                     thepart = parts[5].as_ref();
                 } else {
                     thepart = "255";
                 }
-                dmendval = DataM::compile(ctxt, thepart, "isize", &format!("{}:Buf8Randomize:EndVal:{}", msgtag, thepart));
+                dmendval = DataM::compile(ctxt, thepart, "isize", &format!("{}:Buf8Randomize:EndVal:{}", msgtag, thepart))?;
 
                 if parts.len() > 6 {
-                    panic!("ERRR:{}:Buf8Randomize:Too many args:{}", msgtag, sargs);
+                    return Err(format!("ERRR:{}:Buf8Randomize:Too many args:{}", msgtag, sargs));
                 }
                 return Ok(Op::Buf8Randomize(bufid, dmrandcount, dmstartoffset, dmendoffset, dmstartval, dmendval))
             }
             "bufmerged" | "bufmerged.s" | "bufmerged.b" => {
-                let (bufid, srcs) = sargs.split_once(' ').expect(&format!("ERRR:{}:BufMerged:Extracting dest from {}", msgtag, sargs));
-                let bufid = DataM::compile(ctxt, bufid, "any", &format!("{}:BufMerged:Dest:{}", msgtag, bufid));
+                let (bufid, srcs) = sargs.split_once(' ').ok_or_else(|| format!("ERRR:{}:BufMerged:Extracting dest from {}", msgtag, sargs))?;
+                let bufid = DataM::compile(ctxt, bufid, "any", &format!("{}:BufMerged:Dest:{}", msgtag, bufid))?;
                 let mut vdm = Vec::new();
                 let mut vsrcs = ctxt.tstrx.from_str(srcs, true);
-                let vsrcs = vsrcs.tokens_vec(' ', true, false).expect(&format!("ERRR:{}:BufMerged:Extracting data sources [{}]", msgtag, srcs));
+                let vsrcs = vsrcs.tokens_vec(' ', true, false).map_err(|e| format!("ERRR:{}:BufMerged:Extracting data sources [{}]:{}", msgtag, srcs, e))?;
                 for tnext in vsrcs {
-                    let dm = DataM::compile(ctxt, &tnext, "any", &format!("{}:BufMerged:ProcessingSrc:{}", msgtag, tnext));
+                    let dm = DataM::compile(ctxt, &tnext, "any", &format!("{}:BufMerged:ProcessingSrc:{}", msgtag, tnext))?;
                     vdm.push(dm);
                 }
                 let op_type = sop.split_once('.');
@@ -777,31 +830,52 @@ impl Op {
                 return Ok(Op::BufMerged(mtype, bufid, vdm));
             }
             "letlocal" | "letlocal.b" | "letlocal.s" | "letlocal.i" => {
-                let (vid, vdata) = sargs.split_once(' ').expect(&format!("ERRR:{}:LetLocal+:{}", msgtag, sargs));
-                let viddm = DataM::compile(ctxt, vid, "any", &format!("{}:LetLocal+:Var:{}", msgtag, vid));
-                let datadm = DataM::compile(ctxt, vdata, "any", &format!("{}:LetLocal+:Value:{}", msgtag, vdata));
-                let opdatatype = Op::opcompile_opdatatype_autoinfer_ifreqd(sop, ctxt, &datadm, &format!("{}:LetLocal", msgtag));
+                let (vid, vdata) = sargs.split_once(' ').ok_or_else(|| format!("ERRR:{}:LetLocal+:{}", msgtag, sargs))?;
+                let viddm = DataM::compile(ctxt, vid, "any", &format!("{}:LetLocal+:Var:{}", msgtag, vid))?;
+                let datadm = DataM::compile(ctxt, vdata, "any", &format!("{}:LetLocal+:Value:{}", msgtag, vdata))?;
+                let opdatatype = Op::opcompile_opdatatype_autoinfer_ifreqd(sop, ctxt, &datadm, &format!("{}:LetLocal", msgtag))?;
                 return Ok(Op::LetLocal(opdatatype, viddm, datadm));
             }
 
             "emagic" => {
-                let (smt, sma) = sargs.split_once(' ').expect(&format!("{}:EMagic:Extract args", msgtag));
-                let mtype = DataM::compile(ctxt, smt, "any", &format!("{}:EMagic:Type:{}", msgtag, smt));
-                let marg = DataM::compile(ctxt, sma, "any", &format!("{}:EMagic:Arg:{}", msgtag, sma));
+                let (smt, sma) = sargs.split_once(' ').ok_or_else(|| format!("{}:EMagic:Extract args", msgtag))?;
+                let mtype = DataM::compile(ctxt, smt, "any", &format!("{}:EMagic:Type:{}", msgtag, smt))?;
+                let marg = DataM::compile(ctxt, sma, "any", &format!("{}:EMagic:Arg:{}", msgtag, sma))?;
                 return Ok(Op::EMagic(mtype, marg));
             }
 
             "getsize" => {
-                let (src, dst) = sargs.split_once(' ').expect(&format!("{}:GetSize:Extract args", msgtag));
-                let sdm = DataM::compile(ctxt, src, "any", &format!("{}:GetSize:Src:{}", msgtag, src));
-                let ddm = DataM::compile(ctxt, dst, "any", &format!("{}:GetSize:WriteSizeToVar:{}", msgtag, dst));
+                let (src, dst) = sargs.split_once(' ').ok_or_else(|| format!("{}:GetSize:Extract args", msgtag))?;
+                let sdm = DataM::compile(ctxt, src, "any", &format!("{}:GetSize:Src:{}", msgtag, src))?;
+                let ddm = DataM::compile(ctxt, dst, "any", &format!("{}:GetSize:WriteSizeToVar:{}", msgtag, dst))?;
                 if ddm.is_value() {
-                    panic!("ERRR:{}:GetSize:Dest[{:?}] needs to be a variable", msgtag, ddm);
+                    return Err(format!("ERRR:{}:GetSize:Dest[{:?}] needs to be a variable", msgtag, ddm));
                 }
                 return Ok(Op::GetSize(sdm, ddm));
             }
 
-            _ => panic!("ERRR:{}:UnknownOp:{}", msgtag, sop)
+            _ => return Err(format!("ERRR:{}:UnknownOp:{}", msgtag, sop))
+        }
+    }
+
+    ///
+    /// Collect the labels, functions and fuzzchains referred to by this op, along with the
+    /// number of args passed wrt functions.
+    ///
+    fn refs<'a>(&'a self, refs: &mut Vec<(&'static str, &'a str, usize)>) {
+        match self {
+            Self::JumpRaw(label) | Self::IobAccept(_, Some(label)) => refs.push(("label", label, 0)),
+            Self::CheckJump(_, _, ltlabel, eqlabel, gtlabel) => {
+                for label in [ltlabel, eqlabel, gtlabel] {
+                    if label != "__NEXT__" {
+                        refs.push(("label", label, 0));
+                    }
+                }
+            }
+            Self::Call(fname, passedargs) => refs.push(("function", fname, passedargs.len())),
+            Self::FcGet(fcid, _) => refs.push(("fuzzchain", fcid, 0)),
+            Self::If(_, _, _, nxtop) => nxtop.refs(refs),
+            _ => (),
         }
     }
 
@@ -819,47 +893,64 @@ impl Op {
         return dtype;
     }
 
-    fn run(&self, ctxt: &mut Context, linenum: u32) {
+    ///
+    /// If the io bridge's spawned target has exited, let the oracle check how it exited.
+    ///
+    fn oprun_check_exit(ctxt: &mut Context, ioid: &str) {
+        let zenio = ctxt.iobs.get_mut(ioid);
+        if zenio.is_none() {
+            return;
+        }
+        let status = zenio.unwrap().take_exit_status();
+        if status.is_some() {
+            ctxt.oracle.check_exit(ioid, &status.unwrap(), ctxt.fcrtm.seed(), ctxt.stepu);
+        }
+    }
+
+    fn run(&self, ctxt: &mut Context, linenum: u32) -> Result<(), String> {
         let msgtag = &dformat!("FuzzerK:VM:Op:Run:{}", linenum);
         match self {
             Self::Nop => (),
-            Self::End => process::exit(0),
+            Self::End => ctxt.bend = true,
 
             Self::Inc(vid) => {
                 let val = vid.get_isize(ctxt);
                 if val.is_err() {
-                    panic!("ERRR:{}:Inc:{}:{}", msgtag, vid.identify(), val.unwrap_err());
+                    return Err(format!("ERRR:{}:Inc:{}:{}", msgtag, vid.identify(), val.unwrap_err()));
                 }
                 let mut val = val.unwrap();
                 val += 1;
                 let ok = vid.set_isize(ctxt, val);
                 if ok.is_err() {
-                    panic!("ERRR:{}:Inc:{}:{}", msgtag, vid.identify(), ok.unwrap_err());
+                    return Err(format!("ERRR:{}:Inc:{}:{}", msgtag, vid.identify(), ok.unwrap_err()));
                 }
             }
             Self::Dec(vid) => {
                 let val = vid.get_isize(ctxt);
                 if val.is_err() {
-                    panic!("ERRR:{}:Dec:{}:{}", msgtag, vid.identify(), val.unwrap_err());
+                    return Err(format!("ERRR:{}:Dec:{}:{}", msgtag, vid.identify(), val.unwrap_err()));
                 }
                 let mut val = val.unwrap();
                 val -= 1;
                 let ok = vid.set_isize(ctxt, val);
                 if ok.is_err() {
-                    panic!("ERRR:{}:Dec:{}:{}", msgtag, vid.identify(), ok.unwrap_err());
+                    return Err(format!("ERRR:{}:Dec:{}:{}", msgtag, vid.identify(), ok.unwrap_err()));
                 }
             },
             Self::AluArith(aluop, destvid, dmsrc1, dmsrc2) => {
                 let src1 = dmsrc1.get_isize(ctxt);
                 if src1.is_err() {
-                    panic!("ERRR:{}:AluA:Src1:{}:{}", msgtag, dmsrc1.identify(), src1.unwrap_err());
+                    return Err(format!("ERRR:{}:AluA:Src1:{}:{}", msgtag, dmsrc1.identify(), src1.unwrap_err()));
                 }
                 let src1 = src1.unwrap();
                 let src2 = dmsrc2.get_isize(ctxt);
                 if src2.is_err() {
-                    panic!("ERRR:{}:AluA:Src2:{}:{}", msgtag, dmsrc2.identify(), src2.unwrap_err());
+                    return Err(format!("ERRR:{}:AluA:Src2:{}:{}", msgtag, dmsrc2.identify(), src2.unwrap_err()));
                 }
                 let src2 = src2.unwrap();
+                if (src2 == 0) && matches!(aluop, AluAOP::Div | AluAOP::Mod) {
+                    return Err(format!("ERRR:{}:AluA:{:?}:Src2:{}:Is zero", msgtag, aluop, dmsrc2.identify()));
+                }
                 let res = match aluop {
                     AluAOP::Add => src1 + src2,
                     AluAOP::Sub => src1 - src2,
@@ -869,20 +960,23 @@ impl Op {
                 };
                 let ok = destvid.set_isize(ctxt, res);
                 if ok.is_err() {
-                    panic!("ERRR:{}:AluA:Dest:{:?}:{}:{}", msgtag, aluop, destvid.identify(), ok.unwrap_err());
+                    return Err(format!("ERRR:{}:AluA:Dest:{:?}:{}:{}", msgtag, aluop, destvid.identify(), ok.unwrap_err()));
                 }
             },
             Self::AluLogical(aluop, destvid, dmsrc1, dmsrc2) => {
                 let src1 = dmsrc1.get_bufvu8(ctxt);
                 if src1.is_err() {
-                    panic!("ERRR:{}:AluL:Src1:{}:{}", msgtag, dmsrc1.identify(), src1.unwrap_err());
+                    return Err(format!("ERRR:{}:AluL:Src1:{}:{}", msgtag, dmsrc1.identify(), src1.unwrap_err()));
                 }
                 let src1 = src1.unwrap();
                 let src2 = dmsrc2.get_bufvu8(ctxt);
                 if src2.is_err() {
-                    panic!("ERRR:{}:AluL:Src2:{}:{}", msgtag, dmsrc2.identify(), src2.unwrap_err());
+                    return Err(format!("ERRR:{}:AluL:Src2:{}:{}", msgtag, dmsrc2.identify(), src2.unwrap_err()));
                 }
                 let src2 = src2.unwrap();
+                if src2.len() < src1.len() {
+                    return Err(format!("ERRR:{}:AluL:Src2:{}:Shorter than Src1", msgtag, dmsrc2.identify()));
+                }
                 let mut adj = 0u8;
                 let mut res;
                 let mut vres = Vec::new();
@@ -931,7 +1025,7 @@ impl Op {
                 }
                 let ok = destvid.set_bufvu8(ctxt, vres);
                 if ok.is_err() {
-                    panic!("ERRR:{}:AluL:Dest:{:?}:{}:{}", msgtag, aluop, destvid.identify(), ok.unwrap_err());
+                    return Err(format!("ERRR:{}:AluL:Dest:{:?}:{}:{}", msgtag, aluop, destvid.identify(), ok.unwrap_err()));
                 }
             },
 
@@ -947,27 +1041,35 @@ impl Op {
                         }
                     }
                 }
+                ctxt.oracle.track(ioid, ioaddr);
                 let zenio = IOBridge::new(&ioaddr, &ioargs);
-                ctxt.iobs.insert(ioid.to_string(), zenio);
+                if zenio.is_err() {
+                    let errmsg = zenio.err().unwrap().to_string();
+                    ctxt.oracle.check_connect(ioid, &errmsg, ctxt.fcrtm.seed(), ctxt.stepu);
+                    return Err(format!("ERRR:{}:IobNew:{}:{}", msgtag, ioid, errmsg));
+                }
+                ctxt.iobs.insert(ioid.to_string(), zenio.unwrap());
             }
             Self::IobWrite(ioid, srcdm) => {
                 let buf = srcdm.get_bufvu8(ctxt);
                 if buf.is_err() {
-                    panic!("ERRR:{}:IobWrite:Getting SrcBuf:{:?}:{}", msgtag, srcdm, buf.unwrap_err());
+                    return Err(format!("ERRR:{}:IobWrite:Getting SrcBuf:{:?}:{}", msgtag, srcdm, buf.unwrap_err()));
                 }
                 let buf = buf.unwrap();
                 let zenio = ctxt.iobs.get_mut(ioid);
                 if zenio.is_none() {
-                    panic!("ERRR:{}:IobWrite:Getting IOB:{}", msgtag, ioid);
+                    return Err(format!("ERRR:{}:IobWrite:Getting IOB:{}", msgtag, ioid));
                 }
                 let zenio = zenio.unwrap();
-                let gotr = zenio.write(&buf);
+                let gotr = zenio.write_raw(&buf);
                 if gotr.is_err() {
-                    log_e(&format!("ERRR:{}:IobWrite:{}:Writing src:{:?}:{}", msgtag, ioid, srcdm, gotr.unwrap_err()));
+                    log_e(&format!("ERRR:{}:IobWrite:{}:Writing src:{:?}:{}", msgtag, ioid, srcdm, gotr.as_ref().unwrap_err()));
                 }
+                ctxt.oracle.check(ioid, &gotr, false, ctxt.fcrtm.seed(), ctxt.stepu);
+                Op::oprun_check_exit(ctxt, ioid);
             }
             Self::IobFlush(ioid) => {
-                let zenio = ctxt.iobs.get_mut(ioid).unwrap();
+                let zenio = ctxt.iobs.get_mut(ioid).ok_or_else(|| format!("ERRR:{}:IobFlush:Getting IOB:{}", msgtag, ioid))?;
                 let gotr = zenio.flush();
                 if gotr.is_err() {
                     log_e(&format!("ERRR:{}:IobFlush:{}:{}", msgtag, ioid, gotr.unwrap_err()));
@@ -976,15 +1078,17 @@ impl Op {
             Self::IobRead(ioid, bufid) => {
                 let buf = bufid.get_bufvu8(ctxt);
                 if buf.is_err() {
-                    panic!("ERRR:{}:IobRead:Getting ToBuf:{:?}:{}", msgtag, bufid, buf.unwrap_err());
+                    return Err(format!("ERRR:{}:IobRead:Getting ToBuf:{:?}:{}", msgtag, bufid, buf.unwrap_err()));
                 }
                 let buf = &mut buf.unwrap();
                 let zenio = ctxt.iobs.get_mut(ioid);
                 if zenio.is_none() {
-                    panic!("ERRR:{}:IobRead:Getting IOB:{}", msgtag, ioid);
+                    return Err(format!("ERRR:{}:IobRead:Getting IOB:{}", msgtag, ioid));
                 }
                 let zenio = zenio.unwrap();
-                let gotr = zenio.read(buf);
+                let gotr = zenio.read_raw(buf);
+                ctxt.oracle.check(ioid, &gotr, true, ctxt.fcrtm.seed(), ctxt.stepu);
+                Op::oprun_check_exit(ctxt, ioid);
                 let readsize;
                 if gotr.is_err() {
                     let errmsg = gotr.as_ref().unwrap_err();
@@ -996,43 +1100,61 @@ impl Op {
                 buf.resize(readsize, 0);
                 let ok = bufid.set_bufvu8(ctxt, buf.to_vec());
                 if ok.is_err() {
-                    panic!("ERRR:{}:IobRead:Updating ToBuf:{:?}:{}", msgtag, bufid, ok.unwrap_err());
+                    return Err(format!("ERRR:{}:IobRead:Updating ToBuf:{:?}:{}", msgtag, bufid, ok.unwrap_err()));
                 }
             }
             Self::IobClose(ioid) => {
-                let zenio = ctxt.iobs.get_mut(ioid).unwrap();
+                let zenio = ctxt.iobs.get_mut(ioid).ok_or_else(|| format!("ERRR:{}:IobClose:Getting IOB:{}", msgtag, ioid))?;
                 let gotr = zenio.close();
                 if gotr.is_err() {
                     log_e(&format!("ERRR:{}:IobClose:{}:{}", msgtag, ioid, gotr.unwrap_err()));
                 }
+                Op::oprun_check_exit(ctxt, ioid);
                 ctxt.iobs.remove(ioid);
             }
+            Self::IobAccept(ioid, label) => {
+                let zenio = ctxt.iobs.get_mut(ioid).ok_or_else(|| format!("ERRR:{}:IobAccept:Getting IOB:{}", msgtag, ioid))?;
+                if label.is_none() {
+                    zenio.accept().map_err(|e| format!("ERRR:{}:IobAccept:{}:{}", msgtag, ioid, e))?;
+                    return Ok(());
+                }
+                let label = label.as_ref().unwrap();
+                if !ctxt.lbls.contains_key(label) {
+                    return Err(format!("ERRR:{}:IobAccept:{}:Unknown label:{}", msgtag, ioid, label));
+                }
+                let client = zenio.accept_new().map_err(|e| format!("ERRR:{}:IobAccept:{}:{}", msgtag, ioid, e))?;
+                ctxt.clientscnt += 1;
+                let seed = VM::client_seed(ctxt.fcrtm.seed(), ctxt.clientscnt);
+                log_o(&format!("INFO:{}:IobAccept:{}:Client {} runs from {} with seed {}", msgtag, ioid, ctxt.clientscnt, label, seed));
+                let handle = VM::client_spawn(ctxt, ioid, client, label, seed);
+                ctxt.clients.push((seed, handle));
+            }
             Self::SleepMSec(msecdm) => {
-                let msec = msecdm.get_usize(ctxt).expect(&format!("ERRR:{}:SleepMSec:Value:{}", msgtag, msecdm.identify()));
+                let msec = msecdm.get_usize(ctxt).map_err(|e| format!("ERRR:{}:SleepMSec:Value:{}:{}", msgtag, msecdm.identify(), e))?;
                 thread::sleep(Duration::from_millis(msec as u64));
             }
             Self::FcGet(fcid, vid) => {
-                let fc = ctxt.fcrtm.fchain(&fcid).expect(&format!("ERRR:{}:FcGet:{}:UnknownFC???", msgtag, fcid));
-                let gotfuzz = fc.get(Some(ctxt.stepu));
+                let gotfuzz = ctxt.fcrtm.fchain_get(&fcid, Some(ctxt.stepu)).ok_or_else(|| format!("ERRR:{}:FcGet:{}:UnknownFC???", msgtag, fcid))?;
                 ldebug!(&format!("\n\nDBUG:FcGet:{}:Got:{}:\n\t{:?}\n\t{}", fcid, ctxt.stepu, gotfuzz, String::from_utf8_lossy(&gotfuzz)));
-                vid.set_bufvu8(ctxt, gotfuzz).expect(&format!("ERRR:{}:FcGet:{}:SetDest:{}", msgtag, fcid, vid.identify()));
+                ctxt.oracle.record(ctxt.stepu, &gotfuzz);
+                vid.set_bufvu8(ctxt, gotfuzz).map_err(|e| format!("ERRR:{}:FcGet:{}:SetDest:{}:{}", msgtag, fcid, vid.identify(), e))?;
                 ctxt.stepu += 1;
             }
             Self::If(cop, val1dm, val2dm, nxtop) => {
                 let mut opdo = false;
-                if cop.check(ctxt, val1dm, val2dm) {
+                if cop.check(ctxt, val1dm, val2dm)? {
                     opdo = true;
                 }
                 if opdo {
                     // Translating the label here at runtime, rather than during compile time, allows goto to refer to label
                     // that might not yet have been defined at the point where goto or rather the If condition is encountered.
                     // Especially when only a single pass parsing of the program is done.
-                    nxtop.run(ctxt, linenum);
+                    nxtop.run(ctxt, linenum)?;
                 }
             }
             Self::CheckJump(arg1, arg2, ltlabel, eqlabel, gtlabel) => {
-                let varg1 = arg1.get_isize(ctxt).expect(&format!("{}:CheckJump:GetArg1:{:?}", msgtag, arg1));
-                let varg2 = arg2.get_isize(ctxt).expect(&format!("{}:CheckJump:GetArg2:{:?}", msgtag, arg2));
+                let varg1 = arg1.get_isize(ctxt).map_err(|e| format!("{}:CheckJump:GetArg1:{:?}:{}", msgtag, arg1, e))?;
+                let varg2 = arg2.get_isize(ctxt).map_err(|e| format!("{}:CheckJump:GetArg2:{:?}:{}", msgtag, arg2, e))?;
                 let label;
                 if varg1 < varg2 {
                     label = ltlabel;
@@ -1042,14 +1164,14 @@ impl Op {
                     label = gtlabel;
                 }
                 if label != "__NEXT__" {
-                    ctxt.iptr = *ctxt.lbls.get(label).expect(&format!("ERRR:{}:CheckJump:Label:{}", msgtag, label));
+                    ctxt.iptr = *ctxt.lbls.get(label).ok_or_else(|| format!("ERRR:{}:CheckJump:Label:{}", msgtag, label))?;
                     ctxt.iptr_commonupdate = false;
                 }
             }
             Self::JumpRaw(label) => {
                 let oiptr = ctxt.lbls.get(label);
                 if oiptr.is_none() {
-                    panic!("ERRR:{}:Jump:Label:{}:Unknown?", msgtag, label);
+                    return Err(format!("ERRR:{}:Jump:Label:{}:Unknown?", msgtag, label));
                 }
                 ctxt.iptr = *oiptr.unwrap();
                 ctxt.iptr_commonupdate = false;
@@ -1062,7 +1184,7 @@ impl Op {
             Self::Call(fname, passedargs) => {
                 let finfo = ctxt.func_helper(fname, passedargs);
                 if finfo.is_err() {
-                    panic!("ERRR:{}:Call:{}:{}", msgtag, fname, finfo.unwrap_err());
+                    return Err(format!("ERRR:{}:Call:{}:{}", msgtag, fname, finfo.unwrap_err()));
                 }
                 let (fptr, fargsmap) = finfo.unwrap();
                 // Setup the call
@@ -1073,30 +1195,33 @@ impl Op {
                 ctxt.iptr_commonupdate = false;
             }
             Self::Ret => {
-                ctxt.iptr = ctxt.callretstack.pop().expect(&format!("ERRR:{}:Ret:CallRetStack", msgtag));
-                ctxt.fargsmapstack.pop().expect(&format!("ERRR:{}:Ret:FArgsMapStack", msgtag));
+                ctxt.iptr = ctxt.callretstack.pop().ok_or_else(|| format!("ERRR:{}:Ret:CallRetStack", msgtag))?;
+                ctxt.fargsmapstack.pop().ok_or_else(|| format!("ERRR:{}:Ret:FArgsMapStack", msgtag))?;
                 ctxt.localsstack.pop();
             }
 
             Self::BufNew(bufid, dmbufsize) => {
                 let mut buf = Vec::<u8>::new();
-                let bufsize = dmbufsize.get_usize(ctxt).expect(&format!("{}:BufNew:BufSize", msgtag));
+                let bufsize = dmbufsize.get_usize(ctxt).map_err(|e| format!("{}:BufNew:BufSize:{}", msgtag, e))?;
                 buf.resize(bufsize, 0);
-                bufid.set_bufvu8(ctxt, buf).expect(&format!("{}:BufNew:{:?}", msgtag, bufid));
+                bufid.set_bufvu8(ctxt, buf).map_err(|e| format!("{}:BufNew:{:?}:{}", msgtag, bufid, e))?;
             }
             Self::Buf8Randomize(bufid, dmrandcount, dmstartoffset, dmendoffset, dmstartval, dmendval) => {
                 let b8rmsg = &format!("{}:Buf8Randomize", msgtag);
-                let mut buf = bufid.get_bufvu8(ctxt).expect(&format!("{}:Getting TheBuf:{:?}", b8rmsg, bufid));
+                let mut buf = bufid.get_bufvu8(ctxt).map_err(|e| format!("{}:Getting TheBuf:{:?}:{}", b8rmsg, bufid, e))?;
+                if buf.len() == 0 {
+                    return Err(format!("{}:TheBuf:{:?}:Is empty", b8rmsg, bufid));
+                }
 
-                let randcount = dmrandcount.get_isize(ctxt).expect(&format!("{}:RandCount", b8rmsg));
+                let randcount = dmrandcount.get_isize(ctxt).map_err(|e| format!("{}:RandCount:{}", b8rmsg, e))?;
                 let trandcount;
                 if randcount < 0 {
-                    trandcount = rand::random::<usize>() % buf.len();
+                    trandcount = ctxt.fcrtm.rng().gen::<usize>() % buf.len();
                 } else {
                     trandcount = randcount as usize;
                 }
 
-                let startoffset = dmstartoffset.get_isize(ctxt).expect(&format!("{}:StartOffset", b8rmsg));
+                let startoffset = dmstartoffset.get_isize(ctxt).map_err(|e| format!("{}:StartOffset:{}", b8rmsg, e))?;
                 let tstartoffset;
                 if startoffset < 0 {
                     tstartoffset = 0;
@@ -1104,7 +1229,7 @@ impl Op {
                     tstartoffset = startoffset as usize;
                 }
 
-                let endoffset = dmendoffset.get_isize(ctxt).expect(&format!("{}:EndOffset", b8rmsg));
+                let endoffset = dmendoffset.get_isize(ctxt).map_err(|e| format!("{}:EndOffset:{}", b8rmsg, e))?;
                 let tendoffset;
                 if endoffset < 0 {
                     tendoffset = buf.len()-1;
@@ -1113,10 +1238,13 @@ impl Op {
                 }
 
                 // TOTHINK: Should I truncate silently or should I panic if truncation required.
-                let startval = dmstartval.get_isize(ctxt).expect(&format!("{}:StartVal", b8rmsg)) as u8;
-                let endval = dmendval.get_isize(ctxt).expect(&format!("{}:EndVal", b8rmsg)) as u8;
+                let startval = dmstartval.get_isize(ctxt).map_err(|e| format!("{}:StartVal:{}", b8rmsg, e))? as u8;
+                let endval = dmendval.get_isize(ctxt).map_err(|e| format!("{}:EndVal:{}", b8rmsg, e))? as u8;
 
-                let mut rng = rand::thread_rng();
+                if (tstartoffset > tendoffset) || (tendoffset >= buf.len()) || (startval > endval) {
+                    return Err(format!("{}:Offsets {}-{} or Values {}-{} out of range wrt buf len {}", b8rmsg, tstartoffset, tendoffset, startval, endval, buf.len()));
+                }
+                let rng = ctxt.fcrtm.rng();
                 let offsetwidth = tendoffset - tstartoffset + 1;
                 let valwidth: u16 = endval as u16 - startval as u16 + 1;
                 for _i in 0..trandcount {
@@ -1124,7 +1252,7 @@ impl Op {
                     let curval = startval + (rng.gen::<u16>() % valwidth) as u8;
                     buf[curind] = curval;
                 }
-                bufid.set_bufvu8(ctxt, buf).expect(&format!("{}:Buf:{:?}:SettingResult", b8rmsg, bufid));
+                bufid.set_bufvu8(ctxt, buf).map_err(|e| format!("{}:Buf:{:?}:SettingResult:{}", b8rmsg, bufid, e))?;
             }
             Self::BufMerged(mtype, destbufdm, srcdms) => {
                 let mut destbuf = Vec::new();
@@ -1133,13 +1261,13 @@ impl Op {
                     if *mtype == 'b' {
                         let tbuf = srcdm.get_bufvu8(ctxt);
                         if tbuf.is_err() {
-                            panic!("ERRR:{}:BufMerged.B:Src:{:?}:{}", msgtag, srcdm, tbuf.unwrap_err());
+                            return Err(format!("ERRR:{}:BufMerged.B:Src:{:?}:{}", msgtag, srcdm, tbuf.unwrap_err()));
                         }
                         sbuf = tbuf.unwrap();
                     } else {
                         let tbuf = srcdm.get_string(ctxt);
                         if tbuf.is_err() {
-                            panic!("ERRR:{}:BufMerged.S:Src:{:?}:{}", msgtag, srcdm, tbuf.unwrap_err());
+                            return Err(format!("ERRR:{}:BufMerged.S:Src:{:?}:{}", msgtag, srcdm, tbuf.unwrap_err()));
                         }
                         sbuf = Vec::from(tbuf.unwrap());
                     }
@@ -1148,7 +1276,7 @@ impl Op {
                 ldebug!(&format!("DBUG:{}:BufMerged:{:?}:{:?}", msgtag, destbufdm, destbuf));
                 let ok = destbufdm.set_bufvu8(ctxt, destbuf);
                 if ok.is_err() {
-                    panic!("ERRR:{}:BufMerged.{}:{:?}:{}", msgtag, mtype, destbufdm, ok.unwrap_err());
+                    return Err(format!("ERRR:{}:BufMerged.{}:{:?}:{}", msgtag, mtype, destbufdm, ok.unwrap_err()));
                 }
             }
 
@@ -1160,7 +1288,7 @@ impl Op {
                     'b' => {
                         let tdata = datadm.get_bufvu8(ctxt);
                         if tdata.is_err() {
-                            panic!("ERRR:{}:LetGlobal.b:GetSrcData:{}:{}", msgtag, datadm.identify(), tdata.unwrap_err());
+                            return Err(format!("ERRR:{}:LetGlobal.b:GetSrcData:{}:{}", msgtag, datadm.identify(), tdata.unwrap_err()));
                         }
                         let tdata = tdata.unwrap();
                         vdata = Variant::BufValue(tdata);
@@ -1168,7 +1296,7 @@ impl Op {
                     's' => {
                         let tdata = datadm.get_string(ctxt);
                         if tdata.is_err() {
-                            panic!("ERRR:{}:LetGlobal.s:GetSrcData:{}:{}", msgtag, datadm.identify(), tdata.unwrap_err());
+                            return Err(format!("ERRR:{}:LetGlobal.s:GetSrcData:{}:{}", msgtag, datadm.identify(), tdata.unwrap_err()));
                         }
                         let tdata = tdata.unwrap();
                         vdata = Variant::StrValue(tdata);
@@ -1176,17 +1304,17 @@ impl Op {
                     'i' => {
                         let tdata = datadm.get_isize(ctxt);
                         if tdata.is_err() {
-                            panic!("ERRR:{}:LetGlobal.i:GetSrcData:{}:{}", msgtag, datadm.identify(), tdata.unwrap_err());
+                            return Err(format!("ERRR:{}:LetGlobal.i:GetSrcData:{}:{}", msgtag, datadm.identify(), tdata.unwrap_err()));
                         }
                         let tdata = tdata.unwrap();
                         vdata = Variant::IntValue(tdata);
                     }
-                    _ => panic!("ERRR:{}:LetGlobal:GetSrcData:Unknown type:{}", msgtag, ltype),
+                    _ => return Err(format!("ERRR:{}:LetGlobal:GetSrcData:Unknown type:{}", msgtag, ltype)),
                 }
                 ldebug!(&format!("DBUG:{}:LetGlobal.{}:{:?}:{:?}", msgtag, ltype, vardm, vdata));
                 let ok = vardm.set_value(ctxt, vdata, false);
                 if ok.is_err() {
-                    panic!("ERRR:{}:LetGlobal:Setting the value for:{}:{}", msgtag, vardm.identify(), ok.unwrap_err());
+                    return Err(format!("ERRR:{}:LetGlobal:Setting the value for:{}:{}", msgtag, vardm.identify(), ok.unwrap_err()));
                 }
             }
 
@@ -1198,7 +1326,7 @@ impl Op {
                     'b' => {
                         let tdata = datadm.get_bufvu8(ctxt);
                         if tdata.is_err() {
-                            panic!("ERRR:{}:LetLocal.b:GetSrcData:{}:{}", msgtag, datadm.identify(), tdata.unwrap_err());
+                            return Err(format!("ERRR:{}:LetLocal.b:GetSrcData:{}:{}", msgtag, datadm.identify(), tdata.unwrap_err()));
                         }
                         let tdata = tdata.unwrap();
                         vdata = Variant::BufValue(tdata);
@@ -1206,7 +1334,7 @@ impl Op {
                     's' => {
                         let tdata = datadm.get_string(ctxt);
                         if tdata.is_err() {
-                            panic!("ERRR:{}:LetLocal.s:GetSrcData:{}:{}", msgtag, datadm.identify(), tdata.unwrap_err());
+                            return Err(format!("ERRR:{}:LetLocal.s:GetSrcData:{}:{}", msgtag, datadm.identify(), tdata.unwrap_err()));
                         }
                         let tdata = tdata.unwrap();
                         vdata = Variant::StrValue(tdata);
@@ -1214,34 +1342,35 @@ impl Op {
                     'i' => {
                         let tdata = datadm.get_isize(ctxt);
                         if tdata.is_err() {
-                            panic!("ERRR:{}:LetLocal.i:GetSrcData:{}:{}", msgtag, datadm.identify(), tdata.unwrap_err());
+                            return Err(format!("ERRR:{}:LetLocal.i:GetSrcData:{}:{}", msgtag, datadm.identify(), tdata.unwrap_err()));
                         }
                         let tdata = tdata.unwrap();
                         vdata = Variant::IntValue(tdata);
                     }
-                    _ => panic!("ERRR:{}:LetLocal:GetSrcData:Unknown type:{}", msgtag, ltype),
+                    _ => return Err(format!("ERRR:{}:LetLocal:GetSrcData:Unknown type:{}", msgtag, ltype)),
                 }
                 ldebug!(&format!("DBUG:{}:LetLocal.{}:{:?}:{:?}", msgtag, ltype, vardm, vdata));
                 let ok = vardm.set_value(ctxt, vdata, true);
                 if ok.is_err() {
-                    panic!("ERRR:{}:LetLocal:Setting the value for:{}:{}", msgtag, vardm.identify(), ok.unwrap_err());
+                    return Err(format!("ERRR:{}:LetLocal:Setting the value for:{}:{}", msgtag, vardm.identify(), ok.unwrap_err()));
                 }
             }
 
             Self::EMagic(mtype, marg) => {
-                let mtype = mtype.get_isize(ctxt).expect(&format!("{}:EMagic:MType:{:?}", msgtag, mtype));
+                let mtype = mtype.get_isize(ctxt).map_err(|e| format!("{}:EMagic:MType:{:?}:{}", msgtag, mtype, e))?;
                 if mtype == 0x010 {
-                    let mbuf = marg.get_bufvu8(ctxt).expect(&format!("{}:EMagic:Marg:Buf", msgtag));
+                    let mbuf = marg.get_bufvu8(ctxt).map_err(|e| format!("{}:EMagic:Marg:Buf:{}", msgtag, e))?;
                     log_e(&format!("EMAGIC:{}:{:?}:Len:{}:Cap:{}", mtype, mbuf, mbuf.len(), mbuf.capacity()));
                 }
             }
 
             Self::GetSize(sdm, ddm) => {
-                let tbuf = sdm.get_bufvu8(ctxt).expect(&format!("{}:GetSize:Fetching src:{:?}", msgtag, sdm));
-                ddm.set_isize(ctxt, tbuf.len() as isize).expect(&format!("{}:GetSize:Writing size to:{:?}", msgtag, ddm));
+                let tbuf = sdm.get_bufvu8(ctxt).map_err(|e| format!("{}:GetSize:Fetching src:{:?}:{}", msgtag, sdm, e))?;
+                ddm.set_isize(ctxt, tbuf.len() as isize).map_err(|e| format!("{}:GetSize:Writing size to:{:?}:{}", msgtag, ddm, e))?;
             }
 
         }
+        Ok(())
     }
 
 }
@@ -1261,8 +1390,8 @@ impl VM {
         }
     }
 
-    fn compile_directive(&mut self, sdirplus: &str) {
-        let (sdir, sargs) = sdirplus.split_once(' ').expect(&format!("ERRR:FuzzerK:VM:CompileDirective:{}", sdirplus));
+    fn compile_directive(&mut self, sdirplus: &str) -> Result<(), String> {
+        let (sdir, sargs) = sdirplus.split_once(' ').ok_or_else(|| format!("ERRR:FuzzerK:VM:CompileDirective:Missing args:{}", sdirplus))?;
         match sdir {
             "!label" => {
                 self.ctxt.lbls.insert(sargs.to_string(), self.ops.len());
@@ -1270,7 +1399,7 @@ impl VM {
             "!func" => {
                 let parts: Vec<&str> = sargs.split_whitespace().collect();
                 if parts.len() == 0 {
-                    panic!("ERRR:FuzzerK:VM:CompileDirective:!func:function name missing {}", sdirplus);
+                    return Err(format!("ERRR:FuzzerK:VM:CompileDirective:!func:function name missing {}", sdirplus));
                 }
                 let mut vargs: Vec<String> = Vec::new();
                 for i in 1..parts.len() {
@@ -1278,39 +1407,56 @@ impl VM {
                 }
                 self.ctxt.funcs.insert(parts[0].to_string(), (self.ops.len(),vargs));
                 if self.ctxt.bcompilingfunc {
-                    panic!("ERRR:FuzzerK:VM:CompileDirective:!func:{}, prev func may be missing ret", sdirplus);
+                    return Err(format!("ERRR:FuzzerK:VM:CompileDirective:!func:{}, prev func may be missing ret", sdirplus));
                 }
                 self.ctxt.bcompilingfunc = true;
                 self.ctxt.compilingfunc = parts[0].to_string();
             }
-            _ => panic!("ERRR:FuzzerK:VM:CompileDirective:Unknown:{}", sdirplus),
+            _ => return Err(format!("ERRR:FuzzerK:VM:CompileDirective:Unknown:{}", sdirplus)),
         }
         ldebug!(&format!("DBUG:FuzzerK:VM:Compiled:Directive:{}:{:?}", self.ctxt.compilingline, sdirplus));
+        Ok(())
     }
 
-    pub fn compile_p1(&mut self, ops: Vec<String>) {
+    ///
+    /// Compile the given line (ie self.ctxt.compilingline) of the program
+    ///
+    fn compile_line(&mut self, sop: &str) -> Result<(), FuzzerKError> {
+        let sop = sop.trim();
+        if sop.starts_with("#") || (sop.len() == 0) {
+            return Ok(());
+        }
+        //ldebug!(&format!("DBUG:FuzzerK:VM:Compile:Op:{}:{}", self.ctxt.compilingline, sop));
+        if sop.starts_with("!") {
+            let gotr = self.compile_directive(sop);
+            if gotr.is_err() {
+                return Err(FuzzerKError::Compile { line: self.ctxt.compilingline, src: sop.to_string(), msg: gotr.unwrap_err() });
+            }
+            return Ok(());
+        }
+        self.ctxt.preops.clear();
+        let op = Op::compile(sop, &mut self.ctxt);
+        if op.is_err() {
+            return Err(FuzzerKError::Compile { line: self.ctxt.compilingline, src: sop.to_string(), msg: op.unwrap_err() });
+        }
+        let op = op.unwrap();
+        for i in 0..self.ctxt.preops.len() {
+            let opx = self.ctxt.preops[i].clone();
+            ldebug!(&format!("DBUG:FuzzerK:VM:Compiled:Op:{}:{:?}", self.ctxt.compilingline, opx));
+            self.ops.push((opx, self.ctxt.compilingline));
+        }
+        ldebug!(&format!("DBUG:FuzzerK:VM:Compiled:Op:{}:{:?}", self.ctxt.compilingline, op));
+        self.ops.push((op,self.ctxt.compilingline));
+        Ok(())
+    }
+
+    pub fn compile_p1(&mut self, ops: Vec<String>) -> Result<(), FuzzerKError> {
         self.ctxt.compilingline = 0;
         for sop in ops {
             self.ctxt.compilingline += 1;
-            let sop = sop.trim();
-            if sop.starts_with("#") || (sop.len() == 0) {
-                continue;
-            }
-            //ldebug!(&format!("DBUG:FuzzerK:VM:Compile:Op:{}:{}", self.ctxt.compilingline, sop));
-            if sop.starts_with("!") {
-                self.compile_directive(sop);
-                continue;
-            }
-            self.ctxt.preops.clear();
-            let op = Op::compile(sop, &mut self.ctxt).expect(&format!("ERRR:FuzzerK:VM:Compile:Op:{}", sop));
-            for i in 0..self.ctxt.preops.len() {
-                let opx = self.ctxt.preops[i].clone();
-                ldebug!(&format!("DBUG:FuzzerK:VM:Compiled:Op:{}:{:?}", self.ctxt.compilingline, opx));
-                self.ops.push((opx, self.ctxt.compilingline));
-            }
-            ldebug!(&format!("DBUG:FuzzerK:VM:Compiled:Op:{}:{:?}", self.ctxt.compilingline, op));
-            self.ops.push((op,self.ctxt.compilingline));
+            self.compile_line(&sop)?;
         }
+        Ok(())
     }
 
     ///
@@ -1318,19 +1464,27 @@ impl VM {
     /// NOTE: THe way things are structured, the aot compiled program will still run
     /// even if compile_phase2 is not called.
     ///
-    pub fn compile_p2(&mut self) {
+    pub fn compile_p2(&mut self) -> Result<(), FuzzerKError> {
         let ops = &mut self.ops;
         for i in 0..ops.len() {
             let op = &ops[i];
             match &op.0 {
                 Op::JumpRaw(label) => {
-                    let dptr = self.ctxt.lbls.get(label).expect(&format!("ERRR:FuzzerK:VM:Compile:P2:JumpRaw:target:{}", label));
+                    let dptr = self.ctxt.lbls.get(label);
+                    if dptr.is_none() {
+                        return Err(FuzzerKError::Compile { line: op.1, src: format!("goto {}", label), msg: "ERRR:FuzzerK:VM:Compile:P2:JumpRaw:Unknown target label".to_string() });
+                    }
+                    let dptr = dptr.unwrap();
                     ops[i] = (Op::Jump(*dptr), op.1);
                 }
                 Op::If(cop, arg1, arg2, nxtop) => {
                     match &**nxtop {
                         Op::JumpRaw(label) => {
-                            let dptr = self.ctxt.lbls.get(label).expect(&format!("ERRR:FuzzerK:VM:Compile:P2:If:Goto target:{}", label));
+                            let dptr = self.ctxt.lbls.get(label);
+                            if dptr.is_none() {
+                                return Err(FuzzerKError::Compile { line: op.1, src: format!("goto {}", label), msg: "ERRR:FuzzerK:VM:Compile:P2:If:Unknown goto target label".to_string() });
+                            }
+                            let dptr = dptr.unwrap();
                             ops[i] = (Op::If(cop.clone(), arg1.clone(), arg2.clone(), Box::new(Op::Jump(*dptr))), op.1);
                         }
                         _ => (),
@@ -1339,20 +1493,26 @@ impl VM {
                 _ => (),
             }
         }
+        Ok(())
     }
 
-    pub fn compile(&mut self, ops: Vec<String>) {
-        self.compile_p1(ops);
-        self.compile_p2();
+    pub fn compile(&mut self, ops: Vec<String>) -> Result<(), FuzzerKError> {
+        self.ctxt.srcprg.extend(ops.iter().cloned());
+        self.compile_p1(ops)?;
+        self.compile_p2()
     }
 
-    pub fn load_asmprg(&mut self, asmfile: &str) {
+    pub fn load_asmprg(&mut self, asmfile: &str) -> Result<(), FuzzerKError> {
         if asmfile.len() == 0 {
             log_w("WARN:FuzzerK:VM:LoadASMPrg:Empty filename passed, skipping...");
-            return;
+            return Ok(());
         }
         let mut ops = Vec::<String>::new();
-        let prgdata = fs::read_to_string(asmfile).expect("ERRR:FuzzerK:VM:LoadASMPrg:Loading asm script file");
+        let prgdata = fs::read_to_string(asmfile);
+        if prgdata.is_err() {
+            return Err(FuzzerKError::File(asmfile.to_string(), prgdata.unwrap_err().to_string()));
+        }
+        let prgdata = prgdata.unwrap();
         let prgdata: Vec<&str> =  prgdata.split("\n").collect();
         for l in prgdata {
             //log_d(&format!("IN :{}\n", l));
@@ -1361,10 +1521,10 @@ impl VM {
             //Self::test_bruteforce_nexttoken(&nl);
             ops.push(nl.to_string());
         }
-        self.compile(ops);
+        self.compile(ops)
     }
 
-    pub fn predefined_asmprg(&mut self, fc: &str, loopcnt: usize, ioaddr: &str, ioargshm: &HashMap<String, String>) {
+    pub fn predefined_asmprg(&mut self, fc: &str, loopcnt: usize, ioaddr: &str, ioargshm: &HashMap<String, String>) -> Result<(), FuzzerKError> {
         let mut ioargs = String::new();
         for ioarg in ioargshm {
             let sioarg = format!("{}={} ", ioarg.0, ioarg.1);
@@ -1373,45 +1533,338 @@ impl VM {
         let mut asmprg = Vec::<String>::new();
         asmprg.push("letint loopcnt 0".to_string());
         asmprg.push("!label freshstart".to_string());
-        asmprg.push(format!("iobnew srvX {} {}", ioaddr, ioargs));
+        if ioaddr.contains(char::is_whitespace) {
+            asmprg.push(format!("iobnew srvX \"{}\" {}", ioaddr, ioargs));
+        } else {
+            asmprg.push(format!("iobnew srvX {} {}", ioaddr, ioargs));
+        }
         asmprg.push(format!("fcget {} fuzzgot", fc));
         asmprg.push("iobwrite srvX fuzzgot".to_string());
         asmprg.push("iobflush srvX".to_string());
         asmprg.push("inc loopcnt".to_string());
         asmprg.push(format!("iflt.i loopcnt {} goto freshstart", loopcnt));
-        self.compile(asmprg);
+        self.compile(asmprg)
+    }
+
+    ///
+    /// Compile the specified program, continuing past any issues, and check that the labels,
+    /// functions (and fuzzchains, if cfgfc files were loaded) referred to by it are defined.
+    /// Fuzzchains declared in the cfgfc files, which couldnt be created, are not reported again.
+    ///
+    fn check_asmprg(&mut self, asmfile: &str, fchains: Option<&Vec<&str>>) -> Vec<Diagnostic> {
+        let prgdata = fs::read_to_string(asmfile);
+        if prgdata.is_err() {
+            return vec![Diagnostic::error(asmfile, 0, 0, &format!("ERRR:FuzzerK:VM:Check:{}", prgdata.unwrap_err()))];
+        }
+        let prgdata = prgdata.unwrap();
+        let lines: Vec<&str> = prgdata.split("\n").collect();
+        let mut diags = Vec::new();
+        self.ctxt.compilingline = 0;
+        for l in &lines {
+            self.ctxt.compilingline += 1;
+            let nl = tokensk::util::remove_extra_whitespaces(l);
+            let gotr = self.compile_line(&nl);
+            if let Err(err) = gotr {
+                let msg = match err {
+                    FuzzerKError::Compile { line: _, src: _, msg } => msg,
+                    err => err.to_string(),
+                };
+                diags.push(Diagnostic::error(asmfile, self.ctxt.compilingline as usize, cfgfiles::col_of(l, ""), &msg));
+            }
+        }
+        for (op, line) in &self.ops {
+            let mut refs = Vec::new();
+            op.refs(&mut refs);
+            let line = *line as usize;
+            for (kind, name, nargs) in refs {
+                let issue = match kind {
+                    "label" if !self.ctxt.lbls.contains_key(name) => "Unknown label",
+                    "function" if !self.ctxt.funcs.contains_key(name) => "Unknown function",
+                    "function" if self.ctxt.funcs[name].1.len() != nargs => "Num of required and passed args dont match, wrt function",
+                    "fuzzchain" if fchains.map_or(false, |fchains| !fchains.contains(&name)) => "Unknown fuzzchain",
+                    _ => continue,
+                };
+                let col = cfgfiles::col_of(lines[line-1], name);
+                diags.push(Diagnostic::error(asmfile, line, col, &format!("ERRR:FuzzerK:VM:Check:{} {}", issue, name)));
+            }
+        }
+        diags
     }
 
-    pub fn load_fcrtm(&mut self, cfgfc: &str) {
+    ///
+    /// Check the given cfgfc files and asm program (if any), without running anything, and
+    /// return all the issues found, along with their file, line and column.
+    /// * the cfgfc files are checked for issues with their cfggroups, references to unknown
+    ///   fuzzers in the fuzzchains and fuzzers not used by any fuzzchain or fuzzer (warning).
+    /// * the program is checked for issues compiling it and references to unknown labels,
+    ///   functions and fuzzchains (if cfgfc files are given).
+    ///
+    pub fn check(&mut self, cfgfcs: &Vec<String>, asmfile: &str) -> Vec<Diagnostic> {
+        let mut diags = Vec::new();
+        let mut groups = Vec::new();
+        let mut files = Vec::new();
+        for cfgfc in cfgfcs {
+            let mut cfgcheck = CfgCheck::default();
+            cfgfiles::check_file(cfgfc, &mut self.ctxt.fcrtm, &mut files, &mut cfgcheck);
+            let unknown = self.ctxt.fcrtm.pending_unknown();
+            let cfgendissue = self.ctxt.fcrtm.handle_cfgend().err();
+            diags.append(&mut cfgcheck.diags);
+            diags.append(&mut check::check_fchains(&self.ctxt.fcrtm, &cfgcheck.groups, unknown, cfgendissue));
+            groups.append(&mut cfgcheck.groups);
+        }
+        diags.append(&mut check::check_unused(&self.ctxt.fcrtm, &groups));
+        diags.sort_by(|a, b| (&a.file, a.line, a.col).cmp(&(&b.file, b.line, b.col)));
+        if asmfile.len() > 0 {
+            let fchains: Vec<&str> = groups.iter().filter(|group| check::group_isfchain(group)).filter_map(|group| check::group_name(group)).collect();
+            let mut prgdiags = self.check_asmprg(asmfile, if cfgfcs.len() > 0 { Some(&fchains) } else { None });
+            prgdiags.sort_by_key(|diag| (diag.line, diag.col));
+            diags.append(&mut prgdiags);
+        }
+        diags
+    }
+
+    ///
+    /// Set the seed used by the random generator shared by the fuzzchains and the vm.
+    /// Running the same cfgfc and asmprg files with the same seed, will generate the same data.
+    ///
+    pub fn set_seed(&mut self, seed: u64) {
+        self.ctxt.fcrtm.reseed(seed);
+    }
+
+    pub fn seed(&self) -> u64 {
+        return self.ctxt.fcrtm.seed();
+    }
+
+    ///
+    /// Set the directory, into which the oracle saves the recent fcget buffers
+    /// along with the seed and step, when a io exchange with the target fails.
+    ///
+    pub fn set_crashdir(&mut self, crashdir: &str) {
+        self.ctxt.oracle.set_crashdir(crashdir);
+    }
+
+    ///
+    /// Set the number of recent fcget buffers, which should be saved on a failure.
+    ///
+    pub fn set_crashhistory(&mut self, historylen: usize) {
+        self.ctxt.oracle.set_historylen(historylen);
+    }
+
+    pub fn load_fcrtm(&mut self, cfgfc: &str) -> Result<(), FuzzerKError> {
         if cfgfc.len() == 0 {
             log_w("WARN:FuzzerK:VM:LoadFCRTM:Empty filename passed, skipping...");
+            return Ok(());
+        }
+        self.ctxt.srccfgfcs.push(cfgfc.to_string());
+        cfgfiles::parse_file_deps(cfgfc, &mut self.ctxt.fcrtm, &mut self.ctxt.srccfgdeps)?;
+        if let Some(cfgwatch) = &mut self.ctxt.cfgwatch {
+            cfgwatch.set_files(self.ctxt.srccfgdeps.clone());
+        }
+        Ok(())
+    }
+
+    ///
+    /// Enable or disable watching the loaded cfgfc files (including the files included by them).
+    /// If enabled, whenever the running program jumps back (like to start the next iteration
+    /// of a loop), if any of these files have been edited, the fuzzers and fuzzchains are
+    /// recreated from the cfgfc files. If the edited files have any issue, it is logged and
+    /// the current fuzzers and fuzzchains continue to be used.
+    ///
+    /// NOTE: The recreated fuzzers start afresh, so stateful fuzzers like SequenceFuzzer
+    /// restart from their start value. The step is retained.
+    ///
+    pub fn set_cfgwatch(&mut self, bwatch: bool) {
+        self.ctxt.cfgwatch = None;
+        if bwatch {
+            self.ctxt.cfgwatch = Some(CfgWatch::new(self.ctxt.srccfgdeps.clone()));
+        }
+    }
+
+    ///
+    /// Reload the fuzzers and fuzzchains, if any of the watched cfgfc files have been edited.
+    ///
+    fn cfgwatch_check(&mut self) {
+        let cfgwatch = self.ctxt.cfgwatch.as_mut().unwrap();
+        if !cfgwatch.changed() {
             return;
         }
-        cfgfiles::parse_file(cfgfc, &mut self.ctxt.fcrtm);
+        match self.ctxt.fcrtm.reload(&self.ctxt.srccfgfcs) {
+            Ok(files) => {
+                log_o(&format!("INFO:FuzzerK:VM:CfgWatch:Reloaded {:?}", self.ctxt.srccfgfcs));
+                self.ctxt.srccfgdeps = files;
+                self.ctxt.cfgwatch.as_mut().unwrap().set_files(self.ctxt.srccfgdeps.clone());
+            }
+            Err(err) => log_e(&format!("ERRR:FuzzerK:VM:CfgWatch:Continuing with the current fuzzers:{}", err)),
+        }
     }
 
-    pub fn run(&mut self) {
+    ///
+    /// Handle a concurrent client in its own thread, by running the same cfgfc files and
+    /// program, in a fresh vm, starting from the given label, with the client as the ioid.
+    ///
+    ///
+    /// Derive the seed wrt a concurrent client, by mixing (splitmix64) the seed and client number,
+    /// so that the clients of runs with nearby seeds dont get overlapping random streams.
+    ///
+    fn client_seed(seed: u64, clientcnt: u64) -> u64 {
+        let mut z = seed ^ clientcnt.wrapping_mul(0x9E3779B97F4A7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    fn client_spawn(ctxt: &Context, ioid: &str, client: IOBridge, label: &str, seed: u64) -> thread::JoinHandle<Result<(), FuzzerKError>> {
+        let cfgfcs = ctxt.srccfgfcs.clone();
+        let prg = ctxt.srcprg.clone();
+        let crashdir = ctxt.oracle.crashdir().to_string();
+        let crashhistory = ctxt.oracle.historylen();
+        let ioid = ioid.to_string();
+        let label = label.to_string();
+        thread::spawn(move || {
+            let mut vm = VM::new();
+            vm.set_seed(seed);
+            vm.set_crashdir(&crashdir);
+            vm.set_crashhistory(crashhistory);
+            for cfgfc in cfgfcs {
+                vm.load_fcrtm(&cfgfc)?;
+            }
+            vm.compile(prg)?;
+            vm.ctxt.iobs.insert(ioid, client);
+            vm.ctxt.iptr = vm.ctxt.lbls[&label];
+            vm.run()
+        })
+    }
+
+    ///
+    /// Wait for the threads handling the concurrent clients to finish, logging their failures if any.
+    ///
+    fn clients_join(&mut self) {
+        for (i, (seed, handle)) in self.ctxt.clients.drain(..).enumerate() {
+            match handle.join() {
+                Ok(Ok(())) => (),
+                Ok(Err(err)) => log_e(&format!("ERRR:FuzzerK:VM:Client:{}:Seed:{}:{}", i+1, seed, err)),
+                Err(_) => log_e(&format!("ERRR:FuzzerK:VM:Client:{}:Seed:{}:Panicked", i+1, seed)),
+            }
+        }
+    }
+
+    ///
+    /// Run the compiled program, till it ends or runs past its last op.
+    ///
+    /// If any op fails, the call stack at that point is logged and the failure returned.
+    ///
+    /// Before returning, it waits for any concurrent clients spawned by iobaccept to finish.
+    ///
+    /// If watching the cfgfc files (refer set_cfgwatch), they are checked for edits, whenever
+    /// the program jumps back.
+    ///
+    pub fn run(&mut self) -> Result<(), FuzzerKError> {
+        self.ctxt.bend = false;
         loop {
-            if self.ctxt.iptr >= self.ops.len() {
+            if self.ctxt.bend || (self.ctxt.iptr >= self.ops.len()) {
                 break;
             }
             let theop = &self.ops[self.ctxt.iptr];
             ldebug!(&format!("INFO:FuzzerK:VM:Op:ToRun:{}:{}:{:?}", theop.1, self.ctxt.iptr, theop.0));
+            let previptr = self.ctxt.iptr;
             self.ctxt.iptr_commonupdate = true;
-            let rt = panic::catch_unwind(panic::AssertUnwindSafe(||{
-                theop.0.run(&mut self.ctxt, theop.1);
-            }));
+            let rt = theop.0.run(&mut self.ctxt, theop.1);
             if rt.is_err() {
                 for i in (0..self.ctxt.callretstack.len()).rev() {
                     let iptr = self.ctxt.callretstack[i];
                     let fargsmap = &self.ctxt.fargsmapstack[i];
-                    println!("{}:{}:{:?}:{:?}", i, iptr, self.ops[iptr], fargsmap);
+                    log_e(&format!("ERRR:FuzzerK:VM:Run:CallStack:{}:{}:{:?}:{:?}", i, iptr, self.ops[iptr], fargsmap));
                 }
-                process::exit(-12);
+                let err = FuzzerKError::Run { line: theop.1, op: format!("{:?}", theop.0), msg: rt.unwrap_err() };
+                self.clients_join();
+                return Err(err);
             }
             if self.ctxt.iptr_commonupdate {
                 self.ctxt.iptr += 1;
             }
+            if (self.ctxt.iptr <= previptr) && self.ctxt.cfgwatch.is_some() {
+                self.cfgwatch_check();
+            }
+        }
+        self.clients_join();
+        Ok(())
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vm_client_seed() {
+        assert_eq!(VM::client_seed(5, 1), VM::client_seed(5, 1));
+        // runs with nearby seeds dont share the clients' seeds
+        let mut seeds = Vec::new();
+        for seed in 0..8 {
+            for clientcnt in 1..8 {
+                seeds.push(VM::client_seed(seed, clientcnt));
+            }
+        }
+        let cnt = seeds.len();
+        seeds.sort();
+        seeds.dedup();
+        assert_eq!(seeds.len(), cnt);
+        assert!(seeds.iter().all(|&seed| seed > 64));
+    }
+
+    #[test]
+    fn vm_iobaccept() {
+        use std::io::Read;
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let clients = std::thread::spawn(move || {
+            let connect = || {
+                for _i in 0..50 {
+                    let ts = std::net::TcpStream::connect(("127.0.0.1", port));
+                    if ts.is_ok() {
+                        return ts.unwrap();
+                    }
+                    std::thread::sleep(std::time::Duration::from_millis(100));
+                }
+                panic!("client couldnt connect");
+            };
+            let mut got = vec![String::new(), String::new(), String::new()];
+            let mut ts = connect();
+            ts.read_to_string(&mut got[0]).unwrap();
+            // the 2nd client is served only after the 3rd client is spawned
+            let mut ts2 = connect();
+            let mut ts3 = connect();
+            ts2.read_to_string(&mut got[1]).unwrap();
+            ts3.read_to_string(&mut got[2]).unwrap();
+            got
+        });
+        let mut vm = crate::vm::VM::new();
+        let prg = vec![
+            format!("iobnew srv tcpserver:127.0.0.1:{}", port),
+            "iobwrite srv \"seq\"".to_string(),
+            "iobaccept srv".to_string(),
+            "iobaccept srv spawn session".to_string(),
+            "!label session".to_string(),
+            "iobwrite srv \"hi\"".to_string(),
+            "iobclose srv".to_string(),
+            "end".to_string(),
+        ];
+        vm.compile(prg).unwrap();
+        vm.run().unwrap();
+        assert_eq!(clients.join().unwrap(), vec!["seq", "hi", "hi"]);
+    }
+
+    #[test]
+    fn vm_run_errors() {
+        use crate::error::FuzzerKError;
+        // a func arg used, without calling the func, is a run error and not a panic
+        let mut vm = crate::vm::VM::new();
+        let prg = ["goto inside", "!func fn1 arg1", "!label inside", "letglobal.i x arg1", "ret", "end"];
+        vm.compile(prg.iter().map(|l| l.to_string()).collect()).unwrap();
+        match vm.run().unwrap_err() {
+            FuzzerKError::Run { line, msg, .. } => assert!((line == 4) && msg.contains("Not within a function call"), "{} {}", line, msg),
+            err => panic!("{}", err),
         }
     }
 
@@ -44,53 +44,53 @@ impl DataM {
     ///   * it needs to start with a alpabhetic char
     ///   * it could either be a func arg or local variable or a global variable.
     ///
-    pub fn compile(ctxt: &Context, sdata: &str, stype: &str, smsg: &str) -> DataM {
+    pub fn compile(ctxt: &Context, sdata: &str, stype: &str, smsg: &str) -> Result<DataM, String> {
         let mut sdata = ctxt.tstrx.from_str(sdata, true);
         if sdata.remaining_len() == 0 {
-            panic!("ERRR:{}:DataM:Compile:{}:Data token empty", smsg, stype);
+            return Err(format!("ERRR:{}:DataM:Compile:{}:Data token empty", smsg, stype));
         }
         let schar = sdata.char_first().unwrap();
         let echar = sdata.char_last().unwrap();
 
         if schar.is_numeric() || schar == '+' || schar == '-' {
-            let idata = integer::intvalue(sdata.the_str()).expect(&format!("ERRR:{}:DataM:Compile:IntLiteral[{}]:Conversion", smsg, sdata));
-            return DataM::Value(Variant::IntValue(idata));
+            let idata = integer::intvalue(sdata.the_str()).map_err(|e| format!("ERRR:{}:DataM:Compile:IntLiteral[{}]:Conversion:{}", smsg, sdata, e))?;
+            return Ok(DataM::Value(Variant::IntValue(idata)));
         }
 
         if sdata.len() >= 2 {
 
             if schar == '"' || echar == '"' {
                 if schar != echar {
-                    panic!("ERRR:{}:DataM:Compile:StringLiteral:Mising double quote at one of the ends:[{}]", smsg, sdata);
+                    return Err(format!("ERRR:{}:DataM:Compile:StringLiteral:Mising double quote at one of the ends:[{}]", smsg, sdata));
                 }
-                let tdata = sdata.nexttok(' ', true).expect(&format!("ERRR:{}:DataM:Compile:StringLiteral~[{}]:Processing...", smsg, sdata));
+                let tdata = sdata.nexttok(' ', true).map_err(|e| format!("ERRR:{}:DataM:Compile:StringLiteral~[{}]:Processing...:{}", smsg, sdata, e))?;
                 if sdata.remaining_len() > 0 {
-                    panic!("ERRR:{}:DataM:Compile:StringLiteral:Extra data [{}] beyond end of the string[{}]???", smsg, sdata, tdata);
+                    return Err(format!("ERRR:{}:DataM:Compile:StringLiteral:Extra data [{}] beyond end of the string[{}]???", smsg, sdata, tdata));
                 }
                 let mut rdata = tdata.as_str();
-                rdata = rdata.strip_prefix('"').expect(&format!("ERRR:{}:DataM:Compile:StringLiteral:Missing double quote at start of {}", smsg, sdata));
-                rdata = rdata.strip_suffix('"').expect(&format!("ERRR:{}:DataM:Compile:StringLiteral:Missing double quote at end of {}", smsg, sdata));
-                return DataM::Value(Variant::StrValue(rdata.to_string()));
+                rdata = rdata.strip_prefix('"').ok_or_else(|| format!("ERRR:{}:DataM:Compile:StringLiteral:Missing double quote at start of {}", smsg, sdata))?;
+                rdata = rdata.strip_suffix('"').ok_or_else(|| format!("ERRR:{}:DataM:Compile:StringLiteral:Missing double quote at end of {}", smsg, sdata))?;
+                return Ok(DataM::Value(Variant::StrValue(rdata.to_string())));
             }
 
             if sdata.len() > 2 {
                 if sdata.the_str().starts_with("$0x") {
-                    let bdata = hex::vu8_from_hex(&sdata.the_str()[3..]).expect(&format!("ERRR:{}:DataM:Compile:BufHexString:Conversion:{}", smsg, sdata));
-                    return DataM::Value(Variant::BufValue(bdata));
+                    let bdata = hex::vu8_from_hex(&sdata.the_str()[3..]).map_err(|e| format!("ERRR:{}:DataM:Compile:BufHexString:Conversion:{}:{}", smsg, sdata, e))?;
+                    return Ok(DataM::Value(Variant::BufValue(bdata)));
                 }
                 if sdata.the_str().starts_with("__") {
                     if sdata.the_str() == "__TIME__STAMP__" {
-                        return DataM::Value(Variant::XTimeStamp);
+                        return Ok(DataM::Value(Variant::XTimeStamp));
                     }
                     if sdata.the_str().starts_with("__RANDOM__BYTES__") {
-                        let (_random, bytelen) = sdata.the_str().split_once("__BYTES__").expect(&format!("ERRR:{}:DataM:Compile:RandomBytes:{}", smsg, sdata));
-                        let bytelen = usize::from_str_radix(bytelen, 10).expect(&format!("ERRR:{}:DataM:Compile:RandomBytes:{}", smsg, sdata));
-                        return DataM::XOp(XOpData::RandomBytes(Box::new(DataM::Value(Variant::IntValue(bytelen as isize)))));
+                        let (_random, bytelen) = sdata.the_str().split_once("__BYTES__").ok_or_else(|| format!("ERRR:{}:DataM:Compile:RandomBytes:{}", smsg, sdata))?;
+                        let bytelen = usize::from_str_radix(bytelen, 10).map_err(|e| format!("ERRR:{}:DataM:Compile:RandomBytes:{}:{}", smsg, sdata, e))?;
+                        return Ok(DataM::XOp(XOpData::RandomBytes(Box::new(DataM::Value(Variant::IntValue(bytelen as isize))))));
                     }
-                    panic!("ERRR:{}:DataM:Compile:{}:Unknown Special Tag {}???", smsg, stype, sdata);
+                    return Err(format!("ERRR:{}:DataM:Compile:{}:Unknown Special Tag {}???", smsg, stype, sdata));
                 }
                 if schar == '!' && echar == ')' {
-                    let xop = sdata.peel_bracket('(').unwrap();
+                    let xop = sdata.peel_bracket('(').map_err(|e| format!("ERRR:{}:DataM:Compile:XOp:{}:{}", smsg, sdata, e))?;
                     let sarg1;
                     let bdm2;
                     let tdata;
@@ -99,18 +99,18 @@ impl DataM {
                             // ALERT: For now does not support
                             // * indexing of indexing using XOping syntax
                             let tindex;
-                            (tdata, tindex) = sdata.split_once(',').expect(&format!("ERRR:{}:DataM:Compile:XOp:{}:Extracting args:{}", smsg, xop, sdata));
+                            (tdata, tindex) = sdata.split_once(',').map_err(|e| format!("ERRR:{}:DataM:Compile:XOp:{}:Extracting args:{}:{}", smsg, xop, sdata, e))?;
                             sarg1 = tdata.as_str();
-                            let idm = DataM::compile(ctxt, &tindex, stype, &format!("{}:XOp-{}:{}",smsg, xop, tindex));
+                            let idm = DataM::compile(ctxt, &tindex, stype, &format!("{}:XOp-{}:{}",smsg, xop, tindex))?;
                             bdm2 = Some(Box::new(idm));
                         }
-                        "!timestamp" => return DataM::XOp(XOpData::TimeStamp),
+                        "!timestamp" => return Ok(DataM::XOp(XOpData::TimeStamp)),
                         _ => {
                             sarg1 = sdata.the_str();
                             bdm2 = None;
                         }
                     }
-                    let dm = DataM::compile(ctxt, sarg1, stype, &format!("{}:XOp-{}:{}",smsg, xop, sarg1));
+                    let dm = DataM::compile(ctxt, sarg1, stype, &format!("{}:XOp-{}:{}",smsg, xop, sarg1))?;
                     let boxdm = Box::new(dm);
                     let xdata = match xop.as_str() {
                         "!str" => XOpData::Str(boxdm),
@@ -119,21 +119,21 @@ impl DataM {
                         "!byteele" | "!be" => XOpData::ByteEle(boxdm, bdm2.unwrap()),
                         "!arrayele" | "!ae" => XOpData::ArrayEle(boxdm, bdm2.unwrap()),
                         "!randombytes" => XOpData::RandomBytes(boxdm),
-                        _ => panic!("ERRR:{}:DataM:{}:Unknown XOp type:{:?}", smsg, stype, xop),
+                        _ => return Err(format!("ERRR:{}:DataM:{}:Unknown XOp type:{:?}", smsg, stype, xop)),
                     };
-                    return DataM::XOp(xdata);
+                    return Ok(DataM::XOp(xdata));
                 }
             }
 
         }
 
         if !schar.is_alphabetic() {
-            panic!("ERRR:{}:DataM:{}:Variable name {} should start with a alphabetic char", smsg, stype, sdata);
+            return Err(format!("ERRR:{}:DataM:{}:Variable name {} should start with a alphabetic char", smsg, stype, sdata));
         }
         let index;
         let var;
         if echar == ']' {
-            var = sdata.peel_bracket('[').expect(&format!("ERRR:{}:DataM:Compile:{}:Invalid array indexing???:{}", smsg, stype, sdata));
+            var = sdata.peel_bracket('[').map_err(|e| format!("ERRR:{}:DataM:Compile:{}:Invalid array indexing???:{}:{}", smsg, stype, sdata, e))?;
             index = sdata.to_string();
             sdata = ctxt.tstrx.from_str(&var, true);
         } else {
@@ -149,10 +149,10 @@ impl DataM {
         }
         let dm = DataM::Variable(datakind, sdata.to_string());
         if index == "" {
-            return dm;
+            return Ok(dm);
         }
-        let idm = DataM::compile(ctxt, &index, stype, &format!("{}:Indexing:{}", smsg, index));
-        return DataM::XOp(XOpData::ByteEle(Box::new(dm), Box::new(idm)));
+        let idm = DataM::compile(ctxt, &index, stype, &format!("{}:Indexing:{}", smsg, index))?;
+        return Ok(DataM::XOp(XOpData::ByteEle(Box::new(dm), Box::new(idm))));
     }
 
     ///
@@ -197,7 +197,7 @@ impl DataM {
             }
             Self::Variable(datakind,vid) => {
                 let ovalue = ctxt.var_get(datakind, vid);
-                if ovalue.is_some() {
+                if ovalue.is_ok() {
                     return ovalue.unwrap().get_type();
                 }
             }
@@ -233,14 +233,14 @@ impl DataM {
             }
             Self::Variable(datakind, vid) => {
                 let ovalue = ctxt.var_get(datakind, vid);
-                if ovalue.is_some() {
+                if ovalue.is_ok() {
                     let ivalue = ovalue.unwrap().get_isize();
                     if ivalue.is_ok() {
                         return ivalue;
                     }
                     return Err(format!("DataM:GetISize:Var:{}", ivalue.unwrap_err()));
                 }
-                return Err(format!("DataM:GetISize:Var:Unknown:{}", vid));
+                return Err(format!("DataM:GetISize:Var:{}", ovalue.unwrap_err()));
             }
             Self::XOp(xdata) => {
                 let ival = xdata.get_isize(ctxt);
@@ -284,10 +284,10 @@ impl DataM {
             }
             DataM::Variable(datakind, vid) => {
                 let ovalue = ctxt.var_get(datakind, vid);
-                if ovalue.is_some() {
+                if ovalue.is_ok() {
                     return Ok(ovalue.unwrap().get_string());
                 }
-                return Err(format!("DataM:GetString:Var:Unknown:{}", vid));
+                return Err(format!("DataM:GetString:Var:{}", ovalue.unwrap_err()));
             }
             Self::XOp(xdata) => {
                 let sdata = xdata.get_string(ctxt);
@@ -314,10 +314,10 @@ impl DataM {
             }
             DataM::Variable(datakind, vid) => {
                 let ovalue = ctxt.var_get(datakind, vid);
-                if ovalue.is_some() {
+                if ovalue.is_ok() {
                     return Ok(ovalue.unwrap().get_bufvu8());
                 }
-                return Err(format!("DataM:GetBuf:Var:Unknown:{}", vid));
+                return Err(format!("DataM:GetBuf:Var:{}", ovalue.unwrap_err()));
             }
             Self::XOp(xdata) => {
                 let bval = xdata.get_bufvu8(ctxt);
@@ -335,6 +335,9 @@ impl DataM {
         let ival = self.get_bufvu8(ctxt);
         match ival {
             Ok(ival) => {
+                if index >= ival.len() {
+                    return Err(format!("DataM:GetByteEle:Index {} beyond buf len {}", index, ival.len()));
+                }
                 return Ok(ival[index]);
             }
             Err(msg) => return Err(format!("DataM:GetByteEle:{}", msg)),
@@ -363,15 +366,15 @@ impl DataM {
             Self::Variable(datakind, vname) => {
                 let oval = ctxt.var_get(datakind, vname);
                 match oval {
-                    Some(vval) => {
+                    Ok(vval) => {
                         let oval = vval.get_arrayelement(index);
                         if oval.is_err() {
                             return Err(format!("DataM:GetArrayEle:Var:{:?}:{}", self, oval.unwrap_err()));
                         }
                         return oval;
                     }
-                    None => {
-                        return Err(format!("DataM:GetArrayEle:Var:{:?}:Unknown var", self));
+                    Err(msg) => {
+                        return Err(format!("DataM:GetArrayEle:Var:{:?}:{}", self, msg));
                     }
                 }
             }
@@ -391,10 +394,10 @@ impl DataM {
             Self::Value(oval) => return Ok(oval.clone()),
             Self::Variable(datakind, vname) => {
                 let oval = ctxt.var_get(datakind, vname);
-                if oval.is_some() {
+                if oval.is_ok() {
                     return Ok(oval.unwrap().clone());
                 }
-                return Err(format!("DataM:GetVal:Var:Unknown:{}", vname));
+                return Err(format!("DataM:GetVal:Var:{}", oval.unwrap_err()));
             }
             Self::XOp(xdata) => {
                 let vval = xdata.get_value(ctxt);
@@ -411,11 +414,11 @@ impl DataM {
             Self::Value(oval) => return Ok((oval.get_type(), oval.clone())),
             Self::Variable(datakind, vname) => {
                 let oval = ctxt.var_get(datakind, vname);
-                if oval.is_some() {
+                if oval.is_ok() {
                     let oval = oval.unwrap();
                     return Ok((oval.get_type(), oval.clone()));
                 }
-                return Err(format!("DataM:GetTypeVal:Var:Unknown:{}", vname));
+                return Err(format!("DataM:GetTypeVal:Var:{}", oval.unwrap_err()));
             }
             Self::XOp(xdata) => {
                 let oval = xdata.get_value(ctxt);
@@ -429,25 +432,28 @@ impl DataM {
     }
 
     #[allow(dead_code)]
-    fn get_bufvu8_mut<'a>(&'a self, ctxt: &'a mut Context, smsg: &str) -> &'a mut Vec<u8> {
+    fn get_bufvu8_mut<'a>(&'a self, ctxt: &'a mut Context, smsg: &str) -> Result<&'a mut Vec<u8>, String> {
         match self {
-            Self::Value(_) => panic!("ERRR:{}:GetBufVu8Mut:Cant return mutable ref to values", smsg),
+            Self::Value(_) => Err(format!("{}:GetBufVu8Mut:Cant return mutable ref to values", smsg)),
             Self::Variable(datakind, vid) => {
                 if let DataKind::FuncArg = datakind {
-                    panic!("ERRR:{}:GetBufVu8Mut:FuncArg cant be used mutably/to-write-to its existing buf, currently", smsg);
+                    return Err(format!("{}:GetBufVu8Mut:FuncArg cant be used mutably/to-write-to its existing buf, currently", smsg));
                 }
                 if ctxt.var_islocal(vid) {
-                    panic!("ERRR:{}:GetBufVu8Mut:Localvar cant be used mutably/to-write-to its existing buf, currently", smsg);
+                    return Err(format!("{}:GetBufVu8Mut:Localvar cant be used mutably/to-write-to its existing buf, currently", smsg));
+                }
+                let vvalue = ctxt.globals.get_mut(vid);
+                if vvalue.is_none() {
+                    return Err(format!("{}:GetBufVu8Mut:Unknown:{}", smsg, vid));
                 }
-                let vvalue = ctxt.globals.get_mut(vid).unwrap();
-                let gotr = vvalue.get_bufvu8_mut();
+                let gotr = vvalue.unwrap().get_bufvu8_mut();
                 if gotr.is_none() {
-                    panic!("ERRR:{}:GetBufVu8Mut:Some issue with getting mut buf wrt:{}", smsg, vid);
+                    return Err(format!("{}:GetBufVu8Mut:Not a buffer:{}", smsg, vid));
                 }
-                return gotr.unwrap();
+                return Ok(gotr.unwrap());
             }
             Self::XOp(_xdata) => {
-                panic!("ERRR:{}:DataM:GetBufVu8Mut:XOp:{:?}:Not supported", smsg, self);
+                Err(format!("{}:DataM:GetBufVu8Mut:XOp:{:?}:Not supported", smsg, self))
             }
         }
     }
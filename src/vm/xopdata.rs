@@ -221,13 +221,13 @@ impl XOpData {
                 return Ok(uts.to_ne_bytes().to_vec());
             },
             Self::RandomBytes(bytelen) => {
-                let mut rng = rand::thread_rng();
                 let mut vdata: Vec<u8> = Vec::new();
                 let bytelen = bytelen.get_usize(ctxt);
                 if bytelen.is_err() {
                     return Err(format!("XOpData:GetBuf:{:?}:Bytelen:{}", self, bytelen.unwrap_err()));
                 }
                 let bytelen = bytelen.unwrap();
+                let rng = ctxt.fcrtm.rng();
                 for _i in 0..bytelen {
                     vdata.push(rng.gen_range(0..=255)); // rusty 0..256
                 }
@@ -0,0 +1,545 @@
+//!
+//! XmlFuzzer: Generate structure aware mutations of a sample xml document, including
+//! attribute and entity abuse.
+//!
+//! HanishKVC, 2022
+//!
+
+use std::collections::VecDeque;
+use rand::{Rng, RngCore};
+
+use crate::cfgfiles::FromVecStrings;
+use crate::json::{HUGE_NUMBERS, fromvs_structmutate};
+
+
+/// The default cap on the size (in bytes) of the expansion of the billion laughs entity
+pub const ENTITYCAP_DEFAULT: usize = 1000000;
+/// The number of attributes added, wrt attribute abuse
+const ATTR_MANY: usize = 1000;
+/// The size of the attribute value, wrt attribute abuse
+const ATTR_HUGE: usize = 65536;
+
+const UNTERMINATED: &[&str] = &[
+    "<!-- unterminated",
+    "<![CDATA[unterminated",
+    "<unterminated",
+    "<x a=\"unterminated>",
+    "<?pi unterminated",
+];
+
+const INVALID_REFS: &[&str] = &[
+    "&#xFFFFFFFF;",
+    "&#0;",
+    "&#xD800;",
+    "&#x;",
+    "&#65",
+    "&undefined;",
+    "&amp",
+    "&",
+    "<",
+];
+
+
+///
+/// A xml node. Text and attribute values are kept as in the source text (ie references
+/// are not expanded), so that they are regenerated as is.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum XNode {
+    Element { name: String, attrs: Vec<(String, String)>, children: Vec<XNode> },
+    Text(String),
+    /// Comments, CDATA sections, processing instructions, doctype and malformed markup
+    Raw(String),
+}
+
+impl XNode {
+
+    ///
+    /// Append the xml text to the passed buf
+    ///
+    pub fn serialize(&self, buf: &mut Vec<u8>) {
+        match self {
+            XNode::Element { name, attrs, children } => {
+                buf.extend_from_slice(format!("<{}", name).as_bytes());
+                for (aname, avalue) in attrs {
+                    let quote = if avalue.contains('"') { '\'' } else { '"' };
+                    buf.extend_from_slice(format!(" {}={}{}{}", aname, quote, avalue, quote).as_bytes());
+                }
+                if children.len() == 0 {
+                    buf.extend_from_slice(b"/>");
+                    return;
+                }
+                buf.push(b'>');
+                for child in children {
+                    child.serialize(buf);
+                }
+                buf.extend_from_slice(format!("</{}>", name).as_bytes());
+            }
+            XNode::Text(text) | XNode::Raw(text) => buf.extend_from_slice(text.as_bytes()),
+        }
+    }
+
+    ///
+    /// Get the paths (child indexes from the root) of all the elements in the tree
+    ///
+    fn paths(&self, path: &mut Vec<usize>, paths: &mut Vec<Vec<usize>>) {
+        if let XNode::Element { children, .. } = self {
+            paths.push(path.clone());
+            for (i, child) in children.iter().enumerate() {
+                path.push(i);
+                child.paths(path, paths);
+                path.pop();
+            }
+        }
+    }
+
+    fn node_mut(&mut self, path: &[usize]) -> &mut XNode {
+        if path.len() == 0 {
+            return self;
+        }
+        match self {
+            XNode::Element { children, .. } => children[path[0]].node_mut(&path[1..]),
+            _ => self,
+        }
+    }
+
+}
+
+
+///
+/// A xml document, ie the root element, with the xml declaration, doctype, comments, ...
+/// before and after it.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct XDoc {
+    prolog: Vec<XNode>,
+    pub root: XNode,
+    epilog: Vec<XNode>,
+}
+
+impl XDoc {
+
+    ///
+    /// Parse the given xml text
+    ///
+    pub fn parse(sxml: &str) -> Result<XDoc, String> {
+        let mut i = 0;
+        let prolog = Self::parse_misc(sxml, &mut i, true)?;
+        if !sxml[i..].starts_with('<') {
+            return Err(format!("ERRR:XDoc:Parse:Missing root element at {}", i));
+        }
+        let root = Self::parse_element(sxml, &mut i)?;
+        let epilog = Self::parse_misc(sxml, &mut i, false)?;
+        if i < sxml.len() {
+            return Err(format!("ERRR:XDoc:Parse:Unexpected data after root element at {}", i));
+        }
+        Ok(XDoc {
+            prolog,
+            root,
+            epilog,
+        })
+    }
+
+    ///
+    /// Get the text from the current position till (and including) the given end marker
+    ///
+    fn scan_until(sxml: &str, i: &mut usize, end: &str) -> Result<String, String> {
+        let iend = sxml[*i..].find(end);
+        if iend.is_none() {
+            return Err(format!("ERRR:XDoc:Parse:Missing {} wrt markup at {}", end, i));
+        }
+        let iend = *i + iend.unwrap() + end.len();
+        let text = sxml[*i..iend].to_string();
+        *i = iend;
+        Ok(text)
+    }
+
+    fn skip_ws(sxml: &str, i: &mut usize) {
+        while sxml[*i..].starts_with(|c: char| c.is_ascii_whitespace()) {
+            *i += 1;
+        }
+    }
+
+    fn scan_name(sxml: &str, i: &mut usize) -> Result<String, String> {
+        let iend = sxml[*i..].find(|c: char| c.is_ascii_whitespace() || "/>=".contains(c)).map(|iend| *i + iend).unwrap_or(sxml.len());
+        if iend == *i {
+            return Err(format!("ERRR:XDoc:Parse:Missing name at {}", i));
+        }
+        let name = sxml[*i..iend].to_string();
+        *i = iend;
+        Ok(name)
+    }
+
+    ///
+    /// Parse the whitespaces, comments, processing instructions and doctype (if allowed)
+    ///
+    fn parse_misc(sxml: &str, i: &mut usize, bdoctype: bool) -> Result<Vec<XNode>, String> {
+        let mut nodes = Vec::new();
+        loop {
+            let start = *i;
+            Self::skip_ws(sxml, i);
+            if *i > start {
+                nodes.push(XNode::Text(sxml[start..*i].to_string()));
+                continue;
+            }
+            let rest = &sxml[*i..];
+            if rest.starts_with("<?") {
+                nodes.push(XNode::Raw(Self::scan_until(sxml, i, "?>")?));
+            } else if rest.starts_with("<!--") {
+                nodes.push(XNode::Raw(Self::scan_until(sxml, i, "-->")?));
+            } else if bdoctype && rest.starts_with("<!DOCTYPE") {
+                // the internal subset can contain >, so look for the > outside it
+                let mut bsubset = false;
+                let iend = rest.find(|c: char| {
+                    if c == '[' || c == ']' {
+                        bsubset = c == '[';
+                    }
+                    !bsubset && (c == '>')
+                });
+                if iend.is_none() {
+                    return Err(format!("ERRR:XDoc:Parse:Unterminated doctype at {}", i));
+                }
+                let iend = *i + iend.unwrap() + 1;
+                nodes.push(XNode::Raw(sxml[*i..iend].to_string()));
+                *i = iend;
+            } else {
+                return Ok(nodes);
+            }
+        }
+    }
+
+    fn parse_element(sxml: &str, i: &mut usize) -> Result<XNode, String> {
+        *i += 1;
+        let name = Self::scan_name(sxml, i)?;
+        let mut attrs = Vec::new();
+        loop {
+            Self::skip_ws(sxml, i);
+            let rest = &sxml[*i..];
+            if rest.starts_with("/>") {
+                *i += 2;
+                return Ok(XNode::Element { name, attrs, children: Vec::new() });
+            }
+            if rest.starts_with('>') {
+                *i += 1;
+                break;
+            }
+            let aname = Self::scan_name(sxml, i)?;
+            Self::skip_ws(sxml, i);
+            if !sxml[*i..].starts_with('=') {
+                return Err(format!("ERRR:XDoc:Parse:Missing = wrt attribute {} at {}", aname, i));
+            }
+            *i += 1;
+            Self::skip_ws(sxml, i);
+            let quote = sxml[*i..].chars().next();
+            if (quote != Some('"')) && (quote != Some('\'')) {
+                return Err(format!("ERRR:XDoc:Parse:Missing quote wrt attribute {} at {}", aname, i));
+            }
+            *i += 1;
+            let mut avalue = Self::scan_until(sxml, i, &quote.unwrap().to_string())?;
+            avalue.pop();
+            attrs.push((aname, avalue));
+        }
+        let mut children = Vec::new();
+        loop {
+            let rest = &sxml[*i..];
+            if rest.starts_with("</") {
+                *i += 2;
+                let ename = Self::scan_name(sxml, i)?;
+                Self::skip_ws(sxml, i);
+                if (ename != name) || !sxml[*i..].starts_with('>') {
+                    return Err(format!("ERRR:XDoc:Parse:Mismatched end tag {} wrt {} at {}", ename, name, i));
+                }
+                *i += 1;
+                return Ok(XNode::Element { name, attrs, children });
+            }
+            if rest.starts_with("<!--") {
+                children.push(XNode::Raw(Self::scan_until(sxml, i, "-->")?));
+            } else if rest.starts_with("<![CDATA[") {
+                children.push(XNode::Raw(Self::scan_until(sxml, i, "]]>")?));
+            } else if rest.starts_with("<?") {
+                children.push(XNode::Raw(Self::scan_until(sxml, i, "?>")?));
+            } else if rest.starts_with('<') {
+                children.push(Self::parse_element(sxml, i)?);
+            } else {
+                let iend = rest.find('<');
+                if iend.is_none() {
+                    return Err(format!("ERRR:XDoc:Parse:Unterminated element {}", name));
+                }
+                children.push(XNode::Text(rest[..iend.unwrap()].to_string()));
+                *i += iend.unwrap();
+            }
+        }
+    }
+
+    ///
+    /// Append the xml text to the passed buf
+    ///
+    pub fn serialize(&self, buf: &mut Vec<u8>) {
+        for node in &self.prolog {
+            node.serialize(buf);
+        }
+        self.root.serialize(buf);
+        for node in &self.epilog {
+            node.serialize(buf);
+        }
+    }
+
+    ///
+    /// Replace the doctype (if any) with the given one
+    ///
+    fn set_doctype(&mut self, doctype: String) {
+        self.prolog.retain(|node| !matches!(node, XNode::Raw(r) if r.starts_with("<!DOCTYPE")));
+        let at = match self.prolog.first() {
+            Some(XNode::Raw(r)) if r.starts_with("<?xml") => 1,
+            _ => 0,
+        };
+        self.prolog.insert(at, XNode::Raw(doctype));
+    }
+
+}
+
+
+///
+/// Generate structure aware mutations of a sample xml document, with a chance of
+/// mutate percent, applying upto maxmutations of the below at random elements in the tree
+/// * type confusion, deep nesting, huge numbers, duplicate attributes, unterminated markup,
+///   invalid references, attribute abuse, entity abuse (billion laughs and external entity)
+///
+#[derive(Debug)]
+pub struct XmlFuzzer {
+    sample: XDoc,
+    mutate: usize,
+    maxmutations: usize,
+    nesting: usize,
+    /// The max size of the expansion of the billion laughs entity
+    entitycap: usize,
+}
+
+impl XmlFuzzer {
+
+    pub fn new(sample: XDoc, mutate: usize, maxmutations: usize, nesting: usize, entitycap: usize) -> XmlFuzzer {
+        XmlFuzzer {
+            sample,
+            mutate,
+            maxmutations,
+            nesting,
+            entitycap,
+        }
+    }
+
+    ///
+    /// Get the billion laughs doctype, with as many levels of 10 fold expansion as allowed
+    /// by the entity cap, and the reference to the top level entity.
+    ///
+    fn billion_laughs(&self, rootname: &str) -> (String, String) {
+        let mut levels = 0;
+        let mut size: usize = 3;
+        while size.checked_mul(10).map_or(false, |s| s <= self.entitycap) {
+            size *= 10;
+            levels += 1;
+        }
+        let mut doctype = format!("<!DOCTYPE {} [<!ENTITY lol0 \"lol\">", rootname);
+        for level in 1..=levels {
+            doctype.push_str(&format!("<!ENTITY lol{} \"{}\">", level, format!("&lol{};", level-1).repeat(10)));
+        }
+        doctype.push_str("]>");
+        (doctype, format!("&lol{};", levels))
+    }
+
+    fn mutate_doc(&self, doc: &mut XDoc, rng: &mut dyn RngCore) {
+        let mut paths = Vec::new();
+        doc.root.paths(&mut Vec::new(), &mut paths);
+        if paths.len() == 0 {
+            // the root itself was replaced by a earlier mutation
+            return;
+        }
+        let path = paths[rng.gen::<usize>() % paths.len()].clone();
+        let kind = rng.gen::<usize>() % 8;
+        if kind == 7 {
+            let rootname = if let XNode::Element { name, .. } = &doc.root { name.clone() } else { String::new() };
+            let (doctype, reference) = if rng.gen::<bool>() {
+                self.billion_laughs(&rootname)
+            } else {
+                (format!("<!DOCTYPE {} [<!ENTITY xxe SYSTEM \"file:///etc/passwd\">]>", rootname), "&xxe;".to_string())
+            };
+            doc.set_doctype(doctype);
+            if let XNode::Element { children, .. } = doc.root.node_mut(&path) {
+                children.push(XNode::Text(reference));
+            }
+            return;
+        }
+        let node = doc.root.node_mut(&path);
+        if kind == 1 {
+            // kept as text, so that deep nesting doesnt need deep recursion wrt the tree
+            let mut inner = Vec::new();
+            node.serialize(&mut inner);
+            let name = if let XNode::Element { name, .. } = node { name.clone() } else { "a".to_string() };
+            *node = XNode::Raw(format!("{}{}{}", format!("<{}>", name).repeat(self.nesting), String::from_utf8_lossy(&inner), format!("</{}>", name).repeat(self.nesting)));
+            return;
+        }
+        let (attrs, children) = match node {
+            XNode::Element { attrs, children, .. } => (attrs, children),
+            _ => return,
+        };
+        match kind {
+            0 => {
+                // element content as text or text content within a element
+                if children.iter().any(|c| matches!(c, XNode::Element { .. })) {
+                    let mut text = Vec::new();
+                    for child in children.iter() {
+                        child.serialize(&mut text);
+                    }
+                    let text = String::from_utf8_lossy(&text).replace('&', "&amp;").replace('<', "&lt;");
+                    *children = vec![XNode::Text(text)];
+                } else {
+                    let inner = children.clone();
+                    *children = vec![XNode::Element { name: "item".to_string(), attrs: Vec::new(), children: inner.clone() }, XNode::Element { name: "item".to_string(), attrs: Vec::new(), children: inner }];
+                }
+            }
+            2 => {
+                let huge = HUGE_NUMBERS[rng.gen::<usize>() % HUGE_NUMBERS.len()].to_string();
+                if (attrs.len() > 0) && rng.gen::<bool>() {
+                    let ai = rng.gen::<usize>() % attrs.len();
+                    attrs[ai].1 = huge;
+                } else {
+                    *children = vec![XNode::Text(huge)];
+                }
+            }
+            3 => {
+                if attrs.len() > 0 {
+                    let (aname, avalue) = attrs[rng.gen::<usize>() % attrs.len()].clone();
+                    attrs.push((aname, format!("{}x", avalue)));
+                } else {
+                    attrs.push(("a".to_string(), "1".to_string()));
+                    attrs.push(("a".to_string(), "2".to_string()));
+                }
+            }
+            4 => children.push(XNode::Raw(UNTERMINATED[rng.gen::<usize>() % UNTERMINATED.len()].to_string())),
+            5 => children.push(XNode::Text(INVALID_REFS[rng.gen::<usize>() % INVALID_REFS.len()].to_string())),
+            _ => {
+                match rng.gen::<usize>() % 4 {
+                    0 => {
+                        for i in 0..ATTR_MANY {
+                            attrs.push((format!("a{}", i), i.to_string()));
+                        }
+                    }
+                    1 => attrs.push(("a".to_string(), "A".repeat(ATTR_HUGE))),
+                    2 => attrs.push(("a".to_string(), "<&>".to_string())),
+                    _ => attrs.push(("x:a".to_string(), "unbound prefix".to_string())),
+                }
+            }
+        }
+    }
+
+}
+
+impl crate::Fuzz for XmlFuzzer {
+
+    fn append_fuzzed_immut(&self, _step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        let mut doc = self.sample.clone();
+        if (rng.gen::<usize>() % 100) < self.mutate {
+            let cnt = 1 + rng.gen::<usize>() % self.maxmutations;
+            for _i in 0..cnt {
+                self.mutate_doc(&mut doc, rng);
+            }
+        }
+        doc.serialize(buf);
+    }
+
+    fn append_fuzzed(&mut self, step: usize, rng: &mut dyn RngCore, buf: &mut Vec<u8>) {
+        return self.append_fuzzed_immut(step, rng, buf);
+    }
+
+}
+
+impl FromVecStrings for XmlFuzzer {
+
+    fn get_name() -> String {
+        return "XmlFuzzer".to_string();
+    }
+
+    ///
+    /// In the cfgfile use
+    /// ### FuzzerType:XmlFuzzer:InstanceName
+    /// * sample: the sample xml document, inline or from a file using $file:path
+    /// * mutate: 0 to 100 (optional, default 100), the percent chance of mutating the document
+    /// * maxmutations: (optional, default 1), the max number of mutations applied at a time
+    /// * nesting: (optional, default 1000), the levels of nesting wrt deep nesting
+    /// * entitycap: (optional, default 1000000), the max size of the billion laughs expansion
+    ///
+    fn from_vs(vs: &mut VecDeque<String>) -> Result<XmlFuzzer, String> {
+        let l = vs.pop_front();
+        if l.is_none() {
+            return Err("ERRR:XmlFuzzer:FromStringVec:Got empty vector".to_string());
+        }
+        let _l = l.unwrap(); // This should identify this particular type of Fuzzer and a runtime instance name
+        let spacesprefix = Self::get_spacesprefix(vs);
+        let sample = Self::get_value(vs, "sample", spacesprefix).map_err(|e| format!("ERRR:XmlFuzzer:GetSample:{}", e))?;
+        let sample = String::from_utf8(sample).map_err(|e| format!("ERRR:XmlFuzzer:Sample:{}", e))?;
+        let sample = XDoc::parse(&sample).map_err(|e| format!("ERRR:XmlFuzzer:Sample:{}", e))?;
+        let (mutate, maxmutations, nesting) = fromvs_structmutate::<Self>(vs, spacesprefix)?;
+        let entitycap = Self::get_uvalue_or(vs, "entitycap", spacesprefix, ENTITYCAP_DEFAULT).map_err(|e| format!("ERRR:XmlFuzzer:GetEntityCap:{}", e))?;
+        Ok(XmlFuzzer::new(sample, mutate, maxmutations, nesting, entitycap))
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::cfggroup;
+    use crate::rtm::RunTimeManager;
+    use crate::cfgfiles::HandleCfgGroup;
+    use crate::Fuzz;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn fuzzer_xml() {
+        let sxml = "<?xml version=\"1.0\"?>\n<!DOCTYPE r [<!ENTITY e \"x\">]>\n<r a='1' b=\"2\"><!-- c --><c>t &amp; &e;</c><![CDATA[<d>]]><e/></r>\n";
+        let xml = XDoc::parse(sxml).unwrap();
+        let mut buf = Vec::new();
+        xml.serialize(&mut buf);
+        assert_eq!(String::from_utf8(buf).unwrap(), sxml.replace("a='1'", "a=\"1\""));
+        assert!(XDoc::parse("<r><c></r>").unwrap_err().contains("Mismatched end tag"));
+        assert!(XDoc::parse("<r a=1/>").unwrap_err().contains("Missing quote"));
+        assert!(XDoc::parse("<r/>x").is_err());
+        // mutations
+        let mut rng = StdRng::seed_from_u64(0);
+        let xf = XmlFuzzer::new(xml, 100, 1, 8, 3000);
+        let mut cntlaughs = 0;
+        for _i in 0..128 {
+            let mut buf = Vec::new();
+            xf.append_fuzzed_immut(0, &mut rng, &mut buf);
+            let sbuf = String::from_utf8(buf).unwrap();
+            if sbuf.contains("<!ENTITY lol0") {
+                // 3 * 10^3 is within the cap, while 3 * 10^4 is not
+                assert!(sbuf.contains("<!ENTITY lol3") && !sbuf.contains("lol4"));
+                assert!(sbuf.contains("&lol3;</"));
+                assert!(!sbuf.contains("<!ENTITY e "));
+                assert!(sbuf.starts_with("<?xml version=\"1.0\"?><!DOCTYPE r ["));
+                cntlaughs += 1;
+            }
+        }
+        assert!(cntlaughs > 0);
+        // the levels are limited by the max usize, if the cap is larger than whats reachable
+        let xf = XmlFuzzer::new(XDoc::parse("<r/>").unwrap(), 100, 1, 8, usize::MAX);
+        let mut cntlaughs = 0;
+        for _i in 0..128 {
+            let mut buf = Vec::new();
+            xf.append_fuzzed_immut(0, &mut rng, &mut buf);
+            if String::from_utf8_lossy(&buf).contains("<!ENTITY lol0") {
+                cntlaughs += 1;
+            }
+        }
+        assert!(cntlaughs > 0);
+        // from cfg
+        let mut rtm = RunTimeManager::new_seeded(0);
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:XmlFuzzer:X01", "  sample: <a><b>1</b></a>", "  maxmutations: 3", "  entitycap: 100"])).unwrap();
+        rtm.handle_cfggroup(&mut cfggroup(&["FuzzChain:FuzzChain:FC01", "  X01"])).unwrap();
+        assert!(rtm.fchain_get("FC01", None).unwrap().len() > 0);
+        let gotr = rtm.handle_cfggroup(&mut cfggroup(&["FuzzerType:XmlFuzzer:X02", "  sample: <a>", "  maxmutations: 0"]));
+        assert!(gotr.unwrap_err().contains("Unterminated element a"));
+    }
+
+}